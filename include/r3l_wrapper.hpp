@@ -0,0 +1,84 @@
+
+/* RAII wrapper over the C ABI, appended by generate_cpp_wrapper. */
+#ifndef R3L_WRAPPER_HPP
+#define R3L_WRAPPER_HPP
+
+#include <memory>
+#include <optional>
+#include <string>
+#include <string_view>
+#include <vector>
+
+namespace r3l {
+
+struct TableDeleter {
+  void operator()(r3localetest::LocaleTable *table) const noexcept {
+    r3localetest::free_locale_table(table);
+  }
+};
+
+/* Owns a table and frees it when it goes out of scope. Values returned by get() point into the
+ * table and are valid until it is destroyed. */
+class LocaleTable {
+public:
+  static std::optional<LocaleTable> load(const std::string &path,
+                                         r3localetest::ParseR3Error *error = nullptr) {
+    r3localetest::AllocationResult result = r3localetest::get_locale_table(path.c_str());
+    if (error != nullptr) {
+      *error = result.AllocationState;
+    }
+    if (result.Table == nullptr) {
+      return std::nullopt;
+    }
+    return LocaleTable(result.Table);
+  }
+
+  /* The first table wins when several contain the same key. */
+  static std::optional<LocaleTable> merge(const std::vector<const LocaleTable *> &tables,
+                                          r3localetest::MergeTableError *error = nullptr) {
+    std::vector<const r3localetest::LocaleTable *> raw_tables;
+    raw_tables.reserve(tables.size());
+    for (const LocaleTable *table : tables) {
+      raw_tables.push_back(table->raw());
+    }
+    return from_merge(r3localetest::merge_locale_table_c(raw_tables.data(), raw_tables.size()), error);
+  }
+
+  std::optional<std::string_view> get(std::string_view key) const {
+    r3localetest::FindEntryResult result = r3localetest::get_entry(
+        raw(), reinterpret_cast<const uint8_t *>(key.data()), key.size());
+    if (result.AllocationState != r3localetest::FindEntryError::Normal) {
+      return std::nullopt;
+    }
+    return std::string_view(reinterpret_cast<const char *>(result.ValuePtr), result.ValueLen);
+  }
+
+  std::optional<LocaleTable> clone(r3localetest::MergeTableError *error = nullptr) const {
+    return from_merge(r3localetest::locale_table_clone(raw()), error);
+  }
+
+  const r3localetest::LocaleTable *raw() const noexcept { return handle_.get(); }
+
+  /* Hands ownership back to the caller, who must free the table with free_locale_table. */
+  r3localetest::LocaleTable *release() noexcept { return handle_.release(); }
+
+private:
+  explicit LocaleTable(r3localetest::LocaleTable *table) : handle_(table) {}
+
+  static std::optional<LocaleTable> from_merge(r3localetest::MergeResult result,
+                                               r3localetest::MergeTableError *error) {
+    if (error != nullptr) {
+      *error = result.MergeState;
+    }
+    if (result.Table == nullptr) {
+      return std::nullopt;
+    }
+    return LocaleTable(result.Table);
+  }
+
+  std::unique_ptr<r3localetest::LocaleTable, TableDeleter> handle_;
+};
+
+} // namespace r3l
+
+#endif // R3L_WRAPPER_HPP
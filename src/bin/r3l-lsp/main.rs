@@ -0,0 +1,48 @@
+mod protocol;
+mod server;
+
+use protocol::{field, parse_error, read_message, write_message};
+use reloaded3_localisation::locale_api::formats::json::JsonValue;
+use server::Server;
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+
+//Language server for .r3l files over stdio, started by editor extensions
+fn main() -> ExitCode {
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut output = io::stdout().lock();
+    let mut server = Server::default();
+    let mut shutdown_requested = false;
+
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(Ok(message))) => message,
+            //The client is still there, so tell it what was wrong and wait for the next message
+            Ok(Some(Err(invalid))) => {
+                eprintln!("r3l-lsp: {}", invalid.0);
+                if let Err(error) = write_message(&mut output, &parse_error(invalid.0)) {
+                    eprintln!("r3l-lsp: {error}");
+                    return ExitCode::FAILURE;
+                }
+                continue;
+            }
+            Ok(None) => return ExitCode::FAILURE,
+            Err(error) => {
+                eprintln!("r3l-lsp: {error}");
+                return ExitCode::FAILURE;
+            }
+        };
+        match field(&message, &["method"]).and_then(JsonValue::as_str) {
+            //The protocol asks for a failing exit code when the client exits without shutting down first
+            Some("exit") => return if shutdown_requested { ExitCode::SUCCESS } else { ExitCode::FAILURE },
+            Some("shutdown") => shutdown_requested = true,
+            _ => (),
+        }
+        for reply in server.handle(&message) {
+            if let Err(error) = write_message(&mut output, &reply) {
+                eprintln!("r3l-lsp: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+}
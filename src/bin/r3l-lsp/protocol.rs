@@ -0,0 +1,204 @@
+use reloaded3_localisation::locale_api::formats::json::{JsonValue, parse_json, quote};
+use std::io::{self, BufRead, Read, Write};
+
+//Larger bodies are skipped instead of read, a client can't make the server allocate whatever it claims to send
+const MAX_MESSAGE_LENGTH: usize = 32 << 20;
+
+//A message that was framed correctly but can't be handled, answered with a parse error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidMessage(pub &'static str);
+
+//Reads one `Content-Length` framed JSON-RPC message, None once the client closes the stream. A message that isn't
+//valid JSON is returned as InvalidMessage, so the server can answer it and keep going.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Result<JsonValue, InvalidMessage>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Ok(Some(Err(InvalidMessage("missing Content-Length header"))));
+    };
+    if length > MAX_MESSAGE_LENGTH {
+        let skipped = io::copy(&mut input.by_ref().take(length as u64), &mut io::sink())?;
+        if skipped < length as u64 {
+            return Ok(None);
+        }
+        return Ok(Some(Err(InvalidMessage("message is too large"))));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let Ok(body) = String::from_utf8(body) else {
+        return Ok(Some(Err(InvalidMessage("message is not UTF-8"))));
+    };
+    Ok(Some(parse_json(&body).map_err(|_| InvalidMessage("message is not valid JSON"))))
+}
+
+pub fn write_message(output: &mut impl Write, body: &str) -> io::Result<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+//Writes a parsed value back as JSON, only needed to echo request ids
+pub fn to_json(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => "null".to_string(),
+        JsonValue::Bool(value) => value.to_string(),
+        JsonValue::Number(number) if number.fract() == 0.0 => format!("{}", *number as i64),
+        JsonValue::Number(number) => number.to_string(),
+        JsonValue::String(text) => quote(text),
+        JsonValue::Array(items) => format!("[{}]", items.iter().map(to_json).collect::<Vec<_>>().join(",")),
+        JsonValue::Object(members) => {
+            let members: Vec<String> = members
+                .iter()
+                .map(|(name, value)| format!("{}:{}", quote(name), to_json(value)))
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+    }
+}
+
+pub fn response(id: &JsonValue, result: &str) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{result}}}", to_json(id))
+}
+
+//JSON-RPC's answer to a message that couldn't be parsed, which has no id to reply to
+pub fn parse_error(message: &str) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"id\":null,\"error\":{{\"code\":-32700,\"message\":{}}}}}", quote(message))
+}
+
+pub fn notification(method: &str, params: &str) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"method\":{},\"params\":{params}}}", quote(method))
+}
+
+//Follows a path of object members, e.g. `["textDocument", "uri"]`
+pub fn field<'a>(value: &'a JsonValue, path: &[&str]) -> Option<&'a JsonValue> {
+    path.iter().try_fold(value, |value, name| value.get(name))
+}
+
+pub fn number(value: Option<&JsonValue>) -> Option<usize> {
+    match value? {
+        JsonValue::Number(number) if *number >= 0.0 => Some(*number as usize),
+        _ => None,
+    }
+}
+
+//A line and a UTF-16 column, the unit LSP positions are counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    pub fn from_offset(text: &str, offset: usize) -> Position {
+        let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+        Position {
+            line: text[..offset].matches('\n').count(),
+            character: text[line_start..offset].encode_utf16().count(),
+        }
+    }
+
+    //Byte offset in the text, clamped to the end of the line
+    pub fn to_offset(self, text: &str) -> usize {
+        let mut line_start = 0;
+        for _ in 0..self.line {
+            match text[line_start..].find('\n') {
+                Some(index) => line_start += index + 1,
+                None => return text.len(),
+            }
+        }
+        let line_end = text[line_start..].find('\n').map_or(text.len(), |index| line_start + index);
+        let mut units = 0;
+        for (index, character) in text[line_start..line_end].char_indices() {
+            if units >= self.character {
+                return line_start + index;
+            }
+            units += character.len_utf16();
+        }
+        line_end
+    }
+}
+
+pub fn range(text: &str, start: usize, end: usize) -> String {
+    let (start, end) = (Position::from_offset(text, start), Position::from_offset(text, end));
+    format!(
+        "{{\"start\":{{\"line\":{},\"character\":{}}},\"end\":{{\"line\":{},\"character\":{}}}}}",
+        start.line, start.character, end.line, end.character
+    )
+}
+
+//Handles `file://` URIs with percent encoding, on Windows the drive letter path keeps no leading slash
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    let is_drive_path = path.len() > 2 && path.starts_with('/') && path.as_bytes()[2] == b':';
+    Some(if is_drive_path { path[1..].to_string() } else { path })
+}
+
+pub fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_and_uris() {
+        let text = "## 🎮 menu\n[[play]]\nPlay\n";
+        let offset = text.find("menu").expect("Missing text");
+        let position = Position::from_offset(text, offset);
+        assert_eq!(position, Position { line: 0, character: 6 });
+        assert_eq!(position.to_offset(text), offset);
+
+        assert_eq!(uri_to_path("file:///home/me/my%20mod/fr.r3l").as_deref(), Some("/home/me/my mod/fr.r3l"));
+        assert_eq!(uri_to_path("file:///C:/mods/fr.r3l").as_deref(), Some("C:/mods/fr.r3l"));
+        assert_eq!(path_to_uri("/home/me/my mod/fr.r3l"), "file:///home/me/my%20mod/fr.r3l");
+    }
+
+    #[test]
+    fn test_read_invalid_messages() {
+        let stream = format!(
+            "Content-Length: 5\r\n\r\nnot {{Content-Length: {}\r\n\r\n{}Content-Length: 2\r\n\r\n{{}}",
+            MAX_MESSAGE_LENGTH + 1,
+            " ".repeat(MAX_MESSAGE_LENGTH + 1)
+        );
+        let mut input = stream.as_bytes();
+        let invalid = |message| -> Option<Result<JsonValue, InvalidMessage>> { Some(Err(InvalidMessage(message))) };
+        assert_eq!(read_message(&mut input).ok(), Some(invalid("message is not valid JSON")));
+        assert_eq!(read_message(&mut input).ok(), Some(invalid("message is too large")));
+        assert_eq!(read_message(&mut input).ok(), Some(Some(Ok(JsonValue::Object(Vec::new())))));
+        assert_eq!(read_message(&mut input).ok(), Some(None));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::protocol::{Position, field, notification, number, path_to_uri, range, response, uri_to_path};
+use reloaded3_localisation::locale_api::formats::json::{JsonValue, quote};
+use reloaded3_localisation::{
+    BlockLine, BlockTracker, LocaleDocument, ParseR3Error, find_text_outside_entries, parse_r3locale_bytes,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+const ERROR: u8 = 1;
+const WARNING: u8 = 2;
+
+//The `[[key]]` headers of a file with the byte range of the key, found line by line like LocaleDocument::parse
+fn key_headers(text: &str) -> Vec<(String, usize, usize)> {
+    let mut headers = Vec::new();
+    let mut line_start = 0;
+    let mut blocks = BlockTracker::default();
+    for line in text.split_inclusive('\n') {
+        let in_block = blocks.classify(line.trim_end_matches(['\r', '\n']).as_bytes()) != BlockLine::Outside;
+        if let Some((key, _)) = line
+            .strip_prefix("[[")
+            .filter(|_| !in_block)
+            .and_then(|header| header.split_once("]]"))
+        {
+            let start = line_start + 2 + (key.len() - key.trim_start().len());
+            headers.push((key.trim().to_string(), start, start + key.trim().len()));
+        }
+        line_start += line.len();
+    }
+    headers
+}
+
+//The key header on the line of the position, or the key-like word under it, e.g. in a comment
+fn key_at(text: &str, position: Position) -> Option<String> {
+    let offset = position.to_offset(text);
+    if let Some((key, _, _)) = key_headers(text).into_iter().find(|(_, start, end)| {
+        let line_start = text[..*start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = text[*end..].find('\n').map_or(text.len(), |index| end + index);
+        (line_start..=line_end).contains(&offset)
+    }) {
+        return Some(key);
+    }
+
+    let is_key_character = |character: char| character.is_alphanumeric() || matches!(character, '_' | '.' | '|' | '-');
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, character)| is_key_character(*character))
+        .last()
+        .map_or(offset, |(index, _)| index);
+    let end = text[offset..]
+        .char_indices()
+        .find(|(_, character)| !is_key_character(*character))
+        .map_or(text.len(), |(index, _)| offset + index);
+    (start < end).then(|| text[start..end].to_string())
+}
+
+#[derive(Default)]
+pub struct Server {
+    //Text of open files by URI, newer than what's on disk while the file is unsaved
+    open_files: HashMap<String, String>,
+}
+
+impl Server {
+    //Returns the messages to send back, responses and notifications alike
+    pub fn handle(&mut self, message: &JsonValue) -> Vec<String> {
+        let method = field(message, &["method"]).and_then(JsonValue::as_str).unwrap_or_default();
+        let id = field(message, &["id"]);
+        let params = field(message, &["params"]).unwrap_or(&JsonValue::Null);
+        let uri = field(params, &["textDocument", "uri"])
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let position = match (
+            number(field(params, &["position", "line"])),
+            number(field(params, &["position", "character"])),
+        ) {
+            (Some(line), Some(character)) => Some(Position { line, character }),
+            _ => None,
+        };
+
+        let result = match method {
+            "initialize" => Some(
+                "{\"capabilities\":{\"textDocumentSync\":1,\"definitionProvider\":true,\"hoverProvider\":true,\"completionProvider\":{\"triggerCharacters\":[\"[\"]}},\"serverInfo\":{\"name\":\"r3l-lsp\"}}"
+                    .to_string(),
+            ),
+            "shutdown" => Some("null".to_string()),
+            "textDocument/didOpen" => {
+                let text = field(params, &["textDocument", "text"]).and_then(JsonValue::as_str).unwrap_or_default();
+                self.open_files.insert(uri.clone(), text.to_string());
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                //Full document sync, the last change holds the whole text
+                let text = match field(params, &["contentChanges"]) {
+                    Some(JsonValue::Array(changes)) => changes.last().and_then(|change| change.get("text")),
+                    _ => None,
+                };
+                if let Some(text) = text.and_then(JsonValue::as_str) {
+                    self.open_files.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.open_files.remove(&uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    &format!("{{\"uri\":{},\"diagnostics\":[]}}", quote(&uri)),
+                )];
+            }
+            "textDocument/definition" => Some(position.map_or("null".to_string(), |position| self.definition(&uri, position))),
+            "textDocument/hover" => Some(position.map_or("null".to_string(), |position| self.hover(&uri, position))),
+            "textDocument/completion" => Some(self.completion(&uri)),
+            _ => None,
+        };
+
+        match (id, result) {
+            (Some(id), Some(result)) => vec![response(id, &result)],
+            //Unknown requests still need an answer so the client doesn't wait forever
+            (Some(id), None) => vec![response(id, "null")],
+            _ => Vec::new(),
+        }
+    }
+
+    fn text(&self, uri: &str) -> Option<String> {
+        match self.open_files.get(uri) {
+            Some(text) => Some(text.clone()),
+            None => fs::read_to_string(uri_to_path(uri)?).ok(),
+        }
+    }
+
+    //Other `.r3l` files next to the file, which are normally the other languages of the same pack
+    fn siblings(&self, uri: &str) -> Vec<(String, String)> {
+        let Some(path) = uri_to_path(uri) else {
+            return Vec::new();
+        };
+        let Some(directory) = Path::new(&path).parent() else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(directory) else {
+            return Vec::new();
+        };
+        let mut siblings: Vec<(String, String)> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|sibling| sibling.extension().is_some_and(|extension| extension == "r3l") && sibling.as_path() != Path::new(&path))
+            .filter_map(|sibling| {
+                let sibling_uri = path_to_uri(&sibling.to_string_lossy());
+                let text = self.text(&sibling_uri)?;
+                Some((sibling_uri, text))
+            })
+            .collect();
+        siblings.sort();
+        siblings
+    }
+
+    fn diagnostics(&self, uri: &str) -> String {
+        let text = self.text(uri).unwrap_or_default();
+        let mut diagnostics = Vec::new();
+        let mut push = |start: usize, end: usize, severity: u8, message: &str| {
+            diagnostics.push(format!(
+                "{{\"range\":{},\"severity\":{severity},\"source\":\"r3l\",\"message\":{}}}",
+                range(&text, start, end),
+                quote(message)
+            ));
+        };
+
+        let mut seen = HashSet::new();
+        for (key, start, end) in key_headers(&text) {
+            if !seen.insert(key.clone()) {
+                push(start, end, ERROR, &format!("duplicate key `{key}`"));
+            }
+        }
+        if let Ok(spans) = find_text_outside_entries(&mut text.clone().into_bytes()) {
+            for span in spans {
+                push(span.start, span.end, WARNING, "text outside of an entry is ignored");
+            }
+        }
+        //Errors with a location are reported above, the rest has no better place than the start of the file
+        match parse_r3locale_bytes(&mut text.clone().into_bytes()) {
+            Ok(_) | Err(ParseR3Error::DuplicateKeys | ParseR3Error::TextOutsideEntry) => (),
+            Err(error) => push(0, 0, ERROR, &format!("{error:?}")),
+        }
+
+        notification(
+            "textDocument/publishDiagnostics",
+            &format!("{{\"uri\":{},\"diagnostics\":[{}]}}", quote(uri), diagnostics.join(",")),
+        )
+    }
+
+    //Jumps to the same key in the other languages, or to its header when the cursor is on a mention of the key
+    fn definition(&self, uri: &str, position: Position) -> String {
+        let Some(text) = self.text(uri) else {
+            return "null".to_string();
+        };
+        let Some(key) = key_at(&text, position) else {
+            return "null".to_string();
+        };
+        let mut files = vec![(uri.to_string(), text)];
+        files.extend(self.siblings(uri));
+
+        let locations: Vec<String> = files
+            .iter()
+            .flat_map(|(file_uri, file_text)| {
+                key_headers(file_text)
+                    .into_iter()
+                    .filter(|(header_key, _, _)| *header_key == key)
+                    .map(move |(_, start, end)| {
+                        format!("{{\"uri\":{},\"range\":{}}}", quote(file_uri), range(file_text, start, end))
+                    })
+            })
+            .collect();
+        format!("[{}]", locations.join(","))
+    }
+
+    //Shows the value of the key in every language next to the file
+    fn hover(&self, uri: &str, position: Position) -> String {
+        let Some(key) = self.text(uri).and_then(|text| key_at(&text, position)) else {
+            return "null".to_string();
+        };
+        let mut lines = Vec::new();
+        for (sibling_uri, sibling_text) in self.siblings(uri) {
+            let Ok(document) = LocaleDocument::parse(&sibling_text) else {
+                continue;
+            };
+            let Some(entry) = document.find(&key) else {
+                continue;
+            };
+            let language = document.language().unwrap_or_else(|| {
+                uri_to_path(&sibling_uri)
+                    .and_then(|path| Some(Path::new(&path).file_stem()?.to_string_lossy().into_owned()))
+                    .unwrap_or_default()
+            });
+            lines.push(format!("**{language}**: {}", entry.parsed_value().replace('\n', "  \n")));
+        }
+        if lines.is_empty() {
+            return "null".to_string();
+        }
+        format!(
+            "{{\"contents\":{{\"kind\":\"markdown\",\"value\":{}}}}}",
+            quote(&lines.join("\n\n"))
+        )
+    }
+
+    //Keys of the other languages that this file doesn't have yet
+    fn completion(&self, uri: &str) -> String {
+        //Keys of this file and keys already offered
+        let mut skipped: HashSet<String> = self
+            .text(uri)
+            .map(|text| key_headers(&text).into_iter().map(|(key, _, _)| key).collect())
+            .unwrap_or_default();
+        let mut items: Vec<String> = Vec::new();
+        for (_, sibling_text) in self.siblings(uri) {
+            let Ok(document) = LocaleDocument::parse(&sibling_text) else {
+                continue;
+            };
+            for entry in &document.entries {
+                if !skipped.insert(entry.key.clone()) {
+                    continue;
+                }
+                items.push(format!(
+                    "{{\"label\":{},\"kind\":14,\"detail\":{}}}",
+                    quote(&entry.key),
+                    quote(&entry.parsed_value())
+                ));
+            }
+        }
+        format!("[{}]", items.join(","))
+    }
+}
@@ -0,0 +1,74 @@
+use reloaded3_localisation::{FormatRegistry, LineEnding};
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let registry = FormatRegistry::with_builtin_formats();
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+    };
+    let paths: Vec<&String> = args
+        .iter()
+        .enumerate()
+        .filter(|(index, arg)| {
+            !arg.starts_with("--") && (*index == 0 || !matches!(args[index - 1].as_str(), "--from" | "--to"))
+        })
+        .map(|(_, arg)| arg)
+        .collect();
+    let [input, output] = paths[..] else {
+        let names: Vec<&str> = registry.formats().map(|format| format.name()).collect();
+        eprintln!("r3l convert: expected an input and an output file, known formats: {}", names.join(", "));
+        return ExitCode::from(2);
+    };
+
+    //Formats are picked from the file extensions unless given with --from and --to
+    let from = match option("--from") {
+        Some(name) => registry.by_name(name),
+        None => registry.for_path(Path::new(input)),
+    };
+    let to = match option("--to") {
+        Some(name) => registry.by_name(name),
+        None => registry.for_path(Path::new(output)),
+    };
+    let (Some(from), Some(to)) = (from, to) else {
+        eprintln!("r3l convert: unknown format, use --from and --to");
+        return ExitCode::from(2);
+    };
+
+    let bytes = match fs::read(input) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("{input}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let converted = match from
+        .read(&bytes)
+        .and_then(|document| to.write_with_line_ending(&document, line_ending(args)))
+    {
+        Ok(converted) => converted,
+        Err(error) => {
+            eprintln!("r3l convert: {error:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(error) = fs::write(output, converted) {
+        eprintln!("{output}: {error}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+//`--lf` or `--crlf`, otherwise the input's line ending is kept
+pub fn line_ending(args: &[String]) -> LineEnding {
+    if args.iter().any(|arg| arg == "--crlf") {
+        LineEnding::CrLf
+    } else if args.iter().any(|arg| arg == "--lf") {
+        LineEnding::Lf
+    } else {
+        LineEnding::Preserve
+    }
+}
@@ -0,0 +1,36 @@
+use reloaded3_localisation::LocaleDocument;
+use reloaded3_localisation::locale_api::diff::{diff_documents, render_json, render_patch};
+use std::fs;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let patch = args.iter().any(|arg| arg == "--patch");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [old_path, new_path] = paths[..] else {
+        eprintln!("r3l diff: expected an old and a new file");
+        return ExitCode::from(2);
+    };
+
+    let Some(old) = read_document(old_path) else {
+        return ExitCode::FAILURE;
+    };
+    let Some(new) = read_document(new_path) else {
+        return ExitCode::FAILURE;
+    };
+    let changes = diff_documents(&old, &new);
+    if patch {
+        print!("{}", render_patch(old_path, new_path, &changes));
+    } else {
+        print!("{}", render_json(&changes));
+    }
+    ExitCode::SUCCESS
+}
+
+fn read_document(path: &str) -> Option<LocaleDocument> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| eprintln!("{path}: {error}"))
+        .ok()?;
+    LocaleDocument::parse(&text)
+        .map_err(|error| eprintln!("{path}: {error:?}"))
+        .ok()
+}
@@ -0,0 +1,52 @@
+use crate::convert::line_ending;
+use reloaded3_localisation::{LocaleDocument, WriteOptions};
+use std::fs;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    if paths.is_empty() {
+        eprintln!("r3l fmt: no files given");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        let mut document = match LocaleDocument::parse(&text) {
+            Ok(document) => document,
+            Err(error) => {
+                eprintln!("{path}: {error:?}");
+                failed = true;
+                continue;
+            }
+        };
+        document.canonicalize();
+        let canonical = document.write(&WriteOptions {
+            line_ending: line_ending(args),
+            ..Default::default()
+        });
+        if canonical == text {
+            continue;
+        }
+
+        if check {
+            //Pipelines only need the list of files to reformat
+            println!("{path}");
+            failed = true;
+        } else if let Err(error) = fs::write(path, canonical) {
+            eprintln!("{path}: {error}");
+            failed = true;
+        }
+    }
+
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -0,0 +1,43 @@
+use reloaded3_localisation::locale_api::string_freeze::{FreezeChange, StringFreeze};
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let Some((frozen, files)) = args.split_first().filter(|(_, files)| !files.is_empty()) else {
+        eprintln!("r3l freeze-check: expected the frozen file and the files to check");
+        return ExitCode::from(2);
+    };
+    let freeze = match StringFreeze::from_file(Path::new(frozen)) {
+        Ok(freeze) => freeze,
+        Err(error) => {
+            eprintln!("{frozen}: {error:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let version = freeze.version.as_deref().unwrap_or("the freeze");
+
+    let mut failed = false;
+    for path in files {
+        let checked = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| freeze.check(&text).map_err(|error| format!("{error:?}")));
+        let violations = match checked {
+            Ok(violations) => violations,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        for violation in &violations {
+            let change = match violation.change {
+                FreezeChange::Added => "added",
+                FreezeChange::Changed => "changed",
+            };
+            println!("{path}: {} {change} after {version}", violation.key);
+        }
+        failed |= !violations.is_empty();
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -0,0 +1,71 @@
+use reloaded3_localisation::LocaleDocument;
+use reloaded3_localisation::locale_api::analysis::{extract_terms, glossary_document};
+use std::fs;
+use std::process::ExitCode;
+
+//Terms used fewer times than this are usually not worth a glossary entry
+const DEFAULT_MIN_OCCURRENCES: usize = 3;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let Some((action, rest)) = args.split_first() else {
+        eprintln!("r3l glossary: expected `extract`");
+        return ExitCode::from(2);
+    };
+    if action != "extract" {
+        eprintln!("r3l glossary: unknown action `{action}`");
+        return ExitCode::from(2);
+    }
+
+    let mut min_occurrences = DEFAULT_MIN_OCCURRENCES;
+    let mut paths: Vec<&String> = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--min" {
+            match rest.next().and_then(|count| count.parse().ok()) {
+                Some(count) => min_occurrences = count,
+                None => {
+                    eprintln!("r3l glossary: --min expects a number");
+                    return ExitCode::from(2);
+                }
+            }
+        } else {
+            paths.push(arg);
+        }
+    }
+    let (source_path, output_path) = match paths.as_slice() {
+        [source] => (source, None),
+        [source, output] => (source, Some(output)),
+        _ => {
+            eprintln!("r3l glossary: expected a source file and an optional output file");
+            return ExitCode::from(2);
+        }
+    };
+
+    let text = match fs::read_to_string(source_path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("{source_path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let document = match LocaleDocument::parse(&text) {
+        Ok(document) => document,
+        Err(error) => {
+            eprintln!("{source_path}: {error:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let terms = extract_terms(&document, min_occurrences);
+    let glossary = glossary_document(document.language().as_deref(), &terms).to_string();
+    match output_path {
+        Some(output_path) => {
+            if let Err(error) = fs::write(output_path, glossary) {
+                eprintln!("{output_path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{glossary}"),
+    }
+    ExitCode::SUCCESS
+}
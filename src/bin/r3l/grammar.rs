@@ -0,0 +1,24 @@
+use reloaded3_localisation::textmate_grammar;
+use std::fs;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let grammar = textmate_grammar();
+    match args {
+        [] => {
+            print!("{grammar}");
+            ExitCode::SUCCESS
+        }
+        [output] => match fs::write(output, grammar) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("{output}: {error}");
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("r3l grammar: expected at most one output file");
+            ExitCode::from(2)
+        }
+    }
+}
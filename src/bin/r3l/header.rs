@@ -0,0 +1,26 @@
+use reloaded3_localisation::{HeaderLanguage, generate_cpp_wrapper, generate_header};
+use std::path::Path;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let language = if args.iter().any(|arg| arg == "--cpp") { HeaderLanguage::Cpp } else { HeaderLanguage::C };
+    let wrapper = args.iter().any(|arg| arg == "--cpp-wrapper");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [output] = paths[..] else {
+        eprintln!("r3l header: expected an output file");
+        return ExitCode::from(2);
+    };
+
+    let generated = if wrapper {
+        generate_cpp_wrapper(Path::new(output))
+    } else {
+        generate_header(Path::new(output), language)
+    };
+    match generated {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{output}: {error:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
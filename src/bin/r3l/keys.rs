@@ -0,0 +1,25 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+//Keys are stored as hex text, so they can be pasted into a mod's or game's source
+pub fn read_key(path: &str) -> Option<[u8; 32]> {
+    let text = fs::read_to_string(path).ok()?;
+    let text = text.trim();
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+    let mut key = [0; 32];
+    for (index, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(key)
+}
+
+pub fn write_key(path: &str, key: &[u8]) -> io::Result<()> {
+    let hex = key.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    });
+    fs::write(path, hex + "\n")
+}
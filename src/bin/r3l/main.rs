@@ -0,0 +1,84 @@
+mod convert;
+mod diff;
+mod fmt;
+mod freeze;
+mod glossary;
+mod grammar;
+#[cfg(feature = "cbindgen")]
+mod header;
+#[cfg(any(feature = "encryption", feature = "signatures"))]
+mod keys;
+mod merge;
+mod rename;
+mod report;
+#[cfg(feature = "encryption")]
+mod seal;
+#[cfg(feature = "signatures")]
+mod sign;
+mod watch;
+
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: r3l <command> [options] <files...>
+
+Commands:
+  convert [--from <format>] [--to <format>] [--lf|--crlf] <input> <output>
+                   Convert between .r3l and other formats, picked from the file extensions by default.
+                   Line endings of the input are kept unless --lf or --crlf is given
+  diff [--patch] <old> <new>
+                   List changed entries as JSON, or as a unified diff style patch for review threads
+  fmt [--check] [--lf|--crlf] <files...>
+                   Rewrite files in canonical form, or only report files that are not
+  freeze-check <frozen> <files...>
+                   List keys added or changed since the string freeze in <frozen>, fails if there are any
+  glossary extract [--min <count>] <source> [output]
+                   Write frequent names and phrases of a file as a glossary to fill in
+  grammar [output]
+                   Write the TextMate grammar for .r3l files, to stdout if no output is given
+  header [--cpp | --cpp-wrapper] <output>
+                   Write the C or C++ header for this build, or the C++ header with the RAII wrapper,
+                   needs the cbindgen feature
+  merge [--dry-run] <output> <inputs...>
+                   Merge files with the first input winning, or only list what would be overridden
+  rename-key <old> <new> <files...>
+                   Rename a key and its variants in every file of a language pack
+  report [--html] [--latin1|--ascii] <source> <translations...>
+                   Print coverage and validation results for each translation
+  seal <key file> <input> <output> | seal --new-key <key file>
+                   Encrypt a file into a sealed .r3lb pack, or write a new random key as hex,
+                   needs the encryption feature
+  sign <key file> <packs...> | sign --new-key <key file>
+                   Write a .sig file next to each pack, or a new signing key and its public key as hex,
+                   needs the signatures feature
+  verify <public key file> <packs...>
+                   Check packs against their .sig files, needs the signatures feature
+  watch [--source <file>] <directory>
+                   Validate .r3l files again whenever they are saved, linting against the source file if given";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, rest)) if command == "convert" => convert::run(rest),
+        Some((command, rest)) if command == "diff" => diff::run(rest),
+        Some((command, rest)) if command == "fmt" => fmt::run(rest),
+        Some((command, rest)) if command == "freeze-check" => freeze::run(rest),
+        Some((command, rest)) if command == "glossary" => glossary::run(rest),
+        Some((command, rest)) if command == "grammar" => grammar::run(rest),
+        #[cfg(feature = "cbindgen")]
+        Some((command, rest)) if command == "header" => header::run(rest),
+        Some((command, rest)) if command == "merge" => merge::run(rest),
+        Some((command, rest)) if command == "rename-key" => rename::run(rest),
+        Some((command, rest)) if command == "report" => report::run(rest),
+        #[cfg(feature = "encryption")]
+        Some((command, rest)) if command == "seal" => seal::run(rest),
+        #[cfg(feature = "signatures")]
+        Some((command, rest)) if command == "sign" => sign::run(rest),
+        #[cfg(feature = "signatures")]
+        Some((command, rest)) if command == "verify" => sign::run_verify(rest),
+        Some((command, rest)) if command == "watch" => watch::run(rest),
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
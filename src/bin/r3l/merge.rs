@@ -0,0 +1,80 @@
+use reloaded3_localisation::{LocaleDocument, merge_preview, parse_r3locale_bytes};
+use std::fs;
+use std::process::ExitCode;
+use xxhash_rust::xxh3::xxh3_64;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let Some((output_path, input_paths)) = paths.split_first() else {
+        eprintln!("r3l merge: expected an output file followed by the inputs, highest priority first");
+        return ExitCode::from(2);
+    };
+    if input_paths.is_empty() {
+        eprintln!("r3l merge: no input files given");
+        return ExitCode::from(2);
+    }
+
+    let mut texts = Vec::with_capacity(input_paths.len());
+    for path in input_paths {
+        match fs::read_to_string(path) {
+            Ok(text) => texts.push(text),
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let mut documents = Vec::with_capacity(texts.len());
+    for (path, text) in input_paths.iter().zip(&texts) {
+        match LocaleDocument::parse(text) {
+            Ok(document) => documents.push(document),
+            Err(error) => {
+                eprintln!("{path}: {error:?}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if dry_run {
+        return print_preview(input_paths, &texts, &documents);
+    }
+    let references: Vec<&LocaleDocument> = documents.iter().collect();
+    if let Err(error) = fs::write(output_path, LocaleDocument::merge(&references).to_string()) {
+        eprintln!("{output_path}: {error}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+//Uses the same preview the runtime merge would, with key names taken from the documents
+fn print_preview(paths: &[&String], texts: &[String], documents: &[LocaleDocument]) -> ExitCode {
+    let mut tables = Vec::with_capacity(texts.len());
+    for (path, text) in paths.iter().zip(texts) {
+        let mut bytes = text.clone().into_bytes();
+        match parse_r3locale_bytes(&mut bytes) {
+            Ok(table) => tables.push(table),
+            Err(error) => {
+                eprintln!("{path}: {error:?}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let references: Vec<_> = tables.iter().collect();
+    let preview = merge_preview(&references);
+
+    println!("{} entries after merging", preview.entry_count);
+    for (index, path) in paths.iter().enumerate() {
+        println!("{path}: {} of {} entries used", preview.contributed[index], documents[index].entries.len());
+    }
+    for conflict in &preview.overrides {
+        let key = documents[conflict.overridden]
+            .entries
+            .iter()
+            .find(|entry| xxh3_64(entry.key.as_bytes()) == conflict.key)
+            .map_or("<unknown>", |entry| entry.key.as_str());
+        let note = if conflict.same_value { " (same value)" } else { "" };
+        println!("{key}: {} overrides {}{note}", paths[conflict.winner], paths[conflict.overridden]);
+    }
+    ExitCode::SUCCESS
+}
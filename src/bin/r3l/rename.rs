@@ -0,0 +1,60 @@
+use reloaded3_localisation::{LocaleDocument, RenameIssue};
+use reloaded3_localisation::locale_api::refactor::{rename_key, rename_key_in_text};
+use std::fs;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let [old, new, paths @ ..] = args else {
+        eprintln!("r3l rename-key: expected the old key, the new key and the files of the pack");
+        return ExitCode::from(2);
+    };
+    if paths.is_empty() {
+        eprintln!("r3l rename-key: no files given");
+        return ExitCode::from(2);
+    }
+
+    let mut texts = Vec::with_capacity(paths.len());
+    let mut documents = Vec::with_capacity(paths.len());
+    for path in paths {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        };
+        match LocaleDocument::parse(&text) {
+            Ok(document) => documents.push(document),
+            Err(error) => {
+                eprintln!("{path}: {error:?}");
+                return ExitCode::FAILURE;
+            }
+        }
+        texts.push(text);
+    }
+
+    //Checked on every file first, so a conflict never leaves the pack half renamed
+    let report = rename_key(&mut documents, old, new);
+    if !report.issues.is_empty() {
+        for issue in &report.issues {
+            if let RenameIssue::Conflict { document, key } = issue {
+                eprintln!("{}: `{key}` already exists", paths[*document]);
+            }
+        }
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    for (path, text) in paths.iter().zip(&texts) {
+        let renamed = rename_key_in_text(text, old, new);
+        if renamed == *text {
+            continue;
+        }
+        if let Err(error) = fs::write(path, renamed) {
+            eprintln!("{path}: {error}");
+            failed = true;
+        }
+    }
+    println!("Renamed {} entries", report.renamed);
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -0,0 +1,52 @@
+use reloaded3_localisation::{CharacterSet, LanguageReport, LocaleDocument, Report, ReportFormat};
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let format = if args.iter().any(|arg| arg == "--html") { ReportFormat::Html } else { ReportFormat::Markdown };
+    let allowed = if args.iter().any(|arg| arg == "--latin1") {
+        Some(CharacterSet::latin1())
+    } else if args.iter().any(|arg| arg == "--ascii") {
+        Some(CharacterSet::ascii())
+    } else {
+        None
+    };
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let Some((source_path, translation_paths)) = paths.split_first() else {
+        eprintln!("r3l report: expected a source file followed by translations");
+        return ExitCode::from(2);
+    };
+
+    let Some(source) = read_document(source_path) else {
+        return ExitCode::FAILURE;
+    };
+    let mut report = Report::default();
+    for path in translation_paths {
+        let Some(translation) = read_document(path) else {
+            return ExitCode::FAILURE;
+        };
+        //Files without an `@language` header are named after the file, e.g. `fr.r3l`
+        let language = translation.language().unwrap_or_else(|| {
+            Path::new(path.as_str())
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        report
+            .languages
+            .push(LanguageReport::compare(&language, &source, &translation, allowed.as_ref()));
+    }
+
+    print!("{}", report.render(format));
+    ExitCode::SUCCESS
+}
+
+fn read_document(path: &str) -> Option<LocaleDocument> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| eprintln!("{path}: {error}"))
+        .ok()?;
+    LocaleDocument::parse(&text)
+        .map_err(|error| eprintln!("{path}: {error:?}"))
+        .ok()
+}
@@ -0,0 +1,42 @@
+use crate::keys::{read_key, write_key};
+use reloaded3_localisation::{generate_pack_key, seal_locale_bytes};
+use std::fs;
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    let (key_path, input, output) = match args {
+        [flag, path] if flag == "--new-key" => {
+            return match write_key(path, &generate_pack_key()) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(error) => {
+                    eprintln!("{path}: {error}");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        [key_path, input, output] => (key_path, input, output),
+        _ => {
+            eprintln!("r3l seal: expected a key file, an input and an output");
+            return ExitCode::from(2);
+        }
+    };
+
+    let Some(key) = read_key(key_path) else {
+        eprintln!("{key_path}: expected 64 hex characters");
+        return ExitCode::FAILURE;
+    };
+    let text = match fs::read(input) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("{input}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match fs::write(output, seal_locale_bytes(&text, &key)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{output}: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
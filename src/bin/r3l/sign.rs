@@ -0,0 +1,62 @@
+use crate::keys::{read_key, write_key};
+use reloaded3_localisation::{generate_signing_key, public_key, sign_pack, verify_pack};
+use std::path::Path;
+use std::process::ExitCode;
+
+//`r3l sign --new-key official.key` also writes the public key to `official.key.pub`
+fn new_key(path: &str) -> ExitCode {
+    let signing_key = generate_signing_key();
+    let public_path = format!("{path}.pub");
+    for (path, key) in [(path, signing_key), (public_path.as_str(), public_key(&signing_key))] {
+        if let Err(error) = write_key(path, &key) {
+            eprintln!("{path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn run(args: &[String]) -> ExitCode {
+    let (key_path, packs) = match args {
+        [flag, path] if flag == "--new-key" => return new_key(path),
+        [key_path, packs @ ..] if !packs.is_empty() => (key_path, packs),
+        _ => {
+            eprintln!("r3l sign: expected a key file and packs");
+            return ExitCode::from(2);
+        }
+    };
+    let Some(signing_key) = read_key(key_path) else {
+        eprintln!("{key_path}: expected 64 hex characters");
+        return ExitCode::FAILURE;
+    };
+
+    let mut failed = false;
+    for pack in packs {
+        if let Err(error) = sign_pack(Path::new(pack), &signing_key) {
+            eprintln!("{pack}: {error}");
+            failed = true;
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+//Lists every pack that doesn't match its signature, for checking a release before it is uploaded
+pub fn run_verify(args: &[String]) -> ExitCode {
+    let [key_path, packs @ ..] = args else {
+        eprintln!("r3l verify: expected a public key file and packs");
+        return ExitCode::from(2);
+    };
+    let Some(public_key) = read_key(key_path) else {
+        eprintln!("{key_path}: expected 64 hex characters");
+        return ExitCode::FAILURE;
+    };
+
+    let mut failed = false;
+    for pack in packs {
+        if let Err(error) = verify_pack(Path::new(pack), &public_key) {
+            eprintln!("{pack}: {error:?}");
+            failed = true;
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
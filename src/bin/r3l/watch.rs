@@ -0,0 +1,117 @@
+use reloaded3_localisation::locale_api::lints::lint_translation;
+use reloaded3_localisation::locale_api::parser::{ENTRY_WARNING_THRESHOLD, MAX_ENTRIES};
+use reloaded3_localisation::{LocaleDocument, find_text_outside_entries, parse_r3locale_bytes};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+//Polling keeps this free of platform specific file watching, and is still quick enough to feel instant after a save
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+pub fn run(args: &[String]) -> ExitCode {
+    let source_path = args
+        .iter()
+        .position(|arg| arg == "--source")
+        .and_then(|index| args.get(index + 1));
+    let directories: Vec<&String> = args
+        .iter()
+        .enumerate()
+        .filter(|(index, arg)| !arg.starts_with("--") && (*index == 0 || args[index - 1] != "--source"))
+        .map(|(_, arg)| arg)
+        .collect();
+    let [directory] = directories[..] else {
+        eprintln!("r3l watch: expected one directory");
+        return ExitCode::from(2);
+    };
+
+    println!("Watching {directory} for changes, press Ctrl+C to stop");
+    let mut modified: HashMap<PathBuf, SystemTime> = HashMap::new();
+    loop {
+        let mut files = Vec::new();
+        if let Err(error) = collect_files(Path::new(directory), &mut files) {
+            eprintln!("{directory}: {error}");
+            return ExitCode::FAILURE;
+        }
+        //A changed source can produce new lints in every translation
+        let source_changed = source_path.is_some_and(|source| {
+            files
+                .iter()
+                .any(|(path, time)| path == Path::new(source) && modified.get(path) != Some(time))
+        });
+        let source = source_path.and_then(|path| LocaleDocument::parse(&fs::read_to_string(path).ok()?).ok());
+
+        for (path, time) in &files {
+            if modified.get(path) == Some(time) && !source_changed {
+                continue;
+            }
+            modified.insert(path.clone(), *time);
+            let is_source = source_path.is_some_and(|source| Path::new(source) == path);
+            let diagnostics = validate(path, if is_source { None } else { source.as_ref() });
+            if diagnostics.is_empty() {
+                println!("{}: ok", path.display());
+            }
+            for diagnostic in diagnostics {
+                println!("{}{diagnostic}", path.display());
+            }
+        }
+        modified.retain(|path, _| files.iter().any(|(file, _)| file == path));
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+//Files and directories deleted while the tree is being walked are skipped
+fn collect_files(directory: &Path, files: &mut Vec<(PathBuf, SystemTime)>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files).or_else(skip_not_found)?;
+        } else if path.extension().is_some_and(|extension| extension == "r3l") {
+            match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => files.push((path, modified)),
+                Err(error) => skip_not_found(error)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn skip_not_found(error: io::Error) -> io::Result<()> {
+    if error.kind() == io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+//Messages start with `:line:` or `:` so they can be printed right after the path
+fn validate(path: &Path, source: Option<&LocaleDocument>) -> Vec<String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => return vec![format!(": {error}")],
+    };
+    let table = match parse_r3locale_bytes(&mut text.clone().into_bytes()) {
+        Ok(table) => table,
+        Err(error) => return vec![format!(": {error:?}")],
+    };
+
+    let mut diagnostics = Vec::new();
+    if table.entries.len() >= ENTRY_WARNING_THRESHOLD {
+        diagnostics.push(format!(": {} entries, close to the limit of {MAX_ENTRIES}", table.entries.len()));
+    }
+    if let Ok(spans) = find_text_outside_entries(&mut text.clone().into_bytes()) {
+        for span in spans {
+            let line = text[..span.start].matches('\n').count() + 1;
+            diagnostics.push(format!(":{line}: text outside of an entry"));
+        }
+    }
+    if let (Some(source), Ok(document)) = (source, LocaleDocument::parse(&text)) {
+        for lint in lint_translation(source, &document) {
+            diagnostics.push(format!(": `{}`: {}", lint.key, lint.kind.description()));
+        }
+    }
+    diagnostics
+}
@@ -1,15 +1,251 @@
 pub mod locale_api;
+pub use locale_api::analysis::DuplicateGroup;
+pub use locale_api::analysis::DuplicateReport;
+pub use locale_api::analysis::LengthOutlier;
+pub use locale_api::analysis::LengthRatios;
+pub use locale_api::analysis::QualityReport;
+pub use locale_api::analysis::SimilarityCluster;
+pub use locale_api::analysis::Term;
+pub use locale_api::annotations::Annotation;
+pub use locale_api::annotations::Deprecation;
+pub use locale_api::annotations::EntryConstraints;
+pub use locale_api::annotations::WrapHint;
+pub use locale_api::articles::Article;
+pub use locale_api::blocks::BlockLine;
+pub use locale_api::blocks::BlockTracker;
+pub use locale_api::blocks::is_block_delimiter;
+pub use locale_api::blocks::needs_block;
+pub use locale_api::cancellation::CancellationToken;
+pub use locale_api::dialect::Dialect;
+pub use locale_api::dialect::detect_dialect;
+pub use locale_api::dialect::format_spec;
+pub use locale_api::dialect::upgrade_dialect;
+pub use locale_api::diff::EntryChange;
+pub use locale_api::document::DocumentEntry;
+pub use locale_api::document::KeyOrder;
+pub use locale_api::document::LineEnding;
+pub use locale_api::document::LocaleDocument;
+pub use locale_api::document::WriteOptions;
+pub use locale_api::editor::EditorError;
+pub use locale_api::editor::LocaleEditor;
+pub use locale_api::editor::OverrideEntry;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::PACK_KEY_LEN;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::SEALED_PACK_EXTENSION;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::generate_pack_key;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::get_sealed_locale_table;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::is_sealed_pack;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::open_sealed_pack;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::parse_sealed_pack;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::parse_sealed_pack_file;
+#[cfg(feature = "encryption")]
+pub use locale_api::encryption::seal_locale_bytes;
+pub use locale_api::formats::FormatError;
+pub use locale_api::formats::fidelity::FidelityIssue;
+pub use locale_api::formats::fidelity::FidelityReport;
+pub use locale_api::formats::FormatRegistry;
+pub use locale_api::formats::LocaleFormat;
+pub use locale_api::formats::json::JsonFlavor;
+pub use locale_api::formats::yaml::YamlOptions;
+pub use locale_api::experiments::CONTROL_VARIANT;
+pub use locale_api::experiments::ServedVariant;
+pub use locale_api::experiments::ServedVariantResult;
+pub use locale_api::experiments::VariantCount;
+pub use locale_api::experiments::VariantReport;
+pub use locale_api::experiments::r3l_find_entry_for_user;
+pub use locale_api::experiments::variant_index;
+pub use locale_api::formatting::Unit;
+pub use locale_api::formatting::UnitWidth;
+#[cfg(feature = "cbindgen")]
+pub use locale_api::headers::HeaderError;
+#[cfg(feature = "cbindgen")]
+pub use locale_api::headers::HeaderLanguage;
+#[cfg(feature = "cbindgen")]
+pub use locale_api::headers::generate_c_header;
+#[cfg(feature = "cbindgen")]
+pub use locale_api::headers::generate_cpp_wrapper;
+#[cfg(feature = "cbindgen")]
+pub use locale_api::headers::generate_header;
 pub use locale_api::interop::AllocationResult;
 pub use locale_api::interop::FindEntryError;
 pub use locale_api::interop::FindEntryResult;
+pub use locale_api::interop::FreeTableError;
 pub use locale_api::interop::LocaleTable;
+pub use locale_api::interop::LocaleTableSnapshot;
+pub use locale_api::interop::ProgressCallback;
 pub use locale_api::interop::TableEntry;
+pub use locale_api::interop::TableFrozenError;
 pub use locale_api::interop::free_locale_table;
 pub use locale_api::interop::get_entry;
 pub use locale_api::interop::get_locale_table;
 pub use locale_api::interop::get_multiple_locale_tables;
+pub use locale_api::interop::get_multiple_locale_tables_cancellable;
+pub use locale_api::interop::get_multiple_locale_tables_with_progress;
+pub use locale_api::interop::locale_table_clone;
 pub use locale_api::interop::merge_locale_table_c;
+pub use locale_api::interop::merge_locale_table_c_cancellable;
+pub use locale_api::interop::r3l_capabilities;
+pub use locale_api::interop::r3l_freeze_table;
+pub use locale_api::interop::r3l_is_frozen;
+pub use locale_api::interop::r3l_report_leaks;
+pub use locale_api::interop::unload_locale_table;
+pub use locale_api::key_filter::KeyFilter;
+pub use locale_api::keys::KeyId;
+pub use locale_api::keys::intern_key;
+pub use locale_api::keys::interned_key_count;
+pub use locale_api::keys::key_text;
+pub use locale_api::lints::ConfusableCharacter;
+pub use locale_api::lints::InvisibleCharacter;
+pub use locale_api::lints::InvisibleKind;
+pub use locale_api::lints::Lint;
+pub use locale_api::lints::LintKind;
+pub use locale_api::lints::LintOptions;
+pub use locale_api::lints::find_confusable_keys;
+pub use locale_api::lints::find_invisible_characters;
+#[cfg(feature = "lua")]
+pub use locale_api::lua::register_locale;
+pub use locale_api::manager::DirectoryLoad;
+pub use locale_api::manager::LanguageInfo;
+pub use locale_api::manager::LocaleManager;
+pub use locale_api::migration::KeyMigration;
+pub use locale_api::migration::KeyMigrations;
+pub use locale_api::migration::MigrationError;
+pub use locale_api::migration::MigrationRelease;
+pub use locale_api::migration::migrate_table;
+#[cfg(feature = "net")]
+pub use locale_api::net::InstallError;
+#[cfg(feature = "net")]
+pub use locale_api::net::MAX_PACK_SIZE;
+#[cfg(feature = "net")]
+pub use locale_api::net::install_pack;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::net::install_signed_pack;
+#[cfg(feature = "net")]
+pub use locale_api::net::r3l_install_pack;
+pub use locale_api::overlay::CowLocaleTable;
+pub use locale_api::parser::ConfusablePolicy;
+pub use locale_api::parser::EmptyValuePolicy;
+pub use locale_api::parser::InlineComments;
+pub use locale_api::parser::MergeOverride;
+pub use locale_api::parser::MergePreview;
 pub use locale_api::parser::MergeResult;
 pub use locale_api::parser::MergeTableError;
+pub use locale_api::parser::ParseFailure;
+pub use locale_api::parser::ParseOptions;
 pub use locale_api::parser::ParseR3Error;
+pub use locale_api::parser::TrimPolicy;
+pub use locale_api::parser::find_text_outside_entries;
+pub use locale_api::parser::merge_locale_tables;
+pub use locale_api::parser::merge_locale_tables_cancellable;
+pub use locale_api::parser::merge_preview;
 pub use locale_api::parser::parse_r3locale_bytes;
+pub use locale_api::parser::parse_r3locale_bytes_with_options;
+pub use locale_api::parser::parse_r3locale_bytes_with_spans;
+pub use locale_api::plurals::PluralCategory;
+pub use locale_api::plurals::plural_category;
+pub use locale_api::plurals::r3l_get_plural;
+pub use locale_api::preflight::PreflightFile;
+pub use locale_api::preflight::PreflightIssue;
+pub use locale_api::preflight::PreflightReport;
+pub use locale_api::preflight::preflight;
+pub use locale_api::preflight::preflight_file;
+pub use locale_api::preview::PreviewError;
+pub use locale_api::preview::Segment;
+pub use locale_api::preview::SegmentKind;
+pub use locale_api::preview::format_value;
+pub use locale_api::preview::r3l_format_entry_indexed;
+pub use locale_api::preview::snapshot_all;
+pub use locale_api::quarantine::Quarantine;
+pub use locale_api::refactor::RenameIssue;
+pub use locale_api::refactor::RenameReport;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::MAX_OVERRIDE_SIZE;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::OverrideSource;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::RemoteOverrideOptions;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::RemoteOverrides;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::RemoteOverridesResult;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::load_remote_overrides;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub use locale_api::remote::r3l_load_remote_overrides;
+pub use locale_api::report::LanguageReport;
+pub use locale_api::report::Report;
+pub use locale_api::report::ReportFormat;
+pub use locale_api::runtime::InitError;
+pub use locale_api::runtime::LogCallback;
+pub use locale_api::runtime::LogLevel;
+pub use locale_api::runtime::MissingKeyPolicy;
+pub use locale_api::runtime::PanicCallback;
+pub use locale_api::runtime::R3lConfig;
+pub use locale_api::runtime::current_config;
+pub use locale_api::runtime::r3l_init;
+pub use locale_api::runtime::r3l_set_log_callback;
+pub use locale_api::runtime::r3l_shutdown;
+pub use locale_api::scope::ModScope;
+pub use locale_api::scope::SHARED_NAMESPACE;
+pub use locale_api::script::Direction;
+pub use locale_api::script::LanguageMismatch;
+pub use locale_api::script::Script;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::PUBLIC_KEY_LEN;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::SIGNATURE_EXTENSION;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::SIGNATURE_LEN;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::SIGNING_KEY_LEN;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::SignatureError;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::generate_signing_key;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::public_key;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::r3l_verify_pack;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::sign_pack;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::sign_pack_bytes;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::signature_path;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::verify_pack;
+#[cfg(feature = "signatures")]
+pub use locale_api::signing::verify_pack_bytes;
+pub use locale_api::stats::LookupStats;
+pub use locale_api::stats::LookupStatsResult;
+pub use locale_api::stats::r3l_export_usage_csv;
+pub use locale_api::stats::r3l_lookup_stats;
+pub use locale_api::stats::r3l_recent_misses;
+pub use locale_api::stats::r3l_set_lookup_stats;
+pub use locale_api::status::StatusCode;
+pub use locale_api::status::r3l_find_error_code;
+pub use locale_api::status::r3l_merge_error_code;
+pub use locale_api::status::r3l_parse_error_code;
+pub use locale_api::status::r3l_status_name;
+pub use locale_api::string_freeze::FreezeChange;
+pub use locale_api::string_freeze::FreezeViolation;
+pub use locale_api::string_freeze::StringFreeze;
+pub use locale_api::syntax::Token;
+pub use locale_api::syntax::TokenKind;
+pub use locale_api::syntax::textmate_grammar;
+pub use locale_api::syntax::tokenize;
+pub use locale_api::validation::BlockedTermMatch;
+pub use locale_api::validation::BlockedTerms;
+pub use locale_api::validation::CharacterSet;
+pub use locale_api::validation::CharacterViolation;
+pub use locale_api::validation::DeprecatedTranslation;
+pub use locale_api::validation::FormattingIssue;
+pub use locale_api::variants::Case;
+pub use locale_api::variants::Gender;
@@ -0,0 +1,545 @@
+use super::annotations::{Annotation, format_annotations};
+use super::document::{DocumentEntry, LocaleDocument};
+use super::formatting::primary_language;
+use super::parser::{ValuePart, split_placeholders};
+use super::script::stopwords;
+use hashbrown::HashMap;
+use std::borrow::Cow;
+
+//Keys that share one value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub value: String,
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DuplicateReport {
+    //Copy-pasted strings that could be consolidated into one key, in file order
+    pub within_locale: Vec<DuplicateGroup>,
+    //Keys whose value is identical to the reference language, often strings nobody translated
+    pub same_as_reference: Vec<String>,
+}
+
+//Empty values are skipped, they are reported by EmptyValuePolicy instead
+pub fn find_duplicate_values(document: &LocaleDocument, reference: Option<&LocaleDocument>) -> DuplicateReport {
+    let values: Vec<Cow<'_, str>> = document.entries.iter().map(DocumentEntry::parsed_value).collect();
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut group_index: HashMap<&str, usize> = HashMap::new();
+    for (entry, value) in document.entries.iter().zip(&values).filter(|(_, value)| !value.is_empty()) {
+        match group_index.get(&**value) {
+            Some(&index) => groups[index].keys.push(entry.key.clone()),
+            None => {
+                group_index.insert(&**value, groups.len());
+                groups.push(DuplicateGroup {
+                    value: value.to_string(),
+                    keys: vec![entry.key.clone()],
+                });
+            }
+        }
+    }
+    groups.retain(|group| group.keys.len() > 1);
+
+    let same_as_reference = match reference {
+        Some(reference) => document
+            .entries
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| !value.is_empty())
+            .filter(|(entry, value)| {
+                reference
+                    .find(&entry.key)
+                    .is_some_and(|reference_entry| reference_entry.parsed_value() == **value)
+            })
+            .map(|(entry, _)| entry.key.clone())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    DuplicateReport {
+        within_locale: groups,
+        same_as_reference,
+    }
+}
+
+//Values that differ only slightly, e.g. by a trailing full stop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimilarityCluster {
+    //Key and value pairs in file order
+    pub entries: Vec<(String, String)>,
+}
+
+//Edit distance in characters
+pub fn levenshtein(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+    let mut current = vec![0; right.len() + 1];
+    for (i, left_char) in left.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_char != right_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[right.len()]
+}
+
+//Groups values whose edit distance is at most `max_ratio` of the longer value's length, 0.1 allows one edit per ten characters
+//Identical values are left to find_duplicate_values, and compares are pairwise so this is meant for tooling, not runtime
+pub fn cluster_similar_values(document: &LocaleDocument, max_ratio: f64) -> Vec<SimilarityCluster> {
+    let values: Vec<Cow<'_, str>> = document.entries.iter().map(DocumentEntry::parsed_value).collect();
+    let entries: Vec<(&str, &str, usize)> = document
+        .entries
+        .iter()
+        .zip(&values)
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(entry, value)| (entry.key.as_str(), &**value, value.chars().count()))
+        .collect();
+
+    //Union-find over entry indices
+    let mut parent: Vec<usize> = (0..entries.len()).collect();
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+
+    for i in 0..entries.len() {
+        for j in i + 1..entries.len() {
+            let (_, left, left_length) = entries[i];
+            let (_, right, right_length) = entries[j];
+            let max_distance = (left_length.max(right_length) as f64 * max_ratio) as usize;
+            //The length difference is a lower bound of the distance, skipping most pairs cheaply
+            if left == right || left_length.abs_diff(right_length) > max_distance {
+                continue;
+            }
+            if levenshtein(left, right) <= max_distance {
+                let (left_root, right_root) = (root(&mut parent, i), root(&mut parent, j));
+                parent[right_root] = left_root;
+            }
+        }
+    }
+
+    let mut clusters: Vec<(usize, SimilarityCluster)> = Vec::new();
+    for (index, (key, value, _)) in entries.iter().enumerate() {
+        let cluster_root = root(&mut parent, index);
+        let entry = (key.to_string(), value.to_string());
+        match clusters.iter_mut().find(|(existing_root, _)| *existing_root == cluster_root) {
+            Some((_, cluster)) => cluster.entries.push(entry),
+            None => clusters.push((cluster_root, SimilarityCluster { entries: vec![entry] })),
+        }
+    }
+    clusters
+        .into_iter()
+        .map(|(_, cluster)| cluster)
+        .filter(|cluster| cluster.entries.len() > 1)
+        .collect()
+}
+
+//A name or phrase worth adding to a glossary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    //Capitalized terms keep their case, other phrases are lowercase
+    pub text: String,
+    pub occurrences: usize,
+    //Keys using the term, in file order
+    pub keys: Vec<String>,
+}
+
+struct Word<'a> {
+    text: &'a str,
+    starts_sentence: bool,
+    //Set when punctuation or a placeholder follows, terms never span over those
+    ends_phrase: bool,
+}
+
+fn split_words(value: &str) -> Vec<Word<'_>> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut starts_sentence = true;
+    for token in value.split_whitespace() {
+        let text = token.trim_matches(|c: char| !c.is_alphanumeric());
+        let breaks_before = !token.starts_with(char::is_alphanumeric) || token.contains(['{', '}']);
+        if let Some(previous) = words.last_mut().filter(|_| breaks_before) {
+            previous.ends_phrase = true;
+        }
+        if !text.is_empty() && !token.contains(['{', '}']) {
+            words.push(Word {
+                text,
+                starts_sentence,
+                ends_phrase: !token.ends_with(char::is_alphanumeric),
+            });
+        }
+        starts_sentence = token.ends_with(['.', '!', '?', ':']);
+    }
+    words
+}
+
+//Finds names like "Master Sword" and phrases like "save file" used at least `min_occurrences` times, to seed a glossary.
+//Most used terms come first.
+pub fn extract_terms(document: &LocaleDocument, min_occurrences: usize) -> Vec<Term> {
+    let stopwords = stopwords(&document.language().unwrap_or_default());
+    let is_boundary_word = |word: &Word| {
+        word.text.chars().count() > 2
+            && !word.text.chars().all(|c| c.is_numeric())
+            && !stopwords.contains(&word.text.to_lowercase().as_str())
+    };
+
+    let mut terms: Vec<Term> = Vec::new();
+    let mut term_index: HashMap<String, usize> = HashMap::new();
+    let mut count = |text: String, key: &str| match term_index.get(&text) {
+        Some(&index) => {
+            let term = &mut terms[index];
+            term.occurrences += 1;
+            if term.keys.last().is_none_or(|last| last != key) {
+                term.keys.push(key.to_string());
+            }
+        }
+        None => {
+            term_index.insert(text.clone(), terms.len());
+            terms.push(Term {
+                text,
+                occurrences: 1,
+                keys: vec![key.to_string()],
+            });
+        }
+    };
+
+    for entry in &document.entries {
+        let value = entry.parsed_value();
+        let words = split_words(&value);
+
+        //Runs of capitalized words, a lone capitalized word at the start of a sentence is just grammar
+        let mut start = 0;
+        while start < words.len() {
+            if !words[start].text.starts_with(char::is_uppercase) {
+                start += 1;
+                continue;
+            }
+            let mut end = start + 1;
+            while end < words.len() && !words[end - 1].ends_phrase && words[end].text.starts_with(char::is_uppercase) {
+                end += 1;
+            }
+            let mut run = &words[start..end];
+            //"The Master Sword" at the start of a sentence is about the "Master Sword"
+            if run[0].starts_sentence && stopwords.contains(&run[0].text.to_lowercase().as_str()) {
+                run = &run[1..];
+            }
+            if run.len() > 1 || (!run.is_empty() && !run[0].starts_sentence && run[0].text.chars().count() > 1) {
+                let text: Vec<&str> = run.iter().map(|word| word.text).collect();
+                count(text.join(" "), &entry.key);
+            }
+            start = end;
+        }
+
+        //Phrases of two or three words that neither start nor end with a stopword
+        for length in 2..=3 {
+            for phrase in words.windows(length) {
+                let crosses_break = phrase[..length - 1].iter().any(|word| word.ends_phrase);
+                if crosses_break || !is_boundary_word(&phrase[0]) || !is_boundary_word(&phrase[length - 1]) {
+                    continue;
+                }
+                let text: Vec<String> = phrase.iter().map(|word| word.text.to_lowercase()).collect();
+                count(text.join(" "), &entry.key);
+            }
+        }
+    }
+
+    terms.retain(|term| term.occurrences >= min_occurrences);
+    //Capitalized runs already cover their lowercase phrase, and "save file" is redundant when it only occurs inside "delete save file"
+    let redundant: Vec<bool> = terms
+        .iter()
+        .map(|term| {
+            terms.iter().any(|other| {
+                let lowercase = other.text.to_lowercase();
+                (other.text != term.text && lowercase == term.text)
+                    || (other.occurrences == term.occurrences
+                        && lowercase.len() > term.text.len()
+                        && format!(" {lowercase} ").contains(&format!(" {} ", term.text.to_lowercase())))
+            })
+        })
+        .collect();
+    let mut redundant = redundant.into_iter();
+    terms.retain(|_| !redundant.next().unwrap_or(false));
+    terms.sort_by(|left, right| right.occurrences.cmp(&left.occurrences).then_with(|| left.text.cmp(&right.text)));
+    terms
+}
+
+//A glossary file with one entry per term, translators fill in the values in their language's copy
+pub fn glossary_document(language: Option<&str>, terms: &[Term]) -> LocaleDocument {
+    let mut document = LocaleDocument::default();
+    if let Some(language) = language {
+        document.header.push(format!("@language={language}"));
+    }
+    for term in terms {
+        document.entries.push(DocumentEntry {
+            key: term.text.clone(),
+            annotations: format_annotations(&[Annotation {
+                name: "note".to_string(),
+                value: Some(format!("Used {} times", term.occurrences)),
+                args: Vec::new(),
+            }]),
+            value: term.text.clone(),
+            ..Default::default()
+        });
+    }
+    document
+}
+
+//`{name}` placeholders of a value, in order of appearance. Escaped braces like `{{name}}` are text.
+pub fn placeholders(value: &str) -> Vec<&str> {
+    split_placeholders(value)
+        .into_iter()
+        .filter_map(|part| match part {
+            ValuePart::Placeholder(name) => Some(name),
+            ValuePart::Text(_) => None,
+        })
+        .collect()
+}
+
+//Translations may reorder or repeat placeholders, but not add or drop one
+fn placeholders_match(source: &str, translation: &str) -> bool {
+    let sorted = |value| {
+        let mut names = placeholders(value);
+        names.sort_unstable();
+        names.dedup();
+        names
+    };
+    sorted(source) == sorted(translation)
+}
+
+//Allowed translation to source length ratios. German runs long and Chinese runs short compared to English, so pairs
+//can get their own bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthRatios {
+    pub min: f64,
+    pub max: f64,
+    //Sources shorter than this are skipped, "OK" can reasonably become "Einverstanden"
+    pub min_source_length: usize,
+    //Source and target primary language with their own min and max
+    pairs: Vec<(String, String, f64, f64)>,
+}
+
+impl Default for LengthRatios {
+    fn default() -> Self {
+        Self {
+            min: 0.4,
+            max: 2.5,
+            min_source_length: 10,
+            pairs: Vec::new(),
+        }
+    }
+}
+
+impl LengthRatios {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pair(mut self, source: &str, target: &str, min: f64, max: f64) -> Self {
+        self.pairs
+            .push((primary_language(source).to_string(), primary_language(target).to_string(), min, max));
+        self
+    }
+
+    //The bounds for a pair, later pairs win over earlier ones
+    pub fn bounds(&self, source: Option<&str>, target: Option<&str>) -> (f64, f64) {
+        let (Some(source), Some(target)) = (source, target) else {
+            return (self.min, self.max);
+        };
+        let (source, target) = (primary_language(source), primary_language(target));
+        self.pairs
+            .iter()
+            .rev()
+            .find(|(pair_source, pair_target, _, _)| pair_source == source && pair_target == target)
+            .map_or((self.min, self.max), |(_, _, min, max)| (*min, *max))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthOutlier {
+    pub key: String,
+    //Translation length divided by source length, in characters
+    pub ratio: f64,
+}
+
+//Translations far shorter or longer than their source, often truncated, failed machine translations or the wrong string
+//pasted in. The pair is taken from the `@language` headers of both documents.
+pub fn find_length_outliers(base: &LocaleDocument, translated: &LocaleDocument, ratios: &LengthRatios) -> Vec<LengthOutlier> {
+    let (min, max) = ratios.bounds(base.language().as_deref(), translated.language().as_deref());
+    let mut outliers = Vec::new();
+    for source in &base.entries {
+        let source_length = source.parsed_value().chars().count();
+        if source_length < ratios.min_source_length.max(1) {
+            continue;
+        }
+        let translation = translated.find(&source.key).map(DocumentEntry::parsed_value);
+        let Some(translation) = translation.filter(|translation| !translation.is_empty()) else {
+            continue;
+        };
+        let ratio = translation.chars().count() as f64 / source_length as f64;
+        if !(min..=max).contains(&ratio) {
+            outliers.push(LengthOutlier {
+                key: source.key.clone(),
+                ratio,
+            });
+        }
+    }
+    outliers
+}
+
+//Problems of one translation, summed up by score() for players choosing a language
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QualityReport {
+    pub source_keys: usize,
+    //Source keys with a non-empty translation
+    pub translated_keys: usize,
+    //Keys whose translation adds or drops a `{placeholder}`
+    pub placeholder_mismatches: Vec<String>,
+    //Keys whose translation is far shorter or longer than the source
+    pub length_outliers: Vec<String>,
+    //Keys still holding the source text
+    pub same_as_source: Vec<String>,
+}
+
+//Weights of the score parts, coverage matters most since missing strings fall back to another language
+const COVERAGE_WEIGHT: f64 = 0.5;
+const PLACEHOLDER_WEIGHT: f64 = 0.2;
+const LENGTH_WEIGHT: f64 = 0.15;
+const SAME_AS_SOURCE_WEIGHT: f64 = 0.15;
+
+impl QualityReport {
+    //Share of source keys that are translated, from 0 to 1
+    pub fn coverage(&self) -> f64 {
+        if self.source_keys == 0 {
+            1.0
+        } else {
+            self.translated_keys as f64 / self.source_keys as f64
+        }
+    }
+
+    //From 0 to 100, the other checks are scored as the share of translated keys that pass them
+    pub fn score(&self) -> f64 {
+        let passing = |failures: usize| {
+            if self.translated_keys == 0 {
+                0.0
+            } else {
+                1.0 - failures.min(self.translated_keys) as f64 / self.translated_keys as f64
+            }
+        };
+        100.0
+            * (COVERAGE_WEIGHT * self.coverage()
+                + PLACEHOLDER_WEIGHT * passing(self.placeholder_mismatches.len())
+                + LENGTH_WEIGHT * passing(self.length_outliers.len())
+                + SAME_AS_SOURCE_WEIGHT * passing(self.same_as_source.len()))
+    }
+}
+
+pub fn quality_report(base: &LocaleDocument, translated: &LocaleDocument) -> QualityReport {
+    let mut report = QualityReport {
+        source_keys: base.entries.len(),
+        ..Default::default()
+    };
+    for source in &base.entries {
+        let translation = translated.find(&source.key).map(DocumentEntry::parsed_value);
+        let Some(translation) = translation.filter(|translation| !translation.is_empty()) else {
+            continue;
+        };
+        report.translated_keys += 1;
+        if !placeholders_match(&source.parsed_value(), &translation) {
+            report.placeholder_mismatches.push(source.key.clone());
+        }
+    }
+    report.length_outliers = find_length_outliers(base, translated, &LengthRatios::default())
+        .into_iter()
+        .map(|outlier| outlier.key)
+        .collect();
+    report.same_as_source = find_duplicate_values(translated, Some(base)).same_as_reference;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicate_values() {
+        let english = LocaleDocument::parse("[[ok]]\nOK\n[[play]]\nPlay\n[[start]]\nStart\n").expect("Parse failed");
+        let german = LocaleDocument::parse("[[ok]]\nOK\n[[play]]\nSpielen\n[[start]]\nSpielen\n[[empty]]\n\n[[blank]]\n\n")
+            .expect("Parse failed");
+
+        let report = find_duplicate_values(&german, Some(&english));
+        assert_eq!(
+            report.within_locale,
+            vec![DuplicateGroup {
+                value: "Spielen".to_string(),
+                keys: vec!["play".to_string(), "start".to_string()],
+            }]
+        );
+        assert_eq!(report.same_as_reference, vec!["ok"]);
+    }
+
+    #[test]
+    fn test_cluster_similar_values() {
+        let document = LocaleDocument::parse(
+            "[[a]]\nPress any key to continue\n[[b]]\nSave game\n[[c]]\nPress any key to continue.\n[[d]]\nPress any key to continue\n",
+        )
+        .expect("Parse failed");
+
+        let clusters = cluster_similar_values(&document, 0.1);
+        assert_eq!(clusters.len(), 1);
+        let keys: Vec<&str> = clusters[0].entries.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "c", "d"]);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_extract_terms() {
+        let document = LocaleDocument::parse(
+            "@language=en\n[[a]]\nYou found the Master Sword.\n[[b]]\nThe Master Sword glows.\n[[c]]\nDelete save file?\n[[d]]\nCould not delete save file {name}.\n[[e]]\nLoad save file\n",
+        )
+        .expect("Parse failed");
+
+        let terms = extract_terms(&document, 2);
+        let texts: Vec<&str> = terms.iter().map(|term| term.text.as_str()).collect();
+        assert_eq!(texts, vec!["save file", "Master Sword", "delete save file"]);
+        assert_eq!(terms[0].keys, vec!["c", "d", "e"]);
+
+        let glossary = glossary_document(Some("en"), &terms).to_string();
+        assert!(glossary.contains("[[Master Sword]] @note=\"Used 2 times\"\nMaster Sword\n"));
+    }
+
+    #[test]
+    fn test_quality_report() {
+        let base = LocaleDocument::parse(
+            "[[greet]]\nHello {name}, welcome back!\n[[quit]]\nQuit\n[[save]]\nYour progress has been saved.\n[[load]]\nLoad\n",
+        )
+        .expect("Parse failed");
+        let translated = LocaleDocument::parse("[[greet]]\nHallo, willkommen!\n[[quit]]\nQuit\n[[save]]\nOK\n")
+            .expect("Parse failed");
+
+        let report = quality_report(&base, &translated);
+        assert_eq!(report.translated_keys, 3);
+        assert_eq!(report.placeholder_mismatches, vec!["greet"]);
+        assert_eq!(report.length_outliers, vec!["save"]);
+        assert_eq!(report.same_as_source, vec!["quit"]);
+        assert_eq!(placeholders("{a} {{b}} {{{0}}} {} {c"), vec!["a", "0"]);
+        assert!((report.score() - 70.83).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_length_outliers() {
+        let base = LocaleDocument::parse("@language=en\n[[save]]\nYour progress has been saved.\n").expect("Parse failed");
+        let german = LocaleDocument::parse("@language=de\n[[save]]\nDein Spielfortschritt wurde erfolgreich in den Speicherstand gesichert. Danke.\n")
+            .expect("Parse failed");
+
+        let outliers = find_length_outliers(&base, &german, &LengthRatios::default());
+        assert_eq!(outliers.len(), 1);
+        assert!(outliers[0].ratio > 2.5);
+
+        let ratios = LengthRatios::new().with_pair("en", "de", 0.5, 3.0);
+        assert!(find_length_outliers(&base, &german, &ratios).is_empty());
+    }
+}
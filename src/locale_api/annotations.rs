@@ -0,0 +1,255 @@
+use super::parser::ParseR3Error;
+
+//Metadata attached to an entry on its key line, e.g. `[[key]] @context=main_menu @maxlines=2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub name: String,
+    pub value: Option<String>,
+    pub args: Vec<(String, String)>,
+}
+
+impl Annotation {
+    pub fn arg(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(arg_name, _)| arg_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+//Layout limits for UI code and validators, from `@maxlines=2 @wrap=word`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryConstraints {
+    pub max_lines: Option<u32>,
+    pub wrap: Option<WrapHint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum WrapHint {
+    None,
+    Word,
+    Character,
+}
+
+impl EntryConstraints {
+    pub fn from_annotations(annotations: &[Annotation]) -> Result<Option<Self>, ParseR3Error> {
+        let max_lines = match find_annotation(annotations, "maxlines") {
+            Some(annotation) => Some(
+                annotation
+                    .value
+                    .as_deref()
+                    .and_then(|value| value.parse::<u32>().ok())
+                    .ok_or(ParseR3Error::InvalidAnnotation)?,
+            ),
+            None => None,
+        };
+        let wrap = match find_annotation(annotations, "wrap") {
+            Some(annotation) => Some(match annotation.value.as_deref() {
+                Some("none") => WrapHint::None,
+                Some("word") => WrapHint::Word,
+                Some("char") => WrapHint::Character,
+                _ => return Err(ParseR3Error::InvalidAnnotation),
+            }),
+            None => None,
+        };
+
+        if max_lines.is_none() && wrap.is_none() {
+            Ok(None)
+        } else {
+            Ok(Some(Self { max_lines, wrap }))
+        }
+    }
+
+    pub fn allows(&self, value: &str) -> bool {
+        self.max_lines
+            .is_none_or(|max_lines| value.lines().count() <= max_lines as usize)
+    }
+}
+
+//From `@deprecated since=1.4 replacement=new_key`, both arguments are optional
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    pub since: Option<String>,
+    pub replacement: Option<String>,
+}
+
+impl Deprecation {
+    pub fn from_annotations(annotations: &[Annotation]) -> Option<Self> {
+        find_annotation(annotations, "deprecated").map(|annotation| Self {
+            since: annotation.arg("since").map(str::to_string),
+            replacement: annotation.arg("replacement").map(str::to_string),
+        })
+    }
+}
+
+pub fn find_annotation<'a>(annotations: &'a [Annotation], name: &str) -> Option<&'a Annotation> {
+    annotations.iter().find(|annotation| annotation.name == name)
+}
+
+//Sample arguments for previews and tests, from `@example count=3 name="Mario"` and the `example=` of `@placeholder`
+//annotations. `@example` wins when both give a value.
+pub fn example_args(annotations: &[Annotation]) -> Vec<(String, String)> {
+    let mut args: Vec<(String, String)> = annotations
+        .iter()
+        .filter(|annotation| annotation.name == "placeholder")
+        .filter_map(|annotation| Some((annotation.value.clone()?, annotation.arg("example")?.to_string())))
+        .collect();
+    for (name, value) in annotations
+        .iter()
+        .filter(|annotation| annotation.name == "example")
+        .flat_map(|annotation| &annotation.args)
+    {
+        args.retain(|(listed, _)| listed != name);
+        args.push((name.clone(), value.clone()));
+    }
+    args
+}
+
+//`@name` and `@name=value` start a new annotation, bare `arg=value` pairs belong to the previous one
+pub fn parse_annotations(text: &str) -> Result<Vec<Annotation>, ParseR3Error> {
+    let mut annotations: Vec<Annotation> = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (token, remainder) = next_token(rest)?;
+        rest = remainder.trim_start();
+
+        let (name, value) = match token.split_once('=') {
+            Some((name, value)) => (name, Some(unquote(value))),
+            None => (token, None),
+        };
+        if let Some(name) = name.strip_prefix('@') {
+            if name.is_empty() {
+                return Err(ParseR3Error::InvalidAnnotation);
+            }
+            annotations.push(Annotation {
+                name: name.to_string(),
+                value,
+                args: Vec::new(),
+            });
+        } else {
+            match (annotations.last_mut(), value) {
+                (Some(annotation), Some(value)) if !name.is_empty() => {
+                    annotation.args.push((name.to_string(), value))
+                }
+                _ => return Err(ParseR3Error::InvalidAnnotation),
+            }
+        }
+    }
+
+    Ok(annotations)
+}
+
+//Writes annotations back in the form parse_annotations reads, quoting values that contain spaces or quotes and escaping
+//quotes, backslashes and line breaks inside them
+pub fn format_annotations(annotations: &[Annotation]) -> String {
+    let mut output = String::new();
+    for annotation in annotations {
+        if !output.is_empty() {
+            output.push(' ');
+        }
+        output.push('@');
+        output.push_str(&annotation.name);
+        if let Some(value) = &annotation.value {
+            output.push('=');
+            push_value(&mut output, value);
+        }
+        for (name, value) in &annotation.args {
+            output.push(' ');
+            output.push_str(name);
+            output.push('=');
+            push_value(&mut output, value);
+        }
+    }
+    output
+}
+
+fn push_value(output: &mut String, value: &str) {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        output.push_str(value);
+        return;
+    }
+    output.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+//Splits off the next whitespace separated token, keeping quoted sections together. Inside quotes a backslash escapes
+//the character after it.
+pub(crate) fn next_token(text: &str) -> Result<(&str, &str), ParseR3Error> {
+    let mut in_quotes = false;
+    let mut characters = text.char_indices();
+    while let Some((index, character)) = characters.next() {
+        match character {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => {
+                characters.next();
+            }
+            c if c.is_whitespace() && !in_quotes => return Ok((&text[..index], &text[index..])),
+            _ => (),
+        }
+    }
+
+    if in_quotes {
+        Err(ParseR3Error::InvalidAnnotation)
+    } else {
+        Ok((text, ""))
+    }
+}
+
+//Quoted values have their escapes decoded, backslashes before any other character are kept as written
+fn unquote(value: &str) -> String {
+    let Some(quoted) = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut output = String::with_capacity(quoted.len());
+    let mut characters = quoted.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            output.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some(escaped @ ('"' | '\\')) => output.push(escaped),
+            Some(other) => {
+                output.push('\\');
+                output.push(other);
+            }
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_escapes() {
+        let annotations = [Annotation {
+            name: "note".to_string(),
+            value: Some("Say \"hi\"\nto C:\\Games".to_string()),
+            args: vec![("alt".to_string(), "a\\b".to_string())],
+        }];
+        let formatted = format_annotations(&annotations);
+        assert_eq!(formatted, "@note=\"Say \\\"hi\\\"\\nto C:\\\\Games\" alt=\"a\\\\b\"");
+        assert!(!formatted.contains('\n'));
+        assert_eq!(parse_annotations(&formatted), Ok(annotations.to_vec()));
+
+        //Written by hand before escapes existed, unknown escapes and unquoted backslashes are read as they are
+        let legacy = parse_annotations("@path=\"C:\\Games\\x\" @raw=a\\b").expect("Parse failed");
+        assert_eq!(legacy[0].value.as_deref(), Some("C:\\Games\\x"));
+        assert_eq!(legacy[1].value.as_deref(), Some("a\\b"));
+    }
+}
@@ -0,0 +1,154 @@
+use super::annotations::find_annotation;
+use super::interop::LocaleTable;
+use super::variants::Gender;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum Article {
+    Definite,
+    Indefinite,
+}
+
+//Picks the article for a singular noun, based on its gender and the sound it starts with.
+//Returns None for languages without rules here, so callers can fall back to the bare word.
+pub fn article_for(language: &str, gender: Gender, word: &str, article: Article) -> Option<&'static str> {
+    let primary_language = language.split(['-', '_']).next().unwrap_or(language);
+    let first = word.chars().next()?.to_lowercase().next()?;
+    let starts_with_vowel = matches!(first, 'a' | 'e' | 'i' | 'o' | 'u' | 'à' | 'â' | 'é' | 'è' | 'ê' | 'î' | 'ô' | 'û');
+
+    let chosen = match (primary_language, article, gender) {
+        ("en", Article::Definite, _) => "the",
+        ("en", Article::Indefinite, _) if starts_with_vowel => "an",
+        ("en", Article::Indefinite, _) => "a",
+
+        ("fr", Article::Definite, _) if french_elides(word, first, starts_with_vowel) => "l'",
+        ("fr", Article::Definite, Gender::Feminine) => "la",
+        ("fr", Article::Definite, _) => "le",
+        ("fr", Article::Indefinite, Gender::Feminine) => "une",
+        ("fr", Article::Indefinite, _) => "un",
+
+        ("de", Article::Definite, Gender::Masculine) => "der",
+        ("de", Article::Definite, Gender::Feminine) => "die",
+        ("de", Article::Definite, Gender::Neuter) => "das",
+        ("de", Article::Indefinite, Gender::Feminine) => "eine",
+        ("de", Article::Indefinite, _) => "ein",
+
+        ("it", Article::Definite, _) if starts_with_vowel => "l'",
+        ("it", Article::Definite, Gender::Feminine) => "la",
+        ("it", Article::Definite, _) if takes_lo(word) => "lo",
+        ("it", Article::Definite, _) => "il",
+        ("it", Article::Indefinite, Gender::Feminine) if starts_with_vowel => "un'",
+        ("it", Article::Indefinite, Gender::Feminine) => "una",
+        ("it", Article::Indefinite, _) if takes_lo(word) => "uno",
+        ("it", Article::Indefinite, _) => "un",
+
+        ("es", Article::Definite, Gender::Feminine) => "la",
+        ("es", Article::Definite, _) => "el",
+        ("es", Article::Indefinite, Gender::Feminine) => "una",
+        ("es", Article::Indefinite, _) => "un",
+
+        _ => return None,
+    };
+    Some(chosen)
+}
+
+//French words starting with an aspirated h or a consonant y, which take le/la instead of l'
+const FRENCH_NO_ELISION: [&str; 30] = [
+    "hache", "haie", "haine", "hall", "hamac", "hameau", "hamster", "hanche", "handicap", "hangar", "harpe", "hasard",
+    "hâte", "hausse", "haut", "hauteur", "héron", "héros", "hérisson", "hibou", "hockey", "homard", "honte", "houx",
+    "huit", "hurlement", "hutte", "yacht", "yaourt", "yoga",
+];
+
+//Whether a French definite article is elided to l' before the word
+fn french_elides(word: &str, first: char, starts_with_vowel: bool) -> bool {
+    if !(starts_with_vowel || matches!(first, 'h' | 'y' | 'œ' | 'æ' | 'ë' | 'ï' | 'ü')) {
+        return false;
+    }
+    let first_word = word.split([' ', '-']).next().unwrap_or(word).to_lowercase();
+    let singular = first_word.strip_suffix(['s', 'x']).unwrap_or(&first_word);
+    !FRENCH_NO_ELISION.contains(&first_word.as_str()) && !FRENCH_NO_ELISION.contains(&singular)
+}
+
+//Italian masculine nouns starting with s + consonant, z, gn, ps, x or y use lo/uno
+fn takes_lo(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    let mut chars = lower.chars();
+    match (chars.next(), chars.next()) {
+        (Some('s'), Some(second)) => !matches!(second, 'a' | 'e' | 'i' | 'o' | 'u'),
+        (Some('z' | 'x' | 'y'), _) => true,
+        (Some('g'), Some('n')) | (Some('p'), Some('s')) => true,
+        _ => false,
+    }
+}
+
+//Joins an article and a word, without a space after elided articles like l'
+pub fn join_article(article: &str, word: &str) -> String {
+    if article.ends_with('\'') {
+        format!("{article}{word}")
+    } else {
+        format!("{article} {word}")
+    }
+}
+
+impl LocaleTable {
+    //Gender comes from the entry's `@gender` annotation and defaults to masculine
+    pub fn entry_gender(&self, key: &[u8]) -> Gender {
+        match find_annotation(self.entry_annotations(key), "gender")
+            .and_then(|annotation| annotation.value.as_deref())
+        {
+            Some("feminine") => Gender::Feminine,
+            Some("neuter") => Gender::Neuter,
+            _ => Gender::Masculine,
+        }
+    }
+
+    //Returns the value with the right article in front, or the bare value for unsupported languages
+    pub fn with_article(&self, key: &[u8], article: Article) -> Option<String> {
+        let word = self.find_entry(key)?;
+        let article = self
+            .language()
+            .and_then(|language| article_for(language, self.entry_gender(key), word, article));
+        match article {
+            Some(article) => Some(join_article(article, word)),
+            None => Some(word.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_with_article() {
+        let mut french = "@language=fr\n[[sword]] @gender=feminine\népée\n[[shield]]\nbouclier\n[[key]] @gender=feminine\nclé\n"
+            .as_bytes()
+            .to_vec();
+        let table = parse_r3locale_bytes(&mut french).expect("Parse failed");
+
+        assert_eq!(table.with_article(b"sword", Article::Definite).as_deref(), Some("l'épée"));
+        assert_eq!(table.with_article(b"shield", Article::Definite).as_deref(), Some("le bouclier"));
+        assert_eq!(table.with_article(b"key", Article::Definite).as_deref(), Some("la clé"));
+        assert_eq!(table.with_article(b"key", Article::Indefinite).as_deref(), Some("une clé"));
+    }
+
+    #[test]
+    fn test_french_elision() {
+        assert_eq!(article_for("fr", Gender::Masculine, "héros", Article::Definite), Some("le"));
+        assert_eq!(article_for("fr", Gender::Feminine, "hache", Article::Definite), Some("la"));
+        assert_eq!(article_for("fr", Gender::Feminine, "héroïne", Article::Definite), Some("l'"));
+        assert_eq!(article_for("fr", Gender::Masculine, "homme", Article::Definite), Some("l'"));
+        assert_eq!(article_for("fr", Gender::Feminine, "œuvre", Article::Definite), Some("l'"));
+        assert_eq!(article_for("fr", Gender::Feminine, "yeuse", Article::Definite), Some("l'"));
+        assert_eq!(article_for("fr", Gender::Masculine, "yaourt", Article::Definite), Some("le"));
+    }
+
+    #[test]
+    fn test_italian_lo() {
+        assert_eq!(article_for("it", Gender::Masculine, "zaino", Article::Definite), Some("lo"));
+        assert_eq!(article_for("it", Gender::Masculine, "scudo", Article::Indefinite), Some("uno"));
+        assert_eq!(article_for("it", Gender::Masculine, "libro", Article::Definite), Some("il"));
+        assert_eq!(article_for("ja", Gender::Masculine, "剣", Article::Definite), None);
+    }
+}
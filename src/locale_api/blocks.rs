@@ -0,0 +1,132 @@
+use super::parser::{BLOCK_DELIMITER, COMMENT_START, KEY_OPEN, ParseR3Error};
+use super::sanitizer::find_comment;
+use memchr::{memchr, memmem};
+use std::ops::Range;
+
+//Block values hold text that a plain value can't: blank lines at the ends, lines starting with KEY_OPEN and comment
+//markers. The block starts with a line of only BLOCK_DELIMITER right after the key and ends at the next such line,
+//everything in between is the value as written.
+//
+//```
+//[[intro]]
+//"""
+//[[Narrator]] ## not a key or a comment
+//"""
+//```
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockLine {
+    //Not part of a block, read it like any other line
+    Outside,
+    //The line opening or closing a block
+    Delimiter,
+    Content,
+}
+
+//Follows the lines of a file in order and tells which of them belong to a block value, for code that reads files line
+//by line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockTracker {
+    after_key: bool,
+    in_block: bool,
+}
+
+impl BlockTracker {
+    pub fn classify(&mut self, line: &[u8]) -> BlockLine {
+        if self.in_block {
+            self.in_block = !is_block_delimiter(line);
+            return if self.in_block { BlockLine::Content } else { BlockLine::Delimiter };
+        }
+        if self.after_key && is_block_delimiter(line) {
+            self.after_key = false;
+            self.in_block = true;
+            return BlockLine::Delimiter;
+        }
+        self.after_key = line.starts_with(KEY_OPEN.as_bytes());
+        BlockLine::Outside
+    }
+
+    //A block was opened and not closed, the file fails to parse with UnterminatedBlock
+    pub fn is_open(&self) -> bool {
+        self.in_block
+    }
+}
+
+//A line of only BLOCK_DELIMITER, a comment may follow it
+pub fn is_block_delimiter(line: &[u8]) -> bool {
+    let code = find_comment(line).map_or(line, |index| &line[..index]);
+    code.trim_ascii() == BLOCK_DELIMITER.as_bytes()
+}
+
+//A value that has to be written as a block to be read back the same
+pub fn needs_block(value: &str) -> bool {
+    value.contains(COMMENT_START) || needs_block_as_literal(value)
+}
+
+//needs_block for literal document values, LocaleDocument::write escapes their comment markers instead
+pub(crate) fn needs_block_as_literal(value: &str) -> bool {
+    value.lines().any(|line| line.starts_with(KEY_OPEN))
+        || value.lines().next().is_some_and(|line| is_block_delimiter(line.as_bytes()))
+        || value.starts_with(char::is_whitespace)
+        || value.ends_with(char::is_whitespace)
+}
+
+//The contents of every block, from the line after the opening delimiter to the line break before the closing one.
+//Comments outside blocks don't change where blocks are, so this gives the same spans before and after sanitizing.
+pub(crate) fn block_spans(bytes: &[u8]) -> Result<Vec<Range<usize>>, ParseR3Error> {
+    let mut spans = Vec::new();
+    if memmem::find(bytes, BLOCK_DELIMITER.as_bytes()).is_none() {
+        return Ok(spans);
+    }
+    let mut tracker = BlockTracker::default();
+    let mut start = 0;
+    let mut line_start = 0;
+    while line_start <= bytes.len() {
+        let line_end = memchr(b'\n', &bytes[line_start..]).map_or(bytes.len(), |index| line_start + index);
+        if tracker.classify(&bytes[line_start..line_end]) == BlockLine::Delimiter {
+            if tracker.is_open() {
+                start = line_end + 1;
+            } else {
+                spans.push(start..line_start);
+            }
+        }
+        line_start = line_end + 1;
+    }
+    if tracker.is_open() {
+        return Err(ParseR3Error::UnterminatedBlock);
+    }
+    Ok(spans)
+}
+
+pub(crate) fn in_block(spans: &[Range<usize>], position: usize) -> bool {
+    let index = spans.partition_point(|span| span.end <= position);
+    spans.get(index).is_some_and(|span| span.contains(&position))
+}
+
+//The value of a block, without the line break before the closing delimiter
+pub(crate) fn block_value(body: &str) -> &str {
+    let body = body.strip_suffix('\n').unwrap_or(body);
+    body.strip_suffix('\r').unwrap_or(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_spans() {
+        let text = b"[[a]]\n\"\"\" ## opens\n[[b]]\n## kept\n\n\"\"\"\n[[c]]\n\"\"\"\n\"\"\"\n";
+        let spans = block_spans(text).expect("Parse failed");
+        assert_eq!(spans.len(), 2);
+        assert_eq!(&text[spans[0].clone()], b"[[b]]\n## kept\n\n");
+        assert!(spans[1].is_empty());
+        assert!(in_block(&spans, spans[0].start) && !in_block(&spans, 0));
+
+        //Only a delimiter right after a key opens a block
+        assert_eq!(block_spans(b"[[a]]\nText\n\"\"\"\n").expect("Parse failed"), vec![]);
+        assert_eq!(block_spans(b"[[a]]\n\"\"\"\nText\n"), Err(ParseR3Error::UnterminatedBlock));
+
+        assert!(needs_block("## Chapter 1") && needs_block("Line\n[[Narrator]]") && needs_block("\n\nEnd"));
+        assert!(!needs_block("Plain text\nover two lines"));
+    }
+}
@@ -0,0 +1,122 @@
+use super::annotations::Annotation;
+use super::interop::{LocaleTable, TableEntry, TableFrozenError};
+use std::mem::size_of;
+use hashbrown::{HashMap, HashSet, HashTable};
+use lite_strtab::{StringId, StringTableBuilder};
+
+impl LocaleTable {
+    //Fraction of stored strings no entry refers to anymore, from 0.0 to 1.0. Strings become dead when entries are
+    //removed from `entries` or pointed at a different string.
+    pub fn fragmentation(&self) -> f32 {
+        let stored = self.string_values.len();
+        if stored == 0 {
+            return 0.0;
+        }
+        let live = self.live_strings().len();
+        1.0 - live as f32 / stored as f32
+    }
+
+    //Rebuilds the string storage with only the strings entries refer to and remaps the entries to it. Keys that share a
+    //string, e.g. after migrate_table, still share it afterwards. Lookups return the same values as before.
+    pub fn compact(&mut self) -> Result<(), TableFrozenError> {
+        self.check_mutable()?;
+        let mut builder = StringTableBuilder::<u32, u16>::new();
+        let mut remapped: HashMap<(*const u8, usize), StringId<u16>> = HashMap::new();
+        let mut entries: HashTable<TableEntry> = HashTable::with_capacity(self.entries.len());
+        for entry in self.entries.iter() {
+            let value = self.string_values.get(entry.string_id).unwrap_or_default();
+            //The old table held these values, so they always fit in the new one
+            let string_id = *remapped
+                .entry((value.as_ptr(), value.len()))
+                .or_insert_with(|| builder.try_push(value).expect("Failed to compact locale table"));
+            entries.insert_unique(
+                entry.key,
+                TableEntry {
+                    key: entry.key,
+                    string_id,
+                },
+                |e| e.key,
+            );
+        }
+        self.string_values = builder.build();
+        self.entries = entries;
+        Ok(())
+    }
+
+    //Compacts only when at least `threshold` of the stored strings are dead, returns whether it did
+    pub fn compact_if_fragmented(&mut self, threshold: f32) -> Result<bool, TableFrozenError> {
+        let fragmentation = self.fragmentation();
+        let fragmented = fragmentation > 0.0 && fragmentation >= threshold;
+        if fragmented {
+            self.compact()?;
+        }
+        Ok(fragmented)
+    }
+
+    //Estimated heap bytes held by the table: live string bytes and their offsets, the entry table and annotations.
+    //Dead strings are not counted, so compact first for an exact figure.
+    pub fn memory_usage(&self) -> usize {
+        let strings: usize = self.live_strings().iter().map(|(_, len)| len + size_of::<u32>()).sum();
+        let entries = self.entries.capacity() * (size_of::<TableEntry>() + 1);
+        let annotations: usize = self
+            .annotations
+            .values()
+            .map(|annotations| size_of::<u64>() + annotations_size(annotations))
+            .sum();
+        strings + entries + annotations + annotations_size(&self.metadata)
+    }
+
+    //Strings are told apart by address and length, so keys sharing a string count it once. The length is needed since an
+    //empty string starts where the next one does.
+    fn live_strings(&self) -> HashSet<(*const u8, usize)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| self.string_values.get(entry.string_id))
+            .map(|value| (value.as_ptr(), value.len()))
+            .collect()
+    }
+}
+
+fn annotations_size(annotations: &[Annotation]) -> usize {
+    annotations
+        .iter()
+        .map(|annotation| {
+            let args: usize = annotation.args.iter().map(|(name, value)| name.len() + value.len()).sum();
+            size_of::<Annotation>() + annotation.name.len() + annotation.value.as_ref().map_or(0, String::len) + args
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+    use xxhash_rust::xxh3::xxh3_64;
+
+    #[test]
+    fn test_compact() {
+        let mut text = b"[[play]]\nPlay\n[[quit]]\nQuit\n[[help]]\nHelp\n[[back]]\nBack\n".to_vec();
+        let mut table = parse_r3locale_bytes(&mut text).expect("Parse failed");
+        assert_eq!(table.fragmentation(), 0.0);
+        assert_eq!(table.compact_if_fragmented(0.25), Ok(false));
+
+        for key in [b"quit".as_slice(), b"help"] {
+            let hash = xxh3_64(key);
+            if let Ok(entry) = table.entries.find_entry(hash, |entry| entry.key == hash) {
+                entry.remove();
+            }
+        }
+        assert_eq!(table.fragmentation(), 0.5);
+        assert_eq!(table.compact_if_fragmented(0.25), Ok(true));
+
+        assert_eq!(table.fragmentation(), 0.0);
+        assert_eq!(table.string_values.len(), 2);
+        assert_eq!(table.find_entry(b"play"), Some("Play"));
+        assert_eq!(table.find_entry(b"back"), Some("Back"));
+        assert_eq!(table.find_entry(b"quit"), None);
+
+        table.freeze();
+        assert_eq!(table.compact(), Err(TableFrozenError));
+        assert!(table.is_frozen() && !table.clone().is_frozen());
+    }
+}
@@ -0,0 +1,28 @@
+use super::interop::LocaleTable;
+use std::collections::BTreeSet;
+
+impl LocaleTable {
+    //Every character used in values, sorted and without duplicates, for checking font coverage and baking atlases
+    //Control characters like newlines are left out since they never need a glyph
+    pub fn required_codepoints(&self) -> impl Iterator<Item = char> {
+        self.values()
+            .flat_map(str::chars)
+            .filter(|character| !character.is_control())
+            .collect::<BTreeSet<char>>()
+            .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_required_codepoints() {
+        let mut sample = "[[a]]\nbaa\n[[b]]\nÄb\nc\n".as_bytes().to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+
+        let codepoints: Vec<char> = table.required_codepoints().collect();
+        assert_eq!(codepoints, vec!['a', 'b', 'c', 'Ä']);
+    }
+}
@@ -0,0 +1,199 @@
+use super::annotations::{find_annotation, parse_annotations};
+use super::blocks::block_spans;
+use super::formats::json::quote;
+use super::interop::FEATURES;
+use super::parser::{
+    ANNOTATION_START, BLOCK_DELIMITER, COMMENT_ESCAPE, COMMENT_START, KEY_CLOSE, KEY_OPEN, PLACEHOLDER_CLOSE,
+    PLACEHOLDER_OPEN, outside_entry_spans,
+};
+use super::sanitizer::sanitize_r3_locale_file;
+use super::variants::VARIANT_SEPARATOR;
+use std::sync::OnceLock;
+
+//Annotations the crate gives a meaning to, other names are kept but not interpreted
+const ENTRY_ANNOTATIONS: [&str; 9] =
+    ["context", "deprecated", "example", "gender", "maxlines", "note", "placeholder", "variants", "wrap"];
+const FILE_METADATA: [&str; 6] = ["direction", "format", "language", "language_name", "script", "version"];
+
+//Versions of the .r3l syntax. Files declare theirs with an `@format=2` header, see detect_dialect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub enum Dialect {
+    //The original syntax, without annotations or file metadata. Text after a key's brackets and before the first key
+    //was ignored.
+    V1,
+    //Annotations after a key and `@name=value` metadata before the first key
+    #[default]
+    V2,
+}
+
+impl Dialect {
+    pub const CURRENT: Dialect = Dialect::V2;
+
+    pub fn version(self) -> u32 {
+        match self {
+            Dialect::V1 => 1,
+            Dialect::V2 => 2,
+        }
+    }
+
+    pub fn from_version(version: u32) -> Option<Self> {
+        match version {
+            1 => Some(Dialect::V1),
+            2 => Some(Dialect::V2),
+            _ => None,
+        }
+    }
+}
+
+//Uses the `@format` header when there is one. Otherwise a file is version 1 only when text in annotation position
+//can't be read as annotations, since every other version 1 file means the same in the current dialect.
+pub fn detect_dialect(bytes: &[u8]) -> Dialect {
+    let mut sanitized = bytes.to_vec();
+    if sanitize_r3_locale_file(&mut sanitized).is_err() {
+        return Dialect::CURRENT;
+    }
+
+    let blocks = block_spans(&sanitized).unwrap_or_default();
+    let annotation_texts = outside_entry_spans(&sanitized, &blocks)
+        .into_iter()
+        .filter(|span| sanitized[span.start] == ANNOTATION_START)
+        .map(|span| std::str::from_utf8(&sanitized[span]).unwrap_or_default());
+    let mut readable = true;
+    for text in annotation_texts {
+        match parse_annotations(text) {
+            Ok(annotations) => {
+                let declared = find_annotation(&annotations, "format")
+                    .and_then(|annotation| annotation.value.as_deref()?.parse::<u32>().ok())
+                    .and_then(Dialect::from_version);
+                if let Some(dialect) = declared {
+                    return dialect;
+                }
+            }
+            Err(_) => readable = false,
+        }
+    }
+    if readable { Dialect::CURRENT } else { Dialect::V1 }
+}
+
+//Rewrites a version 1 file in the current dialect without losing any text. Text in annotation position becomes a
+//comment and an `@format` header is added. Files already in the current dialect are returned unchanged.
+pub fn upgrade_dialect(text: &str) -> String {
+    if detect_dialect(text.as_bytes()) != Dialect::V1 {
+        return text.to_string();
+    }
+
+    let mut sanitized = text.as_bytes().to_vec();
+    if sanitize_r3_locale_file(&mut sanitized).is_err() {
+        return text.to_string();
+    }
+    let line_ending = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut output = format!("@format={}{line_ending}", Dialect::CURRENT.version());
+    let mut copied = 0;
+    let blocks = block_spans(&sanitized).unwrap_or_default();
+    for span in outside_entry_spans(&sanitized, &blocks)
+        .into_iter()
+        .filter(|span| sanitized[span.start] == ANNOTATION_START)
+    {
+        output.push_str(&text[copied..span.start]);
+        output.push_str(COMMENT_START);
+        output.push(' ');
+        copied = span.start;
+    }
+    output.push_str(&text[copied..]);
+    output
+}
+
+//JSON description of the syntax and enabled features of this build, for tools that have to adapt to the dialect the
+//linked library supports. The `grammar` field is EBNF, built from the parser's token definitions.
+pub fn format_spec() -> &'static str {
+    static SPEC: OnceLock<String> = OnceLock::new();
+    SPEC.get_or_init(|| {
+        let annotation = char::from(ANNOTATION_START);
+        let variant = char::from(VARIANT_SEPARATOR);
+        let escape = char::from(COMMENT_ESCAPE);
+        let grammar = format!(
+            "file = {{ preamble_line }} , {{ entry }} ;\n\
+             preamble_line = [ metadata ] , newline ;\n\
+             metadata = annotations ;\n\
+             entry = key_line , ( block | {{ value_line }} ) ;\n\
+             key_line = \"{KEY_OPEN}\" , key , \"{KEY_CLOSE}\" , [ annotations ] , newline ;\n\
+             key = name , [ \"{variant}\" , variant ] ;\n\
+             annotations = annotation , {{ whitespace , ( annotation | argument ) }} ;\n\
+             annotation = \"{annotation}\" , name , [ \"=\" , annotation_value ] ;\n\
+             argument = name , \"=\" , annotation_value ;\n\
+             annotation_value = quoted_text | bare_text ;\n\
+             value_line = {{ text | escaped_brace | escaped_comment | placeholder }} , newline ;\n\
+             block = block_delimiter , {{ value_line - block_delimiter }} , block_delimiter ; (* comments and keys are text inside *)\n\
+             block_delimiter = '{BLOCK_DELIMITER}' , newline ;\n\
+             escaped_brace = \"{PLACEHOLDER_OPEN}{PLACEHOLDER_OPEN}\" | \"{PLACEHOLDER_CLOSE}{PLACEHOLDER_CLOSE}\" ;\n\
+             escaped_comment = \"{escape}{COMMENT_START}\" ; (* read as {COMMENT_START} *)\n\
+             placeholder = \"{PLACEHOLDER_OPEN}\" , name , \"{PLACEHOLDER_CLOSE}\" ; (* name may be a number for positional arguments *)\n\
+             comment = \"{COMMENT_START}\" , {{ any_character - newline }} ; (* allowed anywhere on a line, unless escaped *)\n"
+        );
+        let list = |names: &[&str]| names.iter().map(|name| quote(name)).collect::<Vec<_>>().join(", ");
+        let dialects = [Dialect::V1, Dialect::V2].map(|dialect| dialect.version().to_string()).join(", ");
+        let features = FEATURES
+            .map(|(name, enabled, _)| format!("    {}: {enabled}", quote(name)))
+            .join(",\n");
+
+        format!(
+            "{{\n  \"version\": {},\n  \"dialects\": [{dialects}],\n  \"tokens\": {{\n    \"key_open\": {},\n    \"key_close\": {},\n    \"comment\": {},\n    \"annotation\": {},\n    \"variant_separator\": {},\n    \"placeholder_open\": {},\n    \"placeholder_close\": {}\n  }},\n  \"entry_annotations\": [{}],\n  \"file_metadata\": [{}],\n  \"features\": {{\n{features}\n  }},\n  \"grammar\": {}\n}}\n",
+            Dialect::CURRENT.version(),
+            quote(KEY_OPEN),
+            quote(KEY_CLOSE),
+            quote(COMMENT_START),
+            quote(&annotation.to_string()),
+            quote(&variant.to_string()),
+            quote(&PLACEHOLDER_OPEN.to_string()),
+            quote(&PLACEHOLDER_CLOSE.to_string()),
+            list(&ENTRY_ANNOTATIONS),
+            list(&FILE_METADATA),
+            quote(&grammar),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::formats::json::{JsonValue, parse_json};
+    use crate::locale_api::parser::{ParseOptions, ParseR3Error, parse_r3locale_bytes, parse_r3locale_bytes_with_options};
+
+    #[test]
+    fn test_dialects() {
+        let old = "@author \"Jane\n[[title]] @todo=\"check length\nTitle\n[[quit]]\nQuit\n";
+        assert_eq!(detect_dialect(old.as_bytes()), Dialect::V1);
+        assert!(matches!(parse_r3locale_bytes(&mut old.as_bytes().to_vec()), Err(ParseR3Error::InvalidAnnotation)));
+
+        let options = ParseOptions {
+            dialect: Dialect::V1,
+            ..Default::default()
+        };
+        let table = parse_r3locale_bytes_with_options(&mut old.as_bytes().to_vec(), &options).expect("Parse failed");
+        assert_eq!(table.find_entry(b"quit"), Some("Quit"));
+        assert!(table.entry_annotations(b"title").is_empty());
+
+        let upgraded = upgrade_dialect(old);
+        assert_eq!(upgraded, "@format=2\n## @author \"Jane\n[[title]] ## @todo=\"check length\nTitle\n[[quit]]\nQuit\n");
+        assert_eq!(detect_dialect(upgraded.as_bytes()), Dialect::V2);
+        let upgraded_table = parse_r3locale_bytes(&mut upgraded.into_bytes()).expect("Parse failed");
+        assert_eq!(upgraded_table.find_entry(b"title"), table.find_entry(b"title"));
+
+        let current = "@language=en\n[[title]] @note=x\nTitle\n";
+        assert_eq!(detect_dialect(current.as_bytes()), Dialect::V2);
+        assert_eq!(upgrade_dialect(current), current);
+        assert_eq!(detect_dialect(b"@format=1\n[[title]] @note=x\nTitle\n"), Dialect::V1);
+    }
+
+    #[test]
+    fn test_format_spec() {
+        let spec = parse_json(format_spec()).expect("Parse failed");
+        assert_eq!(spec.get("version"), Some(&JsonValue::Number(2.0)));
+        assert_eq!(spec.get("tokens").and_then(|tokens| tokens.get("comment")).and_then(JsonValue::as_str), Some("##"));
+        assert_eq!(spec.get("features").and_then(|features| features.get("std")), Some(&JsonValue::Bool(true)));
+        let encryption = spec.get("features").and_then(|features| features.get("encryption"));
+        assert_eq!(encryption, Some(&JsonValue::Bool(cfg!(feature = "encryption"))));
+        assert!(spec.get("grammar").and_then(JsonValue::as_str).is_some_and(|grammar| grammar.contains("key_line = \"[[\"")));
+    }
+}
@@ -0,0 +1,128 @@
+use super::document::{DocumentEntry, LocaleDocument};
+use super::formats::json::quote;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryChange {
+    Added(DocumentEntry),
+    Removed(DocumentEntry),
+    //The value or the annotations changed, comments are not compared
+    Changed { old: DocumentEntry, new: DocumentEntry },
+}
+
+impl EntryChange {
+    pub fn key(&self) -> &str {
+        match self {
+            EntryChange::Added(entry) | EntryChange::Removed(entry) => &entry.key,
+            EntryChange::Changed { new, .. } => &new.key,
+        }
+    }
+}
+
+//Changes in the order of the new document, followed by the removed entries in their old order
+pub fn diff_documents(old: &LocaleDocument, new: &LocaleDocument) -> Vec<EntryChange> {
+    let mut changes = Vec::new();
+    for entry in &new.entries {
+        match old.find(&entry.key) {
+            None => changes.push(EntryChange::Added(entry.clone())),
+            Some(old_entry) if old_entry.value != entry.value || old_entry.annotations != entry.annotations => {
+                changes.push(EntryChange::Changed {
+                    old: old_entry.clone(),
+                    new: entry.clone(),
+                });
+            }
+            Some(_) => (),
+        }
+    }
+    changes.extend(
+        old.entries
+            .iter()
+            .filter(|entry| new.find(&entry.key).is_none())
+            .map(|entry| EntryChange::Removed(entry.clone())),
+    );
+    changes
+}
+
+fn write_entry_lines(output: &mut String, prefix: char, entry: &DocumentEntry) {
+    if entry.annotations.is_empty() {
+        let _ = writeln!(output, "{prefix}[[{}]]", entry.key);
+    } else {
+        let _ = writeln!(output, "{prefix}[[{}]] {}", entry.key, entry.annotations);
+    }
+    for line in entry.value.lines() {
+        let _ = writeln!(output, "{prefix}{line}");
+    }
+}
+
+//Unified diff style output with one hunk per changed entry, for pasting into review threads
+pub fn render_patch(old_name: &str, new_name: &str, changes: &[EntryChange]) -> String {
+    let mut output = String::new();
+    if changes.is_empty() {
+        return output;
+    }
+    let _ = write!(output, "--- {old_name}\n+++ {new_name}\n");
+    for change in changes {
+        let _ = writeln!(output, "@@ {} @@", change.key());
+        match change {
+            EntryChange::Added(entry) => write_entry_lines(&mut output, '+', entry),
+            EntryChange::Removed(entry) => write_entry_lines(&mut output, '-', entry),
+            EntryChange::Changed { old, new } => {
+                write_entry_lines(&mut output, '-', old);
+                write_entry_lines(&mut output, '+', new);
+            }
+        }
+    }
+    output
+}
+
+//`[{"change": "changed", "key": ..., "old": ..., "new": ...}]`, values only, for scripts and CI
+pub fn render_json(changes: &[EntryChange]) -> String {
+    let mut output = String::from("[");
+    for (index, change) in changes.iter().enumerate() {
+        output.push_str(if index == 0 { "\n" } else { ",\n" });
+        let _ = match change {
+            EntryChange::Added(entry) => write!(
+                output,
+                "  {{\"change\": \"added\", \"key\": {}, \"new\": {}}}",
+                quote(&entry.key),
+                quote(&entry.value)
+            ),
+            EntryChange::Removed(entry) => write!(
+                output,
+                "  {{\"change\": \"removed\", \"key\": {}, \"old\": {}}}",
+                quote(&entry.key),
+                quote(&entry.value)
+            ),
+            EntryChange::Changed { old, new } => write!(
+                output,
+                "  {{\"change\": \"changed\", \"key\": {}, \"old\": {}, \"new\": {}}}",
+                quote(&new.key),
+                quote(&old.value),
+                quote(&new.value)
+            ),
+        };
+    }
+    output.push_str(if changes.is_empty() { "]\n" } else { "\n]\n" });
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_documents() {
+        let old = LocaleDocument::parse("[[play]]\nJouer\n[[quit]]\nQuitter\n[[help]]\nAide\n").expect("Parse failed");
+        let new = LocaleDocument::parse("[[play]]\nLancer\nla partie\n[[quit]]\nQuitter\n[[new]]\nNouveau\n")
+            .expect("Parse failed");
+        let changes = diff_documents(&old, &new);
+
+        let keys: Vec<&str> = changes.iter().map(EntryChange::key).collect();
+        assert_eq!(keys, vec!["play", "new", "help"]);
+        assert_eq!(
+            render_patch("a/fr.r3l", "b/fr.r3l", &changes),
+            "--- a/fr.r3l\n+++ b/fr.r3l\n@@ play @@\n-[[play]]\n-Jouer\n+[[play]]\n+Lancer\n+la partie\n@@ new @@\n+[[new]]\n+Nouveau\n@@ help @@\n-[[help]]\n-Aide\n"
+        );
+        assert!(render_json(&changes).contains("{\"change\": \"removed\", \"key\": \"help\", \"old\": \"Aide\"}"));
+    }
+}
@@ -0,0 +1,586 @@
+use super::annotations::{Annotation, find_annotation, next_token, parse_annotations};
+use super::blocks::{BlockLine, BlockTracker};
+use super::parser::{BLOCK_DELIMITER, InlineComments, KEY_CLOSE, ParseR3Error};
+use super::sanitizer::{escape_comments, unescape_comments, without_value_comments};
+use hashbrown::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+//Editable view of a localisation file that keeps comments and ordering, for tools that rewrite files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocaleDocument {
+    //Lines before the first key, e.g. comments and `@language=fr`
+    pub header: Vec<String>,
+    pub entries: Vec<DocumentEntry>,
+    //Comment lines after the value of the last entry
+    pub trailing_comments: Vec<String>,
+    //Fields of the imported format that a localisation file can't hold, e.g. PO `msgctxt` or flags
+    //Keyed by entry key, or by an empty key for file level fields; never written to `.r3l` files
+    pub source_metadata: HashMap<String, Vec<(String, String)>>,
+    //Line ending of the text the document was read from, Preserve for documents built in code
+    pub line_ending: LineEnding,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentEntry {
+    //Comment lines directly above the key, these move with the entry when sorting
+    pub comments: Vec<String>,
+    pub key: String,
+    //Raw text after the closing brackets of the key, normally a list of annotations
+    pub annotations: String,
+    pub value: String,
+    //Written between BLOCK_DELIMITER lines, the value is kept exactly as it is
+    pub block: bool,
+    //Plain text from an import rather than lines read from a file, write escapes its comment markers
+    pub literal: bool,
+}
+
+impl LocaleDocument {
+    pub fn parse(text: &str) -> Result<Self, ParseR3Error> {
+        let mut document = LocaleDocument {
+            line_ending: LineEnding::detect(text),
+            ..Default::default()
+        };
+        let mut value_lines: Vec<&str> = Vec::new();
+        let mut block_lines: Vec<&str> = Vec::new();
+        let mut blocks = BlockTracker::default();
+        for line in text.lines() {
+            match blocks.classify(line.as_bytes()) {
+                BlockLine::Outside => (),
+                BlockLine::Content => {
+                    block_lines.push(line);
+                    continue;
+                }
+                BlockLine::Delimiter => {
+                    if let Some(entry) = document.entries.last_mut().filter(|_| !blocks.is_open()) {
+                        entry.value = block_lines.join("\n");
+                        entry.block = true;
+                        block_lines.clear();
+                    }
+                    continue;
+                }
+            }
+            if let Some(header) = line.strip_prefix("[[") {
+                let (key, annotations) = header.split_once("]]").ok_or(ParseR3Error::BracketMismatch)?;
+                let comments = if document.entries.is_empty() {
+                    //Comment lines right above the first key belong to it rather than to the header
+                    let comment_start = document
+                        .header
+                        .iter()
+                        .rposition(|line| !line.trim_start().starts_with("##"))
+                        .map_or(0, |index| index + 1);
+                    document.header.split_off(comment_start)
+                } else {
+                    document.finish_value(&mut value_lines)
+                };
+                document.entries.push(DocumentEntry {
+                    comments,
+                    key: key.trim().to_string(),
+                    annotations: annotations.trim().to_string(),
+                    value: String::new(),
+                    block: false,
+                    literal: false,
+                });
+            } else if document.entries.is_empty() {
+                document.header.push(line.to_string());
+            } else {
+                value_lines.push(line);
+            }
+        }
+        if blocks.is_open() {
+            return Err(ParseR3Error::UnterminatedBlock);
+        }
+        document.trailing_comments = document.finish_value(&mut value_lines);
+
+        Ok(document)
+    }
+
+    //Stores the collected lines as the value of the last entry, returning the comment lines at its end. Blocks already
+    //have their value, text after them is dropped like the parser ignores it.
+    fn finish_value(&mut self, value_lines: &mut Vec<&str>) -> Vec<String> {
+        let comment_start = value_lines
+            .iter()
+            .rposition(|line| !is_comment_or_blank(line))
+            .map_or(0, |index| index + 1);
+        let comments = value_lines[comment_start..]
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.to_string())
+            .collect();
+        if let Some(entry) = self.entries.last_mut().filter(|entry| !entry.block) {
+            entry.value = value_lines[..comment_start].join("\n");
+        }
+        value_lines.clear();
+        comments
+    }
+
+    //File metadata from annotation lines in the header, same as LocaleTable::metadata
+    pub fn metadata(&self) -> Vec<Annotation> {
+        self.header
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| line.starts_with('@'))
+            .filter_map(|line| parse_annotations(line).ok())
+            .flatten()
+            .collect()
+    }
+
+    pub fn language(&self) -> Option<String> {
+        find_annotation(&self.metadata(), "language").and_then(|annotation| annotation.value.clone())
+    }
+
+    pub fn source_field(&self, key: &str, field: &str) -> Option<&str> {
+        self.source_metadata
+            .get(key)?
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn find(&self, key: &str) -> Option<&DocumentEntry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    //Sorts entries by key and normalizes whitespace, so the same content always writes the same bytes
+    pub fn canonicalize(&mut self) {
+        let header: Vec<String> = self
+            .header
+            .iter()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        self.header = header;
+        for entry in &mut self.entries {
+            for comment in &mut entry.comments {
+                *comment = comment.trim().to_string();
+            }
+            entry.key = entry.key.trim().to_string();
+            entry.annotations = normalize_annotation_spacing(&entry.annotations);
+            //Whitespace in a block is part of its value
+            if !entry.block {
+                entry.value = entry
+                    .value
+                    .lines()
+                    .map(str::trim_end)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .trim_matches('\n')
+                    .to_string();
+            }
+        }
+        for comment in &mut self.trailing_comments {
+            *comment = comment.trim().to_string();
+        }
+        self.entries.sort_by(|left, right| left.key.cmp(&right.key));
+    }
+
+    //Like merge_locale_tables the first document wins, but comments above the same key are combined
+    //without duplicating lines, so translator notes from every document survive the merge
+    pub fn merge(documents: &[&LocaleDocument]) -> LocaleDocument {
+        let mut merged = LocaleDocument {
+            line_ending: documents.first().map(|document| document.line_ending).unwrap_or_default(),
+            ..Default::default()
+        };
+        for document in documents {
+            let metadata_names: Vec<String> = merged.metadata().into_iter().map(|annotation| annotation.name).collect();
+            for line in &document.header {
+                //Later documents can't override file metadata such as `@language`
+                let overrides_metadata = line.trim_start().starts_with('@')
+                    && parse_annotations(line.trim())
+                        .is_ok_and(|annotations| annotations.iter().any(|annotation| metadata_names.contains(&annotation.name)));
+                if !overrides_metadata {
+                    push_unique(&mut merged.header, line);
+                }
+            }
+
+            for entry in &document.entries {
+                match merged.entries.iter_mut().find(|merged_entry| merged_entry.key == entry.key) {
+                    Some(merged_entry) => {
+                        for comment in &entry.comments {
+                            push_unique(&mut merged_entry.comments, comment);
+                        }
+                    }
+                    None => merged.entries.push(entry.clone()),
+                }
+            }
+            for comment in &document.trailing_comments {
+                push_unique(&mut merged.trailing_comments, comment);
+            }
+            for (key, fields) in &document.source_metadata {
+                merged
+                    .source_metadata
+                    .entry(key.clone())
+                    .or_insert_with(|| fields.clone());
+            }
+        }
+        merged
+    }
+
+    pub fn is_canonical(text: &str) -> Result<bool, ParseR3Error> {
+        let mut document = LocaleDocument::parse(text)?;
+        document.canonicalize();
+        Ok(document.to_string() == text)
+    }
+}
+
+impl DocumentEntry {
+    //Invalid annotations are skipped here, the parser reports them when the file is loaded
+    pub fn parsed_annotations(&self) -> Vec<Annotation> {
+        if self.annotations.starts_with('@') {
+            parse_annotations(&self.annotations).unwrap_or_default()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn annotation_value(&self, name: &str) -> Option<String> {
+        find_annotation(&self.parsed_annotations(), name).and_then(|annotation| annotation.value.clone())
+    }
+
+    //The value as LocaleTable holds it with the default ParseOptions, without comments, with whitespace at both ends
+    //trimmed and escaped comment markers unescaped. value keeps the lines as written so documents can be saved again.
+    //Blocks and literal values are taken as written.
+    pub fn parsed_value(&self) -> Cow<'_, str> {
+        if self.block || self.literal {
+            return Cow::Borrowed(&self.value);
+        }
+        let value = match without_value_comments(&self.value, InlineComments::default()) {
+            Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+            Cow::Owned(value) => Cow::Owned(value.trim().to_string()),
+        };
+        unescape_comments(value)
+    }
+}
+
+//Blank lines are always kept since they separate comment blocks
+fn push_unique(lines: &mut Vec<String>, line: &str) {
+    if line.trim().is_empty() || !lines.iter().any(|existing| existing.trim() == line.trim()) {
+        lines.push(line.to_string());
+    }
+}
+
+fn is_comment_or_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with("##")
+}
+
+//Whether a key can be written to a file and read back unchanged. The parser trims keys and ends them at the first
+//KEY_CLOSE, so other keys would be read back as a different key.
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.trim() == key && !key.contains(KEY_CLOSE) && !key.contains(['\n', '\r'])
+}
+
+//Collapses runs of whitespace between annotations, leaving quoted values untouched
+fn normalize_annotation_spacing(annotations: &str) -> String {
+    let mut tokens: Vec<&str> = Vec::new();
+    let mut rest = annotations.trim();
+    while !rest.is_empty() {
+        match next_token(rest) {
+            Ok((token, remainder)) => {
+                tokens.push(token);
+                rest = remainder.trim_start();
+            }
+            //Unterminated quotes are kept as written so the parser still reports them
+            Err(_) => {
+                tokens.push(rest);
+                break;
+            }
+        }
+    }
+    tokens.join(" ")
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub enum KeyOrder {
+    //Entries are written in the order they are stored
+    #[default]
+    Original,
+    //Sorted by key, case-insensitive and with numbers compared by value so `quest_2` comes before `quest_10`
+    Natural,
+    //Sorted by the namespace before the first separator and then by key like Natural, with a blank line only between
+    //namespaces
+    GroupedByNamespace,
+    //Sorted by the bytes of the key, a stable order for diffs that doesn't depend on case folding
+    Lexicographic,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub enum LineEnding {
+    //Keep the line ending of the file the document was read from, LF for documents built in code
+    #[default]
+    Preserve,
+    Lf,
+    //Windows tools such as older mod managers may fail to read files without it
+    CrLf,
+}
+
+impl LineEnding {
+    //Decided by the first line break, text without any counts as LF
+    pub fn detect(text: &str) -> LineEnding {
+        match text.find('\n') {
+            Some(index) if text[..index].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    //Turns Preserve into the line ending a document was read with
+    pub fn resolve(self, original: LineEnding) -> LineEnding {
+        match (self, original) {
+            (LineEnding::Preserve, LineEnding::Preserve) => LineEnding::Lf,
+            (LineEnding::Preserve, original) => original,
+            (chosen, _) => chosen,
+        }
+    }
+
+    //Converts text written with LF line endings, line breaks that already are CRLF are left alone
+    pub fn apply(self, text: String) -> String {
+        if self != LineEnding::CrLf {
+            return text;
+        }
+        let mut converted = String::with_capacity(text.len() + text.len() / 16);
+        let mut previous = None;
+        for character in text.chars() {
+            if character == '\n' && previous != Some('\r') {
+                converted.push('\r');
+            }
+            converted.push(character);
+            previous = Some(character);
+        }
+        converted
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub order: KeyOrder,
+    //Splits the namespace off a key for KeyOrder::GroupedByNamespace, `menu` in `menu.play`
+    pub namespace_separator: char,
+    pub line_ending: LineEnding,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            order: KeyOrder::Original,
+            namespace_separator: '.',
+            line_ending: LineEnding::Preserve,
+        }
+    }
+}
+
+//Case-insensitive comparison where runs of digits are compared as numbers
+pub fn natural_key_cmp(left: &str, right: &str) -> Ordering {
+    let mut left_chars = left.chars().peekable();
+    let mut right_chars = right.chars().peekable();
+    loop {
+        match (left_chars.peek().copied(), right_chars.peek().copied()) {
+            (None, None) => return left.cmp(right),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let left_number = take_digits(&mut left_chars);
+                let right_number = take_digits(&mut right_chars);
+                let ordering = left_number
+                    .len()
+                    .cmp(&right_number.len())
+                    .then_with(|| left_number.cmp(&right_number));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(l), Some(r)) => {
+                let ordering = l.to_lowercase().cmp(r.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                left_chars.next();
+                right_chars.next();
+            }
+        }
+    }
+}
+
+//Digits without leading zeros, so comparing lengths first compares the numbers by value
+fn take_digits(chars: &mut Peekable<Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        if !(digits.is_empty() && digit == '0') {
+            digits.push(digit);
+        }
+    }
+    digits
+}
+
+fn namespace(key: &str, separator: char) -> &str {
+    key.split_once(separator).map_or("", |(namespace, _)| namespace)
+}
+
+impl LocaleDocument {
+    pub fn write(&self, options: &WriteOptions) -> String {
+        let separator = options.namespace_separator;
+        let mut entries: Vec<&DocumentEntry> = self.entries.iter().collect();
+        match options.order {
+            KeyOrder::Original => (),
+            KeyOrder::Natural => entries.sort_by(|left, right| natural_key_cmp(&left.key, &right.key)),
+            KeyOrder::GroupedByNamespace => entries.sort_by(|left, right| {
+                natural_key_cmp(namespace(&left.key, separator), namespace(&right.key, separator))
+                    .then_with(|| natural_key_cmp(&left.key, &right.key))
+            }),
+            KeyOrder::Lexicographic => entries.sort_by(|left, right| left.key.cmp(&right.key)),
+        }
+
+        let mut output = String::new();
+        for line in &self.header {
+            let _ = writeln!(output, "{line}");
+        }
+        if !self.header.is_empty() && !entries.is_empty() {
+            output.push('\n');
+        }
+        for (index, entry) in entries.iter().enumerate() {
+            let starts_group = match options.order {
+                KeyOrder::GroupedByNamespace => {
+                    index == 0 || namespace(&entries[index - 1].key, separator) != namespace(&entry.key, separator)
+                }
+                _ => true,
+            };
+            if index > 0 && starts_group {
+                output.push('\n');
+            }
+            for comment in &entry.comments {
+                let _ = writeln!(output, "{comment}");
+            }
+            if entry.annotations.is_empty() {
+                let _ = writeln!(output, "[[{}]]", entry.key);
+            } else {
+                let _ = writeln!(output, "[[{}]] {}", entry.key, entry.annotations);
+            }
+            if entry.block {
+                let _ = writeln!(output, "{BLOCK_DELIMITER}");
+                if !entry.value.is_empty() {
+                    let _ = writeln!(output, "{}", entry.value);
+                }
+                let _ = writeln!(output, "{BLOCK_DELIMITER}");
+            } else if entry.literal {
+                let _ = writeln!(output, "{}", escape_comments(&entry.value));
+            } else {
+                let _ = writeln!(output, "{}", entry.value);
+            }
+        }
+        if !self.trailing_comments.is_empty() {
+            output.push('\n');
+            for comment in &self.trailing_comments {
+                let _ = writeln!(output, "{comment}");
+            }
+        }
+        options.line_ending.resolve(self.line_ending).apply(output)
+    }
+}
+
+impl fmt::Display for LocaleDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.write(&WriteOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keeps_comments_with_entries() {
+        let document = LocaleDocument::parse("@language=en\n[[b]]\nsecond\n## about a\n[[a]]  @context=menu\nfirst\n")
+            .expect("Parse failed");
+
+        assert_eq!(document.header, vec!["@language=en"]);
+        assert_eq!(document.entries.len(), 2);
+        assert_eq!(document.find("b").map(|entry| entry.value.as_str()), Some("second"));
+        assert_eq!(document.entries[1].comments, vec!["## about a"]);
+        assert_eq!(document.entries[1].annotations, "@context=menu");
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let text = "@language=en   \n\n[[zeta]]\nlast   \n\n\n## first entry\n[[alpha]] @context=menu    @note=\"two  spaces\"\nfirst\n";
+        let mut document = LocaleDocument::parse(text).expect("Parse failed");
+        document.canonicalize();
+
+        let expected = "@language=en\n\n## first entry\n[[alpha]] @context=menu @note=\"two  spaces\"\nfirst\n\n[[zeta]]\nlast\n";
+        assert_eq!(document.to_string(), expected);
+        assert!(LocaleDocument::is_canonical(expected).expect("Parse failed"));
+        assert!(!LocaleDocument::is_canonical(text).expect("Parse failed"));
+    }
+
+    #[test]
+    fn test_write_orders() {
+        let document = LocaleDocument::parse("[[quest_10]]\nTen\n[[menu.quit]]\nQuit\n[[Quest_2]]\nTwo\n[[menu.play]]\nPlay\n")
+            .expect("Parse failed");
+
+        let natural = document.write(&WriteOptions {
+            order: KeyOrder::Natural,
+            ..Default::default()
+        });
+        assert_eq!(natural, "[[menu.play]]\nPlay\n\n[[menu.quit]]\nQuit\n\n[[Quest_2]]\nTwo\n\n[[quest_10]]\nTen\n");
+
+        let grouped = document.write(&WriteOptions {
+            order: KeyOrder::GroupedByNamespace,
+            ..Default::default()
+        });
+        assert_eq!(grouped, "[[Quest_2]]\nTwo\n[[quest_10]]\nTen\n\n[[menu.play]]\nPlay\n[[menu.quit]]\nQuit\n");
+
+        let lexicographic = document.write(&WriteOptions {
+            order: KeyOrder::Lexicographic,
+            ..Default::default()
+        });
+        assert_eq!(lexicographic, "[[Quest_2]]\nTwo\n\n[[menu.play]]\nPlay\n\n[[menu.quit]]\nQuit\n\n[[quest_10]]\nTen\n");
+    }
+
+    #[test]
+    fn test_merge_keeps_comments() {
+        let base = LocaleDocument::parse("@language=fr\n## Title screen\n[[play]]\nJouer\n").expect("Parse failed");
+        let update = LocaleDocument::parse(
+            "@language=en\n## Generated\n\n## Title screen\n[[play]]\nPlay\n## Shown on exit\n[[quit]]\nQuit\n",
+        )
+        .expect("Parse failed");
+        let notes = LocaleDocument::parse("## Keep it short\n[[play]]\nPlay\n").expect("Parse failed");
+
+        let merged = LocaleDocument::merge(&[&base, &update, &notes]);
+        assert_eq!(merged.header, vec!["@language=fr", "## Generated", ""]);
+        let play = merged.find("play").expect("Entry missing");
+        assert_eq!(play.value, "Jouer");
+        assert_eq!(play.comments, vec!["## Title screen", "## Keep it short"]);
+        assert_eq!(merged.find("quit").map(|entry| entry.comments.clone()), Some(vec!["## Shown on exit".to_string()]));
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let text = "@language=en\r\n\r\n[[play]]\r\nPlay\r\n";
+        let mut document = LocaleDocument::parse(text).expect("Parse failed");
+        document.canonicalize();
+        assert_eq!(document.line_ending, LineEnding::CrLf);
+        assert_eq!(document.to_string(), text);
+
+        let options = WriteOptions {
+            line_ending: LineEnding::Lf,
+            ..Default::default()
+        };
+        assert_eq!(document.write(&options), "@language=en\n\n[[play]]\nPlay\n");
+        assert_eq!(LineEnding::CrLf.apply("a\r\nb\n".to_string()), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        let text = "[[credits]]\n\"\"\"\n[[Lead]] Ana\n\n## Music\n\"\"\"\n\n[[play]]\nPlay\n";
+        let document = LocaleDocument::parse(text).expect("Parse failed");
+        let credits = document.find("credits").expect("Entry missing");
+        assert!(credits.block);
+        assert_eq!(credits.value, "[[Lead]] Ana\n\n## Music");
+        assert_eq!(document.entries.len(), 2);
+        assert_eq!(document.to_string(), text);
+        assert!(matches!(LocaleDocument::parse("[[a]]\n\"\"\"\nText\n"), Err(ParseR3Error::UnterminatedBlock)));
+    }
+}
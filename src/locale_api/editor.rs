@@ -0,0 +1,253 @@
+use super::blocks::{is_block_delimiter, needs_block};
+use super::document::{LocaleDocument, is_valid_key};
+use super::interop::LocaleTable;
+use super::parser::BLOCK_DELIMITER;
+use hashbrown::HashMap;
+use std::fs;
+use std::path::Path;
+use xxhash_rust::xxh3::xxh3_64;
+
+//A user-override layer on top of a loaded LocaleTable, for retranslating strings in game
+pub struct LocaleEditor {
+    base: LocaleTable,
+    overrides: Vec<OverrideEntry>,
+    override_index: HashMap<u64, usize>,
+    //Keys marked reviewed without an override, their base value is the reviewed one
+    reviewed_base: HashMap<u64, String>,
+}
+
+//Written above a reviewed override, and followed by the key for keys reviewed at their base value
+const REVIEWED_MARKER: &str = "## reviewed";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverrideEntry {
+    pub key: String,
+    pub value: String,
+    pub reviewed: bool,
+}
+
+impl LocaleEditor {
+    pub fn new(base: LocaleTable) -> Self {
+        Self {
+            base,
+            overrides: Vec::new(),
+            override_index: HashMap::new(),
+            reviewed_base: HashMap::new(),
+        }
+    }
+
+    pub fn base(&self) -> &LocaleTable {
+        &self.base
+    }
+
+    pub fn overrides(&self) -> &[OverrideEntry] {
+        &self.overrides
+    }
+
+    pub fn find_entry(&self, key: &[u8]) -> Option<&str> {
+        match self.override_index.get(&xxh3_64(key)) {
+            Some(&index) => Some(self.overrides[index].value.as_str()),
+            None => self.base.find_entry(key),
+        }
+    }
+
+    //Changing a value clears its reviewed state. Keys that can't be written between brackets fail with InvalidKey.
+    pub fn set_entry(&mut self, key: &str, value: &str) -> Result<(), EditorError> {
+        if !is_valid_key(key) {
+            return Err(EditorError::InvalidKey);
+        }
+        let hash = xxh3_64(key.as_bytes());
+        self.reviewed_base.remove(&hash);
+        match self.override_index.get(&hash) {
+            Some(&index) => {
+                let entry = &mut self.overrides[index];
+                entry.value = value.to_string();
+                entry.reviewed = false;
+            }
+            None => {
+                self.override_index.insert(hash, self.overrides.len());
+                self.overrides.push(OverrideEntry {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reviewed: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    //Marking a key that has no override records its base value as reviewed without adding an override
+    pub fn mark_reviewed(&mut self, key: &str) -> Result<(), EditorError> {
+        let hash = xxh3_64(key.as_bytes());
+        if let Some(&index) = self.override_index.get(&hash) {
+            self.overrides[index].reviewed = true;
+        } else if self.base.find_entry(key.as_bytes()).is_some() {
+            self.reviewed_base.insert(hash, key.to_string());
+        } else {
+            return Err(EditorError::NoEntryFound);
+        }
+        Ok(())
+    }
+
+    pub fn is_reviewed(&self, key: &str) -> bool {
+        let hash = xxh3_64(key.as_bytes());
+        match self.override_index.get(&hash) {
+            Some(&index) => self.overrides[index].reviewed,
+            None => self.reviewed_base.contains_key(&hash),
+        }
+    }
+
+    //Writes only the overridden entries as a localisation file, in the order they were first edited. Keys reviewed at
+    //their base value are listed in `## reviewed <key>` comments before them.
+    pub fn save_overrides(&self, path: &Path) -> Result<(), EditorError> {
+        let mut output = String::new();
+        let mut reviewed_base: Vec<&String> = self.reviewed_base.values().collect();
+        reviewed_base.sort();
+        for key in &reviewed_base {
+            output.push_str(REVIEWED_MARKER);
+            output.push(' ');
+            output.push_str(key);
+            output.push('\n');
+        }
+        if !reviewed_base.is_empty() {
+            output.push('\n');
+        }
+        for entry in &self.overrides {
+            if !is_representable(&entry.value) {
+                return Err(EditorError::UnrepresentableValue);
+            }
+            if entry.reviewed {
+                output.push_str(REVIEWED_MARKER);
+                output.push('\n');
+            }
+            output.push_str("[[");
+            output.push_str(&entry.key);
+            output.push_str("]]\n");
+            if needs_block(&entry.value) {
+                output.push_str(BLOCK_DELIMITER);
+                output.push('\n');
+                output.push_str(&entry.value);
+                output.push('\n');
+                output.push_str(BLOCK_DELIMITER);
+            } else {
+                output.push_str(&entry.value);
+            }
+            output.push_str("\n\n");
+        }
+
+        fs::write(path, output).map_err(|_| EditorError::FailedToWrite)
+    }
+
+    //Reads a file written by save_overrides back on top of the current overrides, together with its reviewed state.
+    //Keys listed as reviewed at a base value the base table no longer has are dropped.
+    pub fn load_overrides(&mut self, path: &Path) -> Result<(), EditorError> {
+        let text = fs::read_to_string(path).map_err(|_| EditorError::FailedToRead)?;
+        let document = LocaleDocument::parse(&text).map_err(|_| EditorError::InvalidSyntax)?;
+
+        let comments = document
+            .header
+            .iter()
+            .chain(document.entries.iter().flat_map(|entry| &entry.comments))
+            .chain(&document.trailing_comments);
+        for line in comments {
+            let Some(key) = line.trim().strip_prefix(REVIEWED_MARKER).and_then(|rest| rest.strip_prefix(' ')) else {
+                continue;
+            };
+            if self.base.find_entry(key.trim().as_bytes()).is_some() {
+                self.mark_reviewed(key.trim())?;
+            }
+        }
+        for entry in &document.entries {
+            self.set_entry(&entry.key, &entry.parsed_value())?;
+            if entry.comments.iter().any(|line| line.trim() == REVIEWED_MARKER) {
+                self.mark_reviewed(&entry.key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//Values that need it are written as blocks, which can hold anything but a line that would close them
+fn is_representable(value: &str) -> bool {
+    !value.lines().any(|line| is_block_delimiter(line.as_bytes()))
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub enum EditorError {
+    Normal,
+    NoEntryFound,
+    UnrepresentableValue,
+    FailedToWrite,
+    InvalidKey,
+    FailedToRead,
+    //An overrides file that doesn't parse
+    InvalidSyntax,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_overrides_take_priority() {
+        let mut sample = Box::from(*b"[[greeting]]\nHello\n[[farewell]]\nBye\n");
+        let mut editor = LocaleEditor::new(parse_r3locale_bytes(&mut *sample).expect("Parse failed"));
+
+        editor.set_entry("greeting", "Bonjour").expect("Valid key");
+        editor.mark_reviewed("farewell").expect("Key exists in base table");
+
+        assert_eq!(editor.find_entry(b"greeting"), Some("Bonjour"));
+        assert_eq!(editor.find_entry(b"farewell"), Some("Bye"));
+        assert_eq!(editor.overrides().len(), 1);
+        assert!(editor.is_reviewed("farewell") && !editor.is_reviewed("greeting"));
+        assert!(matches!(editor.mark_reviewed("missing"), Err(EditorError::NoEntryFound)));
+
+        editor.set_entry("farewell", "Au revoir").expect("Valid key");
+        assert!(!editor.is_reviewed("farewell"));
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let mut sample = Box::from(*b"[[greeting]]\nHello\n");
+        let mut editor = LocaleEditor::new(parse_r3locale_bytes(&mut *sample).expect("Parse failed"));
+        for key in ["", "a]]b", "two\nlines", " padded", "cr\r"] {
+            assert!(matches!(editor.set_entry(key, "Value"), Err(EditorError::InvalidKey)));
+        }
+        assert!(editor.overrides().is_empty());
+        assert!(editor.set_entry("menu.[[play", "Play").is_ok());
+    }
+
+    #[test]
+    fn test_save_overrides_round_trips() {
+        let mut sample = Box::from(*b"[[greeting]]\nHello\n[[farewell]]\nBye\n");
+        let mut editor = LocaleEditor::new(parse_r3locale_bytes(&mut *sample).expect("Parse failed"));
+        editor.set_entry("greeting", "Bonjour").expect("Valid key");
+        editor.set_entry("credits", "## Credits ##\n[[Studio]]\n").expect("Valid key");
+        editor.mark_reviewed("farewell").expect("Key exists in base table");
+
+        let path = std::env::temp_dir().join("r3l_editor_save_overrides.r3l");
+        editor.save_overrides(&path).expect("Save failed");
+        let mut saved = fs::read(&path).expect("Read failed");
+        fs::remove_file(&path).ok();
+
+        let table = parse_r3locale_bytes(&mut saved).expect("Parse failed");
+        assert_eq!(table.find_entry(b"greeting"), Some("Bonjour"));
+        assert_eq!(table.find_entry(b"farewell"), None);
+        assert_eq!(table.find_entry(b"credits"), Some("## Credits ##\n[[Studio]]\n"));
+
+        let mut reloaded = LocaleEditor::new(editor.base().clone());
+        editor.mark_reviewed("greeting").expect("Key is overridden");
+        editor.save_overrides(&path).expect("Save failed");
+        reloaded.load_overrides(&path).expect("Load failed");
+        fs::remove_file(&path).ok();
+        assert_eq!(reloaded.overrides(), editor.overrides());
+        assert!(reloaded.is_reviewed("greeting") && reloaded.is_reviewed("farewell"));
+        assert!(!reloaded.is_reviewed("credits"));
+        assert_eq!(reloaded.find_entry(b"farewell"), Some("Bye"));
+
+        editor.set_entry("credits", "\"\"\"").expect("Valid key");
+        assert!(matches!(editor.save_overrides(&path), Err(EditorError::UnrepresentableValue)));
+    }
+}
@@ -0,0 +1,138 @@
+use super::ffi_util;
+use super::interop::{AllocationResult, LocaleTable};
+use super::parser::{ParseR3Error, parse_r3locale_bytes};
+use super::runtime::{LogLevel, log};
+use super::status::StatusCode;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::ffi::c_char;
+use std::fs;
+use std::path::Path;
+
+//Sealed packs are .r3l files encrypted with XChaCha20-Poly1305, for mods that ship story text before it is released.
+//This only keeps the text from being read with a text editor or a grep through the game files. The key has to ship
+//with the mod or game that loads the pack, so anyone willing to pull it out of memory or a binary can decrypt it. It is
+//tamper resistance, not DRM.
+//
+//Layout: the magic, a version byte, the 24 byte nonce, then the encrypted file with its 16 byte tag. The magic and
+//version are authenticated along with the text, so changing any byte of the pack fails to open it.
+
+pub const SEALED_PACK_EXTENSION: &str = "r3lb";
+pub const PACK_KEY_LEN: usize = 32;
+const SEALED_PACK_MAGIC: &[u8; 4] = b"R3LB";
+const SEALED_PACK_VERSION: u8 = 1;
+const HEADER_LEN: usize = SEALED_PACK_MAGIC.len() + 1;
+const NONCE_LEN: usize = 24;
+
+//A new random key from the operating system's generator
+pub fn generate_pack_key() -> [u8; PACK_KEY_LEN] {
+    XChaCha20Poly1305::generate_key(&mut OsRng).into()
+}
+
+//Whether the bytes start like a sealed pack, the version is only checked when opening it
+pub fn is_sealed_pack(bytes: &[u8]) -> bool {
+    bytes.starts_with(SEALED_PACK_MAGIC)
+}
+
+//Encrypts the text of a .r3l file with a fresh random nonce, so sealing the same file twice gives different packs
+pub fn seal_locale_bytes(bytes: &[u8], key: &[u8; PACK_KEY_LEN]) -> Vec<u8> {
+    let mut header = SEALED_PACK_MAGIC.to_vec();
+    header.push(SEALED_PACK_VERSION);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: bytes,
+        aad: &header,
+    };
+    //Only fails for inputs of hundreds of gigabytes
+    let encrypted = XChaCha20Poly1305::new(key.into())
+        .encrypt(&nonce, payload)
+        .expect("Locale file too large to seal");
+
+    let mut pack = header;
+    pack.extend_from_slice(&nonce);
+    pack.extend_from_slice(&encrypted);
+    pack
+}
+
+//Decrypts a pack back into the text of the .r3l file. Fails with InvalidPack for data that is not a sealed pack of a
+//known version, and DecryptionFailed for a wrong key or a pack that was modified.
+pub fn open_sealed_pack(pack: &[u8], key: &[u8; PACK_KEY_LEN]) -> Result<Vec<u8>, ParseR3Error> {
+    if !is_sealed_pack(pack) || pack.get(SEALED_PACK_MAGIC.len()) != Some(&SEALED_PACK_VERSION) {
+        return Err(ParseR3Error::InvalidPack);
+    }
+    let Some((nonce, encrypted)) = pack[HEADER_LEN..].split_at_checked(NONCE_LEN) else {
+        return Err(ParseR3Error::InvalidPack);
+    };
+    let payload = Payload {
+        msg: encrypted,
+        aad: &pack[..HEADER_LEN],
+    };
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), payload)
+        .map_err(|_| ParseR3Error::DecryptionFailed)
+}
+
+pub fn parse_sealed_pack(pack: &[u8], key: &[u8; PACK_KEY_LEN]) -> Result<LocaleTable, ParseR3Error> {
+    let mut bytes = open_sealed_pack(pack, key)?;
+    parse_r3locale_bytes(&mut bytes)
+}
+
+pub fn parse_sealed_pack_file(path: &Path, key: &[u8; PACK_KEY_LEN]) -> Result<LocaleTable, ParseR3Error> {
+    if !path.exists() {
+        return Err(ParseR3Error::FileNotFound);
+    }
+    let pack = fs::read(path).map_err(|_| ParseR3Error::FailedToRead)?;
+    parse_sealed_pack(&pack, key).inspect_err(|error| {
+        log(LogLevel::Error, "encryption", StatusCode::from(*error), &path.display().to_string());
+    })
+}
+
+//get_locale_table for sealed packs, `key` points at PACK_KEY_LEN bytes. A null key fails with DecryptionFailed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn get_sealed_locale_table(path: *const c_char, key: *const u8) -> AllocationResult {
+    let failed = |allocation_state| AllocationResult {
+        table: std::ptr::null_mut(),
+        allocation_state,
+    };
+    let path = match unsafe { ffi_util::c_str(path) } {
+        Some(Ok(path)) => path,
+        Some(Err(_)) => return failed(ParseR3Error::InvalidUTF8Path),
+        None => return failed(ParseR3Error::NullPathProvided),
+    };
+    let Some(key) = (unsafe { ffi_util::slice(key, PACK_KEY_LEN) }).and_then(|key| key.try_into().ok()) else {
+        return failed(ParseR3Error::DecryptionFailed);
+    };
+
+    match parse_sealed_pack_file(Path::new(path), key) {
+        Ok(table) => AllocationResult {
+            table: ffi_util::into_handle(table),
+            allocation_state: ParseR3Error::Normal,
+        },
+        Err(parse_error) => failed(parse_error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_pack() {
+        let key = generate_pack_key();
+        let text = b"[[chapter3.ending]]\nThe king was the traitor all along.\n";
+        let pack = seal_locale_bytes(text, &key);
+        assert!(is_sealed_pack(&pack));
+        assert!(memchr::memmem::find(&pack, b"traitor").is_none());
+        assert_ne!(pack, seal_locale_bytes(text, &key));
+
+        let table = parse_sealed_pack(&pack, &key).expect("Parse failed");
+        assert_eq!(table.find_entry(b"chapter3.ending"), Some("The king was the traitor all along."));
+
+        assert_eq!(open_sealed_pack(&pack, &generate_pack_key()).err(), Some(ParseR3Error::DecryptionFailed));
+        let mut tampered = pack.clone();
+        *tampered.last_mut().expect("Empty pack") ^= 1;
+        assert_eq!(open_sealed_pack(&tampered, &key).err(), Some(ParseR3Error::DecryptionFailed));
+        assert_eq!(open_sealed_pack(text, &key).err(), Some(ParseR3Error::InvalidPack));
+        assert_eq!(open_sealed_pack(&pack[..10], &key).err(), Some(ParseR3Error::InvalidPack));
+    }
+}
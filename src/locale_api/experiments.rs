@@ -0,0 +1,211 @@
+use super::annotations::find_annotation;
+use super::ffi_util;
+use super::interop::{FindEntryError, LocaleTable};
+use super::stats::csv_field;
+use super::variants::variant_key;
+use hashbrown::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use xxhash_rust::xxh3::{xxh3_64, xxh3_64_with_seed};
+
+//Wording experiments: a key lists alternative phrasings in a `@variants` annotation and each one is a variant entry,
+//
+//```
+//[[shop.buy]] @variants=urgent,friendly
+//Buy
+//[[shop.buy|urgent]]
+//Buy now!
+//[[shop.buy|friendly]]
+//Get yours
+//```
+//
+//Every user is assigned one phrasing per key from their user id, so the same player sees the same wording every
+//session and on every machine.
+
+//Name of the plain key's own value in a variant group, the phrasing the others are compared against
+pub const CONTROL_VARIANT: &str = "control";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServedVariant<'a> {
+    pub value: &'a str,
+    //CONTROL_VARIANT when the plain key was served
+    pub variant: &'a str,
+}
+
+//Which of `count` phrasings a user gets for a key. Hashing the key along with the user spreads a user over different
+//phrasings of different keys, so one experiment doesn't decide the others.
+pub fn variant_index(user_id: &[u8], key: &[u8], count: usize) -> usize {
+    match count {
+        0 => 0,
+        count => (xxh3_64_with_seed(key, xxh3_64(user_id)) % count as u64) as usize,
+    }
+}
+
+impl LocaleTable {
+    //CONTROL_VARIANT followed by the names in the key's `@variants` annotation, which are separated by commas
+    pub fn variant_group(&self, key: &[u8]) -> Vec<&str> {
+        let mut group = vec![CONTROL_VARIANT];
+        let names = find_annotation(self.entry_annotations(key), "variants").and_then(|names| names.value.as_deref());
+        if let Some(names) = names {
+            group.extend(names.split(',').map(str::trim).filter(|name| !name.is_empty()));
+        }
+        group
+    }
+
+    //The phrasing of a key assigned to a user. A variant missing from the table serves the plain key and reports
+    //CONTROL_VARIANT, so a translation that only has the plain text isn't counted as part of the experiment.
+    pub fn find_entry_for_user(&self, key: &[u8], user_id: &[u8]) -> Option<ServedVariant<'_>> {
+        let group = self.variant_group(key);
+        let variant = group[variant_index(user_id, key, group.len())];
+        let value = match variant {
+            CONTROL_VARIANT => None,
+            name => self.find_entry(&variant_key(key, name)),
+        };
+        match value {
+            Some(value) => Some(ServedVariant { value, variant }),
+            None => self.find_entry(key).map(|value| ServedVariant {
+                value,
+                variant: CONTROL_VARIANT,
+            }),
+        }
+    }
+}
+
+//How often each phrasing was served, to compare with whatever the mod measures per user. Share one between threads,
+//recording takes a lock.
+#[derive(Debug, Default)]
+pub struct VariantReport {
+    served: Mutex<HashMap<(String, String), u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantCount {
+    pub key: String,
+    pub variant: String,
+    pub served: u64,
+}
+
+impl VariantReport {
+    pub fn record(&self, key: &[u8], served: &ServedVariant) {
+        if let Ok(mut counts) = self.served.lock() {
+            let key = String::from_utf8_lossy(key).into_owned();
+            *counts.entry((key, served.variant.to_string())).or_default() += 1;
+        }
+    }
+
+    //Sorted by key, then variant
+    pub fn counts(&self) -> Vec<VariantCount> {
+        let mut counts: Vec<VariantCount> = self
+            .served
+            .lock()
+            .map(|counts| {
+                counts
+                    .iter()
+                    .map(|((key, variant), &served)| VariantCount {
+                        key: key.clone(),
+                        variant: variant.clone(),
+                        served,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        counts.sort_unstable_by(|left, right| left.key.cmp(&right.key).then_with(|| left.variant.cmp(&right.variant)));
+        counts
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("key,variant,served\n");
+        for count in self.counts() {
+            let _ = writeln!(csv, "{},{},{}", csv_field(&count.key), csv_field(&count.variant), count.served);
+        }
+        csv
+    }
+
+    pub fn reset(&self) {
+        if let Ok(mut counts) = self.served.lock() {
+            counts.clear();
+        }
+    }
+}
+
+#[repr(C)]
+pub struct ServedVariantResult {
+    pub value_ptr: *const u8,
+    pub value_len: usize,
+    //Name of the served variant, not nul terminated
+    pub variant_ptr: *const u8,
+    pub variant_len: usize,
+    pub allocation_state: FindEntryError,
+}
+
+//find_entry_for_user for C hosts, `user_id` is any stable bytes identifying the user, e.g. an account id
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_find_entry_for_user(
+    table: *const LocaleTable,
+    key_ptr: *const u8,
+    key_len: usize,
+    user_ptr: *const u8,
+    user_len: usize,
+) -> ServedVariantResult {
+    let failed = |allocation_state| ServedVariantResult {
+        value_ptr: std::ptr::null(),
+        value_len: 0,
+        variant_ptr: std::ptr::null(),
+        variant_len: 0,
+        allocation_state,
+    };
+    let table = match ffi_util::table_ref(table) {
+        Ok(table) => table,
+        Err(error) => return failed(error.into()),
+    };
+    let slices = unsafe { (ffi_util::slice(key_ptr, key_len), ffi_util::slice(user_ptr, user_len)) };
+    let (Some(key), Some(user_id)) = slices else {
+        return failed(FindEntryError::NullKeyPtr);
+    };
+    match table.find_entry_for_user(key, user_id) {
+        Some(served) => ServedVariantResult {
+            value_ptr: served.value.as_ptr(),
+            value_len: served.value.len(),
+            variant_ptr: served.variant.as_ptr(),
+            variant_len: served.variant.len(),
+            allocation_state: FindEntryError::Normal,
+        },
+        None => failed(FindEntryError::NoEntryFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_find_entry_for_user() {
+        let mut sample = b"[[shop.buy]] @variants=urgent,friendly\nBuy\n[[shop.buy|urgent]]\nBuy now!\n\
+                           [[shop.title]]\nShop\n"
+            .to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+        assert_eq!(table.variant_group(b"shop.buy"), vec![CONTROL_VARIANT, "urgent", "friendly"]);
+
+        let report = VariantReport::default();
+        for user in 0..300u32 {
+            let user_id = user.to_le_bytes();
+            let served = table.find_entry_for_user(b"shop.buy", &user_id).expect("Entry missing");
+            assert_eq!(table.find_entry_for_user(b"shop.buy", &user_id), Some(served));
+            //The translation has no `friendly` phrasing, those users get the plain text
+            match served.variant {
+                "urgent" => assert_eq!(served.value, "Buy now!"),
+                variant => assert_eq!((variant, served.value), (CONTROL_VARIANT, "Buy")),
+            }
+            report.record(b"shop.buy", &served);
+        }
+        let counts = report.counts();
+        assert_eq!(counts.len(), 2);
+        assert!(counts.iter().all(|count| count.served > 50));
+        assert!(report.to_csv().starts_with("key,variant,served\nshop.buy,control,"));
+
+        let title = table.find_entry_for_user(b"shop.title", b"player-1");
+        assert_eq!(title.map(|served| served.variant), Some(CONTROL_VARIANT));
+        assert_eq!(table.find_entry_for_user(b"missing", b"player-1"), None);
+    }
+}
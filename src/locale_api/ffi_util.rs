@@ -0,0 +1,285 @@
+use super::interop::{FindEntryError, FreeTableError, LocaleTable};
+use super::parser::MergeTableError;
+use super::preview::PreviewError;
+use super::runtime::{LogLevel, log};
+use super::status::StatusCode;
+#[cfg(feature = "leak-check")]
+use std::backtrace::Backtrace;
+use std::ffi::{CStr, c_char};
+use std::str::Utf8Error;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+//Every pointer the C API receives goes through this module, so the code that trusts the host is in one place.
+//
+//Tables handed to hosts live in a slot map, and hosts get an opaque id typed as a table pointer instead of an address.
+//The id packs the slot index in its low half and the slot's generation in its high half. The generation changes every
+//time the slot is freed, so a stale id fails the check even once its slot holds a new table, and a second
+//free_locale_table reports InvalidHandle instead of freeing someone else's table. Ids are never dereferenced, a
+//freed, foreign or made up pointer is looked up like any other and reported as InvalidHandle.
+//
+//Slots hold an Arc, and exports work on a clone taken under the lock. A table freed by another thread while an export
+//still uses it is then released when that export returns, not in the middle of it.
+
+const INDEX_BITS: u32 = usize::BITS / 2;
+const INDEX_MASK: usize = (1 << INDEX_BITS) - 1;
+//A slot whose generation would no longer fit in an id is retired instead of reused
+const MAX_GENERATION: usize = usize::MAX >> INDEX_BITS;
+
+//Why a pointer from the host was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HandleError {
+    Null,
+    //Not a live table from this library, e.g. one that was already freed
+    Invalid,
+}
+
+struct Slot {
+    //Starts at 1, so ids are never null
+    generation: usize,
+    table: Option<Arc<LocaleTable>>,
+    //Where the table was handed out, only captured with the leak-check feature since it is slow
+    #[cfg(feature = "leak-check")]
+    created: Option<Backtrace>,
+}
+
+#[derive(Default)]
+struct Registry {
+    slots: Vec<Slot>,
+    //Empty slots that can be reused
+    free: Vec<usize>,
+}
+
+impl Registry {
+    fn insert(&mut self, table: LocaleTable) -> Option<usize> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None if self.slots.len() <= INDEX_MASK => {
+                self.slots.push(Slot {
+                    generation: 1,
+                    table: None,
+                    #[cfg(feature = "leak-check")]
+                    created: None,
+                });
+                self.slots.len() - 1
+            }
+            None => return None,
+        };
+        let slot = &mut self.slots[index];
+        slot.table = Some(Arc::new(table));
+        #[cfg(feature = "leak-check")]
+        {
+            slot.created = Some(Backtrace::force_capture());
+        }
+        Some(slot.generation << INDEX_BITS | index)
+    }
+
+    fn get(&self, id: usize) -> Result<&Arc<LocaleTable>, HandleError> {
+        if id == 0 {
+            return Err(HandleError::Null);
+        }
+        self.slots
+            .get(id & INDEX_MASK)
+            .filter(|slot| slot.generation == id >> INDEX_BITS)
+            .and_then(|slot| slot.table.as_ref())
+            .ok_or(HandleError::Invalid)
+    }
+
+    fn remove(&mut self, id: usize) -> Result<Arc<LocaleTable>, HandleError> {
+        self.get(id)?;
+        let index = id & INDEX_MASK;
+        let slot = &mut self.slots[index];
+        #[cfg(feature = "leak-check")]
+        {
+            slot.created = None;
+        }
+        let table = slot.table.take().ok_or(HandleError::Invalid)?;
+        if slot.generation < MAX_GENERATION {
+            slot.generation += 1;
+            self.free.push(index);
+        }
+        Ok(table)
+    }
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+//The registry is only changed by single inserts and removes that can't panic halfway, so a lock poisoned by a panic
+//elsewhere still guards a consistent map and is used as is
+fn read_registry() -> RwLockReadGuard<'static, Registry> {
+    registry().read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write_registry() -> RwLockWriteGuard<'static, Registry> {
+    registry().write().unwrap_or_else(PoisonError::into_inner)
+}
+
+//Stores a table and returns the id handed to hosts, release it with take_table. Null in the unlikely case that every
+//id is in use.
+pub(crate) fn into_handle(table: LocaleTable) -> *mut LocaleTable {
+    match write_registry().insert(table) {
+        Some(id) => std::ptr::without_provenance_mut(id),
+        None => {
+            log(LogLevel::Error, "interop", StatusCode::INVALID_HANDLE, "No table id is left to hand out");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+//The table behind a handle, kept alive until the returned Arc is dropped even if the host frees it meanwhile
+pub(crate) fn table_ref(table: *const LocaleTable) -> Result<Arc<LocaleTable>, HandleError> {
+    read_registry().get(table.addr()).cloned()
+}
+
+//Takes back a table created by into_handle. Its id is retired before the table is returned, so a second call with the
+//same id fails instead of freeing it twice. The table is released once no export still uses it.
+pub(crate) fn take_table(table: *mut LocaleTable) -> Result<Arc<LocaleTable>, HandleError> {
+    write_registry().remove(table.addr())
+}
+
+//Slice from a pointer and length passed by the host. Null is None, so callers can report it, and lengths a real
+//allocation can't have are rejected instead of creating an invalid slice.
+pub(crate) unsafe fn slice<'a, T>(ptr: *const T, len: usize) -> Option<&'a [T]> {
+    if ptr.is_null() || !ptr.is_aligned() || len.checked_mul(size_of::<T>())? > isize::MAX as usize {
+        return None;
+    }
+    Some(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+pub(crate) unsafe fn slice_mut<'a, T>(ptr: *mut T, len: usize) -> Option<&'a mut [T]> {
+    if ptr.is_null() || !ptr.is_aligned() || len.checked_mul(size_of::<T>())? > isize::MAX as usize {
+        return None;
+    }
+    Some(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+}
+
+//Logs every live handle and returns how many there are
+pub(crate) fn report_leaks() -> usize {
+    let registry = read_registry();
+    let mut leaked = 0;
+    for (index, slot) in registry.slots.iter().enumerate().filter(|(_, slot)| slot.table.is_some()) {
+        let id = slot.generation << INDEX_BITS | index;
+        #[cfg(feature = "leak-check")]
+        let message = match &slot.created {
+            Some(created) => format!("Table {id:#x} was created at\n{created}"),
+            None => format!("Table {id:#x} was not freed"),
+        };
+        #[cfg(not(feature = "leak-check"))]
+        let message = format!("Table {id:#x} was not freed");
+        log(LogLevel::Warning, "leaks", StatusCode::LEAKED_HANDLE, &message);
+        leaked += 1;
+    }
+    leaked
+}
+
+//Tables from an array of handles, an error when the array or any of the handles fails the check
+pub(crate) unsafe fn table_refs(
+    tables: *const *const LocaleTable,
+    count: usize,
+) -> Result<Vec<Arc<LocaleTable>>, HandleError> {
+    unsafe { slice(tables, count) }
+        .ok_or(HandleError::Null)?
+        .iter()
+        .map(|&table| table_ref(table))
+        .collect()
+}
+
+//UTF-8 path passed as a nul terminated string, None when it is null
+pub(crate) unsafe fn c_str<'a>(ptr: *const c_char) -> Option<Result<&'a str, Utf8Error>> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(ptr) }.to_str())
+}
+
+//The cancel flag a host passed in, a null flag never cancels
+pub(crate) unsafe fn cancel_flag<'a>(cancel: *const AtomicBool) -> &'a AtomicBool {
+    static NEVER: AtomicBool = AtomicBool::new(false);
+    unsafe { cancel.as_ref() }.unwrap_or(&NEVER)
+}
+
+impl From<HandleError> for FindEntryError {
+    fn from(error: HandleError) -> Self {
+        match error {
+            HandleError::Null => FindEntryError::NullTable,
+            HandleError::Invalid => FindEntryError::InvalidHandle,
+        }
+    }
+}
+
+impl From<HandleError> for FreeTableError {
+    fn from(error: HandleError) -> Self {
+        match error {
+            HandleError::Null => FreeTableError::NullTable,
+            HandleError::Invalid => FreeTableError::InvalidHandle,
+        }
+    }
+}
+
+impl From<HandleError> for MergeTableError {
+    fn from(error: HandleError) -> Self {
+        match error {
+            HandleError::Null => MergeTableError::NullTablePointer,
+            HandleError::Invalid => MergeTableError::InvalidHandle,
+        }
+    }
+}
+
+impl From<HandleError> for PreviewError {
+    fn from(error: HandleError) -> Self {
+        match error {
+            HandleError::Null => PreviewError::NullTable,
+            HandleError::Invalid => PreviewError::InvalidHandle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_handles() {
+        let table = parse_r3locale_bytes(&mut b"[[quit]]\nQuit\n".to_vec()).expect("Parse failed");
+        let handle = into_handle(table);
+        assert_eq!(table_ref(handle).map(|table| table.find_entry(b"quit").map(str::to_string)), Ok(Some("Quit".into())));
+
+        let unmanaged = parse_r3locale_bytes(&mut b"[[quit]]\nQuit\n".to_vec()).expect("Parse failed");
+        assert_eq!(table_ref(&unmanaged).err(), Some(HandleError::Invalid));
+        assert_eq!(table_ref(std::ptr::null()).err(), Some(HandleError::Null));
+        let mixed = [handle.cast_const(), &unmanaged];
+        assert_eq!(unsafe { table_refs(mixed.as_ptr(), 2) }.err(), Some(HandleError::Invalid));
+        assert_eq!(unsafe { table_refs([handle.cast_const()].as_ptr(), 1) }.map(|tables| tables.len()), Ok(1));
+
+        //A table still in use when it is freed stays readable until that use ends
+        let in_use = table_ref(handle).expect("Live handle rejected");
+        assert!(take_table(handle).is_ok());
+        assert_eq!(in_use.find_entry(b"quit"), Some("Quit"));
+        assert_eq!(table_ref(handle).err(), Some(HandleError::Invalid));
+        assert_eq!(take_table(handle).err(), Some(HandleError::Invalid));
+        let words = [1u64, 2, 3];
+        assert!(unsafe { slice(words.as_ptr(), usize::MAX) }.is_none());
+        assert_eq!(unsafe { slice(words.as_ptr(), 2) }, Some(&words[..2]));
+    }
+
+    #[test]
+    fn test_stale_handle_after_reuse() {
+        let parse = || parse_r3locale_bytes(&mut b"[[quit]]\nQuit\n".to_vec()).expect("Parse failed");
+        let stale = into_handle(parse());
+        assert!(take_table(stale).is_ok());
+
+        //The freed slot is reused by one of these, or by a table from another test, and must not be reachable through
+        //the old id
+        let handles: Vec<_> = (0..4).map(|_| into_handle(parse())).collect();
+        assert!(handles.iter().all(|&handle| handle != stale));
+        assert_eq!(table_ref(stale).err(), Some(HandleError::Invalid));
+        assert_eq!(take_table(stale).err(), Some(HandleError::Invalid));
+        for handle in handles {
+            assert!(take_table(handle).is_ok());
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use super::fidelity::{FidelityIssue, FidelityReport};
+use super::{FormatError, LocaleFormat, read_utf8};
+use super::json::{JsonValue, parse_json, quote};
+use crate::locale_api::annotations::{Annotation, find_annotation, format_annotations};
+use crate::locale_api::blocks::needs_block_as_literal;
+use crate::locale_api::document::{DocumentEntry, LineEnding, LocaleDocument};
+use std::fmt::Write;
+
+//Exports to Flutter's `.arb` format, `@placeholder=name type=int example=3` annotations become placeholder metadata
+pub fn export_arb(document: &LocaleDocument) -> String {
+    let mut members: Vec<String> = Vec::new();
+    if let Some(language) = document.language() {
+        members.push(format!("  \"@@locale\": {}", quote(&language)));
+    }
+    for entry in &document.entries {
+        members.push(format!("  {}: {}", quote(&entry.key), quote(&entry.parsed_value())));
+        if let Some(metadata) = entry_metadata(entry) {
+            members.push(format!("  {}: {metadata}", quote(&format!("@{}", entry.key))));
+        }
+    }
+
+    if members.is_empty() {
+        "{}\n".to_string()
+    } else {
+        format!("{{\n{}\n}}\n", members.join(",\n"))
+    }
+}
+
+fn entry_metadata(entry: &DocumentEntry) -> Option<String> {
+    let annotations = entry.parsed_annotations();
+    let mut fields: Vec<String> = Vec::new();
+    if let Some(note) = find_annotation(&annotations, "note").and_then(|annotation| annotation.value.as_deref()) {
+        fields.push(format!("    \"description\": {}", quote(note)));
+    }
+    if let Some(context) = find_annotation(&annotations, "context").and_then(|annotation| annotation.value.as_deref()) {
+        fields.push(format!("    \"context\": {}", quote(context)));
+    }
+
+    let placeholders: Vec<String> = annotations
+        .iter()
+        .filter(|annotation| annotation.name == "placeholder")
+        .filter_map(|annotation| {
+            let name = annotation.value.as_deref()?;
+            let mut placeholder = format!("      {}: {{", quote(name));
+            for (index, (arg, value)) in annotation.args.iter().enumerate() {
+                let separator = if index == 0 { "" } else { "," };
+                let _ = write!(placeholder, "{separator}\n        {}: {}", quote(arg), quote(value));
+            }
+            placeholder.push_str(if annotation.args.is_empty() { "}" } else { "\n      }" });
+            Some(placeholder)
+        })
+        .collect();
+    if !placeholders.is_empty() {
+        fields.push(format!("    \"placeholders\": {{\n{}\n    }}", placeholders.join(",\n")));
+    }
+
+    (!fields.is_empty()).then(|| format!("{{\n{}\n  }}", fields.join(",\n")))
+}
+
+pub fn import_arb(text: &str) -> Result<LocaleDocument, FormatError> {
+    import_arb_with_report(text).map(|(document, _)| document)
+}
+
+pub fn import_arb_with_report(text: &str) -> Result<(LocaleDocument, FidelityReport), FormatError> {
+    let JsonValue::Object(members) = parse_json(text)? else {
+        return Err(FormatError::UnsupportedStructure);
+    };
+
+    let mut document = LocaleDocument {
+        line_ending: LineEnding::detect(text),
+        ..Default::default()
+    };
+    let mut report = FidelityReport::default();
+    for (name, value) in &members {
+        if name == "@@locale" {
+            let language = value.as_str().ok_or(FormatError::UnsupportedStructure)?;
+            document.header.push(format!("@language={language}"));
+        } else if name.starts_with("@@") {
+            report.push(FidelityIssue::DroppedField {
+                key: None,
+                field: name.clone(),
+            });
+        } else if !name.starts_with('@') {
+            let value = value.as_str().ok_or(FormatError::UnsupportedStructure)?;
+            let metadata = members
+                .iter()
+                .find(|(metadata_name, _)| metadata_name.strip_prefix('@') == Some(name.as_str()))
+                .map(|(_, metadata)| metadata);
+            document.entries.push(DocumentEntry {
+                key: name.clone(),
+                annotations: metadata
+                    .map(|metadata| metadata_annotations(name, metadata, &mut report))
+                    .unwrap_or_default(),
+                value: value.to_string(),
+                block: needs_block_as_literal(value),
+                literal: true,
+                ..Default::default()
+            });
+        }
+    }
+    Ok((document, report))
+}
+
+fn metadata_annotations(key: &str, metadata: &JsonValue, report: &mut FidelityReport) -> String {
+    let mut dropped = |field: &str| {
+        report.push(FidelityIssue::DroppedField {
+            key: Some(key.to_string()),
+            field: field.to_string(),
+        })
+    };
+    if let JsonValue::Object(fields) = metadata {
+        for (field, _) in fields {
+            if !matches!(field.as_str(), "context" | "description" | "placeholders") {
+                dropped(field);
+            }
+        }
+    }
+
+    let mut annotations: Vec<Annotation> = Vec::new();
+    let mut push = |name: &str, value: Option<String>, args: Vec<(String, String)>| {
+        annotations.push(Annotation {
+            name: name.to_string(),
+            value,
+            args,
+        })
+    };
+
+    if let Some(context) = metadata.get("context").and_then(scalar_to_string) {
+        push("context", Some(context), Vec::new());
+    }
+    if let Some(description) = metadata.get("description").and_then(scalar_to_string) {
+        push("note", Some(description), Vec::new());
+    }
+    if let Some(JsonValue::Object(placeholders)) = metadata.get("placeholders") {
+        for (name, fields) in placeholders {
+            let args = match fields {
+                JsonValue::Object(fields) => fields
+                    .iter()
+                    .filter_map(|(field, value)| match scalar_to_string(value) {
+                        Some(value) => Some((field.clone(), value)),
+                        None => {
+                            dropped(&format!("placeholders.{name}.{field}"));
+                            None
+                        }
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            push("placeholder", Some(name.clone()), args);
+        }
+    }
+    format_annotations(&annotations)
+}
+
+pub struct ArbFormat;
+
+impl LocaleFormat for ArbFormat {
+    fn name(&self) -> &str {
+        "arb"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["arb"]
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        import_arb(read_utf8(bytes)?)
+    }
+
+    fn read_with_report(&self, bytes: &[u8]) -> Result<(LocaleDocument, FidelityReport), FormatError> {
+        import_arb_with_report(read_utf8(bytes)?)
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        Ok(export_arb(document).into_bytes())
+    }
+}
+
+//Nested objects such as `optionalParameters` have no annotation equivalent and are dropped
+fn scalar_to_string(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(text) => Some(text.clone()),
+        JsonValue::Number(number) => Some(number.to_string()),
+        JsonValue::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arb_round_trip() {
+        let source = "@language=fr\n[[coins]] @note=\"Coin counter\" @placeholder=count type=int example=3\nVous avez {count} pièces\n[[quit]]\nQuitter\n";
+        let document = LocaleDocument::parse(source).expect("Parse failed");
+
+        let arb = export_arb(&document);
+        assert!(arb.contains("\"@@locale\": \"fr\""));
+        assert!(arb.contains("\"count\": {\n        \"type\": \"int\",\n        \"example\": \"3\"\n      }"));
+
+        let imported = import_arb(&arb).expect("Import failed");
+        assert_eq!(imported.language().as_deref(), Some("fr"));
+        assert_eq!(imported.entries.len(), 2);
+        assert_eq!(imported.entries[0].value, "Vous avez {count} pièces");
+        assert_eq!(
+            imported.entries[0].annotations,
+            "@note=\"Coin counter\" @placeholder=count type=int example=3"
+        );
+    }
+
+    #[test]
+    fn test_arb_description_escapes() {
+        let arb = "{\n  \"quit\": \"Quit\",\n  \"@quit\": {\"description\": \"The \\\"Quit\\\" button\\nC:\\\\Games\"}\n}\n";
+        let imported = import_arb(arb).expect("Import failed");
+
+        let reparsed = LocaleDocument::parse(&imported.to_string()).expect("Parse failed");
+        assert_eq!(reparsed.entries.len(), 1);
+        assert_eq!(reparsed.entries[0].value, "Quit");
+        assert_eq!(
+            reparsed.entries[0].annotation_value("note").as_deref(),
+            Some("The \"Quit\" button\nC:\\Games")
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::locale_api::document::LocaleDocument;
+
+//Something a conversion could not carry over, so pipeline owners know what to re-add by hand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FidelityIssue {
+    //A whole entry is missing after the conversion
+    DroppedEntry { key: String },
+    ChangedValue { key: String },
+    DroppedAnnotation { key: String, name: String },
+    //A file level annotation such as `@language`
+    DroppedMetadata { name: String },
+    //Comments of an entry, or of the file header and end when key is None
+    DroppedComments { key: Option<String> },
+    //A field of the source format that has no equivalent in a localisation file, e.g. ARB `optionalParameters`
+    DroppedField { key: Option<String>, field: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FidelityReport {
+    pub issues: Vec<FidelityIssue>,
+}
+
+impl FidelityReport {
+    pub fn is_lossless(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn push(&mut self, issue: FidelityIssue) {
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+
+    pub fn extend(&mut self, other: FidelityReport) {
+        for issue in other.issues {
+            self.push(issue);
+        }
+    }
+
+    //Lists everything in the original that the converted document no longer has
+    pub fn compare(original: &LocaleDocument, converted: &LocaleDocument) -> Self {
+        let mut report = FidelityReport::default();
+        let converted_metadata = converted.metadata();
+        for annotation in original.metadata() {
+            if !converted_metadata.contains(&annotation) {
+                report.push(FidelityIssue::DroppedMetadata { name: annotation.name });
+            }
+        }
+        let count_comments =
+            |lines: &[String]| lines.iter().filter(|line| line.trim_start().starts_with("##")).count();
+        if count_comments(&original.header) + count_comments(&original.trailing_comments)
+            > count_comments(&converted.header) + count_comments(&converted.trailing_comments)
+        {
+            report.push(FidelityIssue::DroppedComments { key: None });
+        }
+
+        for entry in &original.entries {
+            let Some(converted_entry) = converted.find(&entry.key) else {
+                report.push(FidelityIssue::DroppedEntry { key: entry.key.clone() });
+                continue;
+            };
+            if converted_entry.parsed_value() != entry.parsed_value() {
+                report.push(FidelityIssue::ChangedValue { key: entry.key.clone() });
+            }
+            if converted_entry.comments.len() < entry.comments.len() {
+                report.push(FidelityIssue::DroppedComments {
+                    key: Some(entry.key.clone()),
+                });
+            }
+            let converted_annotations = converted_entry.parsed_annotations();
+            for annotation in entry.parsed_annotations() {
+                if !converted_annotations.contains(&annotation) {
+                    report.push(FidelityIssue::DroppedAnnotation {
+                        key: entry.key.clone(),
+                        name: annotation.name,
+                    });
+                }
+            }
+        }
+        report
+    }
+}
+
+//Copy of a document with only keys and values, what formats without any metadata keep
+pub fn strip_metadata(document: &LocaleDocument) -> LocaleDocument {
+    LocaleDocument {
+        entries: document
+            .entries
+            .iter()
+            .map(|entry| crate::locale_api::document::DocumentEntry {
+                key: entry.key.clone(),
+                value: entry.value.clone(),
+                block: entry.block,
+                literal: entry.literal,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::formats::FormatRegistry;
+
+    #[test]
+    fn test_conversion_report() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let source = b"@language=fr\n## Menu strings\n[[menu.play]] @context=main_menu\nJouer\n";
+
+        let (_, report) = registry
+            .convert_with_report(source, "r3l", "toml")
+            .expect("Convert failed");
+        assert_eq!(
+            report.issues,
+            vec![
+                FidelityIssue::DroppedMetadata {
+                    name: "language".to_string()
+                },
+                FidelityIssue::DroppedComments {
+                    key: Some("menu.play".to_string())
+                },
+                FidelityIssue::DroppedAnnotation {
+                    key: "menu.play".to_string(),
+                    name: "context".to_string()
+                },
+            ]
+        );
+
+        let (_, report) = registry
+            .convert_with_report(b"[[menu.play]] @context=main_menu\nJouer\n", "r3l", "arb")
+            .expect("Convert failed");
+        assert!(report.is_lossless());
+
+        let arb = b"{\"play\": \"Play\", \"@play\": {\"type\": \"text\"}, \"@@last_modified\": \"2024-01-01\"}";
+        let (_, report) = registry.convert_with_report(arb, "arb", "r3l").expect("Convert failed");
+        assert_eq!(report.issues.len(), 2);
+    }
+}
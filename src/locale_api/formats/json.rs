@@ -0,0 +1,436 @@
+use super::{FormatError, LocaleFormat, read_utf8};
+use crate::locale_api::annotations::{Annotation, format_annotations};
+use crate::locale_api::blocks::needs_block_as_literal;
+use crate::locale_api::document::{DocumentEntry, LineEnding, LocaleDocument, is_valid_key};
+use std::fmt::Write;
+
+//Parsed JSON, objects keep their key order so imported files keep the order of the source
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+//Deeper nesting is rejected as InvalidSyntax, localisation files never come close and it keeps recursion bounded
+const MAX_JSON_DEPTH: usize = 128;
+
+pub fn parse_json(text: &str) -> Result<JsonValue, FormatError> {
+    let mut parser = JsonParser {
+        text,
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position == text.len() {
+        Ok(value)
+    } else {
+        Err(FormatError::InvalidSyntax)
+    }
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    position: usize,
+    //Objects and arrays currently open
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), FormatError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            Ok(())
+        } else {
+            Err(FormatError::InvalidSyntax)
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, FormatError> {
+        self.skip_whitespace();
+        let rest = &self.text[self.position..];
+        match self.peek().ok_or(FormatError::InvalidSyntax)? {
+            '{' | '[' => {
+                if self.depth == MAX_JSON_DEPTH {
+                    return Err(FormatError::InvalidSyntax);
+                }
+                self.depth += 1;
+                let value = if rest.starts_with('{') { self.object() } else { self.array() };
+                self.depth -= 1;
+                value
+            }
+            '"' => self.string().map(JsonValue::String),
+            _ if rest.starts_with("null") => {
+                self.position += 4;
+                Ok(JsonValue::Null)
+            }
+            _ if rest.starts_with("true") => {
+                self.position += 4;
+                Ok(JsonValue::Bool(true))
+            }
+            _ if rest.starts_with("false") => {
+                self.position += 5;
+                Ok(JsonValue::Bool(false))
+            }
+            _ => {
+                let length = rest
+                    .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .unwrap_or(rest.len());
+                let number = rest[..length].parse::<f64>().map_err(|_| FormatError::InvalidSyntax)?;
+                self.position += length;
+                Ok(JsonValue::Number(number))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, FormatError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            self.expect(':')?;
+            members.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(FormatError::InvalidSyntax),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, FormatError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(FormatError::InvalidSyntax),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, FormatError> {
+        if self.peek() != Some('"') {
+            return Err(FormatError::InvalidSyntax);
+        }
+        self.position += 1;
+        let text = self.text;
+        let mut output = String::new();
+        let mut characters = text[self.position..].char_indices();
+        while let Some((offset, character)) = characters.next() {
+            match character {
+                '"' => {
+                    self.position += offset + 1;
+                    return Ok(output);
+                }
+                '\\' => match characters.next().map(|(_, escaped)| escaped) {
+                    Some('n') => output.push('\n'),
+                    Some('r') => output.push('\r'),
+                    Some('t') => output.push('\t'),
+                    Some('b') => output.push('\u{8}'),
+                    Some('f') => output.push('\u{c}'),
+                    Some('u') => {
+                        let mut code = hex_escape(&mut characters)?;
+                        //Characters outside the BMP are escaped as a high surrogate followed by a low one
+                        if (0xD800..0xDC00).contains(&code) {
+                            let low = match (characters.next(), characters.next()) {
+                                (Some((_, '\\')), Some((_, 'u'))) => hex_escape(&mut characters)?,
+                                _ => return Err(FormatError::InvalidSyntax),
+                            };
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return Err(FormatError::InvalidSyntax);
+                            }
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        output.push(char::from_u32(code).ok_or(FormatError::InvalidSyntax)?);
+                    }
+                    Some(escaped @ ('"' | '\\' | '/')) => output.push(escaped),
+                    _ => return Err(FormatError::InvalidSyntax),
+                },
+                c => output.push(c),
+            }
+        }
+        Err(FormatError::InvalidSyntax)
+    }
+}
+
+//The four hex digits after `\u`
+fn hex_escape(characters: &mut std::str::CharIndices) -> Result<u32, FormatError> {
+    let mut code = 0;
+    for _ in 0..4 {
+        let digit = characters
+            .next()
+            .and_then(|(_, digit)| digit.to_digit(16))
+            .ok_or(FormatError::InvalidSyntax)?;
+        code = code * 16 + digit;
+    }
+    Ok(code)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum JsonFlavor {
+    //`{"key": "value"}`, the plain JSON format of Weblate and Crowdin
+    Flat,
+    //`{"key": {"message": "value", "description": "..."}}`, the WebExtension/Chrome JSON format both platforms support.
+    //Keys have to be valid message names.
+    WebExtension,
+}
+
+//Exports a document for hosted translation platforms, keeping the file's key order. WebExtension exports fail with
+//UnsupportedStructure for keys that aren't valid message names, e.g. `menu.play`.
+pub fn export_json(document: &LocaleDocument, flavor: JsonFlavor) -> Result<String, FormatError> {
+    if flavor == JsonFlavor::WebExtension && !document.entries.iter().all(|entry| is_message_name(&entry.key)) {
+        return Err(FormatError::UnsupportedStructure);
+    }
+    let mut output = String::from("{");
+    for (index, entry) in document.entries.iter().enumerate() {
+        output.push_str(if index == 0 { "\n" } else { ",\n" });
+        let value = entry.parsed_value();
+        match flavor {
+            JsonFlavor::Flat => {
+                let _ = write!(output, "  {}: {}", quote(&entry.key), quote(&value));
+            }
+            JsonFlavor::WebExtension => {
+                let _ = write!(output, "  {}: {{\n    \"message\": {}", quote(&entry.key), quote(&value));
+                if let Some(description) = entry.annotation_value("note") {
+                    let _ = write!(output, ",\n    \"description\": {}", quote(&description));
+                }
+                if let Some(context) = entry.annotation_value("context") {
+                    let _ = write!(output, ",\n    \"context\": {}", quote(&context));
+                }
+                output.push_str("\n  }");
+            }
+        }
+    }
+    output.push_str(if document.entries.is_empty() { "}\n" } else { "\n}\n" });
+    Ok(output)
+}
+
+//Message names of the WebExtension format are ASCII letters, digits, `_` and `@`, and names starting with `@@` are
+//reserved for the browser
+fn is_message_name(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with("@@")
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+}
+
+//Reads both flavors, WebExtension descriptions are kept as `@note` annotations and contexts as `@context`
+pub fn import_json(text: &str) -> Result<LocaleDocument, FormatError> {
+    let JsonValue::Object(members) = parse_json(text)? else {
+        return Err(FormatError::UnsupportedStructure);
+    };
+
+    let mut document = LocaleDocument {
+        line_ending: LineEnding::detect(text),
+        ..Default::default()
+    };
+    for (key, value) in members {
+        if !is_valid_key(&key) {
+            return Err(FormatError::UnsupportedStructure);
+        }
+        let (value, fields) = match &value {
+            JsonValue::String(text) => (text.clone(), Vec::new()),
+            object => (
+                object
+                    .get("message")
+                    .and_then(JsonValue::as_str)
+                    .ok_or(FormatError::UnsupportedStructure)?
+                    .to_string(),
+                [("context", "context"), ("note", "description")]
+                    .into_iter()
+                    .filter_map(|(name, field)| Some((name, object.get(field)?.as_str()?)))
+                    .collect(),
+            ),
+        };
+        let annotations: Vec<Annotation> = fields
+            .into_iter()
+            .map(|(name, value)| Annotation {
+                name: name.to_string(),
+                value: Some(value.to_string()),
+                args: Vec::new(),
+            })
+            .collect();
+        let annotations = format_annotations(&annotations);
+        document.entries.push(DocumentEntry {
+            key,
+            annotations,
+            block: needs_block_as_literal(&value),
+            literal: true,
+            value,
+            ..Default::default()
+        });
+    }
+    Ok(document)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonFormat {
+    pub flavor: JsonFlavor,
+}
+
+impl Default for JsonFormat {
+    fn default() -> Self {
+        Self {
+            flavor: JsonFlavor::Flat,
+        }
+    }
+}
+
+impl LocaleFormat for JsonFormat {
+    fn name(&self) -> &str {
+        match self.flavor {
+            JsonFlavor::Flat => "json",
+            JsonFlavor::WebExtension => "webextension",
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        import_json(read_utf8(bytes)?)
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        export_json(document, self.flavor).map(String::into_bytes)
+    }
+}
+
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let value = parse_json("{\"b\": [1, true, null], \"a\": \"line\\n\\u00e9\"}").expect("Parse failed");
+        assert_eq!(value.get("a").and_then(JsonValue::as_str), Some("line\né"));
+        assert_eq!(
+            value.get("b"),
+            Some(&JsonValue::Array(vec![JsonValue::Number(1.0), JsonValue::Bool(true), JsonValue::Null]))
+        );
+        assert!(matches!(parse_json("{\"a\": }"), Err(FormatError::InvalidSyntax)));
+    }
+
+    #[test]
+    fn test_parse_json_unicode_escapes() {
+        assert_eq!(parse_json("\"\\uD83D\\uDE00\"").ok(), Some(JsonValue::String("😀".to_string())));
+        assert_eq!(parse_json("\"\\u00E9\"").ok(), Some(JsonValue::String("é".to_string())));
+        //A lone or reversed surrogate is not a character
+        assert!(matches!(parse_json("\"\\uD83D\""), Err(FormatError::InvalidSyntax)));
+        assert!(matches!(parse_json("\"\\uDE00\\uD83D\""), Err(FormatError::InvalidSyntax)));
+        assert!(matches!(parse_json("\"\\u+0e9\""), Err(FormatError::InvalidSyntax)));
+    }
+
+    #[test]
+    fn test_parse_json_depth() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_json(&nested(MAX_JSON_DEPTH)).is_ok());
+        assert!(matches!(parse_json(&nested(MAX_JSON_DEPTH + 1)), Err(FormatError::InvalidSyntax)));
+        assert!(matches!(parse_json(&"[".repeat(1_000_000)), Err(FormatError::InvalidSyntax)));
+    }
+
+    #[test]
+    fn test_export_json() {
+        let document = LocaleDocument::parse("[[title]] @context=main_menu @note=\"Game title\"\nSay \"hi\"\nnow\n[[quit]]\nQuit\n")
+            .expect("Parse failed");
+
+        assert_eq!(
+            export_json(&document, JsonFlavor::Flat).ok().as_deref(),
+            Some("{\n  \"title\": \"Say \\\"hi\\\"\\nnow\",\n  \"quit\": \"Quit\"\n}\n")
+        );
+        let exported = export_json(&document, JsonFlavor::WebExtension).expect("Export failed");
+        assert_eq!(
+            exported,
+            "{\n  \"title\": {\n    \"message\": \"Say \\\"hi\\\"\\nnow\",\n    \"description\": \"Game title\",\n    \"context\": \"main_menu\"\n  },\n  \"quit\": {\n    \"message\": \"Quit\"\n  }\n}\n"
+        );
+        let imported = import_json(&exported).expect("Import failed");
+        assert_eq!(imported.entries[0].annotation_value("context").as_deref(), Some("main_menu"));
+        assert_eq!(imported.entries[0].annotation_value("note").as_deref(), Some("Game title"));
+
+        let dotted = LocaleDocument::parse("[[menu.play]]\nPlay\n").expect("Parse failed");
+        assert!(export_json(&dotted, JsonFlavor::Flat).is_ok());
+        assert!(matches!(export_json(&dotted, JsonFlavor::WebExtension), Err(FormatError::UnsupportedStructure)));
+    }
+
+    #[test]
+    fn test_import_json_keys() {
+        assert!(import_json("{\"menu.play\": \"Play\"}").is_ok());
+        for key in ["a]]b", "a\nb", " padded", ""] {
+            let text = format!("{{{}: \"Value\"}}", quote(key));
+            assert!(matches!(import_json(&text), Err(FormatError::UnsupportedStructure)), "{key:?}");
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::fidelity::{FidelityReport, strip_metadata};
+use super::{FormatError, LocaleFormat};
+use crate::locale_api::document::{DocumentEntry, LineEnding, LocaleDocument};
+use std::borrow::Cow;
+
+//GUID Unreal writes at the start of every versioned .locres file
+const LOCRES_MAGIC: [u32; 4] = [0x7574140E, 0xFC034A67, 0x9D90154A, 0x1B7F37C3];
+
+//Compact is the oldest versioned layout, every engine version since 4.14 can load it
+const LOCRES_VERSION_COMPACT: u8 = 1;
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+//Unreal's FCrc::StrCrc32, a CRC32 over every UTF-16 TCHAR widened to 4 bytes
+pub fn str_crc32(text: &str) -> u32 {
+    let mut crc = !0u32;
+    for unit in text.encode_utf16() {
+        for byte in u32::from(unit).to_le_bytes() {
+            crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize];
+        }
+    }
+    !crc
+}
+
+//Exports all entries into one namespace, the source document provides the hashes Unreal uses to spot outdated translations
+pub fn export_locres(document: &LocaleDocument, namespace: &str, source: Option<&LocaleDocument>) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::new();
+    for part in LOCRES_MAGIC {
+        output.extend_from_slice(&part.to_le_bytes());
+    }
+    output.push(LOCRES_VERSION_COMPACT);
+    let offset_position = output.len();
+    output.extend_from_slice(&0i64.to_le_bytes());
+
+    //Identical translations are stored once and referenced by index
+    let values: Vec<Cow<'_, str>> = document.entries.iter().map(DocumentEntry::parsed_value).collect();
+    let mut strings: Vec<&str> = Vec::new();
+    output.extend_from_slice(&1u32.to_le_bytes());
+    write_fstring(&mut output, namespace);
+    output.extend_from_slice(&(document.entries.len() as u32).to_le_bytes());
+    for (entry, value) in document.entries.iter().zip(&values) {
+        let source_value = source
+            .and_then(|source| source.find(&entry.key))
+            .map_or_else(|| value.clone(), DocumentEntry::parsed_value);
+        let index = match strings.iter().position(|known| *known == value.as_ref()) {
+            Some(index) => index,
+            None => {
+                strings.push(value);
+                strings.len() - 1
+            }
+        };
+        write_fstring(&mut output, &entry.key);
+        output.extend_from_slice(&str_crc32(&source_value).to_le_bytes());
+        output.extend_from_slice(&(index as i32).to_le_bytes());
+    }
+
+    let strings_offset = output.len() as i64;
+    output[offset_position..offset_position + 8].copy_from_slice(&strings_offset.to_le_bytes());
+    output.extend_from_slice(&(strings.len() as i32).to_le_bytes());
+    for value in strings {
+        write_fstring(&mut output, value);
+    }
+    output
+}
+
+//Export only, .locres files are compiled by Unreal and not meant to be edited
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocresFormat {
+    pub namespace: String,
+}
+
+impl LocaleFormat for LocresFormat {
+    fn name(&self) -> &str {
+        "locres"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locres"]
+    }
+
+    fn read(&self, _bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        Err(FormatError::UnsupportedFormat)
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        Ok(export_locres(document, &self.namespace, None))
+    }
+
+    //Binary, there are no line breaks to convert
+    fn write_with_line_ending(&self, document: &LocaleDocument, _line_ending: LineEnding) -> Result<Vec<u8>, FormatError> {
+        self.write(document)
+    }
+
+    fn write_with_report(&self, document: &LocaleDocument) -> Result<(Vec<u8>, FidelityReport), FormatError> {
+        let report = FidelityReport::compare(document, &strip_metadata(document));
+        Ok((self.write(document)?, report))
+    }
+}
+
+//FString: length with the null terminator, negative when the text is stored as UTF-16
+fn write_fstring(output: &mut Vec<u8>, text: &str) {
+    if text.is_empty() {
+        output.extend_from_slice(&0i32.to_le_bytes());
+    } else if text.is_ascii() {
+        output.extend_from_slice(&(text.len() as i32 + 1).to_le_bytes());
+        output.extend_from_slice(text.as_bytes());
+        output.push(0);
+    } else {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        output.extend_from_slice(&(-(units.len() as i32 + 1)).to_le_bytes());
+        for unit in units {
+            output.extend_from_slice(&unit.to_le_bytes());
+        }
+        output.extend_from_slice(&0u16.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_locres() {
+        let document = LocaleDocument::parse("[[a]]\nb\n[[c]]\nb\n[[d]]\né\n").expect("Parse failed");
+        let bytes = export_locres(&document, "", None);
+
+        assert_eq!(bytes[16], LOCRES_VERSION_COMPACT);
+        //Header, one empty namespace and three keys of one character
+        let strings_offset = 25 + 4 + 4 + 4 + 3 * (6 + 4 + 4);
+        assert_eq!(i64::from_le_bytes(bytes[17..25].try_into().unwrap()), strings_offset as i64);
+        //Two unique strings, one ASCII and one UTF-16
+        assert_eq!(&bytes[strings_offset..strings_offset + 4], &2i32.to_le_bytes());
+        assert_eq!(&bytes[strings_offset + 4..strings_offset + 10], &[2, 0, 0, 0, b'b', 0]);
+        assert_eq!(&bytes[strings_offset + 10..], &[0xFE, 0xFF, 0xFF, 0xFF, 0xE9, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_str_crc32() {
+        assert_eq!(str_crc32(""), 0);
+        assert_ne!(str_crc32("a"), str_crc32("b"));
+        assert_eq!(str_crc32("a"), 0xA2DE4F7A);
+        //Characters outside the BMP are hashed as their surrogate pair
+        assert_eq!(str_crc32("\u{1F600}"), 0x64D1CCDD);
+    }
+}
@@ -0,0 +1,276 @@
+pub mod arb;
+pub mod fidelity;
+pub mod json;
+pub mod locres;
+pub mod po;
+pub mod toml;
+pub mod yaml;
+
+use super::document::{LineEnding, LocaleDocument, WriteOptions};
+use fidelity::FidelityReport;
+use std::borrow::Cow;
+use std::path::Path;
+
+#[derive(Debug)]
+#[repr(C)]
+pub enum FormatError {
+    Normal,
+    InvalidSyntax,
+    //The file is valid but uses a structure that has no equivalent in a localisation file
+    UnsupportedStructure,
+    InvalidUTF8Value,
+    //The format can only be written, or no registered format matches the name or extension
+    UnsupportedFormat,
+}
+
+//A file format that can be converted to and from a LocaleDocument, implement this to add formats from other crates
+pub trait LocaleFormat: Send + Sync {
+    //Name used to pick the format explicitly, e.g. `yaml`
+    fn name(&self) -> &str;
+    //File extensions without the dot, used to pick the format from a path
+    fn extensions(&self) -> &[&str];
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError>;
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError>;
+
+    //Formats with fields that have no equivalent in a localisation file should report what they skip
+    fn read_with_report(&self, bytes: &[u8]) -> Result<(LocaleDocument, FidelityReport), FormatError> {
+        Ok((self.read(bytes)?, FidelityReport::default()))
+    }
+
+    //Text formats are written with LF and converted here, binary formats need to override this to skip the conversion
+    fn write_with_line_ending(&self, document: &LocaleDocument, line_ending: LineEnding) -> Result<Vec<u8>, FormatError> {
+        let bytes = self.write(document)?;
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(line_ending.resolve(document.line_ending).apply(text).into_bytes()),
+            Err(error) => Ok(error.into_bytes()),
+        }
+    }
+
+    //Reads the written bytes back to find out what was lost, write only formats need to override this
+    fn write_with_report(&self, document: &LocaleDocument) -> Result<(Vec<u8>, FidelityReport), FormatError> {
+        let bytes = self.write(document)?;
+        let read_back = self.read(&bytes)?;
+        let report = FidelityReport::compare(document, &read_back);
+        Ok((bytes, report))
+    }
+}
+
+pub(crate) fn read_utf8(bytes: &[u8]) -> Result<&str, FormatError> {
+    std::str::from_utf8(bytes).map_err(|_| FormatError::InvalidUTF8Value)
+}
+
+//Formats that can be looked up by name or extension, later registrations win over earlier ones
+#[derive(Default)]
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn LocaleFormat>>,
+}
+
+impl FormatRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //All formats shipped with this crate, including `.r3l` itself
+    pub fn with_builtin_formats() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(R3lFormat));
+        registry.register(Box::new(json::JsonFormat {
+            flavor: json::JsonFlavor::WebExtension,
+        }));
+        registry.register(Box::new(json::JsonFormat::default()));
+        registry.register(Box::new(arb::ArbFormat));
+        registry.register(Box::new(yaml::YamlFormat::default()));
+        registry.register(Box::new(toml::TomlFormat));
+        registry.register(Box::new(po::PoFormat));
+        registry.register(Box::new(locres::LocresFormat::default()));
+        registry
+    }
+
+    pub fn register(&mut self, format: Box<dyn LocaleFormat>) {
+        self.formats.push(format);
+    }
+
+    pub fn formats(&self) -> impl DoubleEndedIterator<Item = &dyn LocaleFormat> {
+        self.formats.iter().map(|format| format.as_ref())
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&dyn LocaleFormat> {
+        self.formats().rev().find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn by_extension(&self, extension: &str) -> Option<&dyn LocaleFormat> {
+        self.formats().rev().find(|format| {
+            format
+                .extensions()
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+        })
+    }
+
+    pub fn for_path(&self, path: &Path) -> Option<&dyn LocaleFormat> {
+        self.by_extension(path.extension()?.to_str()?)
+    }
+
+    //Keeps the line ending of the input
+    pub fn convert(&self, bytes: &[u8], from: &str, to: &str) -> Result<Vec<u8>, FormatError> {
+        let from = self.by_name(from).ok_or(FormatError::UnsupportedFormat)?;
+        let to = self.by_name(to).ok_or(FormatError::UnsupportedFormat)?;
+        to.write_with_line_ending(&from.read(bytes)?, LineEnding::Preserve)
+    }
+
+    //Same as convert, but also lists everything either side of the conversion couldn't keep
+    pub fn convert_with_report(
+        &self,
+        bytes: &[u8],
+        from: &str,
+        to: &str,
+    ) -> Result<(Vec<u8>, FidelityReport), FormatError> {
+        let from = self.by_name(from).ok_or(FormatError::UnsupportedFormat)?;
+        let to = self.by_name(to).ok_or(FormatError::UnsupportedFormat)?;
+        let (document, mut report) = from.read_with_report(bytes)?;
+        let (converted, write_report) = to.write_with_report(&document)?;
+        report.extend(write_report);
+        Ok((converted, report))
+    }
+}
+
+pub struct R3lFormat;
+
+impl LocaleFormat for R3lFormat {
+    fn name(&self) -> &str {
+        "r3l"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["r3l"]
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        LocaleDocument::parse(read_utf8(bytes)?).map_err(|_| FormatError::InvalidSyntax)
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        Ok(document.to_string().into_bytes())
+    }
+
+    fn write_with_line_ending(&self, document: &LocaleDocument, line_ending: LineEnding) -> Result<Vec<u8>, FormatError> {
+        let options = WriteOptions {
+            line_ending,
+            ..Default::default()
+        };
+        Ok(document.write(&options).into_bytes())
+    }
+}
+
+//Keys split on a separator into nested groups, for formats like YAML that nest instead of using flat keys
+#[derive(Debug, Default)]
+pub(crate) struct KeyTree {
+    pub name: String,
+    pub value: Option<String>,
+    pub children: Vec<KeyTree>,
+}
+
+impl KeyTree {
+    //Fails when a key is both a value and a group, e.g. `menu` and `menu.play`
+    pub fn from_entries<'a>(
+        entries: impl IntoIterator<Item = (&'a str, Cow<'a, str>)>,
+        separator: &str,
+    ) -> Result<Self, FormatError> {
+        let mut root = KeyTree::default();
+        for (key, value) in entries {
+            let mut node = &mut root;
+            for part in key.split(separator) {
+                if node.value.is_some() {
+                    return Err(FormatError::UnsupportedStructure);
+                }
+                let index = match node.children.iter().position(|child| child.name == part) {
+                    Some(index) => index,
+                    None => {
+                        node.children.push(KeyTree {
+                            name: part.to_string(),
+                            ..Default::default()
+                        });
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[index];
+            }
+            if node.value.is_some() || !node.children.is_empty() {
+                return Err(FormatError::UnsupportedStructure);
+            }
+            node.value = Some(value.into_owned());
+        }
+        Ok(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct UpperCaseFormat;
+
+    impl LocaleFormat for UpperCaseFormat {
+        fn name(&self) -> &str {
+            "upper"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["up"]
+        }
+
+        fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+            R3lFormat.read(bytes)
+        }
+
+        fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+            Ok(document.to_string().to_uppercase().into_bytes())
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = FormatRegistry::with_builtin_formats();
+        registry.register(Box::new(UpperCaseFormat));
+
+        assert_eq!(registry.for_path(Path::new("strings/fr.YAML")).map(|format| format.name()), Some("yaml"));
+        assert_eq!(registry.by_extension("up").map(|format| format.name()), Some("upper"));
+        assert_eq!(registry.by_name("po").map(|format| format.name()), Some("po"));
+        assert_eq!(registry.for_path(Path::new("x.po")).map(|format| format.name()), Some("po"));
+        assert_eq!(registry.for_path(Path::new("x.pot")).map(|format| format.name()), Some("po"));
+
+        let converted = registry.convert(b"[[menu.play]]\nPlay\n", "r3l", "toml").expect("Convert failed");
+        assert_eq!(converted, b"[menu]\nplay = \"Play\"\n");
+        let converted = registry.convert(b"[[key]]\nvalue\n", "r3l", "upper").expect("Convert failed");
+        assert_eq!(converted, b"[[KEY]]\nVALUE\n");
+    }
+
+    #[test]
+    fn test_convert_without_comments() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let source = b"[[a]]\nHello ## translator note\n[[b]]\nLine one\n## Whole line comment\nLine two\n";
+        let converted = registry.convert(source, "r3l", "json").expect("Convert failed");
+        assert_eq!(converted, b"{\n  \"a\": \"Hello\",\n  \"b\": \"Line one\\n\\nLine two\"\n}\n");
+
+        //Values match what the parser loads from the same file
+        let table = crate::locale_api::parser::parse_r3locale_bytes(&mut source.to_vec()).expect("Parse failed");
+        let text = std::str::from_utf8(source).expect("Invalid UTF-8");
+        let document = LocaleDocument::parse(text).expect("Parse failed");
+        for entry in &document.entries {
+            assert_eq!(table.find_entry(entry.key.as_bytes()), Some(&*entry.parsed_value()));
+        }
+    }
+
+    #[test]
+    fn test_convert_escaped_comments() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let converted = registry.convert(b"[[a]]\nIssue \\##42 ## tracker\n", "r3l", "json").expect("Convert failed");
+        assert_eq!(converted, b"{\n  \"a\": \"Issue ##42\"\n}\n");
+
+        //Comment markers in imported text are escaped rather than moved into a block
+        let converted = registry.convert(b"{\"a\": \"Issue ##42\"}", "json", "r3l").expect("Convert failed");
+        assert_eq!(converted, b"[[a]]\nIssue \\##42\n");
+        let table = crate::locale_api::parser::parse_r3locale_bytes(&mut converted.clone()).expect("Parse failed");
+        assert_eq!(table.find_entry(b"a"), Some("Issue ##42"));
+    }
+}
@@ -0,0 +1,289 @@
+use super::fidelity::{FidelityIssue, FidelityReport};
+use super::{FormatError, LocaleFormat, read_utf8};
+use crate::locale_api::annotations::{Annotation, format_annotations};
+use crate::locale_api::blocks::needs_block_as_literal;
+use crate::locale_api::document::{DocumentEntry, LineEnding, LocaleDocument};
+
+//Fields kept in LocaleDocument::source_metadata so exporting back to PO writes the same entry
+const RETAINED_COMMENTS: [(&str, &str); 4] = [("#.", "extracted"), ("#:", "reference"), ("#,", "flags"), ("#|", "previous")];
+
+//Header field of PO files written from .r3l documents. Their entries are keyed by `msgid`, and `msgctxt` holds the
+//`@context` annotation instead of the key.
+const MSGID_KEYS_FIELD: &str = "X-R3L-Keys: msgid";
+
+fn keyed_by_msgid(header: &str) -> bool {
+    header.lines().any(|line| line.trim() == MSGID_KEYS_FIELD)
+}
+
+//Gettext PO files, keyed by `msgctxt` when present and by `msgid` otherwise. Files with the MSGID_KEYS_FIELD header
+//are keyed by `msgid`, and their `msgctxt` becomes the `@context` annotation.
+pub fn import_po(text: &str) -> Result<LocaleDocument, FormatError> {
+    let mut document = LocaleDocument {
+        line_ending: LineEnding::detect(text),
+        ..Default::default()
+    };
+    let mut msgid_keys = false;
+    //Blocks are separated by blank lines, which only show up as "\n\n" once CRLF is normalized
+    let text = text.replace("\r\n", "\n");
+    for block in text.split("\n\n").map(str::trim).filter(|block| !block.is_empty()) {
+        let mut comments: Vec<String> = Vec::new();
+        let mut fields: Vec<(String, String)> = Vec::new();
+        let mut current: Option<(String, String)> = None;
+        for line in block.lines().map(str::trim) {
+            if let Some(continuation) = line.strip_prefix('"') {
+                let (_, value) = current.as_mut().ok_or(FormatError::InvalidSyntax)?;
+                value.push_str(&unescape(continuation)?);
+                continue;
+            }
+            fields.extend(current.take());
+
+            if let Some((_, name)) = RETAINED_COMMENTS.iter().find(|(prefix, _)| line.starts_with(prefix)) {
+                fields.push((name.to_string(), line[2..].trim().to_string()));
+            } else if let Some(comment) = line.strip_prefix('#') {
+                comments.push(format!("##{comment}"));
+            } else {
+                let (keyword, quoted) = line.split_once(' ').ok_or(FormatError::InvalidSyntax)?;
+                let quoted = quoted.trim().strip_prefix('"').ok_or(FormatError::InvalidSyntax)?;
+                current = Some((keyword.to_string(), unescape(quoted)?));
+            }
+        }
+        fields.extend(current.take());
+
+        let field = |name: &str| fields.iter().find(|(field, _)| field == name).map(|(_, value)| value.clone());
+        let msgid = field("msgid").ok_or(FormatError::InvalidSyntax)?;
+        let msgstr = field("msgstr").or_else(|| field("msgstr[0]")).unwrap_or_default();
+        //The entry with an empty msgid is the file header, e.g. `Language: fr`
+        if msgid.is_empty() {
+            if let Some(language) = msgstr
+                .lines()
+                .find_map(|line| line.strip_prefix("Language:"))
+                .map(str::trim)
+                .filter(|language| !language.is_empty())
+            {
+                document.header.push(format!("@language={language}"));
+            }
+            document.header.extend(comments);
+            msgid_keys = keyed_by_msgid(&msgstr);
+            document.source_metadata.insert(String::new(), vec![("header".to_string(), msgstr)]);
+            continue;
+        }
+
+        let (key, annotations) = match field("msgctxt") {
+            Some(context) if msgid_keys => (msgid.clone(), context_annotation(context)),
+            context => (context.unwrap_or_else(|| msgid.clone()), String::new()),
+        };
+        let retained: Vec<(String, String)> = fields
+            .into_iter()
+            .filter(|(name, value)| name != "msgstr" && name != "msgstr[0]" && !(name == "msgid" && *value == key))
+            .filter(|(name, _)| !(msgid_keys && name == "msgctxt"))
+            .collect();
+        if !retained.is_empty() {
+            document.source_metadata.insert(key.clone(), retained);
+        }
+        document.entries.push(DocumentEntry {
+            comments,
+            key,
+            annotations,
+            block: needs_block_as_literal(&msgstr),
+            literal: true,
+            value: msgstr,
+            ..Default::default()
+        });
+    }
+    Ok(document)
+}
+
+fn context_annotation(context: String) -> String {
+    format_annotations(&[Annotation {
+        name: "context".to_string(),
+        value: Some(context),
+        args: Vec::new(),
+    }])
+}
+
+fn unescape(quoted: &str) -> Result<String, FormatError> {
+    let inner = quoted.strip_suffix('"').ok_or(FormatError::InvalidSyntax)?;
+    let mut value = String::with_capacity(inner.len());
+    let mut characters = inner.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some(escaped @ ('"' | '\\')) => value.push(escaped),
+                _ => return Err(FormatError::InvalidSyntax),
+            },
+            c => value.push(c),
+        }
+    }
+    Ok(value)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+//The header imported with the document, or a new one keyed by msgid for documents from other formats
+fn header(document: &LocaleDocument) -> String {
+    if let Some(header) = document.source_field("", "header") {
+        return header.to_string();
+    }
+    let mut header = String::from("Content-Type: text/plain; charset=UTF-8\n");
+    if let Some(language) = document.language() {
+        header.push_str(&format!("Language: {language}\n"));
+    }
+    header.push_str(&format!("{MSGID_KEYS_FIELD}\n"));
+    header
+}
+
+//The `@context` annotation written as `msgctxt`, None when the key or a retained context already takes `msgctxt`
+fn exported_context(document: &LocaleDocument, entry: &DocumentEntry, msgid_keys: bool) -> Option<String> {
+    let field = |name: &str| document.source_field(&entry.key, name);
+    if !msgid_keys || field("msgid").is_some() || field("msgctxt").is_some() {
+        return None;
+    }
+    entry.annotation_value("context")
+}
+
+//Fields retained by import_po are written back in their usual order
+pub fn export_po(document: &LocaleDocument) -> String {
+    let mut output = String::new();
+    let header = header(document);
+    let msgid_keys = keyed_by_msgid(&header);
+    for comment in document.header.iter().filter_map(|line| line.strip_prefix("##")) {
+        output.push_str(&format!("#{comment}\n"));
+    }
+    output.push_str(&format!("msgid \"\"\nmsgstr {}\n", escape(&header)));
+
+    for entry in &document.entries {
+        output.push('\n');
+        for comment in entry.comments.iter().filter_map(|line| line.strip_prefix("##")) {
+            output.push_str(&format!("#{comment}\n"));
+        }
+        let field = |name: &str| document.source_field(&entry.key, name);
+        for (prefix, name) in RETAINED_COMMENTS {
+            if let Some(value) = field(name) {
+                output.push_str(&format!("{prefix} {value}\n"));
+            }
+        }
+        //Without a retained msgid the key was the msgid
+        match field("msgid") {
+            Some(msgid) => {
+                output.push_str(&format!("msgctxt {}\nmsgid {}\n", escape(&entry.key), escape(msgid)));
+            }
+            None => {
+                let context = field("msgctxt").map(str::to_string);
+                if let Some(context) = context.or_else(|| exported_context(document, entry, msgid_keys)) {
+                    output.push_str(&format!("msgctxt {}\n", escape(&context)));
+                }
+                output.push_str(&format!("msgid {}\n", escape(&entry.key)));
+            }
+        }
+        let value = entry.parsed_value();
+        if let Some(plural) = field("msgid_plural") {
+            output.push_str(&format!("msgid_plural {}\nmsgstr[0] {}\n", escape(plural), escape(&value)));
+            let mut index = 1;
+            while let Some(form) = field(&format!("msgstr[{index}]")) {
+                output.push_str(&format!("msgstr[{index}] {}\n", escape(form)));
+                index += 1;
+            }
+        } else {
+            output.push_str(&format!("msgstr {}\n", escape(&value)));
+        }
+    }
+    output
+}
+
+pub struct PoFormat;
+
+impl LocaleFormat for PoFormat {
+    fn name(&self) -> &str {
+        "po"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["po", "pot"]
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        import_po(read_utf8(bytes)?)
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        Ok(export_po(document).into_bytes())
+    }
+
+    //Annotations have no PO equivalent besides `@context` written as `msgctxt`, everything else survives the round trip
+    fn write_with_report(&self, document: &LocaleDocument) -> Result<(Vec<u8>, FidelityReport), FormatError> {
+        let mut report = FidelityReport::default();
+        let msgid_keys = keyed_by_msgid(&header(document));
+        for entry in &document.entries {
+            let context_written = exported_context(document, entry, msgid_keys).is_some();
+            for annotation in entry.parsed_annotations() {
+                if context_written && annotation.name == "context" {
+                    continue;
+                }
+                report.push(FidelityIssue::DroppedAnnotation {
+                    key: entry.key.clone(),
+                    name: annotation.name,
+                });
+            }
+        }
+        Ok((self.write(document)?, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_po_round_trip() {
+        let text = "msgid \"\"\nmsgstr \"Language: fr\\n\"\n\n# Shown on the title screen\n#: src/menu.c:12\n#, fuzzy\nmsgctxt \"menu.play\"\nmsgid \"Play\"\nmsgstr \"Jouer\"\n\nmsgid \"Quit\"\nmsgstr \"\"\n\"Quitter \"\n\"le jeu\"\n";
+        let document = import_po(text).expect("Import failed");
+
+        assert_eq!(document.language().as_deref(), Some("fr"));
+        assert_eq!(document.entries[0].key, "menu.play");
+        assert_eq!(document.entries[0].value, "Jouer");
+        assert_eq!(document.entries[0].comments, vec!["## Shown on the title screen"]);
+        assert_eq!(document.source_field("menu.play", "flags"), Some("fuzzy"));
+        assert_eq!(document.source_field("menu.play", "msgid"), Some("Play"));
+        assert_eq!(document.find("Quit").map(|entry| entry.value.as_str()), Some("Quitter le jeu"));
+
+        let exported = export_po(&document);
+        assert_eq!(import_po(&exported).expect("Import failed"), document);
+        assert!(exported.contains("#: src/menu.c:12\n#, fuzzy\nmsgctxt \"menu.play\"\nmsgid \"Play\"\nmsgstr \"Jouer\"\n"));
+    }
+
+    #[test]
+    fn test_context_round_trip() {
+        let source = "@language=fr\n[[menu.play]] @context=\"main menu\"\nJouer\n\n[[menu.quit]]\nQuitter\n";
+        let document = LocaleDocument::parse(source).expect("Parse failed");
+        let (exported, report) = PoFormat.write_with_report(&document).expect("Export failed");
+        assert!(report.is_lossless());
+        let exported = String::from_utf8(exported).expect("Invalid UTF-8");
+        assert!(exported.contains("msgctxt \"main menu\"\nmsgid \"menu.play\"\nmsgstr \"Jouer\"\n"));
+
+        let imported = import_po(&exported).expect("Import failed");
+        assert_eq!(imported.language().as_deref(), Some("fr"));
+        let keys: Vec<&str> = imported.entries.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, vec!["menu.play", "menu.quit"]);
+        assert_eq!(imported.entries[0].annotation_value("context").as_deref(), Some("main menu"));
+        assert_eq!(imported.entries[1].annotations, "");
+        assert_eq!(export_po(&imported), exported);
+    }
+}
@@ -0,0 +1,290 @@
+use super::json::quote;
+use super::{FormatError, KeyTree, LocaleFormat, read_utf8};
+use crate::locale_api::blocks::needs_block_as_literal;
+use crate::locale_api::document::{DocumentEntry, LineEnding, LocaleDocument};
+
+//TOML tables become key namespaces joined with dots, `[menu]` + `play = "Play"` is the key `menu.play`
+pub fn import_toml(text: &str) -> Result<LocaleDocument, FormatError> {
+    let mut document = LocaleDocument {
+        line_ending: LineEnding::detect(text),
+        ..Default::default()
+    };
+    let mut table: Vec<String> = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (line, remainder) = rest.split_once('\n').unwrap_or((rest, ""));
+        rest = remainder;
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = content.strip_prefix('[') {
+            //Arrays of tables have no equivalent in a flat key list
+            if header.starts_with('[') {
+                return Err(FormatError::UnsupportedStructure);
+            }
+            let header = strip_comment(header).strip_suffix(']').ok_or(FormatError::InvalidSyntax)?;
+            table = split_key(header)?;
+            continue;
+        }
+
+        //Quoted keys may contain `=`
+        let split = find_unquoted(content, '=').ok_or(FormatError::InvalidSyntax)?;
+        let (key, value_text) = (&content[..split], &content[split + 1..]);
+        let value_text = value_text.trim_start();
+        let value = if let Some(multiline) = value_text.strip_prefix("\"\"\"") {
+            //The string can span several lines, so it is read from the rest of the file
+            let (value, after) = multiline_string(multiline, rest)?;
+            rest = after;
+            value
+        } else {
+            let (value, trailing) = single_line_string(value_text)?;
+            if !strip_comment(trailing).trim().is_empty() {
+                return Err(FormatError::InvalidSyntax);
+            }
+            value
+        };
+
+        let mut parts = table.clone();
+        parts.extend(split_key(key)?);
+        document.entries.push(DocumentEntry {
+            key: parts.join("."),
+            block: needs_block_as_literal(&value),
+            literal: true,
+            value,
+            ..Default::default()
+        });
+    }
+    Ok(document)
+}
+
+fn strip_comment(text: &str) -> &str {
+    find_unquoted(text, '#').map_or(text, |position| &text[..position]).trim()
+}
+
+//Position of the first `target` that is not inside a `"..."` or `'...'` string
+fn find_unquoted(text: &str, target: char) -> Option<usize> {
+    let mut quote = None;
+    let mut characters = text.char_indices();
+    while let Some((position, character)) = characters.next() {
+        match (quote, character) {
+            (None, c) if c == target => return Some(position),
+            (None, '"' | '\'') => quote = Some(character),
+            (Some('"'), '\\') => {
+                characters.next();
+            }
+            (Some(open), c) if c == open => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+//Splits a dotted key like `menu."sub title".play`, quoted parts may contain dots
+fn split_key(key: &str) -> Result<Vec<String>, FormatError> {
+    let mut parts = Vec::new();
+    let mut rest = key.trim();
+    loop {
+        let (part, remainder) = if rest.starts_with(['"', '\'']) {
+            single_line_string(rest)?
+        } else {
+            let end = rest.find('.').unwrap_or(rest.len());
+            (rest[..end].trim().to_string(), &rest[end..])
+        };
+        if part.is_empty() {
+            return Err(FormatError::InvalidSyntax);
+        }
+        parts.push(part);
+        match remainder.trim_start().strip_prefix('.') {
+            Some(next) => rest = next.trim_start(),
+            None if remainder.trim().is_empty() => return Ok(parts),
+            None => return Err(FormatError::InvalidSyntax),
+        }
+    }
+}
+
+//Reads a basic `"..."` or literal `'...'` string, returning the text after it
+fn single_line_string(text: &str) -> Result<(String, &str), FormatError> {
+    if let Some(inner) = text.strip_prefix('\'') {
+        let end = inner.find('\'').ok_or(FormatError::InvalidSyntax)?;
+        return Ok((inner[..end].to_string(), &inner[end + 1..]));
+    }
+
+    let inner = text.strip_prefix('"').ok_or(FormatError::UnsupportedStructure)?;
+    let mut value = String::new();
+    let mut characters = inner.char_indices();
+    while let Some((position, character)) = characters.next() {
+        match character {
+            '"' => return Ok((value, &inner[position + 1..])),
+            '\\' => push_escape(&mut value, &mut characters)?,
+            c => value.push(c),
+        }
+    }
+    Err(FormatError::InvalidSyntax)
+}
+
+//Reads a `"""` string from the rest of its first line and the following lines
+fn multiline_string<'a>(first_line: &str, following: &'a str) -> Result<(String, &'a str), FormatError> {
+    let mut value = String::new();
+    //A line break right after the opening quotes is not part of the value
+    if !first_line.trim().is_empty() {
+        if let Some(end) = first_line.find("\"\"\"") {
+            return Ok((first_line[..end].to_string(), following));
+        }
+        value.push_str(first_line);
+        value.push('\n');
+    }
+
+    let end = following.find("\"\"\"").ok_or(FormatError::InvalidSyntax)?;
+    let raw = &following[..end];
+    let mut characters = raw.char_indices();
+    while let Some((_, character)) = characters.next() {
+        match character {
+            '\\' => push_escape(&mut value, &mut characters)?,
+            c => value.push(c),
+        }
+    }
+    let after = &following[end + 3..];
+    let after = after.split_once('\n').map_or("", |(_, next)| next);
+    Ok((value, after))
+}
+
+fn push_escape(value: &mut String, characters: &mut std::str::CharIndices) -> Result<(), FormatError> {
+    match characters.next().map(|(_, escaped)| escaped) {
+        Some('n') => value.push('\n'),
+        Some('t') => value.push('\t'),
+        Some('r') => value.push('\r'),
+        Some('b') => value.push('\u{8}'),
+        Some('f') => value.push('\u{c}'),
+        Some('u') => {
+            let hex: String = characters.by_ref().take(4).map(|(_, digit)| digit).collect();
+            let code = u32::from_str_radix(&hex, 16).map_err(|_| FormatError::InvalidSyntax)?;
+            value.push(char::from_u32(code).ok_or(FormatError::InvalidSyntax)?);
+        }
+        Some(escaped @ ('"' | '\\')) => value.push(escaped),
+        _ => return Err(FormatError::InvalidSyntax),
+    }
+    Ok(())
+}
+
+//Keys without dots are written at the top, every group of dotted keys becomes a table
+pub fn export_toml(document: &LocaleDocument) -> Result<String, FormatError> {
+    let tree = KeyTree::from_entries(
+        document
+            .entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.parsed_value())),
+        ".",
+    )?;
+
+    let mut output = String::new();
+    write_table(&mut output, &tree, &mut Vec::new());
+    Ok(output)
+}
+
+fn write_table<'a>(output: &mut String, table: &'a KeyTree, path: &mut Vec<&'a str>) {
+    let has_values = table.children.iter().any(|child| child.value.is_some());
+    if !path.is_empty() && has_values {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        let header: Vec<String> = path.iter().map(|part| toml_key(part)).collect();
+        output.push('[');
+        output.push_str(&header.join("."));
+        output.push_str("]\n");
+    }
+    for child in &table.children {
+        if let Some(value) = &child.value {
+            output.push_str(&toml_key(&child.name));
+            output.push_str(" = ");
+            output.push_str(&quote(value));
+            output.push('\n');
+        }
+    }
+    for child in table.children.iter().filter(|child| child.value.is_none()) {
+        path.push(&child.name);
+        write_table(output, child, path);
+        path.pop();
+    }
+}
+
+pub struct TomlFormat;
+
+impl LocaleFormat for TomlFormat {
+    fn name(&self) -> &str {
+        "toml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["toml"]
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        import_toml(read_utf8(bytes)?)
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        export_toml(document).map(String::into_bytes)
+    }
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_toml() {
+        let text = "title = \"Game\" # shown on boot\n\n[menu]\nplay = 'Play'\n\"quit game\" = \"Quit\\tnow\"\n\n[menu.help]\nintro = \"\"\"\nLine one\nLine two\"\"\"\nend = \"End\"\n";
+        let document = import_toml(text).expect("Import failed");
+
+        let entries: Vec<(&str, &str)> = document
+            .entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.value.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("title", "Game"),
+                ("menu.play", "Play"),
+                ("menu.quit game", "Quit\tnow"),
+                ("menu.help.intro", "Line one\nLine two"),
+                ("menu.help.end", "End")
+            ]
+        );
+        assert!(matches!(import_toml("[[servers]]\nname = \"a\"\n"), Err(FormatError::UnsupportedStructure)));
+    }
+
+    #[test]
+    fn test_quoted_separators() {
+        let text = "[\"tab#1\"] # comment\n\"a=b\" = \"c\" # comment\n'x#y' = 'z'\n";
+        let document = import_toml(text).expect("Import failed");
+
+        let entries: Vec<(&str, &str)> = document
+            .entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.value.as_str()))
+            .collect();
+        assert_eq!(entries, vec![("tab#1.a=b", "c"), ("tab#1.x#y", "z")]);
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let document = LocaleDocument::parse("[[menu.play]]\nPlay\n[[title]]\nGame\n[[menu.quit]]\nQuit\nnow\n")
+            .expect("Parse failed");
+
+        let toml = export_toml(&document).expect("Export failed");
+        assert_eq!(toml, "title = \"Game\"\n\n[menu]\nplay = \"Play\"\nquit = \"Quit\\nnow\"\n");
+        let imported = import_toml(&toml).expect("Import failed");
+        assert_eq!(imported.find("menu.quit").map(|entry| entry.value.as_str()), Some("Quit\nnow"));
+    }
+}
@@ -0,0 +1,345 @@
+use super::json::quote;
+use super::{FormatError, KeyTree, LocaleFormat, read_utf8};
+use crate::locale_api::blocks::needs_block_as_literal;
+use crate::locale_api::document::{DocumentEntry, LineEnding, LocaleDocument};
+
+//Rails style files have the language as the only top level key, e.g. `en: { menu: { play: Play } }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YamlOptions<'a> {
+    //Joins nested keys into flat keys, `menu.play` with the default of `.`
+    pub separator: &'a str,
+    pub language_root: bool,
+}
+
+impl Default for YamlOptions<'_> {
+    fn default() -> Self {
+        Self {
+            separator: ".",
+            language_root: true,
+        }
+    }
+}
+
+//Reads the subset of YAML used by locale files: nested mappings of plain, quoted or block scalars
+pub fn import_yaml(text: &str, options: &YamlOptions) -> Result<LocaleDocument, FormatError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut document = LocaleDocument {
+        line_ending: LineEnding::detect(text),
+        ..Default::default()
+    };
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') || content == "---" {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        while path.last().is_some_and(|(parent_indent, _)| *parent_indent >= indent) {
+            path.pop();
+        }
+        let (key, rest) = split_mapping(content).ok_or(FormatError::UnsupportedStructure)?;
+        if rest.is_empty() {
+            path.push((indent, key));
+            continue;
+        }
+
+        let value = if rest.starts_with('|') || rest.starts_with('>') {
+            let block_end = lines[index..]
+                .iter()
+                .position(|line| !line.trim().is_empty() && line.len() - line.trim_start_matches(' ').len() <= indent)
+                .map_or(lines.len(), |offset| index + offset);
+            let block = &lines[index..block_end];
+            index = block_end;
+            block_scalar(block, rest.starts_with('>'))
+        } else {
+            scalar(rest)?
+        };
+
+        let mut parts: Vec<&str> = path.iter().map(|(_, name)| name.as_str()).collect();
+        parts.push(&key);
+        let key_parts = if options.language_root {
+            let (language, key_parts) = parts.split_first().ok_or(FormatError::UnsupportedStructure)?;
+            let header = format!("@language={language}");
+            if document.header.is_empty() {
+                document.header.push(header);
+            } else if document.header[0] != header {
+                return Err(FormatError::UnsupportedStructure);
+            }
+            key_parts
+        } else {
+            &parts[..]
+        };
+        if key_parts.is_empty() {
+            return Err(FormatError::UnsupportedStructure);
+        }
+        document.entries.push(DocumentEntry {
+            key: key_parts.join(options.separator),
+            block: needs_block_as_literal(&value),
+            literal: true,
+            value,
+            ..Default::default()
+        });
+    }
+    Ok(document)
+}
+
+//Splits `key: value` at the first colon outside of quotes that is followed by a space or the end of the line
+fn split_mapping(content: &str) -> Option<(String, &str)> {
+    let mut quote_char: Option<char> = None;
+    for (position, character) in content.char_indices() {
+        match (quote_char, character) {
+            (None, '"' | '\'') if position == 0 => quote_char = Some(character),
+            (Some(open), c) if c == open => quote_char = None,
+            (None, ':') => {
+                let rest = &content[position + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    let key = content[..position].trim();
+                    let key = key
+                        .strip_prefix(['"', '\''])
+                        .and_then(|key| key.strip_suffix(['"', '\'']))
+                        .unwrap_or(key);
+                    return Some((key.to_string(), rest.trim()));
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn scalar(text: &str) -> Result<String, FormatError> {
+    if let Some(inner) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut characters = inner.chars();
+        while let Some(character) = characters.next() {
+            match character {
+                '"' => return Ok(value),
+                '\\' => value.push(escape(&mut characters)?),
+                c => value.push(c),
+            }
+        }
+        Err(FormatError::InvalidSyntax)
+    } else if let Some(inner) = text.strip_prefix('\'') {
+        let end = inner.rfind('\'').ok_or(FormatError::InvalidSyntax)?;
+        Ok(inner[..end].replace("''", "'"))
+    } else {
+        //Plain scalars end at a comment
+        let value = text.find(" #").map_or(text, |position| &text[..position]);
+        Ok(value.trim().to_string())
+    }
+}
+
+//The character after a backslash in a double quoted scalar, see the escape sequences in the YAML 1.2 spec
+fn escape(characters: &mut std::str::Chars) -> Result<char, FormatError> {
+    let digits = match characters.next().ok_or(FormatError::InvalidSyntax)? {
+        '0' => return Ok('\0'),
+        'a' => return Ok('\u{7}'),
+        'b' => return Ok('\u{8}'),
+        't' | '\t' => return Ok('\t'),
+        'n' => return Ok('\n'),
+        'v' => return Ok('\u{b}'),
+        'f' => return Ok('\u{c}'),
+        'r' => return Ok('\r'),
+        'e' => return Ok('\u{1b}'),
+        'N' => return Ok('\u{85}'),
+        '_' => return Ok('\u{a0}'),
+        'L' => return Ok('\u{2028}'),
+        'P' => return Ok('\u{2029}'),
+        escaped @ (' ' | '"' | '/' | '\\') => return Ok(escaped),
+        'x' => 2,
+        'u' => 4,
+        'U' => 8,
+        _ => return Err(FormatError::InvalidSyntax),
+    };
+    let mut code = 0;
+    for _ in 0..digits {
+        let digit = characters.next().and_then(|digit| digit.to_digit(16)).ok_or(FormatError::InvalidSyntax)?;
+        code = code * 16 + digit;
+    }
+    char::from_u32(code).ok_or(FormatError::InvalidSyntax)
+}
+
+//`|` keeps line breaks, `>` folds lines into one, trailing line breaks are dropped either way
+fn block_scalar(lines: &[&str], folded: bool) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines.iter().map(|line| line.get(indent..).unwrap_or("")).collect();
+    let joined = if folded { lines.join(" ") } else { lines.join("\n") };
+    joined.trim_end().to_string()
+}
+
+pub fn export_yaml(document: &LocaleDocument, options: &YamlOptions) -> Result<String, FormatError> {
+    let tree = KeyTree::from_entries(
+        document
+            .entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.parsed_value())),
+        options.separator,
+    )?;
+
+    let mut output = String::new();
+    if options.language_root {
+        let language = document.language().ok_or(FormatError::UnsupportedStructure)?;
+        output.push_str(&yaml_key(&language));
+        output.push_str(":\n");
+        write_tree(&mut output, &tree.children, 1);
+    } else {
+        write_tree(&mut output, &tree.children, 0);
+    }
+    Ok(output)
+}
+
+fn write_tree(output: &mut String, nodes: &[KeyTree], depth: usize) {
+    for node in nodes {
+        output.push_str(&"  ".repeat(depth));
+        output.push_str(&yaml_key(&node.name));
+        match &node.value {
+            Some(value) => {
+                output.push_str(": ");
+                output.push_str(&quote(value));
+                output.push('\n');
+            }
+            None => {
+                output.push_str(":\n");
+                write_tree(output, &node.children, depth + 1);
+            }
+        }
+    }
+}
+
+//Owned version of YamlOptions for the format registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YamlFormat {
+    pub separator: String,
+    pub language_root: bool,
+}
+
+impl Default for YamlFormat {
+    fn default() -> Self {
+        let options = YamlOptions::default();
+        Self {
+            separator: options.separator.to_string(),
+            language_root: options.language_root,
+        }
+    }
+}
+
+impl YamlFormat {
+    fn options(&self) -> YamlOptions<'_> {
+        YamlOptions {
+            separator: &self.separator,
+            language_root: self.language_root,
+        }
+    }
+}
+
+impl LocaleFormat for YamlFormat {
+    fn name(&self) -> &str {
+        "yaml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["yaml", "yml"]
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<LocaleDocument, FormatError> {
+        import_yaml(read_utf8(bytes)?, &self.options())
+    }
+
+    fn write(&self, document: &LocaleDocument) -> Result<Vec<u8>, FormatError> {
+        export_yaml(document, &self.options()).map(String::into_bytes)
+    }
+}
+
+fn yaml_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_rails_yaml() {
+        let text = "en:\n  menu:\n    play: Play # button\n    quit: \"Quit\\ngame\"\n  intro: |\n    Line one\n    Line two\n  name: 'It''s'\n";
+        let document = import_yaml(text, &YamlOptions::default()).expect("Import failed");
+
+        assert_eq!(document.language().as_deref(), Some("en"));
+        let entries: Vec<(&str, &str)> = document
+            .entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.value.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("menu.play", "Play"),
+                ("menu.quit", "Quit\ngame"),
+                ("intro", "Line one\nLine two"),
+                ("name", "It's")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let document = LocaleDocument::parse("@language=de\n[[menu/play]]\nSpielen\n[[menu/quit]]\nBeenden\n[[title]]\nTitel\n")
+            .expect("Parse failed");
+        let options = YamlOptions {
+            separator: "/",
+            ..Default::default()
+        };
+
+        let yaml = export_yaml(&document, &options).expect("Export failed");
+        assert_eq!(yaml, "de:\n  menu:\n    play: \"Spielen\"\n    quit: \"Beenden\"\n  title: \"Titel\"\n");
+        let imported = import_yaml(&yaml, &options).expect("Import failed");
+        let values = |document: &LocaleDocument| -> Vec<(String, String)> {
+            let entries = document.entries.iter();
+            entries.map(|entry| (entry.key.clone(), entry.parsed_value().into_owned())).collect()
+        };
+        assert_eq!(values(&imported), values(&document));
+
+        let conflicting = LocaleDocument::parse("[[menu]]\nMenu\n[[menu.play]]\nPlay\n").expect("Parse failed");
+        let flat = YamlOptions {
+            language_root: false,
+            ..Default::default()
+        };
+        assert!(matches!(export_yaml(&conflicting, &flat), Err(FormatError::UnsupportedStructure)));
+    }
+
+    #[test]
+    fn test_yaml_escapes_round_trip() {
+        let document = LocaleDocument {
+            header: vec!["@language=en".to_string()],
+            entries: vec![DocumentEntry {
+                key: "intro".to_string(),
+                value: "Line one\r\nLine two\u{1}\t\"end\"".to_string(),
+                literal: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let yaml = export_yaml(&document, &YamlOptions::default()).expect("Export failed");
+        assert_eq!(yaml, "en:\n  intro: \"Line one\\r\\nLine two\\u0001\\t\\\"end\\\"\"\n");
+        let imported = import_yaml(&yaml, &YamlOptions::default()).expect("Import failed");
+        assert_eq!(imported.entries[0].value, document.entries[0].value);
+
+        let text = "en:\n  a: \"\\x41\\U0001F600\\_\\0\"\n  b: \"\\q\"\n";
+        assert!(matches!(import_yaml(text, &YamlOptions::default()), Err(FormatError::InvalidSyntax)));
+        let text = "en:\n  a: \"\\x41\\U0001F600\\_\\0\"\n";
+        let imported = import_yaml(text, &YamlOptions::default()).expect("Import failed");
+        assert_eq!(imported.entries[0].value, "A😀\u{a0}\0");
+    }
+}
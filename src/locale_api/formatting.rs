@@ -0,0 +1,490 @@
+use super::articles::{Article, article_for, join_article};
+use super::interop::LocaleTable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum Unit {
+    Meter,
+    Kilometer,
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum UnitWidth {
+    //Abbreviated symbol, e.g. `5 km`
+    Short,
+    //Full singular or plural name, e.g. `5 kilometers`
+    Long,
+}
+
+struct UnitNames {
+    short: &'static str,
+    one: &'static str,
+    other: &'static str,
+}
+
+const fn names(short: &'static str, one: &'static str, other: &'static str) -> UnitNames {
+    UnitNames { short, one, other }
+}
+
+//Cardinal spell-out for 0 to 12, the range CLDR `spellout-cardinal` covers without compound rules
+const ENGLISH_NUMBERS: [&str; 13] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve",
+];
+
+#[cfg(feature = "cldr")]
+const FRENCH_NUMBERS: [&str; 13] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix", "onze", "douze",
+];
+
+#[cfg(feature = "cldr")]
+const GERMAN_NUMBERS: [&str; 13] = [
+    "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun", "zehn", "elf", "zwölf",
+];
+
+#[cfg(feature = "cldr")]
+const SPANISH_NUMBERS: [&str; 13] = [
+    "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve", "diez", "once", "doce",
+];
+
+#[cfg(feature = "cldr")]
+const ITALIAN_NUMBERS: [&str; 13] = [
+    "zero", "uno", "due", "tre", "quattro", "cinque", "sei", "sette", "otto", "nove", "dieci", "undici", "dodici",
+];
+
+//Strips the region from a tag like `fr-CA` so rules are looked up by language only
+pub fn primary_language(language: &str) -> &str {
+    language.split(['-', '_']).next().unwrap_or(language)
+}
+
+//Each language's name for itself, from CLDR. Small enough to always include, unlike the data behind the `cldr` feature.
+//Regional tags are listed where the region changes the name players look for.
+const ENDONYMS: [(&str, &str); 36] = [
+    ("ar", "العربية"),
+    ("bg", "Български"),
+    ("cs", "Čeština"),
+    ("da", "Dansk"),
+    ("de", "Deutsch"),
+    ("el", "Ελληνικά"),
+    ("en", "English"),
+    ("es", "Español"),
+    ("es-419", "Español (Latinoamérica)"),
+    ("fa", "فارسی"),
+    ("fi", "Suomi"),
+    ("fr", "Français"),
+    ("he", "עברית"),
+    ("hi", "हिन्दी"),
+    ("hu", "Magyar"),
+    ("id", "Indonesia"),
+    ("it", "Italiano"),
+    ("ja", "日本語"),
+    ("ko", "한국어"),
+    ("nb", "Norsk bokmål"),
+    ("nl", "Nederlands"),
+    ("no", "Norsk"),
+    ("pl", "Polski"),
+    ("pt", "Português"),
+    ("pt-BR", "Português (Brasil)"),
+    ("ro", "Română"),
+    ("ru", "Русский"),
+    ("sr", "Српски"),
+    ("sv", "Svenska"),
+    ("th", "ไทย"),
+    ("tr", "Türkçe"),
+    ("uk", "Українська"),
+    ("vi", "Tiếng Việt"),
+    ("zh", "中文"),
+    ("zh-Hans", "简体中文"),
+    ("zh-Hant", "繁體中文"),
+];
+
+//The name a language has in itself, e.g. "Deutsch" for `de`, trying the full tag before the primary language
+pub fn endonym(language: &str) -> Option<&'static str> {
+    let language = language.replace('_', "-");
+    let find = |tag: &str| {
+        ENDONYMS
+            .iter()
+            .find(|(listed, _)| listed.eq_ignore_ascii_case(tag))
+            .map(|(_, name)| *name)
+    };
+    find(&language).or_else(|| find(primary_language(&language)))
+}
+
+fn number_words(language: &str) -> Option<&'static [&'static str; 13]> {
+    match primary_language(language) {
+        "en" => Some(&ENGLISH_NUMBERS),
+        #[cfg(feature = "cldr")]
+        "fr" => Some(&FRENCH_NUMBERS),
+        #[cfg(feature = "cldr")]
+        "de" => Some(&GERMAN_NUMBERS),
+        #[cfg(feature = "cldr")]
+        "es" => Some(&SPANISH_NUMBERS),
+        #[cfg(feature = "cldr")]
+        "it" => Some(&ITALIAN_NUMBERS),
+        _ => None,
+    }
+}
+
+//Returns None for numbers that are too large to spell out, or languages without data
+pub fn spell_number(language: &str, number: u64) -> Option<&'static str> {
+    number_words(language)?.get(usize::try_from(number).ok()?).copied()
+}
+
+fn unit_names(language: &str, unit: Unit) -> Option<UnitNames> {
+    let unit_names = match (primary_language(language), unit) {
+        ("en", Unit::Meter) => names("m", "meter", "meters"),
+        ("en", Unit::Kilometer) => names("km", "kilometer", "kilometers"),
+        ("en", Unit::Second) => names("s", "second", "seconds"),
+        ("en", Unit::Minute) => names("min", "minute", "minutes"),
+        ("en", Unit::Hour) => names("h", "hour", "hours"),
+        ("en", Unit::Day) => names("d", "day", "days"),
+
+        #[cfg(feature = "cldr")]
+        ("fr", Unit::Meter) => names("m", "mètre", "mètres"),
+        #[cfg(feature = "cldr")]
+        ("fr", Unit::Kilometer) => names("km", "kilomètre", "kilomètres"),
+        #[cfg(feature = "cldr")]
+        ("fr", Unit::Second) => names("s", "seconde", "secondes"),
+        #[cfg(feature = "cldr")]
+        ("fr", Unit::Minute) => names("min", "minute", "minutes"),
+        #[cfg(feature = "cldr")]
+        ("fr", Unit::Hour) => names("h", "heure", "heures"),
+        #[cfg(feature = "cldr")]
+        ("fr", Unit::Day) => names("j", "jour", "jours"),
+
+        #[cfg(feature = "cldr")]
+        ("de", Unit::Meter) => names("m", "Meter", "Meter"),
+        #[cfg(feature = "cldr")]
+        ("de", Unit::Kilometer) => names("km", "Kilometer", "Kilometer"),
+        #[cfg(feature = "cldr")]
+        ("de", Unit::Second) => names("Sek.", "Sekunde", "Sekunden"),
+        #[cfg(feature = "cldr")]
+        ("de", Unit::Minute) => names("Min.", "Minute", "Minuten"),
+        #[cfg(feature = "cldr")]
+        ("de", Unit::Hour) => names("Std.", "Stunde", "Stunden"),
+        #[cfg(feature = "cldr")]
+        ("de", Unit::Day) => names("T.", "Tag", "Tage"),
+
+        #[cfg(feature = "cldr")]
+        ("es", Unit::Meter) => names("m", "metro", "metros"),
+        #[cfg(feature = "cldr")]
+        ("es", Unit::Kilometer) => names("km", "kilómetro", "kilómetros"),
+        #[cfg(feature = "cldr")]
+        ("es", Unit::Second) => names("s", "segundo", "segundos"),
+        #[cfg(feature = "cldr")]
+        ("es", Unit::Minute) => names("min", "minuto", "minutos"),
+        #[cfg(feature = "cldr")]
+        ("es", Unit::Hour) => names("h", "hora", "horas"),
+        #[cfg(feature = "cldr")]
+        ("es", Unit::Day) => names("d", "día", "días"),
+
+        #[cfg(feature = "cldr")]
+        ("it", Unit::Meter) => names("m", "metro", "metri"),
+        #[cfg(feature = "cldr")]
+        ("it", Unit::Kilometer) => names("km", "chilometro", "chilometri"),
+        #[cfg(feature = "cldr")]
+        ("it", Unit::Second) => names("s", "secondo", "secondi"),
+        #[cfg(feature = "cldr")]
+        ("it", Unit::Minute) => names("min", "minuto", "minuti"),
+        #[cfg(feature = "cldr")]
+        ("it", Unit::Hour) => names("h", "ora", "ore"),
+        #[cfg(feature = "cldr")]
+        ("it", Unit::Day) => names("g", "giorno", "giorni"),
+
+        _ => return None,
+    };
+    Some(unit_names)
+}
+
+//French treats 0 and 1 as singular, the other supported languages only 1
+pub fn is_singular(language: &str, value: f64) -> bool {
+    match primary_language(language) {
+        "fr" => value.abs() < 2.0,
+        _ => value.abs() == 1.0,
+    }
+}
+
+//Shortest representation of the value, using a decimal comma outside of English
+pub fn format_decimal(language: &str, value: f64) -> String {
+    let formatted = value.to_string();
+    match primary_language(language) {
+        "en" => formatted,
+        _ => formatted.replace('.', ","),
+    }
+}
+
+pub fn format_unit(language: &str, value: f64, unit: Unit, width: UnitWidth) -> Option<String> {
+    let unit_names = unit_names(language, unit)?;
+    let name = match width {
+        UnitWidth::Short => unit_names.short,
+        UnitWidth::Long if is_singular(language, value) => unit_names.one,
+        UnitWidth::Long => unit_names.other,
+    };
+    Some(format!("{} {name}", format_decimal(language, value)))
+}
+
+struct RelativeTemplates {
+    now: &'static str,
+    past_prefix: &'static str,
+    past_suffix: &'static str,
+    future_prefix: &'static str,
+}
+
+fn relative_templates(language: &str) -> Option<RelativeTemplates> {
+    let templates = match primary_language(language) {
+        "en" => RelativeTemplates {
+            now: "now",
+            past_prefix: "",
+            past_suffix: " ago",
+            future_prefix: "in ",
+        },
+        #[cfg(feature = "cldr")]
+        "fr" => RelativeTemplates {
+            now: "maintenant",
+            past_prefix: "il y a ",
+            past_suffix: "",
+            future_prefix: "dans ",
+        },
+        #[cfg(feature = "cldr")]
+        "de" => RelativeTemplates {
+            now: "jetzt",
+            past_prefix: "vor ",
+            past_suffix: "",
+            future_prefix: "in ",
+        },
+        #[cfg(feature = "cldr")]
+        "es" => RelativeTemplates {
+            now: "ahora",
+            past_prefix: "hace ",
+            past_suffix: "",
+            future_prefix: "dentro de ",
+        },
+        #[cfg(feature = "cldr")]
+        "it" => RelativeTemplates {
+            now: "ora",
+            past_prefix: "",
+            past_suffix: " fa",
+            future_prefix: "tra ",
+        },
+        _ => return None,
+    };
+    Some(templates)
+}
+
+//"3 minutes ago" for positive seconds, "in 3 minutes" for negative ones, rounded down to the largest whole unit
+pub fn format_relative_time(seconds: i64, language: &str) -> Option<String> {
+    let templates = relative_templates(language)?;
+    let elapsed = seconds.unsigned_abs();
+    let (count, unit) = match elapsed {
+        0 => return Some(templates.now.to_string()),
+        1..60 => (elapsed, Unit::Second),
+        60..3600 => (elapsed / 60, Unit::Minute),
+        3600..86400 => (elapsed / 3600, Unit::Hour),
+        _ => (elapsed / 86400, Unit::Day),
+    };
+
+    let unit_names = unit_names(language, unit)?;
+    let name = match (primary_language(language), unit) {
+        //German uses the dative after `vor` and `in`, which only differs from the plural for days
+        ("de", Unit::Day) if count != 1 => "Tagen",
+        _ if count == 1 => unit_names.one,
+        _ => unit_names.other,
+    };
+    if seconds > 0 {
+        Some(format!("{}{count} {name}{}", templates.past_prefix, templates.past_suffix))
+    } else {
+        Some(format!("{}{count} {name}", templates.future_prefix))
+    }
+}
+
+struct CurrencyStyle {
+    group_separator: &'static str,
+    decimal_separator: &'static str,
+    symbol_first: bool,
+    //Numbers with fewer integer digits than this are not grouped, e.g. Spanish writes 1234 but 12.345
+    min_grouping_digits: usize,
+}
+
+fn currency_style(language: &str) -> Option<CurrencyStyle> {
+    let style = match primary_language(language) {
+        "en" => CurrencyStyle {
+            group_separator: ",",
+            decimal_separator: ".",
+            symbol_first: true,
+            min_grouping_digits: 4,
+        },
+        #[cfg(feature = "cldr")]
+        "fr" => CurrencyStyle {
+            group_separator: "\u{202F}",
+            decimal_separator: ",",
+            symbol_first: false,
+            min_grouping_digits: 4,
+        },
+        #[cfg(feature = "cldr")]
+        "de" | "it" => CurrencyStyle {
+            group_separator: ".",
+            decimal_separator: ",",
+            symbol_first: false,
+            min_grouping_digits: 4,
+        },
+        #[cfg(feature = "cldr")]
+        "es" => CurrencyStyle {
+            group_separator: ".",
+            decimal_separator: ",",
+            symbol_first: false,
+            min_grouping_digits: 5,
+        },
+        _ => return None,
+    };
+    Some(style)
+}
+
+//Symbol and number of minor unit digits, unknown ISO 4217 codes are shown as the code with 2 digits
+fn currency_info(currency_code: &str) -> (&str, u32) {
+    match currency_code {
+        "USD" => ("$", 2),
+        "EUR" => ("€", 2),
+        "GBP" => ("£", 2),
+        "JPY" => ("¥", 0),
+        "KRW" => ("₩", 0),
+        "RUB" => ("₽", 2),
+        "PLN" => ("zł", 2),
+        "BRL" => ("R$", 2),
+        _ => (currency_code, 2),
+    }
+}
+
+fn group_digits(digits: &str, separator: &str, min_grouping_digits: usize) -> String {
+    if digits.len() < min_grouping_digits {
+        return digits.to_string();
+    }
+
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+//Formats a price given in minor units (e.g. cents), returns None for languages without currency data
+pub fn format_currency(amount_minor_units: i64, currency_code: &str, language: &str) -> Option<String> {
+    let style = currency_style(language)?;
+    let (symbol, minor_digits) = currency_info(currency_code);
+    let divisor = 10u64.pow(minor_digits);
+    let magnitude = amount_minor_units.unsigned_abs();
+    let sign = if amount_minor_units < 0 { "-" } else { "" };
+
+    let mut number = group_digits(
+        &(magnitude / divisor).to_string(),
+        style.group_separator,
+        style.min_grouping_digits,
+    );
+    if minor_digits > 0 {
+        number.push_str(style.decimal_separator);
+        number.push_str(&format!("{:0width$}", magnitude % divisor, width = minor_digits as usize));
+    }
+
+    if style.symbol_first {
+        Some(format!("{sign}{symbol}{number}"))
+    } else {
+        Some(format!("{sign}{number}\u{A0}{symbol}"))
+    }
+}
+
+impl LocaleTable {
+    //"three coins": picks the plural variant of the key with get_plural and spells out small counts. Outside English
+    //"one" agrees with the noun's `@gender` like the indefinite article does, e.g. `une pièce` and `eine Münze`.
+    pub fn spell_count(&self, key: &[u8], count: u64) -> Option<String> {
+        let language = self.language().unwrap_or("en");
+        let noun = self.get_plural(key, count as f64, language)?;
+        let spelled = spell_number(language, count);
+        let article = (count == 1 && spelled.is_some() && primary_language(language) != "en")
+            .then(|| article_for(language, self.entry_gender(key), noun, Article::Indefinite))
+            .flatten();
+        if let Some(article) = article {
+            return Some(join_article(article, noun));
+        }
+        match spelled {
+            Some(number) => Some(format!("{number} {noun}")),
+            None => Some(format!("{count} {noun}")),
+        }
+    }
+
+    //Formats the value in the table's language, or returns None if there is no unit data for it
+    pub fn format_unit(&self, value: f64, unit: Unit, width: UnitWidth) -> Option<String> {
+        format_unit(self.language()?, value, unit, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_spell_count() {
+        let mut sample = "@language=en\n[[coin]]\ncoins\n[[coin|one]]\ncoin\n".as_bytes().to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+
+        assert_eq!(table.spell_count(b"coin", 3).as_deref(), Some("three coins"));
+        assert_eq!(table.spell_count(b"coin", 1).as_deref(), Some("one coin"));
+        assert_eq!(table.spell_count(b"coin", 40).as_deref(), Some("40 coins"));
+        assert_eq!(table.spell_count(b"missing", 2), None);
+    }
+
+    #[test]
+    fn test_format_unit() {
+        assert_eq!(format_unit("en", 5.0, Unit::Kilometer, UnitWidth::Short).as_deref(), Some("5 km"));
+        assert_eq!(format_unit("en-GB", 1.0, Unit::Hour, UnitWidth::Long).as_deref(), Some("1 hour"));
+        assert_eq!(format_unit("en", 2.5, Unit::Minute, UnitWidth::Long).as_deref(), Some("2.5 minutes"));
+        assert_eq!(format_unit("ja", 5.0, Unit::Meter, UnitWidth::Short), None);
+    }
+
+    #[test]
+    fn test_format_currency() {
+        assert_eq!(format_currency(123456, "USD", "en").as_deref(), Some("$1,234.56"));
+        assert_eq!(format_currency(-99, "GBP", "en-GB").as_deref(), Some("-£0.99"));
+        assert_eq!(format_currency(1500, "JPY", "en").as_deref(), Some("¥1,500"));
+        assert_eq!(format_currency(500, "CHF", "en").as_deref(), Some("CHF5.00"));
+        assert_eq!(format_currency(500, "USD", "ja"), None);
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        assert_eq!(format_relative_time(0, "en").as_deref(), Some("now"));
+        assert_eq!(format_relative_time(1, "en").as_deref(), Some("1 second ago"));
+        assert_eq!(format_relative_time(200, "en").as_deref(), Some("3 minutes ago"));
+        assert_eq!(format_relative_time(-7200, "en").as_deref(), Some("in 2 hours"));
+        assert_eq!(format_relative_time(86400, "en").as_deref(), Some("1 day ago"));
+        assert_eq!(format_relative_time(60, "ja"), None);
+    }
+
+    #[cfg(feature = "cldr")]
+    #[test]
+    fn test_cldr_languages() {
+        assert_eq!(spell_number("de", 3), Some("drei"));
+        assert_eq!(format_unit("fr", 1.5, Unit::Hour, UnitWidth::Long).as_deref(), Some("1,5 heure"));
+        assert_eq!(format_unit("it", 2.0, Unit::Second, UnitWidth::Long).as_deref(), Some("2 secondi"));
+        assert_eq!(format_currency(123456, "EUR", "de").as_deref(), Some("1.234,56\u{A0}€"));
+        assert_eq!(format_relative_time(3 * 86400, "de").as_deref(), Some("vor 3 Tagen"));
+
+        let mut german = "@language=de\n[[coin]] @gender=feminine\nMünzen\n[[coin|one]]\nMünze\n".as_bytes().to_vec();
+        let table = parse_r3locale_bytes(&mut german).expect("Parse failed");
+        assert_eq!(table.spell_count(b"coin", 1).as_deref(), Some("eine Münze"));
+        assert_eq!(table.spell_count(b"coin", 3).as_deref(), Some("drei Münzen"));
+        let mut french = "@language=fr\n[[coin]] @gender=feminine\npièces\n[[coin|one]]\npièce\n".as_bytes().to_vec();
+        let table = parse_r3locale_bytes(&mut french).expect("Parse failed");
+        assert_eq!(table.spell_count(b"coin", 1).as_deref(), Some("une pièce"));
+        let mut italian = "@language=it\n[[shield]]\nscudi\n[[shield|one]]\nscudo\n".as_bytes().to_vec();
+        let table = parse_r3locale_bytes(&mut italian).expect("Parse failed");
+        assert_eq!(table.spell_count(b"shield", 1).as_deref(), Some("uno scudo"));
+        assert_eq!(format_currency(123456, "EUR", "es").as_deref(), Some("1234,56\u{A0}€"));
+    }
+}
@@ -0,0 +1,139 @@
+use super::interop::LocaleTable;
+use super::parser::parse_r3locale_bytes;
+use super::preview::format_value;
+use super::runtime::{LogLevel, log};
+use super::status::StatusCode;
+use ::godot::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+struct R3lExtension;
+
+#[gdextension]
+unsafe impl ExtensionLibrary for R3lExtension {}
+
+//How often the loaded file is checked for changes, in seconds
+const RELOAD_INTERVAL: f64 = 0.5;
+
+//Node for Godot based tools, add it to the scene tree so it can watch the loaded file and emit `reloaded` when it
+//changes on disk
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct R3lLocale {
+    table: Option<LocaleTable>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    since_check: f64,
+    #[export]
+    watch: bool,
+    base: Base<Node>,
+}
+
+#[godot_api]
+impl R3lLocale {
+    #[signal]
+    fn reloaded(path: GString);
+
+    //Emitted when a watched file changed but no longer parses, the previous table stays loaded
+    #[signal]
+    fn reload_failed(path: GString, error: GString);
+
+    //Loads a `.r3l` file, `res://` and `user://` paths are resolved by Godot
+    #[func]
+    fn load(&mut self, path: GString) -> bool {
+        let path = PathBuf::from(ProjectSettings::singleton().globalize_path(&path).to_string());
+        let loaded = self.read(&path).is_ok();
+        self.path = Some(path);
+        loaded
+    }
+
+    //Parses file contents directly, e.g. from a text editor in the tool
+    #[func]
+    fn parse(&mut self, text: GString) -> bool {
+        match parse_r3locale_bytes(&mut text.to_string().into_bytes()) {
+            Ok(table) => {
+                self.table = Some(table);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[func]
+    fn has_entry(&self, key: GString) -> bool {
+        self.find(&key).is_some()
+    }
+
+    //Empty when the key is missing, use has_entry to tell an empty value apart
+    #[func]
+    fn get_entry(&self, key: GString) -> GString {
+        self.find(&key).map(GString::from).unwrap_or_default()
+    }
+
+    //Replaces `{name}` placeholders with the matching dictionary values, missing ones are left as written
+    #[func]
+    fn format(&self, key: GString, args: Dictionary) -> GString {
+        let Some(value) = self.find(&key) else {
+            return GString::new();
+        };
+        let args: Vec<(String, String)> = args
+            .iter_shared()
+            .map(|(name, argument)| (name.stringify().to_string(), argument.stringify().to_string()))
+            .collect();
+        GString::from(format_value(value, &args).as_str())
+    }
+
+    #[func]
+    fn get_language(&self) -> GString {
+        self.table
+            .as_ref()
+            .and_then(LocaleTable::language)
+            .map(GString::from)
+            .unwrap_or_default()
+    }
+
+    fn find(&self, key: &GString) -> Option<&str> {
+        self.table.as_ref()?.find_entry(key.to_string().as_bytes())
+    }
+
+    fn read(&mut self, path: &Path) -> Result<(), String> {
+        let mut bytes = fs::read(path).map_err(|error| error.to_string())?;
+        //Recorded before parsing so a broken save is only reported once
+        self.modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        self.table = Some(parse_r3locale_bytes(&mut bytes).map_err(|error| format!("{error:?}"))?);
+        Ok(())
+    }
+}
+
+#[godot_api]
+impl INode for R3lLocale {
+    fn process(&mut self, delta: f64) {
+        self.since_check += delta;
+        if !self.watch || self.since_check < RELOAD_INTERVAL {
+            return;
+        }
+        self.since_check = 0.0;
+
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        let display_path = GString::from(path.to_string_lossy().as_ref());
+        match self.read(&path) {
+            Ok(()) => {
+                log(LogLevel::Info, "reload", StatusCode::RELOADED, &format!("Reloaded {}", path.display()));
+                self.base_mut().emit_signal("reloaded", &[display_path.to_variant()]);
+            }
+            Err(error) => {
+                let message = format!("{}: {error}, keeping the previous table", path.display());
+                log(LogLevel::Warning, "reload", StatusCode::RELOAD_FAILED, &message);
+                self.base_mut()
+                    .emit_signal("reload_failed", &[display_path.to_variant(), GString::from(error.as_str()).to_variant()]);
+            }
+        }
+    }
+}
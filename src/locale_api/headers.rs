@@ -0,0 +1,57 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+//RAII classes over the C ABI, with unique handle types and std::string_view lookups
+const CPP_WRAPPER: &str = include_str!("../../include/r3l_wrapper.hpp");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum HeaderLanguage {
+    C,
+    Cpp,
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub enum HeaderError {
+    Normal,
+    //The cbindgen config shipped with the crate could not be read
+    InvalidConfig,
+    //cbindgen could not parse the crate, usually because `cargo` is not on the PATH
+    GenerationFailed,
+    FailedToWrite,
+}
+
+//Writes the C header for the exports of this exact build, from the crate's own sources and cbindgen config. Call it
+//from a build script or run `r3l header` so the header always matches the library being linked.
+pub fn generate_c_header(path: &Path) -> Result<(), HeaderError> {
+    generate_header(path, HeaderLanguage::C)
+}
+
+pub fn generate_header(path: &Path, language: HeaderLanguage) -> Result<(), HeaderError> {
+    bindings(language)?.write(File::create(path).map_err(|_| HeaderError::FailedToWrite)?);
+    Ok(())
+}
+
+//Writes a single header-only file for native plugins: the C++ bindings followed by the `r3l::LocaleTable` wrapper,
+//which frees its table on destruction and returns values as std::string_view.
+pub fn generate_cpp_wrapper(path: &Path) -> Result<(), HeaderError> {
+    let mut header = Vec::new();
+    bindings(HeaderLanguage::Cpp)?.write(&mut header);
+    header.extend_from_slice(CPP_WRAPPER.as_bytes());
+    fs::write(path, header).map_err(|_| HeaderError::FailedToWrite)
+}
+
+fn bindings(language: HeaderLanguage) -> Result<cbindgen::Bindings, HeaderError> {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config_file = match language {
+        HeaderLanguage::C => "cbindgen_c.toml",
+        HeaderLanguage::Cpp => "cbindgen_cpp.toml",
+    };
+    let config = cbindgen::Config::from_file(crate_dir.join(config_file)).map_err(|_| HeaderError::InvalidConfig)?;
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .map_err(|_| HeaderError::GenerationFailed)
+}
@@ -1,12 +1,21 @@
+use super::annotations::{Annotation, Deprecation, EntryConstraints, find_annotation};
+use super::ffi_util;
 use super::parser::{MergeResult, MergeTableError, ParseR3Error, parse_r3locale_file};
+use super::runtime::{LogLevel, MissingKeyPolicy, current_config, log, log_enabled};
+use super::stats::LookupStats;
+use super::status::StatusCode;
 use crate::locale_api::parser;
-use hashbrown::HashTable;
-use std::ffi::CStr;
+use hashbrown::{HashMap, HashTable};
+use std::ffi::c_void;
 use std::os::raw::c_char;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use lite_strtab::{StringId, StringTable};
 use xxhash_rust::xxh3::xxh3_64;
 
+const MAX_REPLACEMENT_HOPS: usize = 8;
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct TableEntry {
@@ -18,8 +27,15 @@ pub struct TableEntry {
 pub struct LocaleTable {
     pub string_values: StringTable<u32, u16>,
     pub entries: HashTable<TableEntry>,
+    pub annotations: HashMap<u64, Box<[Annotation]>>,
+    pub metadata: Box<[Annotation]>,
+    pub(crate) stats: LookupStats,
+    pub(crate) frozen: AtomicBool,
 }
 
+//Opaque copy of a LocaleTable taken by LocaleTable::snapshot
+pub struct LocaleTableSnapshot(LocaleTable);
+
 #[repr(C)]
 pub struct AllocationResult {
     pub table: *mut LocaleTable,
@@ -40,41 +56,63 @@ pub unsafe extern "C" fn merge_locale_table_c(
 ) -> MergeResult {
     //NOTE: DO NOT FORGET TO NOTE THAT THE FIRST ITEM IN THE ARRAY OF POINTERS WILL WIN
 
-    if tables.is_null() {
-        return MergeResult {
-            table: std::ptr::null_mut(),
-            merge_state: MergeTableError::NullTablePointer,
-        };
-    }
+    let tables = match unsafe { ffi_util::table_refs(tables, count) } {
+        Ok(tables) => tables,
+        Err(error) => {
+            return MergeResult {
+                table: std::ptr::null_mut(),
+                merge_state: error.into(),
+            };
+        }
+    };
 
-    parser::merge_locale_table_rust(unsafe {
-        std::slice::from_raw_parts(tables as *const &LocaleTable, count)
-    })
+    let tables: Vec<&LocaleTable> = tables.iter().map(Arc::as_ref).collect();
+    parser::merge_locale_table_rust(&tables)
 }
 
+//merge_locale_table_c that stops with MergeTableError::Cancelled once the host sets `cancel`, which may be null
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn get_locale_table(path: *const c_char) -> AllocationResult {
-    if path.is_null() {
-        return AllocationResult {
-            table: std::ptr::null_mut(),
-            allocation_state: ParseR3Error::NullPathProvided,
-        };
-    }
+pub unsafe extern "C" fn merge_locale_table_c_cancellable(
+    tables: *const *const LocaleTable,
+    count: usize,
+    cancel: *const AtomicBool,
+) -> MergeResult {
+    let tables = match unsafe { ffi_util::table_refs(tables, count) } {
+        Ok(tables) => tables,
+        Err(error) => {
+            return MergeResult {
+                table: std::ptr::null_mut(),
+                merge_state: error.into(),
+            };
+        }
+    };
+
+    let tables: Vec<&LocaleTable> = tables.iter().map(Arc::as_ref).collect();
+    let cancel = unsafe { ffi_util::cancel_flag(cancel) };
+    parser::merge_result(parser::merge_locale_tables_until(&tables, cancel))
+}
 
-    let c_str = unsafe { CStr::from_ptr(path) };
-    let path_str = match c_str.to_str() {
-        Ok(s) => s,
-        Err(_) => {
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn get_locale_table(path: *const c_char) -> AllocationResult {
+    let path_str = match unsafe { ffi_util::c_str(path) } {
+        Some(Ok(s)) => s,
+        Some(Err(_)) => {
             return AllocationResult {
                 table: std::ptr::null_mut(),
                 allocation_state: ParseR3Error::InvalidUTF8Path,
             };
         }
+        None => {
+            return AllocationResult {
+                table: std::ptr::null_mut(),
+                allocation_state: ParseR3Error::NullPathProvided,
+            };
+        }
     };
 
     match parse_r3locale_file(Path::new(path_str)) {
         Ok(table) => AllocationResult {
-            table: Box::into_raw(Box::new(table)),
+            table: ffi_util::into_handle(table),
             allocation_state: ParseR3Error::Normal,
         },
         Err(parse_error) => AllocationResult {
@@ -89,35 +127,73 @@ pub unsafe extern "C" fn get_multiple_locale_tables(
     paths: *const *const c_char,
     count: usize,
 ) -> MergeResult {
-    if paths.is_null() {
+    unsafe { get_multiple_locale_tables_cancellable(paths, count, std::ptr::null()) }
+}
+
+//get_multiple_locale_tables that stops with MergeTableError::Cancelled once the host sets `cancel`, which may be null.
+//The flag is checked before each file and during the merge.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn get_multiple_locale_tables_cancellable(
+    paths: *const *const c_char,
+    count: usize,
+    cancel: *const AtomicBool,
+) -> MergeResult {
+    unsafe { get_multiple_locale_tables_with_progress(paths, count, cancel, None, std::ptr::null_mut()) }
+}
+
+//Called before each file is read with the number of files already read and the path about to be read, then once more
+//with `files_done == files_total` and a null path when the tables are merged. `user_data` is passed through unchanged.
+pub type ProgressCallback = Option<
+    unsafe extern "C" fn(files_done: usize, files_total: usize, current_path: *const c_char, user_data: *mut c_void),
+>;
+
+//get_multiple_locale_tables_cancellable that reports its progress, for determinate progress bars
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn get_multiple_locale_tables_with_progress(
+    paths: *const *const c_char,
+    count: usize,
+    cancel: *const AtomicBool,
+    progress: ProgressCallback,
+    user_data: *mut c_void,
+) -> MergeResult {
+    let report = |files_done: usize, current_path: *const c_char| {
+        if let Some(progress) = progress {
+            unsafe { progress(files_done, count, current_path, user_data) };
+        }
+    };
+    let Some(path_slice) = (unsafe { ffi_util::slice(paths, count) }) else {
         return MergeResult {
             table: std::ptr::null_mut(),
             merge_state: MergeTableError::NullPathProvided,
         };
-    }
-
-    // Convert raw pointer to slice
-    let path_slice = unsafe { std::slice::from_raw_parts(paths, count) };
+    };
+    let cancel = unsafe { ffi_util::cancel_flag(cancel) };
 
     let mut parsed_tables = Vec::with_capacity(count);
-    for &c_path in path_slice {
-        if c_path.is_null() {
+    for (files_done, &c_path) in path_slice.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
             return MergeResult {
                 table: std::ptr::null_mut(),
-                merge_state: MergeTableError::NullPathProvided,
+                merge_state: MergeTableError::Cancelled,
             };
         }
 
-        let c_str = unsafe { CStr::from_ptr(c_path) };
-        let path_str = match c_str.to_str() {
-            Ok(s) => s,
-            Err(_) => {
+        let path_str = match unsafe { ffi_util::c_str(c_path) } {
+            Some(Ok(s)) => s,
+            Some(Err(_)) => {
                 return MergeResult {
                     table: std::ptr::null_mut(),
                     merge_state: MergeTableError::InvalidUTF8Path,
                 };
             }
+            None => {
+                return MergeResult {
+                    table: std::ptr::null_mut(),
+                    merge_state: MergeTableError::NullPathProvided,
+                };
+            }
         };
+        report(files_done, c_path);
 
         match parse_r3locale_file(Path::new(path_str)) {
             Ok(table) => parsed_tables.push(table),
@@ -132,7 +208,11 @@ pub unsafe extern "C" fn get_multiple_locale_tables(
 
     // References to all tables for merging
     let references: Vec<&LocaleTable> = parsed_tables.iter().collect();
-    parser::merge_locale_table_rust(&references)
+    let merged = parser::merge_result(parser::merge_locale_tables_until(&references, cancel));
+    if !merged.table.is_null() {
+        report(count, std::ptr::null());
+    }
+    merged
 }
 
 #[unsafe(no_mangle)]
@@ -141,42 +221,188 @@ pub unsafe extern "C" fn get_entry(
     key_ptr: *const u8,
     key_len: usize,
 ) -> FindEntryResult {
-    if table.is_null() {
-        return FindEntryResult {
-            value_ptr: std::ptr::null(),
-            value_len: 0,
-            allocation_state: FindEntryError::NullTable,
-        };
-    } else if key_ptr.is_null() {
+    let table = match ffi_util::table_ref(table) {
+        Ok(table) => table,
+        Err(error) => {
+            return FindEntryResult {
+                value_ptr: std::ptr::null(),
+                value_len: 0,
+                allocation_state: error.into(),
+            };
+        }
+    };
+    let Some(key) = (unsafe { ffi_util::slice(key_ptr, key_len) }) else {
         return FindEntryResult {
             value_ptr: std::ptr::null(),
             value_len: 0,
             allocation_state: FindEntryError::NullKeyPtr,
         };
-    }
-
-    let table = unsafe { &*table };
-    let key = unsafe { std::slice::from_raw_parts(key_ptr, key_len) };
+    };
 
     if let Some(value) = table.find_entry(key) {
-        FindEntryResult {
+        return FindEntryResult {
             value_ptr: value.as_ptr(),
             value_len: value.len(),
             allocation_state: FindEntryError::Normal,
+        };
+    }
+
+    if log_enabled(LogLevel::Debug) {
+        log(LogLevel::Debug, "interop", StatusCode::MISSING_KEY, &format!("Missing key {}", String::from_utf8_lossy(key)));
+    }
+    let (value_ptr, value_len) = match current_config().missing_key_policy {
+        MissingKeyPolicy::NotFound => (std::ptr::null(), 0),
+        MissingKeyPolicy::ReturnKey => (key_ptr, key_len),
+        MissingKeyPolicy::Empty => (key_ptr, 0),
+    };
+    FindEntryResult {
+        value_ptr,
+        value_len,
+        allocation_state: FindEntryError::NoEntryFound,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn locale_table_clone(table: *const LocaleTable) -> MergeResult {
+    let table = match ffi_util::table_ref(table) {
+        Ok(table) => table,
+        Err(error) => {
+            return MergeResult {
+                table: std::ptr::null_mut(),
+                merge_state: error.into(),
+            };
         }
-    } else {
-        FindEntryResult {
-            value_ptr: std::ptr::null(),
-            value_len: 0,
-            allocation_state: FindEntryError::NoEntryFound,
+    };
+
+    parser::merge_locale_table_rust(&[&table])
+}
+
+//Freeing a table twice, or a pointer that is not a table, returns InvalidHandle and leaves the allocator alone.
+//Freeing null does nothing and returns NullTable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_locale_table(ptr: *mut LocaleTable) -> FreeTableError {
+    match ffi_util::take_table(ptr) {
+        Ok(table) => {
+            drop(table);
+            FreeTableError::Normal
+        }
+        Err(error) => {
+            let error = FreeTableError::from(error);
+            if error == FreeTableError::InvalidHandle {
+                let message = format!("Refused to free {ptr:p}, it was already freed or is not a table");
+                log(LogLevel::Warning, "interop", StatusCode::INVALID_HANDLE, &message);
+            }
+            error
+        }
+    }
+}
+
+//free_locale_table that returns the estimated bytes reclaimed, for hosts tracking memory when the user switches
+//languages. Returns 0 for a null table or one that is not from this library.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn unload_locale_table(ptr: *mut LocaleTable) -> usize {
+    let Ok(table) = ffi_util::take_table(ptr) else {
+        return 0;
+    };
+    size_of::<LocaleTable>() + table.memory_usage()
+}
+
+//Freezes a table before it is shared with other plugins, see LocaleTable::freeze. Freezing twice does nothing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_freeze_table(table: *const LocaleTable) -> FindEntryError {
+    match ffi_util::table_ref(table) {
+        Ok(table) => {
+            table.freeze();
+            FindEntryError::Normal
         }
+        Err(error) => error.into(),
     }
 }
 
+//False for tables that fail the handle check
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn free_locale_table(ptr: *mut LocaleTable) {
-    if !ptr.is_null() {
-        unsafe { drop(Box::from_raw(ptr)) };
+pub unsafe extern "C" fn r3l_is_frozen(table: *const LocaleTable) -> bool {
+    ffi_util::table_ref(table).is_ok_and(|table| table.is_frozen())
+}
+
+//Logs every table that was handed out and not freed yet as a warning under the "leaks" module, oldest first, and
+//returns how many there are. Call it e.g. when returning to the main menu, where no tables of the last session should
+//be alive. Built with the leak-check feature, each message includes where the table was created.
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_report_leaks() -> usize {
+    ffi_util::report_leaks()
+}
+
+//Bits reported by r3l_capabilities. A bit keeps its value forever, so hosts can test for features that are newer than
+//they are. Reserved bits belong to features this version doesn't have yet and are never set.
+pub const CAPABILITY_ANNOTATIONS: u64 = 1 << 0;
+//LocaleTable::preview, which fills placeholders with sample arguments
+pub const CAPABILITY_PREVIEW: u64 = 1 << 1;
+pub const CAPABILITY_CLDR_DATA: u64 = 1 << 2;
+pub const CAPABILITY_WASM_EXPORTS: u64 = 1 << 3;
+pub const CAPABILITY_SAFETY_CHECKS: u64 = 1 << 4;
+//Reading version 1 files and key migration maps
+pub const CAPABILITY_MIGRATIONS: u64 = 1 << 5;
+//LocaleTable::format_entry, which fills named placeholders with caller supplied arguments
+pub const CAPABILITY_INTERPOLATION: u64 = 1 << 6;
+//LocaleTable::get_plural, which picks a key's plural variant by CLDR rules
+pub const CAPABILITY_PLURAL_RULES: u64 = 1 << 7;
+//Reserved for a compiled binary table format
+pub const CAPABILITY_BINARY_FORMAT: u64 = 1 << 8;
+//Reserved for reloading tables when their files change
+pub const CAPABILITY_WATCH: u64 = 1 << 9;
+//get_sealed_locale_table, built with the encryption feature
+pub const CAPABILITY_SEALED_PACKS: u64 = 1 << 10;
+//r3l_verify_pack, built with the signatures feature
+pub const CAPABILITY_SIGNED_PACKS: u64 = 1 << 11;
+//r3l_install_pack, built with the net feature
+pub const CAPABILITY_PACK_INSTALL: u64 = 1 << 12;
+//r3l_load_remote_overrides, built with the net and signatures features
+pub const CAPABILITY_REMOTE_OVERRIDES: u64 = 1 << 13;
+
+//Every Cargo feature with whether this build has it and the CAPABILITY_ bit it sets, 0 for none. Also listed by
+//dialect::format_spec, so a new feature only has to be added here.
+pub(crate) const FEATURES: [(&str, bool, u64); 14] = [
+    ("additional_locale_safety_checks", cfg!(feature = "additional_locale_safety_checks"), CAPABILITY_SAFETY_CHECKS),
+    ("c-exports", cfg!(feature = "c-exports"), 0),
+    ("cbindgen", cfg!(feature = "cbindgen"), 0),
+    ("cldr", cfg!(feature = "cldr"), CAPABILITY_CLDR_DATA),
+    ("encryption", cfg!(feature = "encryption"), CAPABILITY_SEALED_PACKS),
+    ("godot", cfg!(feature = "godot"), 0),
+    ("jni", cfg!(feature = "jni"), 0),
+    ("leak-check", cfg!(feature = "leak-check"), 0),
+    ("lua", cfg!(feature = "lua"), 0),
+    ("net", cfg!(feature = "net"), CAPABILITY_PACK_INSTALL),
+    ("pgo", cfg!(feature = "pgo"), 0),
+    ("signatures", cfg!(feature = "signatures"), CAPABILITY_SIGNED_PACKS),
+    ("std", cfg!(feature = "std"), 0),
+    ("wasm", cfg!(feature = "wasm"), CAPABILITY_WASM_EXPORTS),
+];
+
+//Features of this build as CAPABILITY_ bits, so hosts can feature-detect instead of checking the version
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_capabilities() -> u64 {
+    //Remote overrides need two features, so they aren't a row of FEATURES
+    let remote_overrides = (cfg!(all(feature = "net", feature = "signatures")), CAPABILITY_REMOTE_OVERRIDES);
+    FEATURES
+        .into_iter()
+        .map(|(_, enabled, bit)| (enabled, bit))
+        .chain([remote_overrides])
+        .filter(|(enabled, _)| *enabled)
+        .fold(
+            CAPABILITY_ANNOTATIONS
+                | CAPABILITY_PREVIEW
+                | CAPABILITY_MIGRATIONS
+                | CAPABILITY_INTERPOLATION
+                | CAPABILITY_PLURAL_RULES,
+            |capabilities, (_, bit)| capabilities | bit,
+        )
+}
+
+impl Clone for LocaleTable {
+    fn clone(&self) -> Self {
+        //A merge of a single table is a deep copy, and its values always fit in the new string table
+        parser::merge_locale_tables(&[self]).expect("Failed to copy locale table")
     }
 }
 
@@ -194,11 +420,108 @@ impl LocaleTable {
         }
     }
 
+    pub fn snapshot(&self) -> LocaleTableSnapshot {
+        LocaleTableSnapshot(self.clone())
+    }
+
+    //Replaces the contents of this table with the state captured by snapshot, lookup stats are kept
+    pub fn restore(&mut self, snapshot: LocaleTableSnapshot) -> Result<(), TableFrozenError> {
+        self.check_mutable()?;
+        let stats = std::mem::take(&mut self.stats);
+        *self = snapshot.0;
+        self.stats = stats;
+        Ok(())
+    }
+
+    //Makes the table read-only for good, e.g. before sharing it with other plugins or threads. Methods that change a
+    //frozen table return TableFrozenError instead of changing it under its readers. Writes to the public fields
+    //bypass this, and freeing the table is still up to its owner. Copies from clone or snapshot are not frozen.
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::Release);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn check_mutable(&self) -> Result<(), TableFrozenError> {
+        if self.is_frozen() {
+            return Err(TableFrozenError);
+        }
+        Ok(())
+    }
+
     pub fn find_entry(&self, key: &[u8]) -> Option<&str> {
-        let hash = xxh3_64(key);
-        self.entries
+        self.lookup(xxh3_64(key), Some(key))
+    }
+
+    //For callers looking up the same key in several tables, so the key is hashed once
+    pub fn find_hash(&self, hash: u64) -> Option<&str> {
+        self.lookup(hash, None)
+    }
+
+    fn lookup(&self, hash: u64, key: Option<&[u8]>) -> Option<&str> {
+        let value = self
+            .entries
             .find(hash, |entry| entry.key == hash)
-            .and_then(|entry| self.string_values.get(entry.string_id))
+            .and_then(|entry| self.string_values.get(entry.string_id));
+        self.stats.record(hash, key, value.is_some());
+        value
+    }
+
+    pub fn lookup_stats(&self) -> &LookupStats {
+        &self.stats
+    }
+
+    //find_hash without counting the lookup
+    pub(crate) fn contains_hash(&self, hash: u64) -> bool {
+        self.entries.find(hash, |entry| entry.key == hash).is_some()
+    }
+
+    pub fn metadata(&self) -> &[Annotation] {
+        &self.metadata
+    }
+
+    //Language tag declared by the file's `@language` header
+    pub fn language(&self) -> Option<&str> {
+        find_annotation(&self.metadata, "language").and_then(|annotation| annotation.value.as_deref())
+    }
+
+    pub fn entry_annotations(&self, key: &[u8]) -> &[Annotation] {
+        self.annotations
+            .get(&xxh3_64(key))
+            .map(|annotations| &annotations[..])
+            .unwrap_or(&[])
+    }
+
+    //Where the string appears in game, from the entry's `@context` annotation
+    pub fn entry_context(&self, key: &[u8]) -> Option<&str> {
+        find_annotation(self.entry_annotations(key), "context")
+            .and_then(|annotation| annotation.value.as_deref())
+    }
+
+    //Layout limits from the entry's `@maxlines` and `@wrap` annotations
+    pub fn entry_constraints(&self, key: &[u8]) -> Option<EntryConstraints> {
+        EntryConstraints::from_annotations(self.entry_annotations(key))
+            .ok()
+            .flatten()
+    }
+
+    pub fn entry_deprecation(&self, key: &[u8]) -> Option<Deprecation> {
+        Deprecation::from_annotations(self.entry_annotations(key))
+    }
+
+    //Like find_entry, but follows `@deprecated replacement=` to the key that replaced this one. Chains are followed
+    //up to MAX_REPLACEMENT_HOPS so a cycle between two keys can't hang the lookup.
+    pub fn find_entry_or_replacement(&self, key: &[u8]) -> Option<&str> {
+        let mut key = key.to_vec();
+        for _ in 0..MAX_REPLACEMENT_HOPS {
+            match self.entry_deprecation(&key).and_then(|deprecation| deprecation.replacement) {
+                Some(replacement) if self.find_entry(replacement.as_bytes()).is_some() => key = replacement.into_bytes(),
+                _ => break,
+            }
+        }
+        self.find_entry(&key)
     }
 }
 
@@ -209,4 +532,40 @@ pub enum FindEntryError {
     NullTable,
     NullKeyPtr,
     NoEntryFound,
+    //The table pointer is not a live table from this library, e.g. one that was already freed
+    InvalidHandle,
+}
+
+//Returned when changing a table that was frozen, see LocaleTable::freeze
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableFrozenError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum FreeTableError {
+    Normal,
+    NullTable,
+    //Already freed or not a table from this library, nothing was freed
+    InvalidHandle,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_double_free() {
+        let table = parse_r3locale_bytes(&mut b"[[quit]]\nQuit\n".to_vec()).expect("Parse failed");
+        let cloned = unsafe { locale_table_clone(&table) };
+        assert!(matches!(cloned.merge_state, MergeTableError::InvalidHandle));
+
+        let merged = parser::merge_locale_table_rust(&[&table]);
+        assert_eq!(unsafe { free_locale_table(merged.table) }, FreeTableError::Normal);
+        assert_eq!(unsafe { free_locale_table(merged.table) }, FreeTableError::InvalidHandle);
+        assert_eq!(unsafe { free_locale_table(std::ptr::null_mut()) }, FreeTableError::NullTable);
+        let key = b"quit";
+        let lookup = unsafe { get_entry(merged.table, key.as_ptr(), key.len()) };
+        assert!(matches!(lookup.allocation_state, FindEntryError::InvalidHandle));
+    }
 }
@@ -0,0 +1,95 @@
+use super::ffi_util;
+use super::interop::LocaleTable;
+use super::parser::{parse_r3locale_bytes, parse_r3locale_file};
+use ::jni::JNIEnv;
+use ::jni::objects::{JByteArray, JClass, JString};
+use ::jni::sys::{jlong, jstring};
+use std::path::Path;
+
+//Exports for `com.reloaded.localisation.LocaleTable`. Tables are handed to Java as a `long` handle that must be
+//released with `free`. Handles are checked like those of the C API, so a zero or freed handle returns null.
+
+const EXCEPTION_CLASS: &str = "java/io/IOException";
+
+fn into_handle(env: &mut JNIEnv, table: Result<LocaleTable, impl std::fmt::Debug>) -> jlong {
+    match table {
+        Ok(table) => ffi_util::into_handle(table) as jlong,
+        Err(error) => {
+            let _ = env.throw_new(EXCEPTION_CLASS, format!("Failed to load locale table: {error:?}"));
+            0
+        }
+    }
+}
+
+fn to_java_string(env: &mut JNIEnv, value: Option<&str>) -> jstring {
+    value
+        .and_then(|value| env.new_string(value).ok())
+        .map_or(std::ptr::null_mut(), JString::into_raw)
+}
+
+//Throws an IOException when the file can't be read or parsed
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_reloaded_localisation_LocaleTable_load<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    path: JString<'local>,
+) -> jlong {
+    let Ok(path) = env.get_string(&path).map(String::from) else {
+        return 0;
+    };
+    let table = parse_r3locale_file(Path::new(&path));
+    into_handle(&mut env, table)
+}
+
+//For files read from the APK's assets, which have no path on disk
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_reloaded_localisation_LocaleTable_parse<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    bytes: JByteArray<'local>,
+) -> jlong {
+    let Ok(mut bytes) = env.convert_byte_array(&bytes) else {
+        return 0;
+    };
+    let table = parse_r3locale_bytes(&mut bytes);
+    into_handle(&mut env, table)
+}
+
+//Returns null when the key is missing
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn Java_com_reloaded_localisation_LocaleTable_getEntry<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    key: JString<'local>,
+) -> jstring {
+    let Ok(table) = ffi_util::table_ref(handle as *const LocaleTable) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(key) = env.get_string(&key).map(String::from) else {
+        return std::ptr::null_mut();
+    };
+    to_java_string(&mut env, table.find_entry(key.as_bytes()))
+}
+
+//Returns null when the file has no `@language`
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn Java_com_reloaded_localisation_LocaleTable_getLanguage<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jstring {
+    let Ok(table) = ffi_util::table_ref(handle as *const LocaleTable) else {
+        return std::ptr::null_mut();
+    };
+    to_java_string(&mut env, table.language())
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "system" fn Java_com_reloaded_localisation_LocaleTable_free<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    drop(ffi_util::take_table(handle as *mut LocaleTable));
+}
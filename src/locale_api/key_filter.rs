@@ -0,0 +1,60 @@
+use super::interop::LocaleTable;
+
+//Bits reserved per key, about 2% false positives with the 3 bits set per key
+const BITS_PER_KEY: usize = 12;
+
+//Blocked Bloom filter over the key hashes of a table. All bits for a key are in one 64 bit word, so a miss is usually
+//rejected with a single memory access instead of a hash table probe. It never rejects a key the table has.
+#[derive(Debug, Clone)]
+pub struct KeyFilter {
+    words: Box<[u64]>,
+}
+
+impl KeyFilter {
+    pub fn new(table: &LocaleTable) -> Self {
+        let word_count = (table.entries.len() * BITS_PER_KEY).div_ceil(64).max(1).next_power_of_two();
+        let mut words = vec![0u64; word_count].into_boxed_slice();
+        for entry in table.entries.iter() {
+            let (word, bits) = Self::position(word_count, entry.key);
+            words[word] |= bits;
+        }
+        Self { words }
+    }
+
+    //False means the table doesn't have the key, true that it may have it
+    pub fn may_contain(&self, hash: u64) -> bool {
+        let (word, bits) = Self::position(self.words.len(), hash);
+        self.words[word] & bits == bits
+    }
+
+    //The word comes from the high bits, the hash table uses the low ones for its buckets
+    fn position(word_count: usize, hash: u64) -> (usize, u64) {
+        let word = (hash >> 32) as usize & (word_count - 1);
+        let bits = (1 << (hash & 63)) | (1 << ((hash >> 6) & 63)) | (1 << ((hash >> 12) & 63));
+        (word, bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+    use std::fmt::Write;
+    use xxhash_rust::xxh3::xxh3_64;
+
+    #[test]
+    fn test_key_filter() {
+        let mut text = String::new();
+        for index in 0..200 {
+            let _ = write!(text, "[[key{index}]]\nValue {index}\n");
+        }
+        let table = parse_r3locale_bytes(&mut text.into_bytes()).expect("Parse failed");
+        let filter = KeyFilter::new(&table);
+
+        assert!((0..200).all(|index| filter.may_contain(xxh3_64(format!("key{index}").as_bytes()))));
+        let false_positives = (0..1000)
+            .filter(|index| filter.may_contain(xxh3_64(format!("missing{index}").as_bytes())))
+            .count();
+        assert!(false_positives < 100, "{false_positives} false positives");
+    }
+}
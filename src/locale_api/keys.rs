@@ -0,0 +1,93 @@
+use super::interop::LocaleTable;
+use super::manager::LocaleManager;
+use super::overlay::CowLocaleTable;
+use hashbrown::HashMap;
+use std::sync::{OnceLock, RwLock};
+use xxhash_rust::xxh3::xxh3_64;
+
+//A pre-hashed key. Tables only store key hashes, so one KeyId looks the key up in every resident language without
+//hashing it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct KeyId(pub u64);
+
+impl KeyId {
+    //Hashes the key without registering its text, for keys that are only looked up
+    pub fn of(key: &str) -> Self {
+        KeyId(xxh3_64(key.as_bytes()))
+    }
+}
+
+//Process-wide key text, stored once however many tables use the key. Texts are kept for the life of the process,
+//so only intern keys the game actually uses, not user input.
+fn registry() -> &'static RwLock<HashMap<KeyId, &'static str>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<KeyId, &'static str>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+//Interning the same key again returns the same id without storing the text twice
+pub fn intern_key(key: &str) -> KeyId {
+    let id = KeyId::of(key);
+    if registry().read().is_ok_and(|keys| keys.contains_key(&id)) {
+        return id;
+    }
+    if let Ok(mut keys) = registry().write() {
+        keys.entry(id).or_insert_with(|| Box::leak(key.into()));
+    }
+    id
+}
+
+//The text of an interned key, e.g. for logging which key is missing
+pub fn key_text(id: KeyId) -> Option<&'static str> {
+    registry().read().ok()?.get(&id).copied()
+}
+
+pub fn interned_key_count() -> usize {
+    registry().read().map(|keys| keys.len()).unwrap_or_default()
+}
+
+impl LocaleTable {
+    pub fn find_id(&self, id: KeyId) -> Option<&str> {
+        self.find_hash(id.0)
+    }
+}
+
+impl CowLocaleTable {
+    pub fn find_id(&self, id: KeyId) -> Option<&str> {
+        self.find_hash(id.0)
+    }
+}
+
+impl LocaleManager {
+    //find_entry and find_in for pre-interned keys
+    pub fn find_id(&self, id: KeyId) -> Option<&str> {
+        self.find_hash(id.0)
+    }
+
+    pub fn find_id_in(&self, language: &str, id: KeyId) -> Option<&str> {
+        self.find_hash_in(language, id.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_intern_key() {
+        let play = intern_key("menu.play");
+        assert_eq!(intern_key(&String::from("menu.play")), play);
+        assert_eq!(key_text(play), Some("menu.play"));
+        assert_eq!(key_text(KeyId::of("never.interned")), None);
+        assert!(interned_key_count() >= 1);
+
+        let mut manager = LocaleManager::new();
+        manager.add_language("de", parse_r3locale_bytes(&mut b"[[menu.play]]\nSpielen\n".to_vec()).expect("Parse failed"));
+        manager.add_language("fr", parse_r3locale_bytes(&mut b"[[menu.play]]\nJouer\n".to_vec()).expect("Parse failed"));
+        assert_eq!(manager.find_id_in("de", play), Some("Spielen"));
+        assert_eq!(manager.table("fr").and_then(|table| table.find_id(play)), Some("Jouer"));
+        assert!(manager.set_language("fr"));
+        assert_eq!(manager.find_id(play), Some("Jouer"));
+    }
+}
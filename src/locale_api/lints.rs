@@ -0,0 +1,313 @@
+use super::annotations::EntryConstraints;
+use super::document::{DocumentEntry, LocaleDocument};
+use super::sanitizer::{confusable_latin, has_confusables, invisible_kind};
+
+//Value hygiene problems, the usual review comments on community translations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum LintKind {
+    //Whitespace at the start of the value that the source doesn't have
+    LeadingWhitespace,
+    //Whitespace at the end of a line that the source doesn't have
+    TrailingWhitespace,
+    DoubleSpace,
+    //The source ends a sentence and the translation doesn't, or the other way around
+    TerminalPunctuation,
+    //Brackets or quotes that are opened and not closed, when they are balanced in the source
+    UnbalancedPairs,
+    //A different number of line breaks than the source, multi-line dialog boxes break when lines are merged or added
+    LineBreaks,
+    //More lines than the `@maxlines` of the source entry, see EntryConstraints::allows
+    TooManyLines,
+}
+
+impl LintKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            LintKind::LeadingWhitespace => "leading whitespace",
+            LintKind::TrailingWhitespace => "trailing whitespace",
+            LintKind::DoubleSpace => "double space",
+            LintKind::TerminalPunctuation => "terminal punctuation differs from source",
+            LintKind::UnbalancedPairs => "unbalanced brackets or quotes",
+            LintKind::LineBreaks => "line break count differs from source",
+            LintKind::TooManyLines => "more lines than @maxlines allows",
+        }
+    }
+}
+
+//Characters that don't show up when reading a file, a key containing one is not found by a lookup for the key as it
+//looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum InvisibleKind {
+    Tab,
+    NonBreakingSpace,
+    //Zero width spaces and byte order marks, and joiners in keys
+    ZeroWidth,
+    //Embeddings, overrides and isolates, and directional marks in keys
+    BidiControl,
+}
+
+impl InvisibleKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            InvisibleKind::Tab => "tab",
+            InvisibleKind::NonBreakingSpace => "non-breaking space",
+            InvisibleKind::ZeroWidth => "zero width character",
+            InvisibleKind::BidiControl => "bidi control character",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvisibleCharacter {
+    pub key: String,
+    //Found in the key rather than the value
+    pub in_key: bool,
+    pub codepoint: char,
+    pub kind: InvisibleKind,
+}
+
+//A character in a key that looks like the Latin `looks_like`, e.g. a Cyrillic `а` in `plаy`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfusableCharacter {
+    pub key: String,
+    pub codepoint: char,
+    pub looks_like: char,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LintOptions {
+    //Allowed difference in line breaks, for UIs that wrap text and only care about rough layout
+    pub line_break_tolerance: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub key: String,
+    pub kind: LintKind,
+}
+
+const TERMINAL_PUNCTUATION: [char; 10] = ['.', '!', '?', ':', '…', '。', '！', '？', '：', '؟'];
+const PAIRS: [(char, char); 6] = [('(', ')'), ('[', ']'), ('{', '}'), ('«', '»'), ('“', '”'), ('「', '」')];
+
+fn has_trailing_whitespace(value: &str) -> bool {
+    value.lines().any(|line| line.ends_with([' ', '\t']))
+}
+
+//Quotes that look the same on both sides count as balanced when they come in pairs
+fn is_balanced(value: &str) -> bool {
+    let count = |wanted: char| value.chars().filter(|&character| character == wanted).count();
+    PAIRS.iter().all(|&(open, close)| count(open) == count(close)) && count('"') % 2 == 0
+}
+
+//Lints one translated value, only reporting problems the source doesn't share
+pub fn lint_value(source: &str, translation: &str) -> Vec<LintKind> {
+    lint_value_with_options(source, translation, &LintOptions::default())
+}
+
+pub fn lint_value_with_options(source: &str, translation: &str, options: &LintOptions) -> Vec<LintKind> {
+    let mut lints = Vec::new();
+    if translation.starts_with(char::is_whitespace) && !source.starts_with(char::is_whitespace) {
+        lints.push(LintKind::LeadingWhitespace);
+    }
+    if has_trailing_whitespace(translation) && !has_trailing_whitespace(source) {
+        lints.push(LintKind::TrailingWhitespace);
+    }
+    if translation.contains("  ") && !source.contains("  ") {
+        lints.push(LintKind::DoubleSpace);
+    }
+    if source.trim_end().ends_with(TERMINAL_PUNCTUATION) != translation.trim_end().ends_with(TERMINAL_PUNCTUATION) {
+        lints.push(LintKind::TerminalPunctuation);
+    }
+    if !is_balanced(translation) && is_balanced(source) {
+        lints.push(LintKind::UnbalancedPairs);
+    }
+    let line_breaks = |value: &str| value.matches('\n').count();
+    if line_breaks(source).abs_diff(line_breaks(translation)) > options.line_break_tolerance {
+        lints.push(LintKind::LineBreaks);
+    }
+    lints
+}
+
+//Lints every translated entry against its source, in source order, including the `@maxlines` limit of the source
+//entry. Missing and empty translations are skipped.
+pub fn lint_translation(source: &LocaleDocument, translation: &LocaleDocument) -> Vec<Lint> {
+    lint_translation_with_options(source, translation, &LintOptions::default())
+}
+
+pub fn lint_translation_with_options(source: &LocaleDocument, translation: &LocaleDocument, options: &LintOptions) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for source_entry in &source.entries {
+        let translated = translation.find(&source_entry.key).map(DocumentEntry::parsed_value);
+        let Some(translated) = translated.filter(|translated| !translated.is_empty()) else {
+            continue;
+        };
+        let source_value = source_entry.parsed_value();
+        let mut kinds = lint_value_with_options(&source_value, &translated, options);
+        //Invalid constraints are reported by the parser
+        let constraints = EntryConstraints::from_annotations(&source_entry.parsed_annotations()).ok().flatten();
+        if constraints.is_some_and(|constraints| !constraints.allows(&translated)) {
+            kinds.push(LintKind::TooManyLines);
+        }
+        lints.extend(kinds.into_iter().map(|kind| Lint {
+            key: source_entry.key.clone(),
+            kind,
+        }));
+    }
+    lints
+}
+
+//Reports each invisible character once per key and side, in file order. ParseOptions::normalize_invisible replaces
+//or removes them when parsing.
+pub fn find_invisible_characters(document: &LocaleDocument) -> Vec<InvisibleCharacter> {
+    let mut found: Vec<InvisibleCharacter> = Vec::new();
+    for entry in &document.entries {
+        let first = found.len();
+        let value = entry.parsed_value();
+        for (text, in_key) in [(entry.key.as_str(), true), (&*value, false)] {
+            for codepoint in text.chars() {
+                let Some(kind) = invisible_kind(codepoint, in_key) else {
+                    continue;
+                };
+                if found[first..]
+                    .iter()
+                    .any(|character| character.in_key == in_key && character.codepoint == codepoint)
+                {
+                    continue;
+                }
+                found.push(InvisibleCharacter {
+                    key: entry.key.clone(),
+                    in_key,
+                    codepoint,
+                    kind,
+                });
+            }
+        }
+    }
+    found
+}
+
+//Reports each lookalike character once per key, in file order. Only keys that also contain Latin letters are checked,
+//ParseOptions::confusable_keys decides whether the parser replaces them.
+pub fn find_confusable_keys(document: &LocaleDocument) -> Vec<ConfusableCharacter> {
+    let mut found: Vec<ConfusableCharacter> = Vec::new();
+    for entry in document.entries.iter().filter(|entry| has_confusables(&entry.key)) {
+        let first = found.len();
+        for codepoint in entry.key.chars() {
+            let Some(looks_like) = confusable_latin(codepoint) else {
+                continue;
+            };
+            if found[first..].iter().any(|character| character.codepoint == codepoint) {
+                continue;
+            }
+            found.push(ConfusableCharacter {
+                key: entry.key.clone(),
+                codepoint,
+                looks_like,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::{
+        ConfusablePolicy, ParseOptions, parse_r3locale_bytes, parse_r3locale_bytes_with_options,
+    };
+
+    #[test]
+    fn test_lint_value() {
+        assert_eq!(lint_value("Game saved.", "Partie sauvegardée."), vec![]);
+        assert_eq!(
+            lint_value("Game saved.", " Partie  sauvegardée "),
+            vec![LintKind::LeadingWhitespace, LintKind::TrailingWhitespace, LintKind::DoubleSpace, LintKind::TerminalPunctuation]
+        );
+        assert_eq!(lint_value("Press (A) to \"jump\"", "Appuyez sur (A pour « sauter"), vec![LintKind::UnbalancedPairs]);
+        assert_eq!(lint_value("保存しました。", "Saved."), vec![]);
+    }
+
+    #[test]
+    fn test_invisible_characters() {
+        let text = "[[play\u{200B}]]\nPlay\tnow\t\u{A0}!\n[[quit]]\n\u{2067}Quit\u{2069} \u{200D}\n";
+        let document = LocaleDocument::parse(text).expect("Parse failed");
+        let found: Vec<(bool, char, InvisibleKind)> = find_invisible_characters(&document)
+            .into_iter()
+            .map(|character| (character.in_key, character.codepoint, character.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (true, '\u{200B}', InvisibleKind::ZeroWidth),
+                (false, '\t', InvisibleKind::Tab),
+                (false, '\u{A0}', InvisibleKind::NonBreakingSpace),
+                (false, '\u{2067}', InvisibleKind::BidiControl),
+                (false, '\u{2069}', InvisibleKind::BidiControl),
+            ]
+        );
+
+        let options = ParseOptions {
+            normalize_invisible: true,
+            ..Default::default()
+        };
+        let table = parse_r3locale_bytes_with_options(&mut text.as_bytes().to_vec(), &options).expect("Parse failed");
+        assert_eq!(table.find_entry(b"play"), Some("Play now  !"));
+        assert_eq!(table.find_entry(b"quit"), Some("Quit \u{200D}"));
+        let table = parse_r3locale_bytes(&mut text.as_bytes().to_vec()).expect("Parse failed");
+        assert_eq!(table.find_entry(b"play"), None);
+    }
+
+    #[test]
+    fn test_confusable_keys() {
+        //Cyrillic а and о and a fullwidth p, and a key written entirely in Cyrillic
+        let text = "[[menu.plаy]]\nPlay\n[[menu.о\u{FF50}tiоns]]\nOptions\n[[меню]]\nМеню\n";
+        let document = LocaleDocument::parse(text).expect("Parse failed");
+        let found: Vec<(char, char)> = find_confusable_keys(&document)
+            .into_iter()
+            .map(|character| (character.codepoint, character.looks_like))
+            .collect();
+        assert_eq!(found, vec![('а', 'a'), ('о', 'o'), ('\u{FF50}', 'p')]);
+
+        let table = parse_r3locale_bytes(&mut text.as_bytes().to_vec()).expect("Parse failed");
+        assert_eq!(table.find_entry(b"menu.play"), None);
+        let options = ParseOptions {
+            confusable_keys: ConfusablePolicy::Normalize,
+            ..Default::default()
+        };
+        let table = parse_r3locale_bytes_with_options(&mut text.as_bytes().to_vec(), &options).expect("Parse failed");
+        assert_eq!(table.find_entry(b"menu.play"), Some("Play"));
+        assert_eq!(table.find_entry(b"menu.options"), Some("Options"));
+        assert_eq!(table.find_entry("меню".as_bytes()), Some("Меню"));
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        let source = "You found a key.\nIt opens the cellar.";
+        assert_eq!(lint_value(source, "Du hast einen Schlüssel gefunden. Er öffnet den Keller."), vec![LintKind::LineBreaks]);
+        let options = LintOptions {
+            line_break_tolerance: 1,
+        };
+        assert_eq!(lint_value_with_options(source, "Du hast einen Schlüssel gefunden.\nEr öffnet\nden Keller.", &options), vec![]);
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let source = LocaleDocument::parse("[[hint]] @maxlines=2\nYou found a key.\nIt opens the cellar.\n[[title]]\nCellar\n")
+            .expect("Parse failed");
+        let translation = LocaleDocument::parse("[[hint]]\nDu hast einen\nSchlüssel gefunden.\nEr öffnet den Keller.\n[[title]]\nDer\nKeller\n")
+            .expect("Parse failed");
+        let options = LintOptions {
+            line_break_tolerance: 1,
+        };
+        let lints = lint_translation_with_options(&source, &translation, &options);
+        assert_eq!(
+            lints,
+            vec![Lint {
+                key: "hint".to_string(),
+                kind: LintKind::TooManyLines
+            }]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use super::interop::LocaleTable;
+use super::preview::format_value;
+use ::mlua::{Lua, Table, Value};
+use std::sync::Arc;
+
+//Registers a global `locale` table into a mod's Lua state:
+//
+//```lua
+//locale.get("menu.play")                      -- value, or nil when the key is missing
+//locale.format("score", { name = "Mario" })   -- value with `{name}` placeholders replaced
+//locale.language()                            -- the file's @language, or nil
+//```
+//
+//Arguments are converted with Lua's tostring, placeholders without an argument are left as written.
+pub fn register_locale(lua: &Lua, table: Arc<LocaleTable>) -> mlua::Result<()> {
+    let locale = lua.create_table()?;
+
+    let source = Arc::clone(&table);
+    let get = lua.create_function(move |_, key: String| Ok(source.find_entry(key.as_bytes()).map(str::to_string)))?;
+    locale.set("get", get)?;
+
+    let source = Arc::clone(&table);
+    let format = lua.create_function(move |_, (key, args): (String, Option<Table>)| {
+        let Some(value) = source.find_entry(key.as_bytes()) else {
+            return Ok(None);
+        };
+        let mut arguments = Vec::new();
+        for pair in args.iter().flat_map(|args| args.pairs::<String, Value>()) {
+            let (name, argument) = pair?;
+            arguments.push((name, argument.to_string()?));
+        }
+        Ok(Some(format_value(value, &arguments)))
+    })?;
+    locale.set("format", format)?;
+
+    let language = lua.create_function(move |_, ()| Ok(table.language().map(str::to_string)))?;
+    locale.set("language", language)?;
+
+    lua.globals().set("locale", locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_register_locale() {
+        let mut text = b"@language=en\n[[score]]\n{name} has {points} points\n".to_vec();
+        let table = parse_r3locale_bytes(&mut text).expect("Parse failed");
+        let lua = Lua::new();
+        register_locale(&lua, Arc::new(table)).expect("Register failed");
+
+        let formatted: String = lua
+            .load("return locale.format('score', { name = 'Mario', points = 3 })")
+            .eval()
+            .expect("Script failed");
+        assert_eq!(formatted, "Mario has 3 points");
+        let missing: Option<String> = lua.load("return locale.get('missing')").eval().expect("Script failed");
+        assert_eq!(missing, None);
+        let language: String = lua.load("return locale.language()").eval().expect("Script failed");
+        assert_eq!(language, "en");
+    }
+}
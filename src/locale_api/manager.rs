@@ -0,0 +1,571 @@
+use super::annotations::find_annotation;
+use super::cancellation::CancellationToken;
+use super::formatting::endonym;
+use super::interop::LocaleTable;
+use super::parser::{ParseR3Error, parse_r3locale_bytes, parse_r3locale_file};
+use super::quarantine::Quarantine;
+use super::runtime::{LogLevel, log};
+use super::stats::LookupStats;
+use super::status::StatusCode;
+use super::string_freeze::{FreezeViolation, StringFreeze};
+use hashbrown::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use xxhash_rust::xxh3::xxh3_64;
+
+//A loaded language, as shown in a settings menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageInfo {
+    pub tag: String,
+    //The language's name for itself, e.g. "Deutsch"
+    pub endonym: Option<String>,
+}
+
+//A language added with register_language is parsed on first use, a failed parse is remembered so it isn't retried on
+//every lookup
+struct Language {
+    tag: String,
+    path: Option<PathBuf>,
+    table: OnceLock<Option<LocaleTable>>,
+}
+
+impl Language {
+    fn loaded(tag: String, table: LocaleTable) -> Self {
+        Self {
+            tag,
+            path: None,
+            table: OnceLock::from(Some(table)),
+        }
+    }
+
+    fn table(&self) -> Option<&LocaleTable> {
+        self.table
+            .get_or_init(|| {
+                let path = self.path.as_deref()?;
+                parse_r3locale_file(path)
+                    .inspect_err(|error| {
+                        log(LogLevel::Warning, "manager", StatusCode::from(*error), &path.display().to_string());
+                    })
+                    .ok()
+            })
+            .as_ref()
+    }
+}
+
+//What LocaleManager::load_directory_quarantined did with each file
+#[derive(Debug, Default)]
+pub struct DirectoryLoad {
+    pub languages: Vec<String>,
+    pub failed: Vec<(PathBuf, ParseR3Error)>,
+    //Quarantined files that were not parsed
+    pub skipped: Vec<PathBuf>,
+}
+
+//Loaded languages of a game or mod, with lookups going to the active language first
+#[derive(Default)]
+pub struct LocaleManager {
+    languages: Vec<Language>,
+    active: Option<usize>,
+    //Compiled into the binary, used for keys the active language is missing
+    embedded_default: Option<LocaleTable>,
+    //Only misses of the embedded default count, a key found there is not blank on screen
+    stats: LookupStats,
+    //Strings of each mod and of SHARED_NAMESPACE by language, in the order they were added. Only a ModScope reads them.
+    namespaces: HashMap<String, Vec<(String, LocaleTable)>>,
+    //Set on release branches, strings of the frozen language are checked against it as they are added
+    string_freeze: Option<StringFreeze>,
+}
+
+impl LocaleManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //For strings compiled in with `include_bytes!`, so there's always something to show even when every file on disk is
+    //missing or corrupted. Errors here mean the embedded file itself is broken, which a test should catch before release.
+    pub fn with_embedded_default(bytes: &[u8]) -> Result<Self, ParseR3Error> {
+        let mut bytes = bytes.to_vec();
+        Ok(Self {
+            embedded_default: Some(parse_r3locale_bytes(&mut bytes)?),
+            ..Self::default()
+        })
+    }
+
+    pub fn embedded_default(&self) -> Option<&LocaleTable> {
+        self.embedded_default.as_ref()
+    }
+
+    //Replaces a language that's already loaded or registered
+    pub fn add_language(&mut self, language: &str, table: LocaleTable) {
+        let frozen = self.string_freeze.as_ref().filter(|freeze| freeze.language.as_deref() == Some(language));
+        if let Some(freeze) = frozen {
+            let violations = freeze.check_table(&table);
+            if !violations.is_empty() {
+                let message = format!("{} keys of {language} changed since the string freeze", violations.len());
+                log(LogLevel::Warning, "manager", StatusCode::STRING_FREEZE_VIOLATION, &message);
+            }
+        }
+        self.replace(Language::loaded(language.to_string(), table));
+    }
+
+    //Declares a string freeze, None lifts it. Strings of the freeze's language added afterwards log a
+    //STRING_FREEZE_VIOLATION warning when they differ from the frozen ones.
+    pub fn set_string_freeze(&mut self, freeze: Option<StringFreeze>) {
+        self.string_freeze = freeze;
+    }
+
+    pub fn string_freeze(&self) -> Option<&StringFreeze> {
+        self.string_freeze.as_ref()
+    }
+
+    //Keys added or changed since the string freeze in the freeze's language, or in the embedded default when the
+    //frozen file has no `@language` header. Empty when there is no freeze or the language isn't loaded.
+    pub fn string_freeze_violations(&self) -> Vec<FreezeViolation> {
+        let Some(freeze) = &self.string_freeze else {
+            return Vec::new();
+        };
+        let table = match &freeze.language {
+            Some(language) => self.table(language),
+            None => self.embedded_default.as_ref(),
+        };
+        table.map(|table| freeze.check_table(table)).unwrap_or_default()
+    }
+
+    //Makes a language available without parsing it, the file is parsed the first time the language is used or
+    //prefetched. Registering every available language at startup costs no parses. Replaces a language that's already
+    //loaded or registered.
+    pub fn register_language(&mut self, language: &str, path: &Path) {
+        self.replace(Language {
+            tag: language.to_string(),
+            path: Some(path.to_path_buf()),
+            table: OnceLock::new(),
+        });
+    }
+
+    //Parses a registered language now instead of on first use, e.g. from a background thread while the settings
+    //menu is open. Returns false when the language isn't known or its file fails to parse.
+    pub fn prefetch(&self, language: &str) -> bool {
+        self.table(language).is_some()
+    }
+
+    //Whether the language has been parsed, without parsing it
+    pub fn is_loaded(&self, language: &str) -> bool {
+        self.find_language(language)
+            .is_some_and(|language| language.table.get().is_some_and(Option::is_some))
+    }
+
+    //Named by the file's `@language` header, or by the file name when there is none, e.g. `fr.r3l`.
+    //A file that fails to load leaves the manager unchanged, lookups keep using the embedded default.
+    pub fn load_file(&mut self, path: &Path) -> Result<String, ParseR3Error> {
+        let table = parse_r3locale_file(path)?;
+        let language = language_of(path, &table);
+        self.add_language(&language, table);
+        Ok(language)
+    }
+
+    //Loads every `.r3l` file in a directory in file name order and returns their languages. Nothing is added unless
+    //every file loads, so a cancelled or failed load leaves the manager as it was.
+    pub fn load_directory(&mut self, directory: &Path, cancel: &CancellationToken) -> Result<Vec<String>, ParseR3Error> {
+        let paths = locale_files(directory)?;
+        let mut loaded = Vec::with_capacity(paths.len());
+        for path in &paths {
+            if cancel.is_cancelled() {
+                return Err(ParseR3Error::Cancelled);
+            }
+            let table = parse_r3locale_file(path)?;
+            loaded.push((language_of(path, &table), table));
+        }
+        let languages = loaded.iter().map(|(language, _)| language.clone()).collect();
+        for (language, table) in loaded {
+            self.add_language(&language, table);
+        }
+        Ok(languages)
+    }
+
+    //load_directory for installs with mods that may be broken. Files that fail are skipped and recorded in the
+    //quarantine instead of failing the whole load, and quarantined files are skipped with a logged warning without
+    //being parsed. Files that load are released from the quarantine. Save the quarantine afterwards to persist it.
+    pub fn load_directory_quarantined(
+        &mut self,
+        directory: &Path,
+        cancel: &CancellationToken,
+        quarantine: &mut Quarantine,
+    ) -> Result<DirectoryLoad, ParseR3Error> {
+        let mut result = DirectoryLoad::default();
+        let mut loaded = Vec::new();
+        for path in locale_files(directory)? {
+            if cancel.is_cancelled() {
+                return Err(ParseR3Error::Cancelled);
+            }
+            if quarantine.is_quarantined(&path) {
+                log(LogLevel::Warning, "quarantine", StatusCode::SKIPPED_QUARANTINED, &format!("Skipped quarantined {}", path.display()));
+                result.skipped.push(path);
+                continue;
+            }
+            match parse_r3locale_file(&path) {
+                Ok(table) => {
+                    quarantine.release(&path);
+                    loaded.push((language_of(&path, &table), table));
+                }
+                Err(error) => {
+                    quarantine.record_failure(&path);
+                    result.failed.push((path, error));
+                }
+            }
+        }
+        for (language, table) in loaded {
+            self.add_language(&language, table);
+            result.languages.push(language);
+        }
+        Ok(result)
+    }
+
+    //Compacts every loaded table with at least `threshold` of its strings dead, see LocaleTable::compact. Frozen
+    //tables are left alone. Returns the number of tables compacted.
+    pub fn compact_fragmented(&mut self, threshold: f32) -> usize {
+        self.languages
+            .iter_mut()
+            .filter_map(|language| language.table.get_mut()?.as_mut())
+            .chain(self.embedded_default.as_mut())
+            .map(|table| table.compact_if_fragmented(threshold))
+            .filter(|compacted| *compacted == Ok(true))
+            .count()
+    }
+
+    //Drops a language's table and returns the estimated bytes reclaimed, see LocaleTable::memory_usage. A registered
+    //language stays available and is parsed again when next used, one added from memory is removed. Unloading the
+    //active language leaves no language active. None when the language isn't loaded.
+    pub fn unload(&mut self, language: &str) -> Option<usize> {
+        let index = self.languages.iter().position(|loaded| loaded.tag == language)?;
+        let reclaimed = self.languages[index].table.get()?.as_ref()?.memory_usage();
+        let removed = self.languages[index].path.is_none();
+        if removed {
+            self.languages.remove(index);
+        } else {
+            self.languages[index].table = OnceLock::new();
+        }
+
+        self.active = match self.active {
+            Some(active) if active == index => None,
+            Some(active) if removed && active > index => Some(active - 1),
+            active => active,
+        };
+        Some(reclaimed)
+    }
+
+    //Adds a mod's strings, or shared ones with SHARED_NAMESPACE, see scope_for. Replaces the namespace's table for the
+    //language if there already is one. The first language added is the namespace's fallback.
+    pub fn add_namespace_language(&mut self, namespace: &str, language: &str, table: LocaleTable) {
+        let languages = self.namespaces.entry_ref(namespace).or_default();
+        match languages.iter_mut().find(|(tag, _)| tag == language) {
+            Some((_, loaded)) => *loaded = table,
+            None => languages.push((language.to_string(), table)),
+        }
+    }
+
+    //Drops all strings of a namespace, e.g. when a mod is disabled. Returns false when it had none.
+    pub fn remove_namespace(&mut self, namespace: &str) -> bool {
+        self.namespaces.remove(namespace).is_some()
+    }
+
+    //A namespace's table for the language, or its fallback when it doesn't have the language
+    pub(crate) fn namespace_table(&self, namespace: &str, language: Option<&str>) -> Option<&LocaleTable> {
+        let languages = self.namespaces.get(namespace)?;
+        languages
+            .iter()
+            .find(|(tag, _)| Some(tag.as_str()) == language)
+            .or(languages.first())
+            .map(|(_, table)| table)
+    }
+
+    //Parses a registered language on first use
+    pub fn table(&self, language: &str) -> Option<&LocaleTable> {
+        self.find_language(language)?.table()
+    }
+
+    //Returns false and keeps the current language when the language isn't loaded, or is registered but fails to parse
+    pub fn set_language(&mut self, language: &str) -> bool {
+        let found = self
+            .languages
+            .iter()
+            .position(|loaded| loaded.tag == language && loaded.table().is_some());
+        match found {
+            Some(index) => {
+                self.active = Some(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.active.map(|index| self.languages[index].tag.as_str())
+    }
+
+    //In load order. The name comes from the file's `@language_name` header when set, otherwise from built in CLDR names.
+    //Languages that haven't been parsed yet use the CLDR name, listing them doesn't parse them.
+    pub fn available_languages(&self) -> Vec<LanguageInfo> {
+        self.languages
+            .iter()
+            .map(|language| LanguageInfo {
+                tag: language.tag.clone(),
+                endonym: language
+                    .table
+                    .get()
+                    .and_then(Option::as_ref)
+                    .and_then(|table| find_annotation(table.metadata(), "language_name"))
+                    .and_then(|annotation| annotation.value.clone())
+                    .or_else(|| endonym(&language.tag).map(str::to_string)),
+            })
+            .collect()
+    }
+
+    pub fn find_entry(&self, key: &[u8]) -> Option<&str> {
+        let hash = xxh3_64(key);
+        let value = self.find_active(hash);
+        self.stats.record(hash, Some(key), value.is_some());
+        value
+    }
+
+    //Looks up one key in a given language without changing the active one, e.g. for a server sending messages to
+    //players with different languages. Falls back to the embedded default like find_entry.
+    pub fn find_in(&self, language: &str, key: &[u8]) -> Option<&str> {
+        let hash = xxh3_64(key);
+        let value = self.find_language_hash(language, hash);
+        self.stats.record(hash, Some(key), value.is_some());
+        value
+    }
+
+    pub(crate) fn find_hash(&self, hash: u64) -> Option<&str> {
+        let value = self.find_active(hash);
+        self.stats.record(hash, None, value.is_some());
+        value
+    }
+
+    pub(crate) fn find_hash_in(&self, language: &str, hash: u64) -> Option<&str> {
+        let value = self.find_language_hash(language, hash);
+        self.stats.record(hash, None, value.is_some());
+        value
+    }
+
+    //Lookups that returned nothing at all, after the fallback to the embedded default. Misses of a single language
+    //are counted by that table's own stats.
+    pub fn lookup_stats(&self) -> &LookupStats {
+        &self.stats
+    }
+
+    //Whether the active language or the embedded default has the key, without counting lookups
+    pub(crate) fn contains_hash(&self, hash: u64) -> bool {
+        let active = self.active.and_then(|index| self.languages[index].table.get()?.as_ref());
+        active.into_iter().chain(&self.embedded_default).any(|table| table.contains_hash(hash))
+    }
+
+    fn find_active(&self, hash: u64) -> Option<&str> {
+        self.active
+            .and_then(|index| self.languages[index].table()?.find_hash(hash))
+            .or_else(|| self.embedded_default.as_ref()?.find_hash(hash))
+    }
+
+    fn find_language_hash(&self, language: &str, hash: u64) -> Option<&str> {
+        self.table(language)
+            .and_then(|table| table.find_hash(hash))
+            .or_else(|| self.embedded_default.as_ref()?.find_hash(hash))
+    }
+
+    fn find_language(&self, language: &str) -> Option<&Language> {
+        self.languages.iter().find(|loaded| loaded.tag == language)
+    }
+
+    fn replace(&mut self, language: Language) {
+        match self.languages.iter_mut().find(|loaded| loaded.tag == language.tag) {
+            Some(loaded) => *loaded = language,
+            None => self.languages.push(language),
+        }
+    }
+}
+
+//`.r3l` files directly in a directory, in file name order
+fn locale_files(directory: &Path) -> Result<Vec<PathBuf>, ParseR3Error> {
+    if !directory.is_dir() {
+        return Err(ParseR3Error::FileNotFound);
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|_| ParseR3Error::FailedToRead)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "r3l"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+//Named by the file's `@language` header, or by the file name when there is none
+fn language_of(path: &Path, table: &LocaleTable) -> String {
+    match table.language() {
+        Some(language) => language.to_string(),
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_default() {
+        let mut manager =
+            LocaleManager::with_embedded_default(b"[[play]]\nPlay\n[[quit]]\nQuit\n").expect("Parse failed");
+        assert_eq!(manager.find_entry(b"play"), Some("Play"));
+
+        assert!(manager.load_file(Path::new("missing/fr.r3l")).is_err());
+        assert_eq!(manager.find_entry(b"quit"), Some("Quit"));
+
+        let mut french = Box::from(*b"@language=fr\n[[play]]\nJouer\n");
+        manager.add_language("fr", parse_r3locale_bytes(&mut *french).expect("Parse failed"));
+        assert!(manager.set_language("fr"));
+        assert_eq!(manager.find_entry(b"play"), Some("Jouer"));
+        assert_eq!(manager.find_entry(b"quit"), Some("Quit"));
+    }
+
+    #[test]
+    fn test_find_in() {
+        let mut manager = LocaleManager::with_embedded_default(b"[[greet]]\nHello\n").expect("Parse failed");
+        let mut german = Box::from(*b"[[greet]]\nHallo\n");
+        manager.add_language("de", parse_r3locale_bytes(&mut *german).expect("Parse failed"));
+
+        assert_eq!(manager.find_in("de", b"greet"), Some("Hallo"));
+        assert_eq!(manager.find_in("ja", b"greet"), Some("Hello"));
+        assert_eq!(manager.language(), None);
+    }
+
+    #[test]
+    fn test_string_freeze_violations() {
+        let mut manager = LocaleManager::new();
+        let freeze = StringFreeze::parse("@language=en\n[[greet]]\nHello\n").expect("Parse failed");
+        manager.set_string_freeze(Some(freeze));
+        assert!(manager.string_freeze_violations().is_empty());
+
+        let mut english = Box::from(*b"[[greet]]\nHello there\n");
+        manager.add_language("en", parse_r3locale_bytes(&mut *english).expect("Parse failed"));
+        let violations = manager.string_freeze_violations();
+        assert_eq!(violations.iter().map(|violation| violation.key.as_str()).collect::<Vec<_>>(), vec!["greet"]);
+
+        manager.set_string_freeze(None);
+        assert!(manager.string_freeze_violations().is_empty());
+    }
+
+    #[test]
+    fn test_available_languages() {
+        let mut manager = LocaleManager::new();
+        let mut japanese = Box::from(*b"[[greet]]\nhi\n");
+        let mut custom = Box::from("@language_name=Klingon\n[[greet]]\nnuqneH\n".as_bytes());
+        manager.add_language("ja", parse_r3locale_bytes(&mut *japanese).expect("Parse failed"));
+        manager.add_language("tlh", parse_r3locale_bytes(&mut custom).expect("Parse failed"));
+
+        let names: Vec<Option<String>> = manager
+            .available_languages()
+            .into_iter()
+            .map(|language| language.endonym)
+            .collect();
+        assert_eq!(names, vec![Some("日本語".to_string()), Some("Klingon".to_string())]);
+        assert_eq!(endonym("pt_BR"), Some("Português (Brasil)"));
+        assert_eq!(endonym("de-AT"), Some("Deutsch"));
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let directory = std::env::temp_dir().join("r3l_manager_load_directory");
+        fs::create_dir_all(&directory).expect("Failed to create directory");
+        fs::write(directory.join("de.r3l"), "[[greet]]\nHallo\n").expect("Failed to write");
+        fs::write(directory.join("fr.r3l"), "[[greet]]\nBonjour\n").expect("Failed to write");
+        fs::write(directory.join("notes.txt"), "not a locale").expect("Failed to write");
+
+        let mut manager = LocaleManager::new();
+        let cancel = CancellationToken::new();
+        cancel.clone().cancel();
+        assert!(matches!(manager.load_directory(&directory, &cancel), Err(ParseR3Error::Cancelled)));
+        assert!(manager.available_languages().is_empty());
+
+        let languages = manager.load_directory(&directory, &CancellationToken::new()).expect("Load failed");
+        assert_eq!(languages, vec!["de", "fr"]);
+        assert_eq!(manager.find_in("fr", b"greet"), Some("Bonjour"));
+    }
+
+    #[test]
+    fn test_register_language() {
+        let directory = std::env::temp_dir().join("r3l_manager_register_language");
+        fs::create_dir_all(&directory).expect("Failed to create directory");
+        fs::write(directory.join("it.r3l"), "@language_name=Italiano (custom)\n[[greet]]\nCiao\n").expect("Failed to write");
+        fs::write(directory.join("broken.r3l"), "[[greet\nBroken\n").expect("Failed to write");
+
+        let mut manager = LocaleManager::new();
+        manager.register_language("it", &directory.join("it.r3l"));
+        manager.register_language("xx", &directory.join("broken.r3l"));
+        assert!(!manager.is_loaded("it"));
+        assert_eq!(manager.available_languages()[0].endonym.as_deref(), Some("Italiano"));
+        assert!(!manager.is_loaded("it"));
+
+        assert!(manager.prefetch("it"));
+        assert!(manager.is_loaded("it"));
+        assert_eq!(manager.available_languages()[0].endonym.as_deref(), Some("Italiano (custom)"));
+        assert_eq!(manager.find_in("it", b"greet"), Some("Ciao"));
+
+        assert!(!manager.set_language("xx"));
+        assert!(!manager.is_loaded("xx"));
+        assert!(manager.set_language("it"));
+        assert_eq!(manager.find_entry(b"greet"), Some("Ciao"));
+    }
+
+    #[test]
+    fn test_unload() {
+        let mut manager = LocaleManager::with_embedded_default(b"[[greet]]\nHello\n").expect("Parse failed");
+        manager.add_language("de", parse_r3locale_bytes(&mut b"[[greet]]\nHallo\n".to_vec()).expect("Parse failed"));
+        manager.add_language("fr", parse_r3locale_bytes(&mut b"[[greet]]\nBonjour\n".to_vec()).expect("Parse failed"));
+        assert!(manager.set_language("fr"));
+
+        assert!(manager.unload("de").is_some_and(|reclaimed| reclaimed > 0));
+        assert_eq!(manager.unload("de"), None);
+        assert_eq!(manager.language(), Some("fr"));
+        assert_eq!(manager.find_entry(b"greet"), Some("Bonjour"));
+
+        assert!(manager.unload("fr").is_some());
+        assert_eq!(manager.language(), None);
+        assert_eq!(manager.find_entry(b"greet"), Some("Hello"));
+    }
+
+    #[test]
+    fn test_load_directory_quarantined() {
+        let directory = std::env::temp_dir().join("r3l_manager_quarantine");
+        fs::create_dir_all(&directory).expect("Failed to create directory");
+        fs::write(directory.join("de.r3l"), "[[greet]]\nHallo\n").expect("Failed to write");
+        fs::write(directory.join("xx.r3l"), "[[greet\nBroken\n").expect("Failed to write");
+        let list = directory.join("quarantine.txt");
+        let _ = fs::remove_file(&list);
+
+        let mut quarantine = Quarantine::load(&list, 2).expect("Load failed");
+        for _ in 0..2 {
+            let mut manager = LocaleManager::new();
+            let load = manager
+                .load_directory_quarantined(&directory, &CancellationToken::new(), &mut quarantine)
+                .expect("Load failed");
+            assert_eq!(load.languages, vec!["de"]);
+            assert_eq!(load.failed.len(), 1);
+        }
+        quarantine.save(&list).expect("Save failed");
+
+        let mut quarantine = Quarantine::load(&list, 2).expect("Load failed");
+        assert!(quarantine.is_quarantined(&directory.join("xx.r3l")));
+        let mut manager = LocaleManager::new();
+        let load = manager
+            .load_directory_quarantined(&directory, &CancellationToken::new(), &mut quarantine)
+            .expect("Load failed");
+        assert!(load.failed.is_empty());
+        assert_eq!(load.skipped, vec![directory.join("xx.r3l")]);
+
+        quarantine.release(&directory.join("xx.r3l"));
+        assert_eq!(quarantine.failure_count(&directory.join("xx.r3l")), 0);
+    }
+}
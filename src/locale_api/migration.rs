@@ -0,0 +1,148 @@
+use super::interop::{LocaleTable, TableEntry, TableFrozenError};
+use super::parser::{KEY_CLOSE, KEY_OPEN, insert_into_hashtable};
+use std::fs;
+use std::path::Path;
+use xxhash_rust::xxh3::xxh3_64;
+
+//One line of a migration file. A rename has one key on each side, a merge several old keys and a split several new
+//ones. Old lookups of a split key resolve to the first new key, so list the one that keeps the old meaning first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMigration {
+    pub old_keys: Vec<String>,
+    pub new_keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationRelease {
+    pub version: String,
+    pub migrations: Vec<KeyMigration>,
+}
+
+//Keys renamed, split or merged in each release, oldest release first:
+//
+//```
+//[[1.4]]
+//menu.start -> menu.play
+//hud.hp, hud.mp -> hud.stats
+//dialog.full -> dialog.title, dialog.body
+//```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyMigrations {
+    pub releases: Vec<MigrationRelease>,
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub enum MigrationError {
+    Normal,
+    FileNotFound,
+    FailedToRead,
+    //A line is not a `[[version]]` header or an `old -> new` mapping
+    InvalidSyntax,
+    //A mapping comes before the first `[[version]]` header
+    MappingOutsideRelease,
+}
+
+impl KeyMigrations {
+    pub fn parse(text: &str) -> Result<Self, MigrationError> {
+        let mut releases: Vec<MigrationRelease> = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("##") {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix(KEY_OPEN) {
+                let version = header.strip_suffix(KEY_CLOSE).ok_or(MigrationError::InvalidSyntax)?;
+                releases.push(MigrationRelease {
+                    version: version.trim().to_string(),
+                    migrations: Vec::new(),
+                });
+                continue;
+            }
+
+            let (old_keys, new_keys) = line.split_once("->").ok_or(MigrationError::InvalidSyntax)?;
+            let keys = |list: &str| -> Vec<String> {
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            };
+            let migration = KeyMigration {
+                old_keys: keys(old_keys),
+                new_keys: keys(new_keys),
+            };
+            if migration.old_keys.is_empty() || migration.new_keys.is_empty() {
+                return Err(MigrationError::InvalidSyntax);
+            }
+            releases
+                .last_mut()
+                .ok_or(MigrationError::MappingOutsideRelease)?
+                .migrations
+                .push(migration);
+        }
+        Ok(Self { releases })
+    }
+
+    pub fn load_file(path: &Path) -> Result<Self, MigrationError> {
+        if !path.exists() {
+            return Err(MigrationError::FileNotFound);
+        }
+        Self::parse(&fs::read_to_string(path).map_err(|_| MigrationError::FailedToRead)?)
+    }
+}
+
+//Makes old keys resolve against a table written for the newest release, so lookups from mods built for older releases
+//keep working during a transition. Old keys share the new key's string and annotations, nothing is copied. Releases are
+//applied newest first, so a key renamed twice resolves through both renames. Keys the table still has are left alone.
+//Returns the number of old keys added.
+pub fn migrate_table(table: &mut LocaleTable, migrations: &KeyMigrations) -> Result<usize, TableFrozenError> {
+    table.check_mutable()?;
+    let mut added = 0;
+    for release in migrations.releases.iter().rev() {
+        for migration in &release.migrations {
+            let new_hash = xxh3_64(migration.new_keys[0].as_bytes());
+            let Some(&TableEntry { string_id, .. }) = table.entries.find(new_hash, |entry| entry.key == new_hash) else {
+                continue;
+            };
+            let annotations = table.annotations.get(&new_hash).cloned();
+            for old_key in &migration.old_keys {
+                if insert_into_hashtable(&mut table.entries, old_key.as_bytes(), string_id).is_err() {
+                    continue;
+                }
+                if let Some(annotations) = &annotations {
+                    table.annotations.insert(xxh3_64(old_key.as_bytes()), annotations.clone());
+                }
+                added += 1;
+            }
+        }
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_migrate_table() {
+        let migrations = KeyMigrations::parse(
+            "## Menu rework\n[[1.2]]\nmenu.begin -> menu.start\n[[1.4]]\nmenu.start -> menu.play\nhud.hp, hud.mp -> hud.stats\ndialog.full -> dialog.title, dialog.body\n",
+        )
+        .expect("Parse failed");
+        assert_eq!(migrations.releases[1].version, "1.4");
+        assert_eq!(migrations.releases[1].migrations[1].old_keys, vec!["hud.hp", "hud.mp"]);
+
+        let mut sample = Box::from(*b"[[menu.play]]\nPlay\n[[hud.stats]]\nStats\n[[dialog.title]]\nTitle\n[[dialog.body]]\nBody\n[[hud.mp]]\nMana\n");
+        let mut table = parse_r3locale_bytes(&mut *sample).expect("Parse failed");
+        assert_eq!(migrate_table(&mut table, &migrations), Ok(4));
+
+        assert_eq!(table.find_entry(b"menu.begin"), Some("Play"));
+        assert_eq!(table.find_entry(b"menu.start"), Some("Play"));
+        assert_eq!(table.find_entry(b"hud.hp"), Some("Stats"));
+        assert_eq!(table.find_entry(b"hud.mp"), Some("Mana"));
+        assert_eq!(table.find_entry(b"dialog.full"), Some("Title"));
+        assert!(matches!(KeyMigrations::parse("a -> b\n"), Err(MigrationError::MappingOutsideRelease)));
+        assert!(matches!(KeyMigrations::parse("[[1.0]]\na b\n"), Err(MigrationError::InvalidSyntax)));
+    }
+}
@@ -1,3 +1,55 @@
+pub mod analysis;
+pub mod annotations;
+pub mod articles;
+pub mod blocks;
+pub mod cancellation;
+pub mod compaction;
+pub mod coverage;
+pub mod dialect;
+pub mod diff;
+pub mod document;
+pub mod editor;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod experiments;
+mod ffi_util;
+pub mod formats;
+pub mod formatting;
+#[cfg(feature = "godot")]
+pub mod gdextension;
+#[cfg(feature = "cbindgen")]
+pub mod headers;
 pub mod interop;
+#[cfg(feature = "jni")]
+pub mod jni;
+pub mod key_filter;
+pub mod keys;
+pub mod lints;
+#[cfg(feature = "lua")]
+pub mod lua;
+pub mod manager;
+pub mod migration;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod overlay;
 pub mod parser;
+pub mod plurals;
+pub mod preflight;
+pub mod preview;
+pub mod quarantine;
+pub mod refactor;
+#[cfg(all(feature = "net", feature = "signatures"))]
+pub mod remote;
+pub mod report;
+pub mod runtime;
 mod sanitizer;
+pub mod scope;
+pub mod script;
+#[cfg(feature = "signatures")]
+pub mod signing;
+pub mod stats;
+pub mod status;
+pub mod string_freeze;
+pub mod syntax;
+pub mod validation;
+pub mod variants;
@@ -0,0 +1,162 @@
+use super::ffi_util;
+#[cfg(feature = "signatures")]
+use super::signing::{PUBLIC_KEY_LEN, SignatureError, signature_path, verify_pack_bytes};
+use sha2::{Digest, Sha256};
+use std::ffi::{OsString, c_char};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use ureq::Agent;
+
+//Downloads larger than this are refused, the largest real packs are a few megabytes
+pub const MAX_PACK_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum InstallError {
+    Normal,
+    NullPathProvided,
+    InvalidUTF8Path,
+    //The expected hash is not 64 hex characters
+    InvalidHash,
+    DownloadFailed,
+    //Larger than MAX_PACK_SIZE
+    PackTooLarge,
+    //The download doesn't have the expected SHA-256 hash
+    HashMismatch,
+    FailedToWrite,
+    //The pack's `.sig` file could not be downloaded
+    MissingSignature,
+    //The pack doesn't match its signature, or the public key is not an ed25519 key
+    InvalidSignature,
+    //Signed, but not a locale file the parser accepts
+    InvalidPack,
+}
+
+//SHA-256 as it is usually published next to a download, 64 hex characters in either case
+fn parse_sha256(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut hash = [0; 32];
+    for (index, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(hash)
+}
+
+//Fails with PackTooLarge past `limit` bytes, without reading the rest
+pub(crate) fn download(agent: &Agent, url: &str, limit: u64) -> Result<Vec<u8>, InstallError> {
+    let response = agent.get(url).call().map_err(|_| InstallError::DownloadFailed)?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|_| InstallError::DownloadFailed)?;
+    if bytes.len() as u64 > limit {
+        return Err(InstallError::PackTooLarge);
+    }
+    Ok(bytes)
+}
+
+//Writes next to the destination and renames over it, so a game that loads the pack meanwhile sees either the old or
+//the new pack, never half of one
+pub(crate) fn write_atomically(dest: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = dest.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut partial = OsString::from(dest.as_os_str());
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let written = File::create(&partial).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    written
+        .and_then(|()| fs::rename(&partial, dest))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&partial);
+        })
+}
+
+fn check_hash(bytes: &[u8], expected_hash: &str) -> Result<(), InstallError> {
+    let expected = parse_sha256(expected_hash).ok_or(InstallError::InvalidHash)?;
+    if Sha256::digest(bytes).as_slice() != expected {
+        return Err(InstallError::HashMismatch);
+    }
+    Ok(())
+}
+
+//Downloads a pack, checks it against the SHA-256 hash its publisher lists and installs it at `dest`, replacing the
+//pack there. Nothing is written unless the hash matches, and the old pack stays in place if anything fails.
+pub fn install_pack(url: &str, dest: &Path, expected_hash: &str) -> Result<(), InstallError> {
+    parse_sha256(expected_hash).ok_or(InstallError::InvalidHash)?;
+    let bytes = download(&ureq::agent(), url, MAX_PACK_SIZE)?;
+    check_hash(&bytes, expected_hash)?;
+    write_atomically(dest, &bytes).map_err(|_| InstallError::FailedToWrite)
+}
+
+//install_pack that also downloads the pack's signature from `url.sig` and checks it with verify_pack_bytes. The
+//signature is installed next to the pack, so verify_pack passes for it later.
+#[cfg(feature = "signatures")]
+pub fn install_signed_pack(
+    url: &str,
+    dest: &Path,
+    expected_hash: &str,
+    public_key: &[u8; PUBLIC_KEY_LEN],
+) -> Result<(), InstallError> {
+    parse_sha256(expected_hash).ok_or(InstallError::InvalidHash)?;
+    let agent = ureq::agent();
+    let bytes = download(&agent, url, MAX_PACK_SIZE)?;
+    check_hash(&bytes, expected_hash)?;
+    let signature = download(&agent, &format!("{url}.sig"), MAX_PACK_SIZE).map_err(|_| InstallError::MissingSignature)?;
+    verify_pack_bytes(&bytes, &signature, public_key).map_err(|error| match error {
+        SignatureError::MissingSignature => InstallError::MissingSignature,
+        _ => InstallError::InvalidSignature,
+    })?;
+    //With the signature first, a crash in between leaves a pack that fails verification rather than one that passes
+    write_atomically(&signature_path(dest), &signature).map_err(|_| InstallError::FailedToWrite)?;
+    write_atomically(dest, &bytes).map_err(|_| InstallError::FailedToWrite)
+}
+
+//install_pack for C mod managers, `expected_hash` is a nul terminated hex string. Blocks until the download is done.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_install_pack(
+    url: *const c_char,
+    dest: *const c_char,
+    expected_hash: *const c_char,
+) -> InstallError {
+    let strings = unsafe { (ffi_util::c_str(url), ffi_util::c_str(dest), ffi_util::c_str(expected_hash)) };
+    let (Some(url), Some(dest), Some(expected_hash)) = strings else {
+        return InstallError::NullPathProvided;
+    };
+    let (Ok(url), Ok(dest), Ok(expected_hash)) = (url, dest, expected_hash) else {
+        return InstallError::InvalidUTF8Path;
+    };
+    match install_pack(url, Path::new(dest), expected_hash) {
+        Ok(()) => InstallError::Normal,
+        Err(error) => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_checks() {
+        let pack = b"[[greeting]]\nHello\n";
+        assert_eq!(check_hash(pack, "abc"), Err(InstallError::InvalidHash));
+        assert_eq!(check_hash(pack, &"0".repeat(64)), Err(InstallError::HashMismatch));
+        let actual: String = Sha256::digest(pack).iter().map(|byte| format!("{byte:02X}")).collect();
+        assert_eq!(check_hash(pack, &actual), Ok(()));
+
+        let dest = std::env::temp_dir().join("r3l_net_install").join("fr.r3l");
+        write_atomically(&dest, b"old").expect("Write failed");
+        write_atomically(&dest, pack).expect("Write failed");
+        assert_eq!(fs::read(&dest).expect("Read failed"), pack);
+        assert!(!dest.with_extension("r3l.part").exists());
+        assert_eq!(install_pack("http://127.0.0.1:9/fr.r3l", &dest, "xyz"), Err(InstallError::InvalidHash));
+    }
+}
@@ -0,0 +1,116 @@
+use super::annotations::Annotation;
+use super::interop::LocaleTable;
+use super::key_filter::KeyFilter;
+use super::parser::{MergeTableError, merge_locale_tables};
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64;
+
+//Small override tables layered over a large shared base without copying it. Each lookup checks the layers from the
+//top down before the base. Every layer has a KeyFilter, so layers without the key are usually skipped with one memory
+//access, and only layers that may have it cost a hash probe.
+#[derive(Clone)]
+pub struct CowLocaleTable {
+    base: Arc<LocaleTable>,
+    //Highest priority first
+    layers: Vec<LocaleTable>,
+    //One for each layer, in the same order
+    filters: Vec<KeyFilter>,
+}
+
+impl CowLocaleTable {
+    pub fn new(base: Arc<LocaleTable>) -> Self {
+        Self {
+            base,
+            layers: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn base(&self) -> &Arc<LocaleTable> {
+        &self.base
+    }
+
+    //Highest priority first
+    pub fn layers(&self) -> &[LocaleTable] {
+        &self.layers
+    }
+
+    //Adds a layer above the existing ones, so its entries win over every other layer and the base
+    pub fn push_layer(&mut self, layer: LocaleTable) {
+        self.filters.insert(0, KeyFilter::new(&layer));
+        self.layers.insert(0, layer);
+    }
+
+    //Adds a layer below the existing ones and above the base
+    pub fn push_layer_below(&mut self, layer: LocaleTable) {
+        self.filters.push(KeyFilter::new(&layer));
+        self.layers.push(layer);
+    }
+
+    pub fn find_entry(&self, key: &[u8]) -> Option<&str> {
+        self.find_hash(xxh3_64(key))
+    }
+
+    pub(crate) fn find_hash(&self, hash: u64) -> Option<&str> {
+        self.candidates(hash).find_map(|table| table.find_hash(hash))
+    }
+
+    //Annotations of the layer that provides the value
+    pub fn entry_annotations(&self, key: &[u8]) -> &[Annotation] {
+        let hash = xxh3_64(key);
+        self.candidates(hash)
+            .find(|table| table.find_hash(hash).is_some())
+            .map(|table| table.entry_annotations(key))
+            .unwrap_or(&[])
+    }
+
+    //The base decides the language, layers usually only carry a few strings
+    pub fn language(&self) -> Option<&str> {
+        self.base.language()
+    }
+
+    //Copies everything into a single table, e.g. to hand it over the C API
+    pub fn to_table(&self) -> Result<LocaleTable, MergeTableError> {
+        let mut tables: Vec<&LocaleTable> = self.layers.iter().collect();
+        tables.push(&self.base);
+        merge_locale_tables(&tables)
+    }
+
+    //Layers whose filter doesn't rule the key out, then the base
+    fn candidates(&self, hash: u64) -> impl Iterator<Item = &LocaleTable> {
+        self.layers
+            .iter()
+            .zip(&self.filters)
+            .filter(move |(_, filter)| filter.may_contain(hash))
+            .map(|(layer, _)| layer)
+            .chain(std::iter::once(&*self.base))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_cow_locale_table() {
+        let parse = |text: &[u8]| parse_r3locale_bytes(&mut text.to_vec()).expect("Parse failed");
+        let base = Arc::new(parse(b"@language=en\n[[play]]\nPlay\n[[quit]]\nQuit\n[[help]]\nHelp\n"));
+        let mut overlay = CowLocaleTable::new(Arc::clone(&base));
+        overlay.push_layer(parse(b"[[play]] @note=first\nStart\n"));
+        overlay.push_layer(parse(b"[[play]]\nGo\n[[quit]]\nExit\n"));
+        overlay.push_layer_below(parse(b"[[help]]\nManual\n[[play]]\nBegin\n"));
+
+        assert_eq!(overlay.find_entry(b"play"), Some("Go"));
+        assert_eq!(overlay.find_entry(b"quit"), Some("Exit"));
+        assert_eq!(overlay.find_entry(b"help"), Some("Manual"));
+        assert_eq!(overlay.find_entry(b"missing"), None);
+        assert!(overlay.entry_annotations(b"play").is_empty());
+        assert_eq!(overlay.language(), Some("en"));
+        assert_eq!(Arc::strong_count(&base), 2);
+
+        let merged = overlay.to_table().expect("Merge failed");
+        assert_eq!(merged.find_entry(b"play"), Some("Go"));
+        assert_eq!(merged.find_entry(b"help"), Some("Manual"));
+    }
+}
@@ -1,30 +1,185 @@
+use super::annotations::{Annotation, EntryConstraints, parse_annotations};
+use super::blocks::{block_spans, block_value, in_block};
+use super::cancellation::CancellationToken;
+use super::dialect::Dialect;
+use super::ffi_util;
 use super::interop::LocaleTable;
 use super::interop::TableEntry;
-use super::sanitizer::sanitize_r3_locale_file;
-use hashbrown::HashTable;
-use memchr::{memchr, memmem};
+use super::runtime::{LogLevel, log, log_enabled};
+use super::sanitizer::{
+    count_invisible, has_confusables, normalize_confusables, normalize_invisible, sanitize_r3_locale_file,
+    sanitize_with_inline_comments, unescape_comments,
+};
+use super::stats::LookupStats;
+use super::status::StatusCode;
+use hashbrown::hash_map::Entry;
+use hashbrown::{HashMap, HashTable};
+use memchr::{memchr, memchr2, memmem};
+use std::borrow::Cow;
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use xxhash_rust::xxh3::xxh3_64;
 use lite_strtab::{StringTableBuilder, StringId};
 
+//Token definitions shared with the tokenizer and the generated editor grammars, so they can't drift from the parser
+pub const KEY_OPEN: &str = "[[";
+pub const KEY_CLOSE: &str = "]]";
+pub const COMMENT_START: &str = "##";
+//Written before COMMENT_START to keep it as text, `Issue \##42` reads as `Issue ##42`
+pub const COMMENT_ESCAPE: u8 = b'\\';
+pub const ANNOTATION_START: u8 = b'@';
+//Opens and closes a block value on a line of its own, see blocks::BlockTracker
+pub const BLOCK_DELIMITER: &str = "\"\"\"";
+//Placeholders are only looked at in values, after parsing. A doubled brace is a literal one, see split_placeholders.
+pub const PLACEHOLDER_OPEN: char = '{';
+pub const PLACEHOLDER_CLOSE: char = '}';
+
+//Values are addressed by 16 bit string ids, so a table holds at most this many entries
+pub const MAX_ENTRIES: usize = u16::MAX as usize;
+//Tables this large are reported with a warning, so packs that keep growing fail loudly before they fail to load
+pub const ENTRY_WARNING_THRESHOLD: usize = MAX_ENTRIES / 10 * 9;
+
+//Warns about tables close to MAX_ENTRIES, `source` names the file or merge in the message
+pub(crate) fn warn_if_near_entry_limit(entries: usize, source: &str) {
+    if entries >= ENTRY_WARNING_THRESHOLD {
+        let message = format!("{source} has {entries} entries, close to the limit of {MAX_ENTRIES}");
+        log(LogLevel::Warning, "parser", StatusCode::NEAR_ENTRY_LIMIT, &message);
+    }
+}
+
 pub fn parse_r3locale_file(path: &Path) -> Result<LocaleTable, ParseR3Error> {
     if !path.exists() {
         return Err(ParseR3Error::FileNotFound);
     }
     let mut bytes = fs::read(path).map_err(|_| ParseR3Error::FailedToRead)?;
     parse_r3locale_bytes(&mut *bytes)
+        .inspect(|table| warn_if_near_entry_limit(table.entries.len(), &path.display().to_string()))
+        .inspect_err(|error| {
+            log(LogLevel::Error, "parser", StatusCode::from(*error), &path.display().to_string());
+        })
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    //Rejects any non-comment text before the first key, after the closing brackets of a key or after a block value
+    pub strict: bool,
+    pub empty_values: EmptyValuePolicy,
+    //Syntax version to read the file as, see dialect::detect_dialect
+    pub dialect: Dialect,
+    //Whitespace removed around the key between the brackets
+    pub key_trim: TrimPolicy,
+    //Whitespace removed around each value, indentation inside multi-line values can be meaningful
+    pub value_trim: TrimPolicy,
+    //Replaces tabs and non-breaking spaces in keys and values with spaces and removes zero width and bidi control
+    //characters, see lints::find_invisible_characters
+    pub normalize_invisible: bool,
+    //What to do with keys that mix Latin letters with lookalikes from other scripts, see lints::find_confusable_keys
+    pub confusable_keys: ConfusablePolicy,
+    //When COMMENT_START in a value line starts a comment, elsewhere it always does
+    pub inline_comments: InlineComments,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InlineComments {
+    //Wherever it is on the line, `Issue ##42` needs an escape
+    #[default]
+    Anywhere,
+    //Only with whitespace or the line's start before it and whitespace or the line's end after it, so `Issue ##42`
+    //and `C##` are text while `Press Start  ## shown on the title screen` ends in a comment
+    Separated,
+    //Only at the start of the line, text before it makes it part of the value
+    WholeLine,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfusablePolicy {
+    //Keys are kept as written and a warning is logged
+    #[default]
+    Warn,
+    //Lookalike characters are replaced with the Latin ones, so the key is found by what it looks like
+    Normalize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrimPolicy {
+    //Whitespace at both ends is removed
+    #[default]
+    Both,
+    //Blank lines at both ends and the indentation shared by all lines are removed, relative indentation is kept
+    Indentation,
+    //Only whitespace at the end is removed
+    Trailing,
+    //Kept as written, only the line break before the next key is removed from values
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyValuePolicy {
+    //Empty values are stored as legitimate empty strings
+    #[default]
+    Allow,
+    //Empty values fail the parse with ParseR3Error::EmptyValue
+    Error,
+    //Empty values are left out of the table so lookups fall back to the next merged table
+    Untranslated,
 }
 
 //Parses a reloaded 3 localisation file and returns a LocaleTable
 pub fn parse_r3locale_bytes(bytes: &mut [u8]) -> Result<LocaleTable, ParseR3Error> {
-    match sanitize_r3_locale_file(bytes) {
-        Ok(_) => (),
-        Err(e) => return Err(e),
-    };
-    
-    let opening_brackets_matches_initial: Vec<usize> =
-        memmem::find_iter(&bytes, b"[[").collect();
+    parse_r3locale_bytes_with_options(bytes, &ParseOptions::default())
+}
+
+pub fn parse_r3locale_bytes_with_options(
+    bytes: &mut [u8],
+    options: &ParseOptions,
+) -> Result<LocaleTable, ParseR3Error> {
+    parse_r3locale_bytes_with_spans(bytes, options).map_err(|failure| failure.error)
+}
+
+//Like parse_r3locale_bytes_with_options, but strict mode also returns where the text outside entries is
+pub fn parse_r3locale_bytes_with_spans(bytes: &mut [u8], options: &ParseOptions) -> Result<LocaleTable, ParseFailure> {
+    //Version 1 has no block values, a `"""` line after a key is text like any other
+    let blocks = if options.dialect == Dialect::V1 { Vec::new() } else { block_spans(bytes)? };
+    let trailing_comments = sanitize_with_inline_comments(bytes, options.inline_comments, &blocks)?;
+
+    //Version 1 has no annotations or metadata, text after a key or before the first key was ignored
+    if options.dialect == Dialect::V1 {
+        for span in outside_entry_spans(bytes, &blocks) {
+            bytes[span].fill(b' ');
+        }
+    }
+
+    if options.strict {
+        let spans = stray_text_spans(bytes, &blocks);
+        if !spans.is_empty() {
+            return Err(ParseFailure {
+                error: ParseR3Error::TextOutsideEntry,
+                spans,
+            });
+        }
+    } else if log_enabled(LogLevel::Warning) {
+        let ignored = stray_text_spans(bytes, &blocks).len();
+        if ignored > 0 {
+            log(LogLevel::Warning, "parser", StatusCode::IGNORED_TEXT_OUTSIDE_ENTRY, &format!("Ignored {ignored} pieces of text outside entries"));
+        }
+    }
+    parse_entries(bytes, options, &blocks, &trailing_comments).map_err(ParseFailure::from)
+}
+
+//Everything after sanitizing, `blocks` and `trailing_comments` are the byte ranges found before it
+fn parse_entries(
+    bytes: &mut [u8],
+    options: &ParseOptions,
+    blocks: &[Range<usize>],
+    trailing_comments: &[Range<usize>],
+) -> Result<LocaleTable, ParseR3Error> {
+
+    //Lines inside block values are never keys
+    let opening_brackets_matches_initial: Vec<usize> = memmem::find_iter(&bytes, KEY_OPEN)
+        .filter(|&position| !in_block(blocks, position))
+        .collect();
     let mut opening_brackets_matches_final: Vec<usize> =
         Vec::with_capacity(opening_brackets_matches_initial.len());
     let mut closing_brackets_matches_final: Vec<usize> =
@@ -33,7 +188,7 @@ pub fn parse_r3locale_bytes(bytes: &mut [u8]) -> Result<LocaleTable, ParseR3Erro
     for item in &opening_brackets_matches_initial {
         if *item == 0 || bytes[item - 1] == b'\n' {
             opening_brackets_matches_final.push(*item);
-            if let Some(close_pos) = memmem::find(&bytes[*item..], b"]]") {
+            if let Some(close_pos) = memmem::find(&bytes[*item..], KEY_CLOSE.as_bytes()) {
                 closing_brackets_matches_final.push(item + close_pos);
                 if let Some(value_open_pos) = memchr(b'\n', &bytes[item + close_pos..]) {
                     value_start.push(item + close_pos + value_open_pos);
@@ -53,41 +208,346 @@ pub fn parse_r3locale_bytes(bytes: &mut [u8]) -> Result<LocaleTable, ParseR3Erro
     value_start.dedup();
     value_start.sort();
 
+    //Annotation lines before the first key describe the whole file, e.g. `@language=fr`
+    let mut metadata: Vec<Annotation> = Vec::new();
+    let preamble_end = opening_brackets_matches_final.first().copied().unwrap_or(bytes.len());
+    for line in line_ranges(bytes, 0..preamble_end) {
+        let text = std::str::from_utf8(&bytes[line])
+            .expect("Invalid UTF-8 input")
+            .trim();
+        if text.starts_with('@') {
+            metadata.extend(parse_annotations(text)?);
+        }
+    }
+
     let mut string_table_builder = StringTableBuilder::<u32, u16>::new();
     let mut locale_hash_table: HashTable<TableEntry> = HashTable::new();
+    let mut annotations: HashMap<u64, Box<[Annotation]>> = HashMap::new();
+    let count_invisible_characters = log_enabled(LogLevel::Warning);
+    let mut invisible = 0;
+    let mut confusable_keys = 0;
     for i in 0..opening_brackets_matches_final
         .len()
         .min(closing_brackets_matches_final.len())
         .min(value_start.len())
     {
-        let key = std::str::from_utf8(
-            &bytes
-                [opening_brackets_matches_final[i] + 2..closing_brackets_matches_final[i]],
-        )
-        .expect("Invalid UTF-8 input")
-        .trim()
-        .as_bytes();
-        let value = std::str::from_utf8(
-            &bytes[value_start[i]
-                ..*opening_brackets_matches_final
-                    .get(i + 1)
-                    .unwrap_or(&bytes.len())],
+        let key = trim_text(
+            std::str::from_utf8(
+                &bytes
+                    [opening_brackets_matches_final[i] + 2..closing_brackets_matches_final[i]],
+            )
+            .expect("Invalid UTF-8 input"),
+            options.key_trim,
+        );
+        if count_invisible_characters {
+            invisible += count_invisible(&key, true);
+        }
+        let key = if options.normalize_invisible {
+            normalize_invisible(key, true)
+        } else {
+            key
+        };
+        let key = if has_confusables(&key) {
+            confusable_keys += 1;
+            match options.confusable_keys {
+                ConfusablePolicy::Warn => key,
+                ConfusablePolicy::Normalize => normalize_confusables(key),
+            }
+        } else {
+            key
+        };
+        let key = key.as_bytes();
+        //Text after the closing brackets is only meaningful if it is a list of annotations
+        let header_tail = std::str::from_utf8(
+            &bytes[closing_brackets_matches_final[i] + 2..value_start[i]],
         )
         .expect("Invalid UTF-8 input")
         .trim();
-        let string_id = string_table_builder.try_push(value).map_err(|_| ParseR3Error::InvalidUTF8Value)?;
+        let entry_annotations = if header_tail.starts_with('@') {
+            parse_annotations(header_tail)?
+        } else {
+            Vec::new()
+        };
+        EntryConstraints::from_annotations(&entry_annotations)?;
+        //value_start is the line break that ends the key's line
+        let value_end = *opening_brackets_matches_final.get(i + 1).unwrap_or(&bytes.len());
+        //The first block after the key is its own when it starts before the next key
+        let block = blocks
+            .get(blocks.partition_point(|span| span.start <= value_start[i]))
+            .filter(|span| span.start < value_end);
+        let text;
+        let value = match block {
+            //Blocks are taken as written, text after the closing delimiter is reported by stray_text_spans
+            Some(span) => Cow::Borrowed(block_value(
+                std::str::from_utf8(&bytes[span.clone()]).expect("Invalid UTF-8 input"),
+            )),
+            None => {
+                text = without_comments(bytes, value_start[i] + 1..value_end, trailing_comments);
+                let value = match options.value_trim {
+                    TrimPolicy::None => {
+                        let value = text.strip_suffix('\n').unwrap_or(&text);
+                        Cow::Borrowed(value.strip_suffix('\r').unwrap_or(value))
+                    }
+                    policy => trim_text(&text, policy),
+                };
+                unescape_comments(value)
+            }
+        };
+        if count_invisible_characters {
+            invisible += count_invisible(&value, false);
+        }
+        let value = if options.normalize_invisible {
+            normalize_invisible(value, false)
+        } else {
+            value
+        };
+        let value = value.as_ref();
+        if value.is_empty() {
+            match options.empty_values {
+                EmptyValuePolicy::Allow => (),
+                EmptyValuePolicy::Error => return Err(ParseR3Error::EmptyValue),
+                EmptyValuePolicy::Untranslated => {
+                    if log_enabled(LogLevel::Info) {
+                        let key = String::from_utf8_lossy(key);
+                        log(LogLevel::Info, "parser", StatusCode::EMPTY_VALUE_LEFT_OUT, &format!("Left out empty value of {key}"));
+                    }
+                    continue;
+                }
+            }
+        }
+        if locale_hash_table.len() >= MAX_ENTRIES {
+            return Err(ParseR3Error::TooManyEntries);
+        }
+        let string_id = string_table_builder.try_push(value).map_err(|_| ParseR3Error::TooManyEntries)?;
         if insert_into_hashtable(&mut locale_hash_table, key, string_id).is_err() {
             return Err(ParseR3Error::DuplicateKeys);
         }
+        if !entry_annotations.is_empty() {
+            annotations.insert(xxh3_64(key), entry_annotations.into_boxed_slice());
+        }
+    }
 
+    if invisible > 0 {
+        let action = if options.normalize_invisible { "normalized" } else { "kept" };
+        let message = format!("Found {invisible} tabs or invisible characters in keys and values and {action} them");
+        log(LogLevel::Warning, "parser", StatusCode::INVISIBLE_CHARACTERS, &message);
+    }
+    if confusable_keys > 0 && log_enabled(LogLevel::Warning) {
+        let action = match options.confusable_keys {
+            ConfusablePolicy::Warn => "kept them",
+            ConfusablePolicy::Normalize => "replaced them with Latin letters",
+        };
+        let message = format!("Found {confusable_keys} keys with Cyrillic, Greek or fullwidth lookalikes and {action}");
+        log(LogLevel::Warning, "parser", StatusCode::CONFUSABLE_KEYS, &message);
     }
 
     Ok(LocaleTable {
         string_values: string_table_builder.build(),
         entries: locale_hash_table,
+        annotations,
+        metadata: metadata.into_boxed_slice(),
+        stats: LookupStats::default(),
+        frozen: AtomicBool::new(false),
+    })
+}
+
+//The text of a value without its comments and the whitespace before them, which are blanked by now
+fn without_comments<'a>(bytes: &'a [u8], range: Range<usize>, comments: &[Range<usize>]) -> Cow<'a, str> {
+    let first = comments.partition_point(|comment| comment.start < range.start);
+    let mut text = Vec::new();
+    let mut start = range.start;
+    for comment in comments[first..].iter().take_while(|comment| comment.start < range.end) {
+        text.extend_from_slice(&bytes[start..comment.start]);
+        start = comment.end;
+    }
+    if start == range.start {
+        return Cow::Borrowed(std::str::from_utf8(&bytes[range]).expect("Invalid UTF-8 input"));
+    }
+    text.extend_from_slice(&bytes[start..range.end]);
+    Cow::Owned(String::from_utf8(text).expect("Invalid UTF-8 input"))
+}
+
+fn trim_text(text: &str, policy: TrimPolicy) -> Cow<'_, str> {
+    match policy {
+        TrimPolicy::Both => Cow::Borrowed(text.trim()),
+        TrimPolicy::Trailing => Cow::Borrowed(text.trim_end()),
+        TrimPolicy::None => Cow::Borrowed(text),
+        TrimPolicy::Indentation => remove_indentation(text),
+    }
+}
+
+//Drops blank lines at both ends and the spaces and tabs every other line starts with. Blank lines in between keep
+//their line break, and the line break of the last line is dropped.
+fn remove_indentation(text: &str) -> Cow<'_, str> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let Some(first) = lines.iter().position(|line| !is_blank(line)) else {
+        return Cow::Borrowed("");
+    };
+    let last = lines.iter().rposition(|line| !is_blank(line)).unwrap_or(first);
+    let lines = &lines[first..=last];
+    let shared = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    let mut result = String::with_capacity(text.len());
+    for (index, &line) in lines.iter().enumerate() {
+        if index > 0 {
+            result.push('\n');
+        }
+        let line = if index == lines.len() - 1 {
+            line.strip_suffix('\r').unwrap_or(line)
+        } else {
+            line
+        };
+        //Every line that isn't blank starts with at least `shared` spaces and tabs
+        if is_blank(line) {
+            result.push_str(line.trim_start_matches([' ', '\t']));
+        } else {
+            result.push_str(&line[shared..]);
+        }
+    }
+    Cow::Owned(result)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+//A piece of a value, see split_placeholders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValuePart<'a> {
+    Text(&'a str),
+    //The name between the braces, a number for positional arguments
+    Placeholder(&'a str),
+}
+
+//Splits a value into text and `{name}` placeholders. `{{` and `}}` are a literal brace, so `{{name}}` is the text
+//`{name}`. Braces that don't form a placeholder are kept as text. Every consumer of placeholders goes through this, so
+//they agree on what a placeholder is.
+pub(crate) fn split_placeholders(value: &str) -> Vec<ValuePart<'_>> {
+    let (open, close) = (PLACEHOLDER_OPEN as u8, PLACEHOLDER_CLOSE as u8);
+    let bytes = value.as_bytes();
+    let mut parts = Vec::new();
+    let mut text_start = 0;
+    let mut position = 0;
+    while position < bytes.len() {
+        let byte = bytes[position];
+        if (byte == open || byte == close) && bytes.get(position + 1) == Some(&byte) {
+            //The first brace ends the text, the second one is dropped
+            push_text(&mut parts, &value[text_start..=position]);
+            position += 2;
+            text_start = position;
+            continue;
+        }
+        let name_start = position + 1;
+        let name_end = (byte == open)
+            .then(|| memchr2(open, close, &bytes[name_start..]))
+            .flatten()
+            .map(|index| name_start + index)
+            .filter(|&end| end > name_start && bytes[end] == close);
+        match name_end {
+            Some(end) => {
+                push_text(&mut parts, &value[text_start..position]);
+                parts.push(ValuePart::Placeholder(&value[name_start..end]));
+                position = end + 1;
+                text_start = position;
+            }
+            None => position += 1,
+        }
+    }
+    push_text(&mut parts, &value[text_start..]);
+    parts
+}
+
+fn push_text<'a>(parts: &mut Vec<ValuePart<'a>>, text: &'a str) {
+    if !text.is_empty() {
+        parts.push(ValuePart::Text(text));
+    }
+}
+
+//Returns the byte ranges of all text that does not belong to any entry, comments excluded
+pub fn find_text_outside_entries(bytes: &mut [u8]) -> Result<Vec<Range<usize>>, ParseR3Error> {
+    let blocks = block_spans(bytes)?;
+    sanitize_r3_locale_file(bytes)?;
+    Ok(stray_text_spans(bytes, &blocks))
+}
+
+//Text outside entries that isn't annotations, including any text between the end of a block and the next key
+fn stray_text_spans(bytes: &[u8], blocks: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut spans: Vec<Range<usize>> = outside_entry_spans(bytes, blocks)
+        .into_iter()
+        .filter(|span| bytes[span.start] != ANNOTATION_START)
+        .collect();
+    let key_starts = key_line_starts(bytes, blocks);
+    for block in blocks {
+        //The closing delimiter is the line block.end starts
+        let after_delimiter = memchr(b'\n', &bytes[block.end..]).map_or(bytes.len(), |pos| block.end + pos + 1);
+        let next_key = key_starts
+            .get(key_starts.partition_point(|&start| start < block.end))
+            .copied()
+            .unwrap_or(bytes.len());
+        for line in line_ranges(bytes, after_delimiter..next_key) {
+            push_if_not_blank(&mut spans, bytes, line);
+        }
+    }
+    spans.sort_by_key(|span| span.start);
+    spans
+}
+
+//Trimmed text before the first key and after the closing brackets of each key, annotations and metadata included.
+//Expects comments to be sanitized already, `blocks` are the file's block_spans.
+pub(crate) fn outside_entry_spans(bytes: &[u8], blocks: &[Range<usize>]) -> Vec<Range<usize>> {
+    let key_starts = key_line_starts(bytes, blocks);
+
+    let mut spans = Vec::new();
+    let preamble_end = key_starts.first().copied().unwrap_or(bytes.len());
+    for line in line_ranges(bytes, 0..preamble_end) {
+        push_if_not_blank(&mut spans, bytes, line);
+    }
+    for &start in &key_starts {
+        if let Some(close_pos) = memmem::find(&bytes[start..], KEY_CLOSE.as_bytes()) {
+            let header_end = start + close_pos + 2;
+            let line_end = memchr(b'\n', &bytes[header_end..])
+                .map_or(bytes.len(), |pos| header_end + pos);
+            push_if_not_blank(&mut spans, bytes, header_end..line_end);
+        }
+    }
+    spans
+}
+
+//KEY_OPEN at the start of a line and outside blocks
+fn key_line_starts(bytes: &[u8], blocks: &[Range<usize>]) -> Vec<usize> {
+    memmem::find_iter(bytes, KEY_OPEN)
+        .filter(|&pos| (pos == 0 || bytes[pos - 1] == b'\n') && !in_block(blocks, pos))
+        .collect()
+}
+
+fn line_ranges(bytes: &[u8], range: Range<usize>) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut start = range.start;
+    std::iter::from_fn(move || {
+        if start >= range.end {
+            return None;
+        }
+        let end = memchr(b'\n', &bytes[start..range.end]).map_or(range.end, |pos| start + pos);
+        let line = start..end;
+        start = end + 1;
+        Some(line)
     })
 }
 
+fn push_if_not_blank(spans: &mut Vec<Range<usize>>, bytes: &[u8], range: Range<usize>) {
+    let text = &bytes[range.clone()];
+    if let (Some(first), Some(last)) = (
+        text.iter().position(|b| !b.is_ascii_whitespace()),
+        text.iter().rposition(|b| !b.is_ascii_whitespace()),
+    ) {
+        spans.push(range.start + first..range.start + last + 1);
+    }
+}
+
 pub fn insert_into_hashtable(
     table: &mut HashTable<TableEntry>,
     key: &[u8],
@@ -115,6 +575,7 @@ pub fn insert_into_hashtable(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::locale_api::annotations::WrapHint;
 
     #[test]
     fn test_parse_and_find_entry() {
@@ -158,6 +619,317 @@ mod tests {
         let result = parse_r3locale_bytes(&mut *sample);
         assert!(matches!(result, Err(ParseR3Error::DuplicateKeys)));
     }
+
+    #[test]
+    fn test_strict_rejects_text_outside_entries() {
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let mut leading = Box::from(*b"stray text\n[[key]]\nvalue\n");
+        let result = parse_r3locale_bytes_with_options(&mut *leading, &strict);
+        assert!(matches!(result, Err(ParseR3Error::TextOutsideEntry)));
+        let mut leading = Box::from(*b"stray text\n[[key]]\nvalue\n");
+        let failure = ParseFailure {
+            error: ParseR3Error::TextOutsideEntry,
+            spans: vec![0..10],
+        };
+        assert_eq!(parse_r3locale_bytes_with_spans(&mut *leading, &strict).err(), Some(failure));
+
+        let mut trailing = Box::from(*b"## comment\n[[key]] oops\nvalue\n");
+        let failure = ParseFailure {
+            error: ParseR3Error::TextOutsideEntry,
+            spans: vec![19..23],
+        };
+        assert_eq!(parse_r3locale_bytes_with_spans(&mut *trailing, &strict).err(), Some(failure));
+
+        let mut clean = Box::from(*b"## comment\n[[key]]\nvalue\n");
+        assert!(parse_r3locale_bytes_with_options(&mut *clean, &strict).is_ok());
+    }
+
+    #[test]
+    fn test_empty_value_policy() {
+        let sample = *b"[[empty]]\n\n[[filled]]\nvalue\n";
+        let with_policy = |empty_values| ParseOptions {
+            empty_values,
+            ..Default::default()
+        };
+
+        let mut bytes = Box::from(sample);
+        let table = parse_r3locale_bytes_with_options(&mut *bytes, &with_policy(EmptyValuePolicy::Allow))
+            .expect("Parse failed");
+        assert_eq!(table.find_entry(b"empty"), Some(""));
+
+        let mut bytes = Box::from(sample);
+        let result = parse_r3locale_bytes_with_options(&mut *bytes, &with_policy(EmptyValuePolicy::Error));
+        assert!(matches!(result, Err(ParseR3Error::EmptyValue)));
+
+        let mut bytes = Box::from(sample);
+        let table = parse_r3locale_bytes_with_options(&mut *bytes, &with_policy(EmptyValuePolicy::Untranslated))
+            .expect("Parse failed");
+        assert_eq!(table.find_entry(b"empty"), None);
+        assert_eq!(table.find_entry(b"filled"), Some("value"));
+    }
+
+    #[test]
+    fn test_trim_policy() {
+        let sample = *b"[[ table ]]\n\n    +---+\n    | a |\n      b  \n\n[[code]]\n  let x = 1;  \n";
+        let with_policy = |key_trim, value_trim| ParseOptions {
+            key_trim,
+            value_trim,
+            ..Default::default()
+        };
+
+        let mut bytes = Box::from(sample);
+        let table = parse_r3locale_bytes(&mut *bytes).expect("Parse failed");
+        assert_eq!(table.find_entry(b"table"), Some("+---+\n    | a |\n      b"));
+
+        let mut bytes = Box::from(sample);
+        let options = with_policy(TrimPolicy::Indentation, TrimPolicy::Indentation);
+        let table = parse_r3locale_bytes_with_options(&mut *bytes, &options).expect("Parse failed");
+        assert_eq!(table.find_entry(b"table "), Some("+---+\n| a |\n  b  "));
+        assert_eq!(table.find_entry(b"code"), Some("let x = 1;  "));
+
+        let mut bytes = Box::from(sample);
+        let options = with_policy(TrimPolicy::Trailing, TrimPolicy::Trailing);
+        let table = parse_r3locale_bytes_with_options(&mut *bytes, &options).expect("Parse failed");
+        assert_eq!(table.find_entry(b" table"), Some("\n    +---+\n    | a |\n      b"));
+
+        let mut bytes = Box::from(sample);
+        let options = with_policy(TrimPolicy::None, TrimPolicy::None);
+        let table = parse_r3locale_bytes_with_options(&mut *bytes, &options).expect("Parse failed");
+        assert_eq!(table.find_entry(b" table "), Some("\n    +---+\n    | a |\n      b  \n"));
+        assert_eq!(table.find_entry(b"code"), Some("  let x = 1;  "));
+    }
+
+    #[test]
+    fn test_clone_table() {
+        let mut sample = Box::from(*b"[[first]]\none\n[[second]]\ntwo\n");
+        let table = parse_r3locale_bytes(&mut *sample).expect("Parse failed");
+        let copy = table.clone();
+        drop(table);
+
+        assert_eq!(copy.find_entry(b"first"), Some("one"));
+        assert_eq!(copy.find_entry(b"second"), Some("two"));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut original = Box::from(*b"[[key]]\noriginal\n");
+        let mut edited = Box::from(*b"[[key]]\nedited\n");
+        let mut table = parse_r3locale_bytes(&mut *original).expect("Parse failed");
+
+        let snapshot = table.snapshot();
+        table = parse_r3locale_bytes(&mut *edited).expect("Parse failed");
+        assert_eq!(table.find_entry(b"key"), Some("edited"));
+
+        table.restore(snapshot).expect("Restore failed");
+        assert_eq!(table.find_entry(b"key"), Some("original"));
+    }
+
+    #[test]
+    fn test_entry_annotations() {
+        let mut sample = Box::from(*b"[[title]] @context=main_menu @note=\"Shown on boot\"\nPlay\n[[plain]]\nvalue\n");
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let table = parse_r3locale_bytes_with_options(&mut *sample, &strict).expect("Parse failed");
+
+        assert_eq!(table.find_entry(b"title"), Some("Play"));
+        assert_eq!(table.entry_context(b"title"), Some("main_menu"));
+        assert_eq!(table.entry_annotations(b"title")[1].value.as_deref(), Some("Shown on boot"));
+        assert_eq!(table.entry_context(b"plain"), None);
+
+        let mut invalid = Box::from(*b"[[title]] @context=\"unterminated\nPlay\n");
+        let result = parse_r3locale_bytes(&mut *invalid);
+        assert!(matches!(result, Err(ParseR3Error::InvalidAnnotation)));
+    }
+
+    #[test]
+    fn test_entry_constraints() {
+        let mut sample = Box::from(*b"[[tooltip]] @maxlines=2 @wrap=word\nLine one\nLine two\n[[free]]\nvalue\n");
+        let table = parse_r3locale_bytes(&mut *sample).expect("Parse failed");
+
+        let constraints = table.entry_constraints(b"tooltip").expect("Constraints missing");
+        assert_eq!(constraints.max_lines, Some(2));
+        assert_eq!(constraints.wrap, Some(WrapHint::Word));
+        assert!(constraints.allows("Line one\nLine two"));
+        assert!(!constraints.allows("One\nTwo\nThree"));
+        assert_eq!(table.entry_constraints(b"free"), None);
+
+        let mut invalid = Box::from(*b"[[tooltip]] @maxlines=two\nvalue\n");
+        let result = parse_r3locale_bytes(&mut *invalid);
+        assert!(matches!(result, Err(ParseR3Error::InvalidAnnotation)));
+    }
+
+    #[test]
+    fn test_deprecation() {
+        let mut sample = Box::from(
+            *b"[[menu.old]] @deprecated since=1.4 replacement=menu.new\nOld\n[[menu.new]]\nNew\n[[loop]] @deprecated replacement=loop\nLoop\n",
+        );
+        let table = parse_r3locale_bytes(&mut *sample).expect("Parse failed");
+
+        let deprecation = table.entry_deprecation(b"menu.old").expect("Deprecation missing");
+        assert_eq!(deprecation.since.as_deref(), Some("1.4"));
+        assert_eq!(table.find_entry(b"menu.old"), Some("Old"));
+        assert_eq!(table.find_entry_or_replacement(b"menu.old"), Some("New"));
+        assert_eq!(table.find_entry_or_replacement(b"loop"), Some("Loop"));
+        assert_eq!(table.entry_deprecation(b"menu.new"), None);
+    }
+
+    #[test]
+    fn test_file_metadata() {
+        let mut sample = Box::from(*b"## French strings\n@language=fr\n[[key]]\nvaleur\n");
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let table = parse_r3locale_bytes_with_options(&mut *sample, &strict).expect("Parse failed");
+        assert_eq!(table.language(), Some("fr"));
+    }
+
+    #[test]
+    fn test_find_text_outside_entries() {
+        let mut sample = Box::from(*b"oops\n[[key]]  trailing \nvalue\n");
+        let spans = find_text_outside_entries(&mut *sample).expect("Sanitize failed");
+        assert_eq!(spans, vec![0..4, 14..22]);
+    }
+
+    #[test]
+    fn test_merge_preview() {
+        let mut mod_file = Box::from(*b"[[play]]\nLancer\n[[quit]]\nQuitter\n");
+        let mut base_file = Box::from(*b"[[play]]\nJouer\n[[quit]]\nQuitter\n[[help]]\nAide\n");
+        let mod_table = parse_r3locale_bytes(&mut *mod_file).expect("Parse failed");
+        let base_table = parse_r3locale_bytes(&mut *base_file).expect("Parse failed");
+
+        let mut preview = merge_preview(&[&mod_table, &base_table]);
+        assert_eq!(preview.entry_count, 3);
+        assert_eq!(preview.contributed, vec![2, 1]);
+        preview.overrides.sort_by_key(|entry| entry.same_value);
+        assert_eq!(preview.overrides.len(), 2);
+        assert_eq!(preview.overrides[0].key, xxh3_64(b"play"));
+        assert!(!preview.overrides[0].same_value && preview.overrides[1].same_value);
+    }
+
+    #[test]
+    fn test_merge_is_deterministic() {
+        let merged_entries = |text: &[u8]| {
+            let table = parse_r3locale_bytes(&mut text.to_vec()).expect("Parse failed");
+            let base = parse_r3locale_bytes(&mut b"[[help]]\nAide\n[[play]]\nJouer\n".to_vec()).expect("Parse failed");
+            let merged = merge_locale_tables(&[&table, &base]).expect("Merge failed");
+            //Iteration order, not sorted, so a different layout would show up
+            merged
+                .entries
+                .iter()
+                .map(|entry| (entry.key, merged.string_values.get(entry.string_id).map(str::to_string)))
+                .collect::<Vec<_>>()
+        };
+
+        let first = merged_entries(b"[[play]]\nLancer\n[[quit]]\nQuitter\n[[back]]\nRetour\n");
+        let reordered = merged_entries(b"[[back]]\nRetour\n[[quit]]\nQuitter\n[[play]]\nLancer\n");
+        assert_eq!(first, reordered);
+        assert_eq!(first.len(), 4);
+    }
+
+    #[test]
+    fn test_entry_limit() {
+        let entries = |range: std::ops::Range<usize>| {
+            range
+                .map(|index| format!("[[key{index}]]\nValue\n"))
+                .collect::<String>()
+                .into_bytes()
+        };
+        let result = parse_r3locale_bytes(&mut entries(0..MAX_ENTRIES + 1));
+        assert!(matches!(result, Err(ParseR3Error::TooManyEntries)));
+
+        let first = parse_r3locale_bytes(&mut entries(0..40000)).expect("Parse failed");
+        let second = parse_r3locale_bytes(&mut entries(30000..70000)).expect("Parse failed");
+        assert!(matches!(merge_locale_tables(&[&first, &second]), Err(MergeTableError::TooManyEntries)));
+    }
+
+    #[test]
+    fn test_escaped_comments() {
+        let mut sample = b"[[issue]]\nIssue \\##42 ## fixed in 1.2\n[[rating]]\n\\###\n".to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+        assert_eq!(table.find_entry(b"issue"), Some("Issue ##42"));
+        assert_eq!(table.find_entry(b"rating"), Some("###"));
+    }
+
+    #[test]
+    fn test_inline_comments() {
+        let sample = b"[[title]] ## menu\nPress Start  ## title screen\nIssue ##42\n  ## shown once\n[[lang]]\nC## ##\n";
+        let parse = |inline_comments, value_trim| {
+            let options = ParseOptions {
+                inline_comments,
+                value_trim,
+                ..Default::default()
+            };
+            parse_r3locale_bytes_with_options(&mut sample.to_vec(), &options).expect("Parse failed")
+        };
+
+        //The whitespace before a trailing comment is not left in the value
+        let anywhere = parse(InlineComments::Anywhere, TrimPolicy::None);
+        assert_eq!(anywhere.find_entry(b"title"), Some("Press Start\nIssue\n"));
+        assert_eq!(anywhere.find_entry(b"lang"), Some("C"));
+
+        let separated = parse(InlineComments::Separated, TrimPolicy::Both);
+        assert_eq!(separated.find_entry(b"title"), Some("Press Start\nIssue ##42"));
+        assert_eq!(separated.find_entry(b"lang"), Some("C##"));
+
+        let whole_line = parse(InlineComments::WholeLine, TrimPolicy::Both);
+        assert_eq!(whole_line.find_entry(b"title"), Some("Press Start  ## title screen\nIssue ##42"));
+        assert_eq!(whole_line.find_entry(b"lang"), Some("C## ##"));
+    }
+
+    #[test]
+    fn test_block_values() {
+        let mut sample =
+            b"[[credits]]\n\"\"\" ## comment\n\n[[Lead]] Ana\n## Music\n  Bo\n\n\"\"\"\n[[next]]\nNext ## note\n"
+                .to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+        assert_eq!(table.find_entry(b"credits"), Some("\n[[Lead]] Ana\n## Music\n  Bo\n"));
+        assert_eq!(table.find_entry(b"next"), Some("Next"));
+        assert_eq!(table.find_entry(b"Lead"), None);
+
+        let mut unterminated = b"[[credits]]\n\"\"\"\n[[Lead]] Ana\n".to_vec();
+        assert!(matches!(parse_r3locale_bytes(&mut unterminated), Err(ParseR3Error::UnterminatedBlock)));
+
+        //Version 1 files have no blocks, so the delimiter is part of the value
+        let mut old = b"[[credits]]\n\"\"\"\n[[Lead]] Ana\n".to_vec();
+        let v1 = ParseOptions {
+            dialect: Dialect::V1,
+            ..Default::default()
+        };
+        let table = parse_r3locale_bytes_with_options(&mut old, &v1).expect("Parse failed");
+        assert_eq!(table.find_entry(b"credits"), Some("\"\"\""));
+        assert_eq!(table.find_entry(b"Lead"), Some(""));
+    }
+
+    #[test]
+    fn test_text_after_block() {
+        let sample = b"[[credits]]\n\"\"\"\nAna\n\"\"\" ## end\nlost line\n\n[[next]]\nNext\n";
+
+        let mut bytes = sample.to_vec();
+        let table = parse_r3locale_bytes(&mut bytes).expect("Parse failed");
+        assert_eq!(table.find_entry(b"credits"), Some("Ana"));
+
+        let mut bytes = sample.to_vec();
+        let spans = find_text_outside_entries(&mut bytes).expect("Parse failed");
+        assert_eq!(spans.iter().map(|span| &sample[span.clone()]).collect::<Vec<_>>(), [&b"lost line"[..]]);
+
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let mut bytes = sample.to_vec();
+        let failure = ParseFailure {
+            error: ParseR3Error::TextOutsideEntry,
+            spans,
+        };
+        assert_eq!(parse_r3locale_bytes_with_spans(&mut bytes, &strict).err(), Some(failure));
+    }
 }
 
 #[repr(C)]
@@ -171,52 +943,176 @@ pub fn get_locale_table_rust(path: &Path) -> Result<LocaleTable, ParseR3Error> {
 }
 
 pub fn merge_locale_table_rust(tables: &[&LocaleTable]) -> MergeResult {
-    let mut builder = StringTableBuilder::<u32, u16>::new();
-    let mut final_table: HashTable<TableEntry> = HashTable::new();
+    merge_result(merge_locale_tables(tables))
+}
 
-    for table in tables {
-        for entry in table.entries.iter() {
-            if final_table.find(entry.key, |e: &TableEntry| e.key == entry.key).is_none()
-            {
-                let value = table
-                    .string_values
-                    .get(entry.string_id)
-                    .unwrap();
-
-                let new_id = match builder.try_push(value) {
-                    Ok(id) => id,
-                    Err(_) => {
-                        return MergeResult {
-                            table: std::ptr::null_mut(),
-                            merge_state: MergeTableError::InvalidUTF8Value,
-                        }
-                    }
-                };
+pub(crate) fn merge_result(merged: Result<LocaleTable, MergeTableError>) -> MergeResult {
+    match merged {
+        Ok(table) => MergeResult {
+            table: ffi_util::into_handle(table),
+            merge_state: MergeTableError::Normal,
+        },
+        Err(merge_error) => MergeResult {
+            table: std::ptr::null_mut(),
+            merge_state: merge_error,
+        },
+    }
+}
 
-                final_table.insert_unique(
-                    entry.key,
-                    TableEntry {
-                        key: entry.key,
-                        string_id: new_id,
-                    },
-                    |e| e.key,
-                );
+pub fn merge_locale_tables(tables: &[&LocaleTable]) -> Result<LocaleTable, MergeTableError> {
+    merge_locale_tables_until(tables, &AtomicBool::new(false))
+}
+
+//Fails with MergeTableError::Cancelled once the token is cancelled, checked between tables and every few thousand entries
+pub fn merge_locale_tables_cancellable(
+    tables: &[&LocaleTable],
+    cancel: &CancellationToken,
+) -> Result<LocaleTable, MergeTableError> {
+    merge_locale_tables_until(tables, cancel.flag())
+}
+
+//Entries merged between checks of the cancel flag
+const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+pub(crate) fn merge_locale_tables_until(
+    tables: &[&LocaleTable],
+    cancel: &AtomicBool,
+) -> Result<LocaleTable, MergeTableError> {
+    //The winning entry of every key, with the index of its table
+    let mut winners: HashMap<u64, (usize, StringId<u16>)> = HashMap::new();
+    let mut overridden = 0;
+    for (table_index, table) in tables.iter().enumerate() {
+        for (index, entry) in table.entries.iter().enumerate() {
+            if index % CANCEL_CHECK_INTERVAL == 0 && cancel.load(Ordering::Relaxed) {
+                return Err(MergeTableError::Cancelled);
+            }
+            match winners.entry(entry.key) {
+                Entry::Occupied(_) => overridden += 1,
+                Entry::Vacant(vacant) => {
+                    vacant.insert((table_index, entry.string_id));
+                }
             }
         }
+        //Checked per table so the message can name the table that went over the limit
+        if winners.len() > MAX_ENTRIES {
+            let message = format!(
+                "Table {table_index} of {} brings the merge to {} distinct keys, more than the limit of {MAX_ENTRIES}",
+                tables.len(),
+                winners.len()
+            );
+            log(LogLevel::Error, "merge", StatusCode::TOO_MANY_ENTRIES, &message);
+            return Err(MergeTableError::TooManyEntries);
+        }
+    }
+    warn_if_near_entry_limit(winners.len(), &format!("The merge of {} tables", tables.len()));
+
+    //Values are stored in key hash order rather than hash table iteration order, so the same inputs always give a
+    //byte-identical table that can be cached or fingerprinted
+    let mut ordered: Vec<(u64, usize, StringId<u16>)> =
+        winners.into_iter().map(|(key, (table_index, string_id))| (key, table_index, string_id)).collect();
+    ordered.sort_unstable_by_key(|(key, _, _)| *key);
+
+    let mut builder = StringTableBuilder::<u32, u16>::new();
+    let mut final_table: HashTable<TableEntry> = HashTable::with_capacity(ordered.len());
+    let mut final_annotations: HashMap<u64, Box<[Annotation]>> = HashMap::new();
+    for (key, table_index, string_id) in ordered {
+        let table = tables[table_index];
+        let value = table
+            .string_values
+            .get(string_id)
+            .unwrap();
+
+        let new_id = builder
+            .try_push(value)
+            .map_err(|_| MergeTableError::TooManyEntries)?;
+
+        final_table.insert_unique(
+            key,
+            TableEntry {
+                key,
+                string_id: new_id,
+            },
+            |e| e.key,
+        );
+        if let Some(annotations) = table.annotations.get(&key) {
+            final_annotations.insert(key, annotations.clone());
+        }
     }
 
-    let final_strings = builder.build();
+    if overridden > 0 && log_enabled(LogLevel::Info) {
+        let message = format!("{overridden} entries overridden by earlier tables while merging {} tables", tables.len());
+        log(LogLevel::Info, "merge", StatusCode::ENTRIES_OVERRIDDEN, &message);
+    }
 
-    MergeResult {
-        table: Box::into_raw(Box::new(LocaleTable {
-            string_values: final_strings,
-            entries: final_table,
-        })),
-        merge_state: MergeTableError::Normal,
+    Ok(LocaleTable {
+        string_values: builder.build(),
+        entries: final_table,
+        annotations: final_annotations,
+        //The highest priority table decides the language of the merged table
+        metadata: tables
+            .first()
+            .map(|table| table.metadata.clone())
+            .unwrap_or_default(),
+        stats: LookupStats::default(),
+        frozen: AtomicBool::new(false),
+    })
+}
+
+//A key found in more than one table, with indices into the slice given to merge_preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeOverride {
+    pub key: u64,
+    pub winner: usize,
+    pub overridden: usize,
+    //Overrides with the same text change nothing for players
+    pub same_value: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergePreview {
+    //Entries the merged table would have
+    pub entry_count: usize,
+    //Entries each table would contribute, by table index
+    pub contributed: Vec<usize>,
+    pub overrides: Vec<MergeOverride>,
+}
+
+//What merge_locale_tables would do with the same tables, without copying any strings or building the table
+pub fn merge_preview(tables: &[&LocaleTable]) -> MergePreview {
+    let mut winners: HashMap<u64, usize> = HashMap::new();
+    let mut preview = MergePreview {
+        contributed: vec![0; tables.len()],
+        ..Default::default()
+    };
+
+    for (index, table) in tables.iter().enumerate() {
+        for entry in table.entries.iter() {
+            match winners.get(&entry.key) {
+                Some(&winner) => {
+                    let winning_table = tables[winner];
+                    let winning_value = winning_table
+                        .entries
+                        .find(entry.key, |winning| winning.key == entry.key)
+                        .and_then(|winning| winning_table.string_values.get(winning.string_id));
+                    preview.overrides.push(MergeOverride {
+                        key: entry.key,
+                        winner,
+                        overridden: index,
+                        same_value: winning_value.is_some() && winning_value == table.string_values.get(entry.string_id),
+                    });
+                }
+                None => {
+                    winners.insert(entry.key, index);
+                    preview.contributed[index] += 1;
+                }
+            }
+        }
     }
+    preview.entry_count = winners.len();
+    preview
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub enum ParseR3Error {
     Normal,
@@ -228,6 +1124,35 @@ pub enum ParseR3Error {
     InvalidUTF8Path,
     NullPathProvided,
     DuplicateKeys,
+    TextOutsideEntry,
+    EmptyValue,
+    InvalidAnnotation,
+    //Stopped through a CancellationToken or cancel flag
+    Cancelled,
+    //More than MAX_ENTRIES entries, or more value text than 32 bit offsets can address
+    TooManyEntries,
+    //Not a sealed pack, or one from a newer version of the library
+    InvalidPack,
+    //A sealed pack opened with the wrong key, or modified after it was sealed
+    DecryptionFailed,
+    //A block value has no closing delimiter
+    UnterminatedBlock,
+}
+
+//A ParseR3Error with the byte ranges it is about, only TextOutsideEntry has any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFailure {
+    pub error: ParseR3Error,
+    pub spans: Vec<Range<usize>>,
+}
+
+impl From<ParseR3Error> for ParseFailure {
+    fn from(error: ParseR3Error) -> Self {
+        ParseFailure {
+            error,
+            spans: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -243,6 +1168,18 @@ pub enum MergeTableError {
     InvalidUTF8Path,
     NullPathProvided,
     DuplicateKeys,
+    TextOutsideEntry,
+    EmptyValue,
+    InvalidAnnotation,
+    //Stopped through a CancellationToken or cancel flag
+    Cancelled,
+    //A table pointer that is not a live table from this library, e.g. one that was already freed
+    InvalidHandle,
+    //The merged table would have more than MAX_ENTRIES entries
+    TooManyEntries,
+    InvalidPack,
+    DecryptionFailed,
+    UnterminatedBlock,
 }
 
 impl From<ParseR3Error> for MergeTableError {
@@ -257,6 +1194,14 @@ impl From<ParseR3Error> for MergeTableError {
             ParseR3Error::InvalidUTF8Path => MergeTableError::InvalidUTF8Path,
             ParseR3Error::NullPathProvided => MergeTableError::NullPathProvided,
             ParseR3Error::DuplicateKeys => MergeTableError::DuplicateKeys,
+            ParseR3Error::TextOutsideEntry => MergeTableError::TextOutsideEntry,
+            ParseR3Error::EmptyValue => MergeTableError::EmptyValue,
+            ParseR3Error::InvalidAnnotation => MergeTableError::InvalidAnnotation,
+            ParseR3Error::Cancelled => MergeTableError::Cancelled,
+            ParseR3Error::TooManyEntries => MergeTableError::TooManyEntries,
+            ParseR3Error::InvalidPack => MergeTableError::InvalidPack,
+            ParseR3Error::DecryptionFailed => MergeTableError::DecryptionFailed,
+            ParseR3Error::UnterminatedBlock => MergeTableError::UnterminatedBlock,
         }
     }
 }
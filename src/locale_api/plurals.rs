@@ -0,0 +1,201 @@
+use super::ffi_util;
+use super::formatting::primary_language;
+use super::interop::{FindEntryError, FindEntryResult, LocaleTable};
+use super::variants::variant_key;
+use std::ffi::c_char;
+
+//CLDR plural categories, written as key variants such as `[[items.count|few]]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn name(self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+//CLDR plural operands of the absolute value: the integer digits, the number of visible fraction digits and the fraction
+//digits as a number. Numbers are read in their shortest form, so 1.0 has no fraction digits.
+struct Operands {
+    i: u64,
+    v: usize,
+    f: u64,
+}
+
+fn operands(value: f64) -> Operands {
+    let text = value.abs().to_string();
+    let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    Operands {
+        i: integer.parse().unwrap_or(u64::MAX),
+        v: fraction.len(),
+        f: fraction.parse().unwrap_or(0),
+    }
+}
+
+//Cardinal plural category of a number, from the CLDR rules of the language. Languages without built in rules use the
+//English ones, 1 is `one` and everything else `other`.
+pub fn plural_category(language: &str, value: f64) -> PluralCategory {
+    let Operands { i, v, f } = operands(value);
+    let integer = v == 0;
+    let (i10, i100) = (i % 10, i % 100);
+    let (f10, f100) = (f % 10, f % 100);
+    match primary_language(language) {
+        "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" => PluralCategory::Other,
+        "fr" | "es" | "it" | "pt" | "ca" if integer && i != 0 && i % 1_000_000 == 0 => PluralCategory::Many,
+        //European Portuguese follows the English rule
+        "pt" if language.ends_with("PT") && integer && i == 1 => PluralCategory::One,
+        "fr" | "pt" if i <= 1 && !language.ends_with("PT") => PluralCategory::One,
+        "es" | "el" | "hu" | "tr" | "bg" | "nb" | "no" if integer && i == 1 => PluralCategory::One,
+        "da" if (integer && i == 1) || (f != 0 && i <= 1) => PluralCategory::One,
+        "ru" | "uk" | "be" if integer => match (i10, i100) {
+            (1, _) if i100 != 11 => PluralCategory::One,
+            (2..=4, _) if !(12..=14).contains(&i100) => PluralCategory::Few,
+            _ => PluralCategory::Many,
+        },
+        "pl" if integer => match (i10, i100) {
+            _ if i == 1 => PluralCategory::One,
+            (2..=4, _) if !(12..=14).contains(&i100) => PluralCategory::Few,
+            _ => PluralCategory::Many,
+        },
+        "cs" | "sk" => match i {
+            _ if !integer => PluralCategory::Many,
+            1 => PluralCategory::One,
+            2..=4 => PluralCategory::Few,
+            _ => PluralCategory::Other,
+        },
+        "hr" | "sr" | "bs" => {
+            if (integer && i10 == 1 && i100 != 11) || (f10 == 1 && f100 != 11) {
+                PluralCategory::One
+            } else if (integer && (2..=4).contains(&i10) && !(12..=14).contains(&i100))
+                || ((2..=4).contains(&f10) && !(12..=14).contains(&f100))
+            {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Other
+            }
+        }
+        "lt" if !integer => PluralCategory::Many,
+        "lt" => match (i10, i100) {
+            (_, 11..=19) => PluralCategory::Other,
+            (1, _) => PluralCategory::One,
+            (2..=9, _) => PluralCategory::Few,
+            _ => PluralCategory::Other,
+        },
+        "ro" if integer && i == 1 => PluralCategory::One,
+        "ro" if !integer || i == 0 || (1..=19).contains(&i100) => PluralCategory::Few,
+        "he" if (integer && i == 1) || (i == 0 && !integer) => PluralCategory::One,
+        "he" if integer && i == 2 => PluralCategory::Two,
+        "ar" if integer => match (i, i100) {
+            (0, _) => PluralCategory::Zero,
+            (1, _) => PluralCategory::One,
+            (2, _) => PluralCategory::Two,
+            (_, 3..=10) => PluralCategory::Few,
+            (_, 11..=99) => PluralCategory::Many,
+            _ => PluralCategory::Other,
+        },
+        "ar" | "ru" | "uk" | "be" | "pl" | "ro" | "he" => PluralCategory::Other,
+        "fr" | "pt" | "es" | "el" | "hu" | "tr" | "bg" | "nb" | "no" | "da" => PluralCategory::Other,
+        _ if integer && i == 1 => PluralCategory::One,
+        _ => PluralCategory::Other,
+    }
+}
+
+impl LocaleTable {
+    //"3 items" in the right form: the variant of the key for the number's plural category in `language`, e.g.
+    //`[[items.count|few]]`. Falls back to the `other` variant and then the plain key, so files only need the forms
+    //their language uses.
+    pub fn get_plural(&self, key: &[u8], value: f64, language: &str) -> Option<&str> {
+        let category = plural_category(language, value);
+        self.find_entry(&variant_key(key, category.name()))
+            .or_else(|| self.find_entry(&variant_key(key, PluralCategory::Other.name())))
+            .or_else(|| self.find_entry(key))
+    }
+}
+
+//get_plural for C hosts. `language` is a nul terminated tag, when it is null or not UTF-8 the table's `@language` is
+//used, and English when the table has none.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_get_plural(
+    table: *const LocaleTable,
+    key_ptr: *const u8,
+    key_len: usize,
+    value: f64,
+    language: *const c_char,
+) -> FindEntryResult {
+    let failed = |allocation_state| FindEntryResult {
+        value_ptr: std::ptr::null(),
+        value_len: 0,
+        allocation_state,
+    };
+    let table = match ffi_util::table_ref(table) {
+        Ok(table) => table,
+        Err(error) => return failed(error.into()),
+    };
+    let Some(key) = (unsafe { ffi_util::slice(key_ptr, key_len) }) else {
+        return failed(FindEntryError::NullKeyPtr);
+    };
+    let language = unsafe { ffi_util::c_str(language) }
+        .and_then(Result::ok)
+        .or(table.language())
+        .unwrap_or("en");
+    match table.get_plural(key, value, language) {
+        Some(found) => FindEntryResult {
+            value_ptr: found.as_ptr(),
+            value_len: found.len(),
+            allocation_state: FindEntryError::Normal,
+        },
+        None => failed(FindEntryError::NoEntryFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_plural_category() {
+        use PluralCategory::{Few, Many, One, Other, Two, Zero};
+        let categories = |language: &str, values: &[f64]| -> Vec<PluralCategory> {
+            values.iter().map(|&value| plural_category(language, value)).collect()
+        };
+        assert_eq!(categories("en", &[0.0, 1.0, 2.0, 1.5]), [Other, One, Other, Other]);
+        assert_eq!(categories("fr-CA", &[0.0, 1.5, 2.0, 1_000_000.0]), [One, One, Other, Many]);
+        assert_eq!(categories("ru", &[1.0, 3.0, 5.0, 11.0, 21.0, 22.0, 1.5]), [One, Few, Many, Many, One, Few, Other]);
+        assert_eq!(categories("pl", &[1.0, 3.0, 13.0, 21.0, 22.0]), [One, Few, Many, Many, Few]);
+        assert_eq!(categories("cs", &[1.0, 4.0, 5.0, 0.5]), [One, Few, Other, Many]);
+        assert_eq!(categories("ar", &[0.0, 1.0, 2.0, 5.0, 11.0, 100.0]), [Zero, One, Two, Few, Many, Other]);
+        assert_eq!(categories("ja", &[1.0]), [Other]);
+        assert_eq!(categories("pt-PT", &[0.0, 1.0]), [Other, One]);
+    }
+
+    #[test]
+    fn test_get_plural() {
+        let mut sample = "@language=ru\n[[items.count]]\n{0} предметов\n\
+                          [[items.count|one]]\n{0} предмет\n[[items.count|few]]\n{0} предмета\n"
+            .as_bytes()
+            .to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+        let language = table.language().unwrap_or("en");
+
+        assert_eq!(table.get_plural(b"items.count", 21.0, language), Some("{0} предмет"));
+        assert_eq!(table.get_plural(b"items.count", 3.0, language), Some("{0} предмета"));
+        assert_eq!(table.get_plural(b"items.count", 5.0, language), Some("{0} предметов"));
+        assert_eq!(table.get_plural(b"missing", 5.0, language), None);
+    }
+}
@@ -0,0 +1,185 @@
+use super::annotations::{Annotation, EntryConstraints, find_annotation, parse_annotations};
+use super::blocks::{BlockLine, BlockTracker};
+use super::dialect::{Dialect, detect_dialect};
+use super::parser::{KEY_CLOSE, KEY_OPEN, ParseR3Error};
+use super::sanitizer::sanitize_r3_locale_file;
+use hashbrown::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::xxh3_64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreflightIssue {
+    FailedToRead,
+    //The first error parsing would fail with
+    Syntax(ParseR3Error),
+    //No `@language` header, the language has to be guessed from the file name
+    MissingLanguage,
+    //Declares an `@format` version newer than this library reads
+    UnsupportedFormat(u32),
+    //A version 1 file, readable with Dialect::V1 but worth upgrading with upgrade_dialect
+    OldDialect,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightFile {
+    pub path: PathBuf,
+    pub language: Option<String>,
+    pub issues: Vec<PreflightIssue>,
+}
+
+impl PreflightFile {
+    //OldDialect and MissingLanguage files still load, everything else fails
+    pub fn loads(&self) -> bool {
+        self.issues
+            .iter()
+            .all(|issue| matches!(issue, PreflightIssue::OldDialect | PreflightIssue::MissingLanguage))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightReport {
+    //In path order
+    pub files: Vec<PreflightFile>,
+}
+
+impl PreflightReport {
+    pub fn is_clean(&self) -> bool {
+        self.files.iter().all(|file| file.issues.is_empty())
+    }
+
+    pub fn broken_files(&self) -> impl Iterator<Item = &PreflightFile> {
+        self.files.iter().filter(|file| !file.loads())
+    }
+}
+
+//Checks every `.r3l` file under a directory, subdirectories included, before the game boots. It reads each file once
+//and checks its syntax, `@language` header and format version without building any tables, so the loader can warn
+//about broken mods up front. An unreadable directory gives an empty report.
+pub fn preflight(directory: &Path) -> PreflightReport {
+    let mut paths = Vec::new();
+    collect_locale_files(directory, &mut paths);
+    paths.sort();
+    PreflightReport {
+        files: paths.into_iter().map(|path| preflight_file(&path)).collect(),
+    }
+}
+
+pub fn preflight_file(path: &Path) -> PreflightFile {
+    let mut file = PreflightFile {
+        path: path.to_path_buf(),
+        language: None,
+        issues: Vec::new(),
+    };
+    let Ok(mut bytes) = fs::read(path) else {
+        file.issues.push(PreflightIssue::FailedToRead);
+        return file;
+    };
+
+    let dialect = detect_dialect(&bytes);
+    if dialect == Dialect::V1 {
+        file.issues.push(PreflightIssue::OldDialect);
+    }
+    if let Err(error) = sanitize_r3_locale_file(&mut bytes) {
+        file.issues.push(PreflightIssue::Syntax(error));
+        return file;
+    }
+    let text = std::str::from_utf8(&bytes).unwrap_or_default();
+
+    let metadata = match check_syntax(text, dialect) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            file.issues.push(PreflightIssue::Syntax(error));
+            Vec::new()
+        }
+    };
+    let format = find_annotation(&metadata, "format").and_then(|annotation| annotation.value.as_deref()?.parse::<u32>().ok());
+    if let Some(version) = format.filter(|version| Dialect::from_version(*version).is_none()) {
+        file.issues.push(PreflightIssue::UnsupportedFormat(version));
+    }
+    file.language = find_annotation(&metadata, "language").and_then(|annotation| annotation.value.clone());
+    if file.language.is_none() {
+        file.issues.push(PreflightIssue::MissingLanguage);
+    }
+    file
+}
+
+//The checks parse_r3locale_bytes makes, on sanitized text, without storing any values. Returns the file metadata.
+fn check_syntax(text: &str, dialect: Dialect) -> Result<Vec<Annotation>, ParseR3Error> {
+    let mut metadata = Vec::new();
+    let mut keys = HashSet::new();
+    let mut in_preamble = true;
+    let mut lines = text.split('\n').peekable();
+    let mut blocks = BlockTracker::default();
+    while let Some(line) = lines.next() {
+        if blocks.classify(line.as_bytes()) != BlockLine::Outside {
+            continue;
+        }
+        let Some(header) = line.strip_prefix(KEY_OPEN) else {
+            let trimmed = line.trim();
+            if in_preamble && dialect != Dialect::V1 && trimmed.starts_with('@') {
+                metadata.extend(parse_annotations(trimmed)?);
+            }
+            continue;
+        };
+        in_preamble = false;
+        let (key, tail) = header.split_once(KEY_CLOSE).ok_or(ParseR3Error::BracketMismatch)?;
+        if lines.peek().is_none() {
+            return Err(ParseR3Error::KeyValueMismatch);
+        }
+        let tail = tail.trim();
+        if dialect != Dialect::V1 && tail.starts_with('@') {
+            EntryConstraints::from_annotations(&parse_annotations(tail)?)?;
+        }
+        if !keys.insert(xxh3_64(key.trim().as_bytes())) {
+            return Err(ParseR3Error::DuplicateKeys);
+        }
+    }
+    Ok(metadata)
+}
+
+fn collect_locale_files(directory: &Path, paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        if path.is_dir() {
+            collect_locale_files(&path, paths);
+        } else if path.extension().is_some_and(|extension| extension == "r3l") {
+            paths.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight() {
+        let directory = std::env::temp_dir().join("r3l_preflight");
+        let mod_directory = directory.join("mods").join("speedrun");
+        fs::create_dir_all(&mod_directory).expect("Failed to create directory");
+        fs::write(directory.join("en.r3l"), "@language=en\n[[play]] @note=menu\nPlay\n").expect("Failed to write");
+        fs::write(directory.join("future.r3l"), "@format=9\n@language=fr\n[[play]]\nJouer\n").expect("Failed to write");
+        fs::write(mod_directory.join("de.r3l"), "[[play]]\nSpielen\n[[play]]\nNochmal\n").expect("Failed to write");
+        fs::write(mod_directory.join("old.r3l"), "@author \"Jane\n[[play]]\nPlay\n").expect("Failed to write");
+
+        let report = preflight(&directory);
+        let issues: Vec<(&str, &[PreflightIssue])> = report
+            .files
+            .iter()
+            .map(|file| (file.path.file_name().and_then(|name| name.to_str()).unwrap_or_default(), &file.issues[..]))
+            .collect();
+        assert_eq!(issues[0], ("en.r3l", &[][..]));
+        assert_eq!(issues[1], ("future.r3l", &[PreflightIssue::UnsupportedFormat(9)][..]));
+        assert_eq!(
+            issues[2],
+            ("de.r3l", &[PreflightIssue::Syntax(ParseR3Error::DuplicateKeys), PreflightIssue::MissingLanguage][..])
+        );
+        assert_eq!(issues[3], ("old.r3l", &[PreflightIssue::OldDialect, PreflightIssue::MissingLanguage][..]));
+        assert_eq!(report.files[0].language.as_deref(), Some("en"));
+        assert!(!report.is_clean());
+        assert_eq!(report.broken_files().count(), 2);
+    }
+}
@@ -0,0 +1,393 @@
+use super::annotations::example_args;
+use super::formats::json::{JsonValue, parse_json, quote};
+use super::formatting::format_decimal;
+use super::interop::{LocaleTable, TableEntry};
+use super::ffi_util;
+use super::parser::{PLACEHOLDER_CLOSE, PLACEHOLDER_OPEN, ValuePart, split_placeholders};
+#[cfg(feature = "wasm")]
+use super::interop::AllocationResult;
+#[cfg(feature = "wasm")]
+use super::parser::{ParseR3Error, parse_r3locale_bytes};
+use std::ffi::c_char;
+use std::fmt::Write;
+use std::io::{self, Write as IoWrite};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum SegmentKind {
+    Text,
+    //A placeholder replaced by its argument
+    Argument,
+    //A placeholder without an argument, kept as written so it stands out in the preview
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub text: String,
+    //Placeholder name, None for text
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum PreviewError {
+    Normal,
+    NullTable,
+    NullKeyPtr,
+    NoEntryFound,
+    //The arguments are not a JSON object of strings, numbers and booleans
+    InvalidArguments,
+    //The table pointer is not a live table from this library, e.g. one that was already freed
+    InvalidHandle,
+}
+
+//Reads arguments such as `{"count": 3, "name": "Mario"}`, formatting numbers the way `language` writes them.
+//A `null` argument is None.
+pub fn parse_preview_args(args_json: &str, language: &str) -> Result<Vec<(String, Option<String>)>, PreviewError> {
+    let JsonValue::Object(members) = parse_json(args_json).map_err(|_| PreviewError::InvalidArguments)? else {
+        return Err(PreviewError::InvalidArguments);
+    };
+    let mut args = Vec::with_capacity(members.len());
+    for (name, value) in members {
+        let value = match value {
+            JsonValue::String(text) => Some(text),
+            JsonValue::Number(number) => Some(format_decimal(language, number)),
+            JsonValue::Bool(flag) => Some(flag.to_string()),
+            JsonValue::Null => None,
+            JsonValue::Array(_) | JsonValue::Object(_) => return Err(PreviewError::InvalidArguments),
+        };
+        args.push((name, value));
+    }
+    Ok(args)
+}
+
+//Splits a value into text and placeholders, with each placeholder replaced by its argument when one is given.
+//Placeholders and escaped braces are read by parser::split_placeholders, text between placeholders is one segment.
+pub fn segment_value<N: AsRef<str>, V: AsRef<str>>(value: &str, args: &[(N, V)]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    for part in split_placeholders(value) {
+        let name = match part {
+            ValuePart::Text(text) => {
+                match segments.last_mut() {
+                    Some(last) if last.kind == SegmentKind::Text => last.text.push_str(text),
+                    _ => segments.push(Segment {
+                        kind: SegmentKind::Text,
+                        text: text.to_string(),
+                        name: None,
+                    }),
+                }
+                continue;
+            }
+            ValuePart::Placeholder(name) => name,
+        };
+        segments.push(match args.iter().find(|(argument, _)| argument.as_ref() == name) {
+            Some((_, argument)) => Segment {
+                kind: SegmentKind::Argument,
+                text: argument.as_ref().to_string(),
+                name: Some(name.to_string()),
+            },
+            None => Segment {
+                kind: SegmentKind::Missing,
+                text: format!("{PLACEHOLDER_OPEN}{name}{PLACEHOLDER_CLOSE}"),
+                name: Some(name.to_string()),
+            },
+        });
+    }
+    segments
+}
+
+//The value with every placeholder replaced by its argument, placeholders without one are left as written. Shared by
+//format_entry and the scripting bindings so placeholders work the same in every mod.
+pub fn format_value<N: AsRef<str>, V: AsRef<str>>(value: &str, args: &[(N, V)]) -> String {
+    segment_value(value, args).into_iter().map(|segment| segment.text).collect()
+}
+
+//`[{"kind": "text", "text": "Hello "}, {"kind": "argument", "name": "name", "text": "Mario"}]`
+pub fn segments_json(segments: &[Segment]) -> String {
+    let mut output = String::from("[");
+    for (index, segment) in segments.iter().enumerate() {
+        if index > 0 {
+            output.push_str(", ");
+        }
+        let kind = match segment.kind {
+            SegmentKind::Text => "text",
+            SegmentKind::Argument => "argument",
+            SegmentKind::Missing => "missing",
+        };
+        let _ = write!(output, "{{\"kind\": \"{kind}\"");
+        if let Some(name) = &segment.name {
+            let _ = write!(output, ", \"name\": {}", quote(name));
+        }
+        let _ = write!(output, ", \"text\": {}}}", quote(&segment.text));
+    }
+    output.push(']');
+    output
+}
+
+impl LocaleTable {
+    //"{username} joined" with `[("username", "Mario")]` gives "Mario joined", see format_value. None when the key is
+    //missing.
+    pub fn format_entry<N: AsRef<str>, V: AsRef<str>>(&self, key: &[u8], args: &[(N, V)]) -> Option<String> {
+        self.find_entry(key).map(|value| format_value(value, args))
+    }
+
+    //"{0} joined {1}" with `["Mario", "EU"]` gives "Mario joined EU", for callers that pass arguments as an array.
+    //Named placeholders are left as written.
+    pub fn format_entry_indexed(&self, key: &[u8], args: &[&str]) -> Option<String> {
+        let args: Vec<(String, &str)> = args
+            .iter()
+            .enumerate()
+            .map(|(index, argument)| (index.to_string(), *argument))
+            .collect();
+        self.format_entry(key, &args)
+    }
+
+    //Formats a value with sample arguments for translators to check, numbers use the table's `@language`.
+    //Arguments are added to the entry's examples, and a `null` argument clears an example to show the placeholder.
+    pub fn preview(&self, key: &[u8], args_json: &str) -> Result<Vec<Segment>, PreviewError> {
+        let value = self.find_entry(key).ok_or(PreviewError::NoEntryFound)?;
+        let mut args = example_args(self.entry_annotations(key));
+        for (name, argument) in parse_preview_args(args_json, self.language().unwrap_or("en"))? {
+            args.retain(|(example, _)| *example != name);
+            args.extend(argument.map(|argument| (name, argument)));
+        }
+        Ok(segment_value(value, &args))
+    }
+}
+
+//format_entry_indexed for hosts that pass arguments as an array of `arg_count` nul terminated UTF-8 strings. The result
+//is copied like r3l_recent_misses: returns the bytes it needs and writes nothing when `buffer` is too small. Returns 0
+//when the table fails the handle check, the key is missing or an argument is null or not UTF-8.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_format_entry_indexed(
+    table: *const LocaleTable,
+    key_ptr: *const u8,
+    key_len: usize,
+    args: *const *const c_char,
+    arg_count: usize,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> usize {
+    let Ok(table) = ffi_util::table_ref(table) else {
+        return 0;
+    };
+    let Some(key) = (unsafe { ffi_util::slice(key_ptr, key_len) }) else {
+        return 0;
+    };
+    //No arguments may come as a null array
+    let args = match arg_count {
+        0 => &[][..],
+        _ => match unsafe { ffi_util::slice(args, arg_count) } {
+            Some(args) => args,
+            None => return 0,
+        },
+    };
+    let args: Option<Vec<&str>> = args
+        .iter()
+        .map(|&argument| unsafe { ffi_util::c_str(argument) }?.ok())
+        .collect();
+    let Some(formatted) = args.and_then(|args| table.format_entry_indexed(key, &args)) else {
+        return 0;
+    };
+    if formatted.len() > buffer_len {
+        return formatted.len();
+    }
+    if let Some(buffer) = unsafe { ffi_util::slice_mut(buffer, formatted.len()) } {
+        buffer.copy_from_slice(formatted.as_bytes());
+    }
+    formatted.len()
+}
+
+//Writes every entry with its examples filled in, for golden-file tests that diff localisation output across releases.
+//Tables don't keep key names, so entries are ordered and labelled by key hash, e.g. `[[3b1f09c2d4e5a687]]`. Placeholders
+//without an example are written as they appear in the value.
+pub fn snapshot_all(table: &LocaleTable, writer: &mut impl IoWrite) -> io::Result<()> {
+    let mut entries: Vec<&TableEntry> = table.entries.iter().collect();
+    entries.sort_unstable_by_key(|entry| entry.key);
+    for entry in entries {
+        let value = table.string_values.get(entry.string_id).unwrap_or_default();
+        let examples = table
+            .annotations
+            .get(&entry.key)
+            .map(|annotations| example_args(annotations))
+            .unwrap_or_default();
+        let rendered: String = segment_value(value, &examples)
+            .into_iter()
+            .map(|segment| segment.text)
+            .collect();
+        writeln!(writer, "[[{:016x}]]\n{rendered}", entry.key)?;
+    }
+    Ok(())
+}
+
+//Exports for the browser preview tool. Strings are passed in buffers from preview_alloc, and every buffer the module
+//returns is released with preview_free.
+
+#[cfg(feature = "wasm")]
+#[repr(C)]
+pub struct PreviewResult {
+    //JSON array of segments, see segments_json
+    pub json_ptr: *mut u8,
+    pub json_len: usize,
+    pub preview_state: PreviewError,
+}
+
+#[cfg(feature = "wasm")]
+#[unsafe(no_mangle)]
+pub extern "C" fn preview_alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()).cast()
+}
+
+#[cfg(feature = "wasm")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn preview_free(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        unsafe { drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len))) };
+    }
+}
+
+//Parses the file text in place, the buffer can be freed once the table is created
+#[cfg(feature = "wasm")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn preview_load_table(text_ptr: *mut u8, text_len: usize) -> AllocationResult {
+    let Some(bytes) = (unsafe { ffi_util::slice_mut(text_ptr, text_len) }) else {
+        return AllocationResult {
+            table: std::ptr::null_mut(),
+            allocation_state: ParseR3Error::NullPathProvided,
+        };
+    };
+
+    match parse_r3locale_bytes(bytes) {
+        Ok(table) => AllocationResult {
+            table: ffi_util::into_handle(table),
+            allocation_state: ParseR3Error::Normal,
+        },
+        Err(parse_error) => AllocationResult {
+            table: std::ptr::null_mut(),
+            allocation_state: parse_error,
+        },
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn preview(
+    table: *const LocaleTable,
+    key_ptr: *const u8,
+    key_len: usize,
+    args_ptr: *const u8,
+    args_len: usize,
+) -> PreviewResult {
+    let failed = |preview_state| PreviewResult {
+        json_ptr: std::ptr::null_mut(),
+        json_len: 0,
+        preview_state,
+    };
+    let table = match ffi_util::table_ref(table) {
+        Ok(table) => table,
+        Err(error) => return failed(error.into()),
+    };
+    let Some(key) = (unsafe { ffi_util::slice(key_ptr, key_len) }) else {
+        return failed(PreviewError::NullKeyPtr);
+    };
+    //No arguments previews the value with every placeholder missing
+    let args = match unsafe { ffi_util::slice(args_ptr, args_len) } {
+        Some(args) => std::str::from_utf8(args),
+        None => Ok("{}"),
+    };
+    let Ok(args) = args else {
+        return failed(PreviewError::InvalidArguments);
+    };
+
+    match table.preview(key, args) {
+        Ok(segments) => {
+            let json = segments_json(&segments).into_bytes().into_boxed_slice();
+            let json_len = json.len();
+            PreviewResult {
+                json_ptr: Box::into_raw(json).cast(),
+                json_len,
+                preview_state: PreviewError::Normal,
+            }
+        }
+        Err(error) => failed(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+    use xxhash_rust::xxh3::xxh3_64;
+
+    #[test]
+    fn test_format_entry() {
+        let mut text = b"[[joined]]\n{username} joined {server}, {{x}}\n[[indexed]]\n{1} before {0} {{{0}}}\n".to_vec();
+        let table = parse_r3locale_bytes(&mut text).expect("Parse failed");
+        assert_eq!(
+            table.format_entry(b"joined", &[("username", "Mario")]).as_deref(),
+            Some("Mario joined {server}, {x}")
+        );
+        let args = vec![("server".to_string(), "EU".to_string()), ("username".to_string(), "Luigi".to_string())];
+        assert_eq!(table.format_entry(b"joined", &args).as_deref(), Some("Luigi joined EU, {x}"));
+        assert_eq!(table.format_entry(b"missing", &args), None);
+
+        assert_eq!(table.format_entry_indexed(b"indexed", &["a", "b"]).as_deref(), Some("b before a {a}"));
+        assert_eq!(table.format_entry_indexed(b"indexed", &["a"]).as_deref(), Some("{1} before a {a}"));
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut text = b"@language=de\n[[score]] @example name=Luigi\n{name} hat {points} Punkte {{x}} {bonus}\n".to_vec();
+        let table = parse_r3locale_bytes(&mut text).expect("Parse failed");
+        let segments = table
+            .preview(b"score", "{\"points\": 2.5, \"name\": \"Mario\"}")
+            .expect("Preview failed");
+
+        let found: Vec<(SegmentKind, &str)> = segments
+            .iter()
+            .map(|segment| (segment.kind, segment.text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (SegmentKind::Argument, "Mario"),
+                (SegmentKind::Text, " hat "),
+                (SegmentKind::Argument, "2,5"),
+                (SegmentKind::Text, " Punkte {x} "),
+                (SegmentKind::Missing, "{bonus}"),
+            ]
+        );
+        assert_eq!(
+            segments_json(&segments[..2]),
+            "[{\"kind\": \"argument\", \"name\": \"name\", \"text\": \"Mario\"}, {\"kind\": \"text\", \"text\": \" hat \"}]"
+        );
+        assert_eq!(
+            segments_json(&table.preview(b"score", "{}").expect("Preview failed")[..1]),
+            "[{\"kind\": \"argument\", \"name\": \"name\", \"text\": \"Luigi\"}]"
+        );
+        assert_eq!(
+            table.preview(b"score", "{\"name\": null}").expect("Preview failed")[0].kind,
+            SegmentKind::Missing
+        );
+        assert_eq!(table.preview(b"score", "[1]"), Err(PreviewError::InvalidArguments));
+        assert_eq!(table.preview(b"missing", "{}"), Err(PreviewError::NoEntryFound));
+    }
+
+    #[test]
+    fn test_snapshot_all() {
+        let snapshot = |text: &[u8]| {
+            let table = parse_r3locale_bytes(&mut text.to_vec()).expect("Parse failed");
+            let mut output = Vec::new();
+            snapshot_all(&table, &mut output).expect("Snapshot failed");
+            String::from_utf8(output).expect("Snapshot is not UTF-8")
+        };
+
+        let first = snapshot(b"[[greet]] @example name=Mario\nHello {name}!\n[[score]]\n{points} points\n");
+        let reordered = snapshot(b"[[score]]\n{points} points\n[[greet]] @example name=Mario\nHello {name}!\n");
+        assert_eq!(first, reordered);
+        assert!(first.contains(&format!("[[{:016x}]]\nHello Mario!\n", xxh3_64(b"greet"))));
+        assert!(first.contains("\n{points} points\n"));
+    }
+}
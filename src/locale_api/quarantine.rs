@@ -0,0 +1,113 @@
+use super::runtime::{LogLevel, log};
+use super::status::StatusCode;
+use hashbrown::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Failures {
+    count: u32,
+    //Modification time when the file last failed, seconds since the epoch. A file saved since is tried again.
+    modified: u64,
+}
+
+//Files that failed to parse on earlier launches, persisted between launches so a broken mod is skipped with a warning
+//after `threshold` failures instead of erroring on every launch. A quarantined file is tried again once it changes.
+//
+//Saved as one `count<TAB>modified<TAB>path` line per file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quarantine {
+    threshold: u32,
+    failures: HashMap<PathBuf, Failures>,
+}
+
+impl Quarantine {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            failures: HashMap::new(),
+        }
+    }
+
+    //A missing list is an empty one, e.g. on the first launch. Malformed lines are dropped.
+    pub fn load(path: &Path, threshold: u32) -> io::Result<Self> {
+        let mut quarantine = Self::new(threshold);
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(quarantine),
+            Err(error) => return Err(error),
+        };
+        for line in text.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(count), Some(modified), Some(file)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let (Ok(count), Ok(modified)) = (count.parse(), modified.parse()) else {
+                continue;
+            };
+            quarantine.failures.insert(PathBuf::from(file), Failures { count, modified });
+        }
+        Ok(quarantine)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut files: Vec<_> = self.failures.iter().collect();
+        files.sort_unstable_by_key(|(file, _)| *file);
+        let text: String = files
+            .into_iter()
+            .map(|(file, failures)| format!("{}\t{}\t{}\n", failures.count, failures.modified, file.display()))
+            .collect();
+        fs::write(path, text)
+    }
+
+    //Whether the file failed at least `threshold` times and hasn't changed since
+    pub fn is_quarantined(&self, file: &Path) -> bool {
+        self.failures
+            .get(file)
+            .is_some_and(|failures| failures.count >= self.threshold && failures.modified == modified_secs(file))
+    }
+
+    pub fn failure_count(&self, file: &Path) -> u32 {
+        self.failures.get(file).map_or(0, |failures| failures.count)
+    }
+
+    //A file that changed since its last failure starts counting again
+    pub fn record_failure(&mut self, file: &Path) {
+        let modified = modified_secs(file);
+        let failures = self.failures.entry(file.to_path_buf()).or_insert(Failures { count: 0, modified });
+        if failures.modified != modified {
+            *failures = Failures { count: 0, modified };
+        }
+        failures.count += 1;
+        if failures.count == self.threshold {
+            log(
+                LogLevel::Warning,
+                "quarantine",
+                StatusCode::QUARANTINED,
+                &format!("{} failed to parse {} times, skipping it until it changes", file.display(), failures.count),
+            );
+        }
+    }
+
+    //Forgets a file's failures, e.g. after it loaded or the user asked to retry it
+    pub fn release(&mut self, file: &Path) {
+        self.failures.remove(file);
+    }
+
+    pub fn quarantined(&self) -> impl Iterator<Item = &Path> {
+        self.failures
+            .iter()
+            .filter(|(file, _)| self.is_quarantined(file))
+            .map(|(file, _)| file.as_path())
+    }
+}
+
+fn modified_secs(file: &Path) -> u64 {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_secs())
+}
@@ -0,0 +1,240 @@
+use super::annotations::{format_annotations, parse_annotations};
+use super::blocks::{BlockLine, BlockTracker};
+use super::document::LocaleDocument;
+use hashbrown::HashSet;
+
+//An entry a rename left alone, `document` indexes the slice that was passed in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameIssue {
+    //The new name is already used, renaming would merge two arguments or two keys into one
+    Conflict { document: usize, key: String },
+    //Other languages use the placeholder for this key but this value doesn't
+    MissingPlaceholder { document: usize, key: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenameReport {
+    //Number of entries changed across all documents
+    pub renamed: usize,
+    pub issues: Vec<RenameIssue>,
+}
+
+//Rewrites `{old}` to `{new}` in every language of a pack, along with `@placeholder=old` annotations
+pub fn rename_placeholder(documents: &mut [LocaleDocument], old: &str, new: &str) -> RenameReport {
+    let mut report = RenameReport::default();
+    if old == new {
+        return report;
+    }
+    let old_placeholder = format!("{{{old}}}");
+    let new_placeholder = format!("{{{new}}}");
+
+    //Checked before renaming so entries fixed in an earlier document aren't reported as missing
+    let keys_using_placeholder: HashSet<String> = documents
+        .iter()
+        .flat_map(|document| &document.entries)
+        .filter(|entry| entry.value.contains(&old_placeholder))
+        .map(|entry| entry.key.clone())
+        .collect();
+
+    for (index, document) in documents.iter_mut().enumerate() {
+        for entry in &mut document.entries {
+            let has_old = entry.value.contains(&old_placeholder);
+            let has_new = entry.value.contains(&new_placeholder);
+            if has_old && has_new {
+                report.issues.push(RenameIssue::Conflict {
+                    document: index,
+                    key: entry.key.clone(),
+                });
+                continue;
+            }
+            if !has_old {
+                if !has_new && keys_using_placeholder.contains(&entry.key) {
+                    report.issues.push(RenameIssue::MissingPlaceholder {
+                        document: index,
+                        key: entry.key.clone(),
+                    });
+                }
+                continue;
+            }
+
+            entry.value = entry.value.replace(&old_placeholder, &new_placeholder);
+            let mut annotations = entry.parsed_annotations();
+            let mut renamed_annotation = false;
+            for annotation in annotations
+                .iter_mut()
+                .filter(|annotation| annotation.name == "placeholder" && annotation.value.as_deref() == Some(old))
+            {
+                annotation.value = Some(new.to_string());
+                renamed_annotation = true;
+            }
+            //Other annotations are left as written
+            if renamed_annotation {
+                entry.annotations = format_annotations(&annotations);
+            }
+            report.renamed += 1;
+        }
+    }
+    report
+}
+
+//True for the key itself and its variants, e.g. `items|one` for `items`
+fn is_key_or_variant(key: &str, base: &str) -> bool {
+    key.split('|').next() == Some(base)
+}
+
+//Rewrites `@deprecated replacement=old` to point at the new key, None when the annotations don't mention it
+fn rename_replacement(annotations: &str, old: &str, new: &str) -> Option<String> {
+    if !annotations.starts_with('@') {
+        return None;
+    }
+    let mut annotations = parse_annotations(annotations).ok()?;
+    let mut renamed = false;
+    for (_, value) in annotations
+        .iter_mut()
+        .filter(|annotation| annotation.name == "deprecated")
+        .flat_map(|annotation| annotation.args.iter_mut())
+        .filter(|(name, value)| name == "replacement" && is_key_or_variant(value, old))
+    {
+        *value = format!("{new}{}", &value[old.len()..]);
+        renamed = true;
+    }
+    renamed.then(|| format_annotations(&annotations))
+}
+
+//Renames a key and its variants in every language of a pack, nothing is renamed when the new key already exists anywhere.
+//Entries deprecated in favour of the old key are pointed at the new one
+pub fn rename_key(documents: &mut [LocaleDocument], old: &str, new: &str) -> RenameReport {
+    let mut report = RenameReport::default();
+    if old == new {
+        return report;
+    }
+    for (index, document) in documents.iter().enumerate() {
+        for entry in document.entries.iter().filter(|entry| is_key_or_variant(&entry.key, new)) {
+            report.issues.push(RenameIssue::Conflict {
+                document: index,
+                key: entry.key.clone(),
+            });
+        }
+    }
+    if !report.issues.is_empty() {
+        return report;
+    }
+
+    for document in documents.iter_mut() {
+        for entry in document.entries.iter_mut() {
+            let replacement = rename_replacement(&entry.annotations, old, new);
+            let has_replacement = replacement.is_some();
+            if let Some(annotations) = replacement {
+                entry.annotations = annotations;
+            }
+            if !is_key_or_variant(&entry.key, old) {
+                report.renamed += usize::from(has_replacement);
+                continue;
+            }
+            let renamed = format!("{new}{}", &entry.key[old.len()..]);
+            if let Some(fields) = document.source_metadata.remove(&entry.key) {
+                document.source_metadata.insert(renamed.clone(), fields);
+            }
+            entry.key = renamed;
+            report.renamed += 1;
+        }
+    }
+    report
+}
+
+//Renames a key and its variants in the text of a file, leaving every other byte as it was
+pub fn rename_key_in_text(text: &str, old: &str, new: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut blocks = BlockTracker::default();
+    for line in text.split_inclusive('\n') {
+        let header = Some(line)
+            .filter(|line| blocks.classify(line.trim_end_matches(['\r', '\n']).as_bytes()) == BlockLine::Outside)
+            .and_then(|line| line.strip_prefix("[["))
+            .and_then(|header| header.split_once("]]"));
+        let Some((key, rest)) = header else {
+            output.push_str(line);
+            continue;
+        };
+
+        let key = match key.trim() {
+            trimmed if is_key_or_variant(trimmed, old) => format!("{new}{}", &trimmed[old.len()..]),
+            _ => key.to_string(),
+        };
+        let annotations = rest.trim_end_matches(['\r', '\n']);
+        let rest = match rename_replacement(annotations.trim(), old, new) {
+            Some(renamed) => format!(" {renamed}{}", &rest[annotations.len()..]),
+            None => rest.to_string(),
+        };
+        output.push_str(&format!("[[{key}]]{rest}"));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_rename_placeholder() {
+        let english = LocaleDocument::parse("[[greet]] @placeholder=name type=String\nHello {name}!\n[[other]]\n{name} and {player}\n")
+            .expect("Parse failed");
+        let french = LocaleDocument::parse("[[greet]]\nBonjour {nom} !\n[[other]]\n{name} et {player}\n")
+            .expect("Parse failed");
+        let mut documents = [english, french];
+
+        let report = rename_placeholder(&mut documents, "name", "player");
+        assert_eq!(report.renamed, 1);
+        assert_eq!(documents[0].entries[0].value, "Hello {player}!");
+        assert_eq!(documents[0].entries[0].annotations, "@placeholder=player type=String");
+        assert_eq!(
+            report.issues,
+            vec![
+                RenameIssue::Conflict {
+                    document: 0,
+                    key: "other".to_string()
+                },
+                RenameIssue::MissingPlaceholder {
+                    document: 1,
+                    key: "greet".to_string()
+                },
+                RenameIssue::Conflict {
+                    document: 1,
+                    key: "other".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_key() {
+        let text = "## Shop\r\n[[items]] @note=\"Item count\"\r\n{count} item\r\n[[items|other]]\r\n{count} items\r\n[[items_total]]\r\nTotal\r\n";
+        assert_eq!(
+            rename_key_in_text(text, "items", "inventory.items"),
+            "## Shop\r\n[[inventory.items]] @note=\"Item count\"\r\n{count} item\r\n[[inventory.items|other]]\r\n{count} items\r\n[[items_total]]\r\nTotal\r\n"
+        );
+
+        let mut documents = [LocaleDocument::parse(text).expect("Parse failed")];
+        assert_eq!(rename_key(&mut documents, "items", "inventory.items").renamed, 2);
+        assert_eq!(documents[0].entries[1].key, "inventory.items|other");
+
+        let report = rename_key(&mut documents, "items_total", "inventory.items");
+        assert_eq!(report.renamed, 0);
+        assert_eq!(report.issues.len(), 2);
+    }
+
+    #[test]
+    fn test_rename_key_replacement() {
+        let text = "[[start]] @deprecated since=1.4 replacement=play\nStart\n[[play]]\nPlay\n";
+        let renamed = rename_key_in_text(text, "play", "menu.play");
+        assert_eq!(renamed, "[[start]] @deprecated since=1.4 replacement=menu.play\nStart\n[[menu.play]]\nPlay\n");
+        let table = parse_r3locale_bytes(&mut renamed.into_bytes()).expect("Parse failed");
+        assert_eq!(table.find_entry_or_replacement(b"start"), Some("Play"));
+
+        let mut documents = [LocaleDocument::parse(text).expect("Parse failed")];
+        assert_eq!(rename_key(&mut documents, "play", "menu.play").renamed, 2);
+        assert_eq!(documents[0].entries[0].annotations, "@deprecated since=1.4 replacement=menu.play");
+        let table = parse_r3locale_bytes(&mut documents[0].to_string().into_bytes()).expect("Parse failed");
+        assert_eq!(table.find_entry_or_replacement(b"start"), Some("Play"));
+    }
+}
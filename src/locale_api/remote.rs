@@ -0,0 +1,204 @@
+use super::ffi_util;
+use super::interop::LocaleTable;
+use super::net::{InstallError, download, write_atomically};
+use super::parser::parse_r3locale_bytes;
+use super::runtime::{LogLevel, log};
+use super::signing::{PUBLIC_KEY_LEN, SIGNATURE_LEN, signature_path, verify_pack_bytes};
+use super::status::StatusCode;
+use std::ffi::c_char;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+//Remote overrides are a small signed .r3l file a mod fetches at startup, so typo fixes during a live event reach
+//players without a new release. The file and its `.sig` are cached after each successful download, and the cache is
+//used while it is fresh and whenever the server can't be reached. Both the download and the cache are checked against
+//the public key every time, an override that fails the check is never used.
+
+//Overrides are meant for a handful of fixes, anything larger is refused
+pub const MAX_OVERRIDE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteOverrideOptions {
+    //A cache younger than this is used without asking the server
+    pub max_age: Duration,
+    //Applies to connecting and to each read, so an unreachable server doesn't hold up startup
+    pub timeout: Duration,
+}
+
+impl Default for RemoteOverrideOptions {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(60 * 60),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum OverrideSource {
+    Downloaded,
+    Cached,
+}
+
+pub struct RemoteOverrides {
+    pub table: LocaleTable,
+    pub source: OverrideSource,
+    //Why the cache was used instead of a download, Normal when the cache was fresh or the download succeeded
+    pub download_error: InstallError,
+}
+
+//The age of the cached file, None when there is no cache
+fn cache_age(cache: &Path) -> Option<Duration> {
+    fs::metadata(cache).and_then(|metadata| metadata.modified()).ok()?.elapsed().ok()
+}
+
+//The cache is checked as strictly as a download, anything on disk can be edited
+fn load_cached(cache: &Path, public_key: &[u8; PUBLIC_KEY_LEN]) -> Option<LocaleTable> {
+    let mut bytes = fs::read(cache).ok()?;
+    let signature = fs::read(signature_path(cache)).ok()?;
+    verify_pack_bytes(&bytes, &signature, public_key).ok()?;
+    parse_r3locale_bytes(&mut bytes).ok()
+}
+
+fn fetch(
+    url: &str,
+    cache: &Path,
+    public_key: &[u8; PUBLIC_KEY_LEN],
+    timeout: Duration,
+) -> Result<LocaleTable, InstallError> {
+    let agent = ureq::AgentBuilder::new().timeout_connect(timeout).timeout_read(timeout).build();
+    let bytes = download(&agent, url, MAX_OVERRIDE_SIZE)?;
+    let signature = download(&agent, &format!("{url}.sig"), SIGNATURE_LEN as u64)
+        .map_err(|_| InstallError::MissingSignature)?;
+    verify_pack_bytes(&bytes, &signature, public_key).map_err(|_| InstallError::InvalidSignature)?;
+    //Parsing sanitizes the bytes in place, the cache has to keep them as they were signed
+    let table = parse_r3locale_bytes(&mut bytes.clone()).map_err(|_| InstallError::InvalidPack)?;
+
+    //A cache that can't be written only costs the offline fallback, the download is still good
+    let cached = write_atomically(&signature_path(cache), &signature).and_then(|()| write_atomically(cache, &bytes));
+    if cached.is_err() {
+        log(LogLevel::Warning, "remote", StatusCode::FAILED_TO_WRITE, &cache.display().to_string());
+    }
+    Ok(table)
+}
+
+//Fetches the override table at `url`, signed with the key for `public_key` and its signature at `url.sig`. `cache` is
+//where the last good download is kept. Fails only when neither the server nor the cache has a valid table, with the
+//reason the download failed. Layer the table over the game's strings with CowLocaleTable::push_layer.
+pub fn load_remote_overrides(
+    url: &str,
+    cache: &Path,
+    public_key: &[u8; PUBLIC_KEY_LEN],
+    options: &RemoteOverrideOptions,
+) -> Result<RemoteOverrides, InstallError> {
+    let fresh = cache_age(cache).is_some_and(|age| age < options.max_age);
+    if let Some(table) = fresh.then(|| load_cached(cache, public_key)).flatten() {
+        return Ok(RemoteOverrides {
+            table,
+            source: OverrideSource::Cached,
+            download_error: InstallError::Normal,
+        });
+    }
+
+    let download_error = match fetch(url, cache, public_key, options.timeout) {
+        Ok(table) => {
+            return Ok(RemoteOverrides {
+                table,
+                source: OverrideSource::Downloaded,
+                download_error: InstallError::Normal,
+            });
+        }
+        Err(error) => error,
+    };
+    log(LogLevel::Warning, "remote", StatusCode::from(download_error), url);
+    let table = load_cached(cache, public_key).ok_or(download_error)?;
+    Ok(RemoteOverrides {
+        table,
+        source: OverrideSource::Cached,
+        download_error,
+    })
+}
+
+#[repr(C)]
+pub struct RemoteOverridesResult {
+    //Null when neither the server nor the cache had a valid table
+    pub table: *mut LocaleTable,
+    pub source: OverrideSource,
+    //Why the table is null, or why the cache was used instead of a download
+    pub error: InstallError,
+}
+
+//load_remote_overrides for C hosts. `public_key` points at PUBLIC_KEY_LEN bytes, the timeout is the default one. Blocks
+//until the download is done or times out, free the table with free_locale_table.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_load_remote_overrides(
+    url: *const c_char,
+    cache: *const c_char,
+    public_key: *const u8,
+    max_age_seconds: u64,
+) -> RemoteOverridesResult {
+    let failed = |error| RemoteOverridesResult {
+        table: std::ptr::null_mut(),
+        source: OverrideSource::Cached,
+        error,
+    };
+    let paths = unsafe { (ffi_util::c_str(url), ffi_util::c_str(cache)) };
+    let (Some(url), Some(cache)) = paths else {
+        return failed(InstallError::NullPathProvided);
+    };
+    let (Ok(url), Ok(cache)) = (url, cache) else {
+        return failed(InstallError::InvalidUTF8Path);
+    };
+    let Some(public_key) = (unsafe { ffi_util::slice(public_key, PUBLIC_KEY_LEN) }).and_then(|key| key.try_into().ok())
+    else {
+        return failed(InstallError::InvalidSignature);
+    };
+    let options = RemoteOverrideOptions {
+        max_age: Duration::from_secs(max_age_seconds),
+        ..Default::default()
+    };
+
+    match load_remote_overrides(url, Path::new(cache), public_key, &options) {
+        Ok(overrides) => RemoteOverridesResult {
+            table: ffi_util::into_handle(overrides.table),
+            source: overrides.source,
+            error: overrides.download_error,
+        },
+        Err(error) => failed(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::signing::{generate_signing_key, public_key, sign_pack_bytes};
+
+    #[test]
+    fn test_remote_overrides_fallback() {
+        let signing_key = generate_signing_key();
+        let official = public_key(&signing_key);
+        let cache = std::env::temp_dir().join("r3l_remote_overrides").join("overrides.r3l");
+        let pack = b"[[event.banner]]\nDouble XP weekend!\n";
+        write_atomically(&cache, pack).expect("Write failed");
+        write_atomically(&signature_path(&cache), &sign_pack_bytes(pack, &signing_key)).expect("Write failed");
+
+        //Nothing listens on the discard port, so the download fails right away
+        let url = "http://127.0.0.1:9/overrides.r3l";
+        let stale = RemoteOverrideOptions {
+            max_age: Duration::ZERO,
+            ..Default::default()
+        };
+        let overrides = load_remote_overrides(url, &cache, &official, &stale).expect("Load failed");
+        assert_eq!(overrides.source, OverrideSource::Cached);
+        assert_eq!(overrides.download_error, InstallError::DownloadFailed);
+        assert_eq!(overrides.table.find_entry(b"event.banner"), Some("Double XP weekend!"));
+
+        let fresh = load_remote_overrides(url, &cache, &official, &RemoteOverrideOptions::default());
+        assert_eq!(fresh.map(|overrides| overrides.download_error).ok(), Some(InstallError::Normal));
+
+        let other = public_key(&generate_signing_key());
+        assert_eq!(load_remote_overrides(url, &cache, &other, &stale).err(), Some(InstallError::DownloadFailed));
+    }
+}
@@ -0,0 +1,279 @@
+use super::document::{DocumentEntry, LocaleDocument};
+use super::lints::{Lint, lint_translation};
+use super::script::{LanguageMismatch, check_language};
+use super::validation::{CharacterSet, CharacterViolation, DeprecatedTranslation};
+use std::borrow::Cow;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+//Results for one translation compared against the source language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageReport {
+    pub language: String,
+    pub source_keys: usize,
+    pub translated_keys: usize,
+    pub missing_keys: Vec<String>,
+    //Keys that no longer exist in the source language
+    pub extra_keys: Vec<String>,
+    //Translated keys the source language marks `@deprecated`
+    pub deprecated_keys: Vec<DeprecatedTranslation>,
+    pub character_violations: Vec<CharacterViolation>,
+    pub language_mismatch: Option<LanguageMismatch>,
+    pub lints: Vec<Lint>,
+}
+
+impl LanguageReport {
+    //Character checks are skipped when no CharacterSet is given
+    pub fn compare(
+        language: &str,
+        source: &LocaleDocument,
+        translation: &LocaleDocument,
+        allowed: Option<&CharacterSet>,
+    ) -> Self {
+        let missing_keys: Vec<String> = source
+            .entries
+            .iter()
+            .filter(|entry| translation.find(&entry.key).is_none())
+            .map(|entry| entry.key.clone())
+            .collect();
+        let extra_keys = translation
+            .entries
+            .iter()
+            .filter(|entry| source.find(&entry.key).is_none())
+            .map(|entry| entry.key.clone())
+            .collect();
+        let parsed: Vec<Cow<'_, str>> = translation.entries.iter().map(DocumentEntry::parsed_value).collect();
+        let values: Vec<&str> = parsed.iter().map(|value| &**value).collect();
+
+        Self {
+            language: language.to_string(),
+            source_keys: source.entries.len(),
+            translated_keys: source.entries.len() - missing_keys.len(),
+            missing_keys,
+            extra_keys,
+            deprecated_keys: translation.deprecated_translations(source),
+            character_violations: allowed
+                .map(|allowed| translation.validate_characters(allowed))
+                .unwrap_or_default(),
+            language_mismatch: check_language(language, &values),
+            lints: lint_translation(source, translation),
+        }
+    }
+
+    //Share of source keys that are translated, from 0 to 100
+    pub fn coverage_percent(&self) -> f64 {
+        if self.source_keys == 0 {
+            100.0
+        } else {
+            self.translated_keys as f64 * 100.0 / self.source_keys as f64
+        }
+    }
+}
+
+//Translator facing summary of several languages, e.g. for a release checklist
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub languages: Vec<LanguageReport>,
+}
+
+impl Report {
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.render_markdown(),
+            ReportFormat::Html => self.render_html(),
+        }
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut output = String::from("# Localisation Report\n\n| Language | Coverage | Missing | Extra | Character issues |\n|---|---|---|---|---|\n");
+        for language in &self.languages {
+            let _ = writeln!(
+                output,
+                "| {} | {:.1}% | {} | {} | {} |",
+                language.language,
+                language.coverage_percent(),
+                language.missing_keys.len(),
+                language.extra_keys.len(),
+                language.character_violations.len()
+            );
+        }
+
+        for language in &self.languages {
+            let _ = write!(output, "\n## {}\n", language.language);
+            if let Some(mismatch) = &language.language_mismatch {
+                let _ = write!(output, "\n**Warning:** {}\n", mismatch_message(mismatch));
+            }
+            write_markdown_list(&mut output, "Missing keys", language.missing_keys.iter().map(|key| format!("`{key}`")));
+            write_markdown_list(&mut output, "Extra keys", language.extra_keys.iter().map(|key| format!("`{key}`")));
+            write_markdown_list(
+                &mut output,
+                "Deprecated keys",
+                language.deprecated_keys.iter().map(|deprecated| match &deprecated.deprecation.replacement {
+                    Some(replacement) => format!("`{}`, replaced by `{replacement}`", deprecated.key),
+                    None => format!("`{}`", deprecated.key),
+                }),
+            );
+            write_markdown_list(
+                &mut output,
+                "Unsupported characters",
+                language.character_violations.iter().map(|violation| {
+                    format!("`{}`: {} (U+{:04X})", violation.key, violation.codepoint, violation.codepoint as u32)
+                }),
+            );
+            write_markdown_list(
+                &mut output,
+                "Style issues",
+                language.lints.iter().map(|lint| format!("`{}`: {}", lint.key, lint.kind.description())),
+            );
+            if language.missing_keys.is_empty()
+                && language.extra_keys.is_empty()
+                && language.deprecated_keys.is_empty()
+                && language.character_violations.is_empty()
+                && language.lints.is_empty()
+                && language.language_mismatch.is_none()
+            {
+                output.push_str("\nNo issues found.\n");
+            }
+        }
+        output
+    }
+
+    fn render_html(&self) -> String {
+        let mut output = String::from(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Localisation Report</title></head>\n<body>\n<h1>Localisation Report</h1>\n<table>\n<tr><th>Language</th><th>Coverage</th><th>Missing</th><th>Extra</th><th>Character issues</th></tr>\n",
+        );
+        for language in &self.languages {
+            let _ = writeln!(
+                output,
+                "<tr><td>{}</td><td>{:.1}%</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&language.language),
+                language.coverage_percent(),
+                language.missing_keys.len(),
+                language.extra_keys.len(),
+                language.character_violations.len()
+            );
+        }
+        output.push_str("</table>\n");
+
+        for language in &self.languages {
+            let _ = writeln!(output, "<h2>{}</h2>", escape_html(&language.language));
+            if let Some(mismatch) = &language.language_mismatch {
+                let _ = writeln!(output, "<p><strong>Warning:</strong> {}</p>", escape_html(&mismatch_message(mismatch)));
+            }
+            write_html_list(&mut output, "Missing keys", language.missing_keys.iter().map(|key| format!("<code>{}</code>", escape_html(key))));
+            write_html_list(&mut output, "Extra keys", language.extra_keys.iter().map(|key| format!("<code>{}</code>", escape_html(key))));
+            write_html_list(
+                &mut output,
+                "Deprecated keys",
+                language.deprecated_keys.iter().map(|deprecated| match &deprecated.deprecation.replacement {
+                    Some(replacement) => format!(
+                        "<code>{}</code>, replaced by <code>{}</code>",
+                        escape_html(&deprecated.key),
+                        escape_html(replacement)
+                    ),
+                    None => format!("<code>{}</code>", escape_html(&deprecated.key)),
+                }),
+            );
+            write_html_list(
+                &mut output,
+                "Unsupported characters",
+                language.character_violations.iter().map(|violation| {
+                    format!(
+                        "<code>{}</code>: {} (U+{:04X})",
+                        escape_html(&violation.key),
+                        escape_html(&violation.codepoint.to_string()),
+                        violation.codepoint as u32
+                    )
+                }),
+            );
+            write_html_list(
+                &mut output,
+                "Style issues",
+                language
+                    .lints
+                    .iter()
+                    .map(|lint| format!("<code>{}</code>: {}", escape_html(&lint.key), lint.kind.description())),
+            );
+        }
+        output.push_str("</body>\n</html>\n");
+        output
+    }
+}
+
+fn mismatch_message(mismatch: &LanguageMismatch) -> String {
+    match mismatch.detected_language {
+        Some(detected) => format!("declared as `{}` but reads as `{detected}`", mismatch.declared),
+        None => format!("declared as `{}` but written in {:?} script", mismatch.declared, mismatch.detected_script),
+    }
+}
+
+fn write_markdown_list(output: &mut String, title: &str, items: impl Iterator<Item = String>) {
+    let mut items = items.peekable();
+    if items.peek().is_none() {
+        return;
+    }
+    let _ = write!(output, "\n### {title}\n\n");
+    for item in items {
+        let _ = writeln!(output, "- {item}");
+    }
+}
+
+fn write_html_list(output: &mut String, title: &str, items: impl Iterator<Item = String>) {
+    let mut items = items.peekable();
+    if items.peek().is_none() {
+        return;
+    }
+    let _ = writeln!(output, "<h3>{title}</h3>\n<ul>");
+    for item in items {
+        let _ = writeln!(output, "<li>{item}</li>");
+    }
+    output.push_str("</ul>\n");
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let source = LocaleDocument::parse("[[play]]\nPlay\n[[quit]]\nQuit\n[[start]] @deprecated replacement=play\nStart\n")
+            .expect("Parse failed");
+        let french = LocaleDocument::parse("[[play]]\nJouer \n[[old]]\n<Ancien>\n[[start]]\nCommencer\n").expect("Parse failed");
+        let report = Report {
+            languages: vec![LanguageReport::compare("fr", &source, &french, None)],
+        };
+
+        assert_eq!(report.languages[0].missing_keys, vec!["quit"]);
+        assert_eq!(report.languages[0].extra_keys, vec!["old"]);
+        assert_eq!(report.languages[0].coverage_percent(), 200.0 / 3.0);
+
+        let markdown = report.render(ReportFormat::Markdown);
+        assert!(markdown.contains("| fr | 66.7% | 1 | 1 | 0 |"));
+        assert!(markdown.contains("### Deprecated keys\n\n- `start`, replaced by `play`"));
+        assert!(markdown.contains("### Missing keys\n\n- `quit`"));
+        assert!(markdown.contains("### Style issues\n\n- `play`: trailing whitespace"));
+
+        let html = report.render(ReportFormat::Html);
+        assert!(html.contains("<li><code>quit</code></li>"));
+    }
+}
@@ -0,0 +1,243 @@
+use super::status::StatusCode;
+use std::panic::PanicHookInfo;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+//Process-wide options set by r3l_init. Every exported function works without it, r3l_init only changes behaviour the
+//stateless functions can't be told about per call.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(C)]
+pub enum LogLevel {
+    Error,
+    #[default]
+    Warning,
+    Info,
+    Debug,
+}
+
+//What get_entry returns for a missing key. The state is still NoEntryFound, so hosts that check it can tell the
+//fallback apart from a real value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub enum MissingKeyPolicy {
+    //A null value
+    #[default]
+    NotFound,
+    //The key itself, so missing strings are visible in the UI
+    ReturnKey,
+    //An empty value
+    Empty,
+}
+
+//Strings are UTF-8 and only valid for the duration of the call
+pub type LogCallback = Option<
+    unsafe extern "C" fn(level: LogLevel, module_ptr: *const u8, module_len: usize, message_ptr: *const u8, message_len: usize),
+>;
+pub type PanicCallback = Option<unsafe extern "C" fn(message_ptr: *const u8, message_len: usize)>;
+
+//Allocator hooks are not offered, Rust can only replace its allocator at compile time and tables allocated before
+//r3l_init would be freed by the wrong allocator.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct R3lConfig {
+    //Receives messages at max_log_level and more severe ones
+    pub log: LogCallback,
+    pub max_log_level: LogLevel,
+    //Called with the panic message before the library unwinds or aborts, for the host's crash reporter
+    pub panic: PanicCallback,
+    pub missing_key_policy: MissingKeyPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum InitError {
+    Normal,
+    NullConfig,
+    //r3l_init was already called, call r3l_shutdown first to change the options
+    AlreadyInitialized,
+}
+
+static CONFIG: RwLock<Option<R3lConfig>> = RwLock::new(None);
+//Kept apart from CONFIG so a host can register a log callback without taking over the other options
+static LOGGER: RwLock<(LogCallback, LogLevel)> = RwLock::new((None, LogLevel::Warning));
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+//The panic hook r3l_init installed
+struct InstalledHook {
+    //The hook that was installed before r3l_init, e.g. the host's or the test harness's. The r3l hook chains to it and
+    //r3l_shutdown puts it back.
+    previous: Arc<PanicHook>,
+    //Cleared by r3l_shutdown, after that the r3l hook only forwards to `previous`
+    active: Arc<AtomicBool>,
+    //Address of the r3l hook, to tell whether it is still the installed one
+    address: usize,
+}
+
+static PANIC_HOOK: Mutex<Option<InstalledHook>> = Mutex::new(None);
+
+fn hook_address(hook: &PanicHook) -> usize {
+    std::ptr::from_ref(&**hook).cast::<()>().addr()
+}
+
+//Options in effect, the defaults when r3l_init was not called
+pub fn current_config() -> R3lConfig {
+    CONFIG.read().map(|config| config.unwrap_or_default()).unwrap_or_default()
+}
+
+//Whether a message at this level would reach the callback, check it before building expensive messages
+pub(crate) fn log_enabled(level: LogLevel) -> bool {
+    LOGGER
+        .read()
+        .is_ok_and(|logger| logger.0.is_some() && level <= logger.1)
+}
+
+//Messages start with their status code, e.g. `R3L5001 IgnoredTextOutsideEntry: ...`, so hosts can group them
+pub(crate) fn log(level: LogLevel, module: &str, code: StatusCode, message: &str) {
+    let Ok((Some(sink), max_level)) = LOGGER.read().map(|logger| *logger) else {
+        return;
+    };
+    if level <= max_level {
+        let message = format!("{code}: {message}");
+        unsafe { sink(level, module.as_ptr(), module.len(), message.as_ptr(), message.len()) };
+    }
+}
+
+//Sets or, with a null callback, removes the log callback. Parser warnings, merge conflicts and reloads are reported
+//under the module names "parser", "merge" and "reload", with missed lookups at Debug level under "interop".
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_set_log_callback(callback: LogCallback, max_level: LogLevel) {
+    if let Ok(mut logger) = LOGGER.write() {
+        *logger = (callback, max_level);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_init(config: *const R3lConfig) -> InitError {
+    if config.is_null() {
+        return InitError::NullConfig;
+    }
+    let config = unsafe { *config };
+    let Ok(mut current) = CONFIG.write() else {
+        return InitError::AlreadyInitialized;
+    };
+    if current.is_some() {
+        return InitError::AlreadyInitialized;
+    }
+
+    if let Some(panic) = config.panic {
+        let previous = Arc::new(std::panic::take_hook());
+        let active = Arc::new(AtomicBool::new(true));
+        let (chained, enabled) = (Arc::clone(&previous), Arc::clone(&active));
+        let hook: PanicHook = Box::new(move |info: &PanicHookInfo<'_>| {
+            if enabled.load(Ordering::Acquire) {
+                let message = info.to_string();
+                unsafe { panic(message.as_ptr(), message.len()) };
+            }
+            chained(info);
+        });
+        let address = hook_address(&hook);
+        std::panic::set_hook(hook);
+        if let Ok(mut saved) = PANIC_HOOK.lock() {
+            *saved = Some(InstalledHook {
+                previous,
+                active,
+                address,
+            });
+        }
+    }
+    *current = Some(config);
+    r3l_set_log_callback(config.log, config.max_log_level);
+    InitError::Normal
+}
+
+//Restores the defaults and the panic hook from before r3l_init, the log and panic callbacks are not called after this
+//returns. A hook installed after r3l_init is left in place, the r3l hook it may chain to only forwards from then on.
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_shutdown() {
+    let Ok(mut current) = CONFIG.write() else {
+        return;
+    };
+    current.take();
+    let installed = PANIC_HOOK.lock().ok().and_then(|mut saved| saved.take());
+    if let Some(installed) = installed {
+        installed.active.store(false, Ordering::Release);
+        let hook = std::panic::take_hook();
+        if hook_address(&hook) == installed.address {
+            //Dropping the r3l hook leaves the saved one as the only owner of the previous hook
+            drop(hook);
+            match Arc::try_unwrap(installed.previous) {
+                Ok(previous) => std::panic::set_hook(previous),
+                Err(shared) => std::panic::set_hook(Box::new(move |info| shared(info))),
+            }
+        } else {
+            std::panic::set_hook(hook);
+        }
+    }
+    r3l_set_log_callback(None, LogLevel::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static LOGGED: AtomicUsize = AtomicUsize::new(0);
+    static REPORTED_PANICS: AtomicUsize = AtomicUsize::new(0);
+    static HOST_PANICS: AtomicUsize = AtomicUsize::new(0);
+    static LATER_PANICS: AtomicUsize = AtomicUsize::new(0);
+
+    //Other tests running in parallel log too, so only messages from this test are counted
+    unsafe extern "C" fn count_log(_: LogLevel, module_ptr: *const u8, module_len: usize, _: *const u8, message_len: usize) {
+        if unsafe { std::slice::from_raw_parts(module_ptr, module_len) } == b"test" {
+            LOGGED.fetch_add(message_len, Ordering::SeqCst);
+        }
+    }
+
+    unsafe extern "C" fn count_panic(_: *const u8, _: usize) {
+        REPORTED_PANICS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_init_and_shutdown() {
+        std::panic::set_hook(Box::new(|_| {
+            HOST_PANICS.fetch_add(1, Ordering::SeqCst);
+        }));
+        let config = R3lConfig {
+            log: Some(count_log),
+            panic: Some(count_panic),
+            missing_key_policy: MissingKeyPolicy::ReturnKey,
+            ..Default::default()
+        };
+        assert_eq!(unsafe { r3l_init(&config) }, InitError::Normal);
+        assert_eq!(unsafe { r3l_init(&config) }, InitError::AlreadyInitialized);
+        assert_eq!(current_config().missing_key_policy, MissingKeyPolicy::ReturnKey);
+
+        log(LogLevel::Warning, "test", StatusCode::OK, "four");
+        log(LogLevel::Debug, "test", StatusCode::OK, "filtered");
+        assert_eq!(LOGGED.load(Ordering::SeqCst), "R3L0000 Ok: four".len());
+        assert!(log_enabled(LogLevel::Error));
+        assert!(!log_enabled(LogLevel::Info));
+        //The host's hook still runs after the panic callback
+        assert!(std::panic::catch_unwind(|| panic!("reported")).is_err());
+        assert_eq!((REPORTED_PANICS.load(Ordering::SeqCst), HOST_PANICS.load(Ordering::SeqCst)), (1, 1));
+
+        r3l_shutdown();
+        assert_eq!(current_config().missing_key_policy, MissingKeyPolicy::NotFound);
+        log(LogLevel::Error, "test", StatusCode::OK, "dropped");
+        assert_eq!(LOGGED.load(Ordering::SeqCst), "R3L0000 Ok: four".len());
+        assert!(std::panic::catch_unwind(|| panic!("not reported")).is_err());
+        assert_eq!((REPORTED_PANICS.load(Ordering::SeqCst), HOST_PANICS.load(Ordering::SeqCst)), (1, 2));
+
+        //A hook installed after r3l_init survives r3l_shutdown
+        assert_eq!(unsafe { r3l_init(&config) }, InitError::Normal);
+        std::panic::set_hook(Box::new(|_| {
+            LATER_PANICS.fetch_add(1, Ordering::SeqCst);
+        }));
+        r3l_shutdown();
+        assert!(std::panic::catch_unwind(|| panic!("later hook")).is_err());
+        assert_eq!((REPORTED_PANICS.load(Ordering::SeqCst), LATER_PANICS.load(Ordering::SeqCst)), (1, 1));
+        drop(std::panic::take_hook());
+    }
+}
@@ -1,17 +1,210 @@
-use crate::locale_api::parser::ParseR3Error;
-use memchr::{memchr, memmem};
+use crate::locale_api::blocks::{block_spans, in_block};
+use crate::locale_api::lints::InvisibleKind;
+use crate::locale_api::parser::{COMMENT_ESCAPE, COMMENT_START, InlineComments, KEY_OPEN, ParseR3Error};
+use memchr::{memchr, memmem, memrchr};
+use std::borrow::Cow;
+use std::ops::Range;
 
 pub fn sanitize_r3_locale_file(file: &mut [u8]) -> Result<(), ParseR3Error> {
+    let blocks = block_spans(file)?;
+    sanitize_with_inline_comments(file, InlineComments::Anywhere, &blocks).map(|_| ())
+}
+
+//Blanks comments with spaces, so byte offsets stay the same. Returns the comments of value lines together with the
+//whitespace before them, which the parser leaves out of values instead of keeping it as padding. `blocks` are the
+//block values of the file, see blocks::block_spans, and empty for dialects without them.
+pub(crate) fn sanitize_with_inline_comments(
+    file: &mut [u8],
+    policy: InlineComments,
+    blocks: &[Range<usize>],
+) -> Result<Vec<Range<usize>>, ParseR3Error> {
     if std::str::from_utf8(file).is_err() {
         return Err(ParseR3Error::InvalidUTF8Value);
     }
 
-    let comment_opening_matches: Vec<usize> = memmem::find_iter(&file, "##").collect();
-    for item in &comment_opening_matches {
-        if let Some(close_pos) = memchr(b'\n', &file[*item..]) {
-            file[*item..*item + close_pos].fill(b' ');
+    //Comment markers inside block values and escaped ones are text
+    let comment_opening_matches: Vec<usize> = memmem::find_iter(&file, COMMENT_START)
+        .filter(|&position| !is_escaped(file, position) && !in_block(blocks, position))
+        .collect();
+    let first_key = memmem::find_iter(&file, KEY_OPEN).find(|&position| position == 0 || file[position - 1] == b'\n');
+
+    let mut trailing = Vec::new();
+    let mut blanked_until = 0;
+    for &item in &comment_opening_matches {
+        if item < blanked_until {
+            continue;
+        }
+        let line_start = memrchr(b'\n', &file[..item]).map_or(0, |index| index + 1);
+        let is_value_line =
+            first_key.is_some_and(|key| line_start > key) && !file[line_start..].starts_with(KEY_OPEN.as_bytes());
+        let before = &file[line_start..item];
+        if is_value_line && !starts_comment(policy, before, &file[item + COMMENT_START.len()..]) {
+            continue;
+        }
+        //The line break and a carriage return before it stay, so line endings inside values are kept
+        let mut end = memchr(b'\n', &file[item..]).map_or(file.len(), |index| item + index);
+        if end > item && file[end - 1] == b'\r' {
+            end -= 1;
         }
+        if is_value_line {
+            trailing.push(line_start + before.trim_ascii_end().len()..end);
+        }
+        file[item..end].fill(b' ');
+        blanked_until = end;
     }
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(trailing)
+}
+
+//Whether COMMENT_START in a value line starts a comment, given the text before it on the line and the text after it
+fn starts_comment(policy: InlineComments, before: &[u8], after: &[u8]) -> bool {
+    match policy {
+        InlineComments::Anywhere => true,
+        InlineComments::Separated => {
+            before.last().is_none_or(u8::is_ascii_whitespace) && after.first().is_none_or(u8::is_ascii_whitespace)
+        }
+        InlineComments::WholeLine => before.trim_ascii().is_empty(),
+    }
+}
+
+//Leaves the comments out of value lines the way the parser does, for values kept as they were written. A line that is
+//only a comment becomes an empty line.
+pub(crate) fn without_value_comments(value: &str, policy: InlineComments) -> Cow<'_, str> {
+    if find_comment(value.as_bytes()).is_none() {
+        return Cow::Borrowed(value);
+    }
+    let lines: Vec<&str> = value
+        .split('\n')
+        .map(|line| {
+            let bytes = line.as_bytes();
+            let comment = memmem::find_iter(bytes, COMMENT_START).find(|&position| {
+                !is_escaped(bytes, position)
+                    && starts_comment(policy, &bytes[..position], &bytes[position + COMMENT_START.len()..])
+            });
+            comment.map_or(line, |position| line[..position].trim_ascii_end())
+        })
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+fn is_escaped(text: &[u8], position: usize) -> bool {
+    position > 0 && text[position - 1] == COMMENT_ESCAPE
+}
+
+//Start of the comment on a line, skipping escaped markers like the sanitizer does
+pub(crate) fn find_comment(line: &[u8]) -> Option<usize> {
+    memmem::find_iter(line, COMMENT_START).find(|&position| !is_escaped(line, position))
+}
+
+//Drops the escape in front of escaped comment markers, once comments are gone
+pub(crate) fn unescape_comments(text: Cow<'_, str>) -> Cow<'_, str> {
+    let escaped = format!("{}{COMMENT_START}", COMMENT_ESCAPE as char);
+    if !text.contains(&escaped) {
+        return text;
+    }
+    Cow::Owned(text.replace(&escaped, COMMENT_START))
+}
+
+//Escapes every comment marker of plain text, so it's read back as text outside a block
+pub(crate) fn escape_comments(text: &str) -> Cow<'_, str> {
+    if !text.contains(COMMENT_START) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace(COMMENT_START, &format!("{}{COMMENT_START}", COMMENT_ESCAPE as char)))
+}
+
+//Characters that don't show up or look like a plain space, so two keys that look the same end up with different
+//hashes. Joiners and directional marks are needed by emoji and right-to-left scripts, so they only count in keys.
+pub(crate) fn invisible_kind(character: char, in_key: bool) -> Option<InvisibleKind> {
+    match character {
+        '\t' => Some(InvisibleKind::Tab),
+        '\u{A0}' | '\u{2007}' | '\u{202F}' => Some(InvisibleKind::NonBreakingSpace),
+        '\u{200B}' | '\u{2060}' | '\u{FEFF}' => Some(InvisibleKind::ZeroWidth),
+        '\u{200C}' | '\u{200D}' if in_key => Some(InvisibleKind::ZeroWidth),
+        '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => Some(InvisibleKind::BidiControl),
+        '\u{200E}' | '\u{200F}' | '\u{61C}' if in_key => Some(InvisibleKind::BidiControl),
+        _ => None,
+    }
+}
+
+pub(crate) fn count_invisible(text: &str, in_key: bool) -> usize {
+    text.chars().filter(|&character| invisible_kind(character, in_key).is_some()).count()
+}
+
+//Tabs and non-breaking spaces become plain spaces, the other characters of invisible_kind are removed
+pub(crate) fn normalize_invisible(text: Cow<'_, str>, in_key: bool) -> Cow<'_, str> {
+    if count_invisible(&text, in_key) == 0 {
+        return text;
+    }
+    let normalized = text
+        .chars()
+        .filter_map(|character| match invisible_kind(character, in_key) {
+            None => Some(character),
+            Some(InvisibleKind::Tab | InvisibleKind::NonBreakingSpace) => Some(' '),
+            Some(InvisibleKind::ZeroWidth | InvisibleKind::BidiControl) => None,
+        })
+        .collect();
+    Cow::Owned(normalized)
+}
+//Latin letter or digit a character is commonly mistaken for, Cyrillic and Greek letters that look like Latin ones and
+//fullwidth forms. A key typed with the wrong keyboard layout looks right in review but hashes differently.
+pub(crate) fn confusable_latin(character: char) -> Option<char> {
+    let latin = match character {
+        'а' => 'a',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'ӏ' => 'l',
+        'о' | 'ο' => 'o',
+        'р' | 'ρ' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'ν' => 'v',
+        'ԝ' => 'w',
+        'х' => 'x',
+        'у' => 'y',
+        'А' | 'Α' => 'A',
+        'В' | 'Β' => 'B',
+        'С' => 'C',
+        'Е' | 'Ε' => 'E',
+        'Н' | 'Η' => 'H',
+        'І' | 'Ι' => 'I',
+        'Ј' => 'J',
+        'К' | 'Κ' => 'K',
+        'М' | 'Μ' => 'M',
+        'Ν' => 'N',
+        'О' | 'Ο' => 'O',
+        'Р' | 'Ρ' => 'P',
+        'Ѕ' => 'S',
+        'Т' | 'Τ' => 'T',
+        'Х' | 'Χ' => 'X',
+        'Υ' | 'Ү' => 'Y',
+        'Ζ' => 'Z',
+        '\u{FF10}'..='\u{FF19}' | '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}' | '\u{FF3F}' | '\u{FF0E}' => {
+            char::from_u32(character as u32 - 0xFEE0)?
+        }
+        _ => return None,
+    };
+    Some(latin)
+}
+
+//Confusables only matter in keys that mix them with Latin letters, a key written entirely in Cyrillic or Greek is left
+//alone
+pub(crate) fn has_confusables(key: &str) -> bool {
+    key.chars().any(|character| character.is_ascii_alphabetic())
+        && key.chars().any(|character| confusable_latin(character).is_some())
+}
+
+pub(crate) fn normalize_confusables(key: Cow<'_, str>) -> Cow<'_, str> {
+    if !has_confusables(&key) {
+        return key;
+    }
+    let normalized = key
+        .chars()
+        .map(|character| confusable_latin(character).unwrap_or(character))
+        .collect();
+    Cow::Owned(normalized)
+}
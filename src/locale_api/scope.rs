@@ -0,0 +1,85 @@
+use super::keys::KeyId;
+use super::manager::LocaleManager;
+use xxhash_rust::xxh3::xxh3_64;
+
+//Namespace for strings every mod may read, e.g. from a framework several mods build on
+pub const SHARED_NAMESPACE: &str = "shared";
+
+//What one mod sees of a manager: its own namespace, then SHARED_NAMESPACE, then the base game's strings. Other mods'
+//namespaces are never searched, so a mod can't read their keys, and a key it defines only shadows the base game for
+//itself.
+#[derive(Clone, Copy)]
+pub struct ModScope<'a> {
+    manager: &'a LocaleManager,
+    mod_id: &'a str,
+}
+
+impl LocaleManager {
+    //Namespaces are added with add_namespace_language, a mod without any only sees shared and base game strings
+    pub fn scope_for<'a>(&'a self, mod_id: &'a str) -> ModScope<'a> {
+        ModScope { manager: self, mod_id }
+    }
+}
+
+impl<'a> ModScope<'a> {
+    pub fn mod_id(&self) -> &'a str {
+        self.mod_id
+    }
+
+    pub fn find_entry(&self, key: &[u8]) -> Option<&'a str> {
+        self.find_hash(xxh3_64(key))
+    }
+
+    pub fn find_id(&self, id: KeyId) -> Option<&'a str> {
+        self.find_hash(id.0)
+    }
+
+    //Namespaces use the table of the active language, or their first language when they don't have it
+    fn find_hash(&self, hash: u64) -> Option<&'a str> {
+        let manager = self.manager;
+        let language = manager.language();
+        [self.mod_id, SHARED_NAMESPACE]
+            .into_iter()
+            .find_map(|namespace| manager.namespace_table(namespace, language)?.find_hash(hash))
+            .or_else(|| manager.find_hash(hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_mod_scopes() {
+        let mut manager = LocaleManager::new();
+        let base = parse_r3locale_bytes(&mut b"[[play]]\nPlay\n[[quit]]\nQuit\n".to_vec()).expect("Parse failed");
+        manager.add_language("en", base);
+        manager.set_language("en");
+        let first =
+            parse_r3locale_bytes(&mut b"[[play]]\nStart\n[[first_item]]\nSword\n".to_vec()).expect("Parse failed");
+        manager.add_namespace_language("first", "en", first);
+        let second = parse_r3locale_bytes(&mut b"[[second_item]]\nShield\n".to_vec()).expect("Parse failed");
+        manager.add_namespace_language("second", "de", second);
+        let shared = parse_r3locale_bytes(&mut b"[[menu]]\nMod Menu\n".to_vec()).expect("Parse failed");
+        manager.add_namespace_language(SHARED_NAMESPACE, "en", shared);
+
+        let first = manager.scope_for("first");
+        assert_eq!(first.find_entry(b"play"), Some("Start"));
+        assert_eq!(first.find_entry(b"menu"), Some("Mod Menu"));
+        assert_eq!(first.find_entry(b"quit"), Some("Quit"));
+        assert_eq!(first.find_entry(b"second_item"), None);
+
+        //Falls back to the namespace's only language
+        let second = manager.scope_for("second");
+        assert_eq!(second.find_entry(b"second_item"), Some("Shield"));
+        assert_eq!(second.find_entry(b"play"), Some("Play"));
+        assert_eq!(second.find_entry(b"first_item"), None);
+        assert_eq!(manager.find_entry(b"play"), Some("Play"));
+        assert_eq!(manager.find_entry(b"menu"), None);
+
+        assert!(manager.remove_namespace("first"));
+        assert!(!manager.remove_namespace("first"));
+        assert_eq!(manager.scope_for("first").find_entry(b"play"), Some("Play"));
+    }
+}
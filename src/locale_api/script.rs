@@ -0,0 +1,274 @@
+use super::annotations::find_annotation;
+use super::formatting::primary_language;
+use super::interop::LocaleTable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+    Hangul,
+    //Hiragana and Katakana
+    Kana,
+    Han,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Script {
+    pub fn direction(self) -> Direction {
+        match self {
+            Script::Arabic | Script::Hebrew => Direction::RightToLeft,
+            _ => Direction::LeftToRight,
+        }
+    }
+
+    //Parses an ISO 15924 code, as used in `@script=Arab` and tags like `sr-Latn`
+    pub fn from_code(code: &str) -> Option<Script> {
+        let script = match code.to_ascii_lowercase().as_str() {
+            "latn" => Script::Latin,
+            "cyrl" => Script::Cyrillic,
+            "grek" => Script::Greek,
+            "arab" => Script::Arabic,
+            "hebr" => Script::Hebrew,
+            "deva" => Script::Devanagari,
+            "thai" => Script::Thai,
+            "hang" | "kore" => Script::Hangul,
+            "jpan" | "hira" | "kana" => Script::Kana,
+            "hani" | "hans" | "hant" => Script::Han,
+            _ => return None,
+        };
+        Some(script)
+    }
+}
+
+//Returns None for digits, punctuation and characters of scripts not listed above
+pub fn script_of(character: char) -> Option<Script> {
+    let script = match character {
+        'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' => Script::Greek,
+        '\u{0400}'..='\u{04FF}' => Script::Cyrillic,
+        '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+        '\u{0600}'..='\u{06FF}' => Script::Arabic,
+        '\u{0900}'..='\u{097F}' => Script::Devanagari,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{1100}'..='\u{11FF}' | '\u{AC00}'..='\u{D7AF}' => Script::Hangul,
+        '\u{3040}'..='\u{30FF}' => Script::Kana,
+        '\u{4E00}'..='\u{9FFF}' => Script::Han,
+        _ => return None,
+    };
+    Some(script)
+}
+
+//The script a language is normally written in, None for languages without an entry here
+pub fn expected_script(language: &str) -> Option<Script> {
+    //An explicit script subtag wins, e.g. `sr-Latn` is Latin even though Serbian defaults to Cyrillic
+    if let Some(script) = language
+        .split(['-', '_'])
+        .skip(1)
+        .filter(|subtag| subtag.len() == 4)
+        .find_map(Script::from_code)
+    {
+        return Some(script);
+    }
+
+    let script = match primary_language(language) {
+        "en" | "de" | "fr" | "es" | "it" | "pt" | "nl" | "pl" | "cs" | "sv" | "da" | "fi" | "no" | "nb" | "tr"
+        | "hu" | "ro" | "id" | "vi" => Script::Latin,
+        "ru" | "uk" | "bg" | "sr" | "be" | "kk" => Script::Cyrillic,
+        "el" => Script::Greek,
+        "ar" | "fa" | "ur" => Script::Arabic,
+        "he" | "yi" => Script::Hebrew,
+        "hi" | "mr" | "ne" => Script::Devanagari,
+        "th" => Script::Thai,
+        "ko" => Script::Hangul,
+        "ja" => Script::Kana,
+        "zh" => Script::Han,
+        _ => return None,
+    };
+    Some(script)
+}
+
+//The script used by most letters of the text
+pub fn dominant_script<'a>(texts: impl IntoIterator<Item = &'a str>) -> Option<Script> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for character in texts.into_iter().flat_map(str::chars) {
+        let Some(script) = script_of(character) else {
+            continue;
+        };
+        match counts.iter_mut().find(|(counted, _)| *counted == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+
+    //Japanese mixes kanji with kana, any kana at all means the text is Japanese
+    if counts.iter().any(|(script, _)| *script == Script::Kana) {
+        return Some(Script::Kana);
+    }
+    counts.into_iter().max_by_key(|(_, count)| *count).map(|(script, _)| script)
+}
+
+//Common short words that rarely appear in the other listed languages
+const STOPWORDS: [(&str, &[&str]); 5] = [
+    ("en", &["the", "and", "of", "to", "is", "you", "your", "with", "this", "are", "not", "for"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "mit", "ein", "eine", "zu", "sie", "ich", "auf", "den"]),
+    ("fr", &["le", "les", "et", "est", "pas", "vous", "des", "du", "pour", "avec", "dans", "une"]),
+    ("es", &["el", "los", "las", "y", "está", "para", "por", "que", "usted", "es", "su"]),
+    ("it", &["il", "gli", "è", "di", "che", "non", "sono", "della", "questo", "anche"]),
+];
+
+//The stopwords of a language, English ones for languages not listed above
+pub(crate) fn stopwords(language: &str) -> &'static [&'static str] {
+    let language = primary_language(language);
+    STOPWORDS
+        .iter()
+        .find(|(listed, _)| *listed == language)
+        .map_or(STOPWORDS[0].1, |(_, words)| words)
+}
+
+//Needed word hits before a guess is trusted, so short values like "OK" don't trigger warnings
+const MIN_STOPWORD_HITS: usize = 3;
+
+//Guesses the language of Latin script text from its stopwords
+pub fn guess_latin_language<'a>(texts: impl IntoIterator<Item = &'a str>) -> Option<&'static str> {
+    let mut hits = [0usize; STOPWORDS.len()];
+    for word in texts
+        .into_iter()
+        .flat_map(|text| text.split(|c: char| !c.is_alphabetic()))
+        .filter(|word| !word.is_empty())
+    {
+        let word = word.to_lowercase();
+        for (index, (_, words)) in STOPWORDS.iter().enumerate() {
+            if words.contains(&word.as_str()) {
+                hits[index] += 1;
+            }
+        }
+    }
+
+    let best = (0..hits.len()).max_by_key(|&index| hits[index])?;
+    let is_clear_winner = hits
+        .iter()
+        .enumerate()
+        .all(|(index, &count)| index == best || count < hits[best]);
+    (hits[best] >= MIN_STOPWORD_HITS && is_clear_winner).then_some(STOPWORDS[best].0)
+}
+
+//Reported when a file's `@language` header doesn't match what its values are written in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageMismatch {
+    pub declared: String,
+    pub detected_script: Script,
+    //Only set for Latin script text that clearly reads as another supported language
+    pub detected_language: Option<&'static str>,
+}
+
+pub fn check_language(declared: &str, texts: &[&str]) -> Option<LanguageMismatch> {
+    let expected = expected_script(declared)?;
+    let detected_script = dominant_script(texts.iter().copied())?;
+    let mismatch = |detected_language| LanguageMismatch {
+        declared: declared.to_string(),
+        detected_script,
+        detected_language,
+    };
+
+    //Chinese text is all Han, but Japanese files can also contain plain kanji strings
+    let scripts_match = detected_script == expected || (expected == Script::Kana && detected_script == Script::Han);
+    if !scripts_match {
+        return Some(mismatch(None));
+    }
+
+    let declared_language = primary_language(declared);
+    if expected != Script::Latin || !STOPWORDS.iter().any(|(language, _)| *language == declared_language) {
+        return None;
+    }
+    guess_latin_language(texts.iter().copied())
+        .filter(|detected| *detected != declared_language)
+        .map(|detected| mismatch(Some(detected)))
+}
+
+impl LocaleTable {
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter_map(|entry| self.string_values.get(entry.string_id))
+    }
+
+    //From the `@script` header if present, otherwise derived from the `@language` tag
+    pub fn script(&self) -> Option<Script> {
+        match find_annotation(&self.metadata, "script").and_then(|annotation| annotation.value.as_deref()) {
+            Some(code) => Script::from_code(code),
+            None => expected_script(self.language()?),
+        }
+    }
+
+    //A `@direction=rtl` or `@direction=ltr` header overrides the direction of the script
+    pub fn direction(&self) -> Direction {
+        match find_annotation(&self.metadata, "direction").and_then(|annotation| annotation.value.as_deref()) {
+            Some("rtl") => Direction::RightToLeft,
+            Some("ltr") => Direction::LeftToRight,
+            _ => self.script().map_or(Direction::LeftToRight, Script::direction),
+        }
+    }
+
+    //Checks the `@language` header against the values, returns None when they agree or nothing is declared
+    pub fn language_mismatch(&self) -> Option<LanguageMismatch> {
+        let values: Vec<&str> = self.values().collect();
+        check_language(self.language()?, &values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_english_file_declared_as_german() {
+        let mut sample = "@language=de\n[[intro]]\nThe castle is not open to you yet.\n[[hint]]\nTalk to the guard and show your pass.\n"
+            .as_bytes()
+            .to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+
+        let mismatch = table.language_mismatch().expect("Mismatch not detected");
+        assert_eq!(mismatch.detected_script, Script::Latin);
+        assert_eq!(mismatch.detected_language, Some("en"));
+    }
+
+    #[test]
+    fn test_direction() {
+        let mut arabic = "@language=ar\n[[play]]\nالعب\n".as_bytes().to_vec();
+        let table = parse_r3locale_bytes(&mut arabic).expect("Parse failed");
+        assert_eq!(table.script(), Some(Script::Arabic));
+        assert_eq!(table.direction(), Direction::RightToLeft);
+
+        let mut latin = Box::from(*b"@language=sr-Latn\n[[play]]\nIgraj\n");
+        let table = parse_r3locale_bytes(&mut *latin).expect("Parse failed");
+        assert_eq!(table.script(), Some(Script::Latin));
+        assert_eq!(table.direction(), Direction::LeftToRight);
+
+        let mut overridden = Box::from(*b"@language=en @direction=rtl\n[[play]]\nPlay\n");
+        let table = parse_r3locale_bytes(&mut *overridden).expect("Parse failed");
+        assert_eq!(table.direction(), Direction::RightToLeft);
+    }
+
+    #[test]
+    fn test_script_mismatch() {
+        assert_eq!(check_language("ja", &["剣を手に入れた"]), None);
+        assert_eq!(check_language("zh", &["你好"]), None);
+        assert_eq!(check_language("ru", &["Привет, мир"]), None);
+        let mismatch = check_language("ru", &["Hello world"]).expect("Mismatch not detected");
+        assert_eq!(mismatch.detected_script, Script::Latin);
+        assert_eq!(check_language("de", &["OK", "Menü"]), None);
+    }
+}
@@ -0,0 +1,134 @@
+use super::ffi_util;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand_core::OsRng;
+use std::ffi::{OsString, c_char};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//Detached ed25519 signatures for language packs, so the official distribution channel can prove a pack is the one it
+//published. The signature of `story.r3l` is the 64 raw bytes in `story.r3l.sig`. Any pack file can be signed, sealed
+//packs are signed as they are on disk.
+
+pub const SIGNATURE_EXTENSION: &str = "sig";
+pub const SIGNING_KEY_LEN: usize = 32;
+pub const PUBLIC_KEY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum SignatureError {
+    Normal,
+    NullPathProvided,
+    InvalidUTF8Path,
+    FileNotFound,
+    FailedToRead,
+    //The pack has no signature file next to it
+    MissingSignature,
+    //Not an ed25519 public key, or a null pointer
+    InvalidPublicKey,
+    //The pack or its signature was changed, or it was signed with a different key
+    InvalidSignature,
+}
+
+//A new random signing key from the operating system's generator. Keep it off the machines that build mods, only its
+//public key ships with the game.
+pub fn generate_signing_key() -> [u8; SIGNING_KEY_LEN] {
+    SigningKey::generate(&mut OsRng).to_bytes()
+}
+
+pub fn public_key(signing_key: &[u8; SIGNING_KEY_LEN]) -> [u8; PUBLIC_KEY_LEN] {
+    SigningKey::from_bytes(signing_key).verifying_key().to_bytes()
+}
+
+//`story.r3l.sig` for `story.r3l`
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut signature = OsString::from(path.as_os_str());
+    signature.push(".");
+    signature.push(SIGNATURE_EXTENSION);
+    PathBuf::from(signature)
+}
+
+pub fn sign_pack_bytes(bytes: &[u8], signing_key: &[u8; SIGNING_KEY_LEN]) -> [u8; SIGNATURE_LEN] {
+    SigningKey::from_bytes(signing_key).sign(bytes).to_bytes()
+}
+
+//Writes the signature file of a pack next to it
+pub fn sign_pack(path: &Path, signing_key: &[u8; SIGNING_KEY_LEN]) -> io::Result<()> {
+    let bytes = fs::read(path)?;
+    fs::write(signature_path(path), sign_pack_bytes(&bytes, signing_key))
+}
+
+//Checks bytes the caller already read, so the bytes that are verified are the ones that get parsed
+pub fn verify_pack_bytes(
+    bytes: &[u8],
+    signature: &[u8],
+    public_key: &[u8; PUBLIC_KEY_LEN],
+) -> Result<(), SignatureError> {
+    let public_key = VerifyingKey::from_bytes(public_key).map_err(|_| SignatureError::InvalidPublicKey)?;
+    let signature = Signature::from_slice(signature).map_err(|_| SignatureError::InvalidSignature)?;
+    public_key
+        .verify_strict(bytes, &signature)
+        .map_err(|_| SignatureError::InvalidSignature)
+}
+
+//Checks a pack against the signature file next to it. Loading the file again afterwards reads it twice, when someone
+//could swap it in between read it once and use verify_pack_bytes.
+pub fn verify_pack(path: &Path, public_key: &[u8; PUBLIC_KEY_LEN]) -> Result<(), SignatureError> {
+    if !path.exists() {
+        return Err(SignatureError::FileNotFound);
+    }
+    let bytes = fs::read(path).map_err(|_| SignatureError::FailedToRead)?;
+    let signature = fs::read(signature_path(path)).map_err(|error| match error.kind() {
+        io::ErrorKind::NotFound => SignatureError::MissingSignature,
+        _ => SignatureError::FailedToRead,
+    })?;
+    verify_pack_bytes(&bytes, &signature, public_key)
+}
+
+//verify_pack for C hosts, `public_key` points at PUBLIC_KEY_LEN bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_verify_pack(path: *const c_char, public_key: *const u8) -> SignatureError {
+    let path = match unsafe { ffi_util::c_str(path) } {
+        Some(Ok(path)) => path,
+        Some(Err(_)) => return SignatureError::InvalidUTF8Path,
+        None => return SignatureError::NullPathProvided,
+    };
+    let Some(public_key) = (unsafe { ffi_util::slice(public_key, PUBLIC_KEY_LEN) }).and_then(|key| key.try_into().ok())
+    else {
+        return SignatureError::InvalidPublicKey;
+    };
+    match verify_pack(Path::new(path), public_key) {
+        Ok(()) => SignatureError::Normal,
+        Err(error) => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_pack() {
+        let signing_key = generate_signing_key();
+        let official = public_key(&signing_key);
+        let pack = b"[[greeting]]\nWelcome back!\n";
+        let signature = sign_pack_bytes(pack, &signing_key);
+        assert_eq!(verify_pack_bytes(pack, &signature, &official), Ok(()));
+        assert_eq!(
+            verify_pack_bytes(b"[[greeting]]\nWelcome back, loser!\n", &signature, &official),
+            Err(SignatureError::InvalidSignature)
+        );
+        let other = public_key(&generate_signing_key());
+        assert_eq!(verify_pack_bytes(pack, &signature, &other), Err(SignatureError::InvalidSignature));
+        assert_eq!(verify_pack_bytes(pack, &signature[..10], &official), Err(SignatureError::InvalidSignature));
+
+        let path = std::env::temp_dir().join("r3l_signing_verify_pack.r3l");
+        fs::write(&path, pack).expect("Write failed");
+        let _ = fs::remove_file(signature_path(&path));
+        assert_eq!(verify_pack(&path, &official), Err(SignatureError::MissingSignature));
+        sign_pack(&path, &signing_key).expect("Sign failed");
+        assert_eq!(verify_pack(&path, &official), Ok(()));
+        assert_eq!(signature_path(&path).extension(), Some(SIGNATURE_EXTENSION.as_ref()));
+    }
+}
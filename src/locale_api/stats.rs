@@ -0,0 +1,289 @@
+use super::ffi_util;
+use super::interop::{FindEntryError, LocaleTable};
+use super::keys::{KeyId, key_text};
+use super::manager::LocaleManager;
+use hashbrown::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+//How many of the most recently missed keys are kept
+pub const MISSED_KEY_HISTORY: usize = 32;
+
+//Lookup counters of a table or manager, for finding out why a label shows up blank on a user's machine without a
+//debugger, and which strings are used at all. Off by default, enabling them costs a lock per lookup.
+#[derive(Debug, Default)]
+pub struct LookupStats {
+    enabled: AtomicBool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    recent_misses: Mutex<VecDeque<String>>,
+    usage: Mutex<HashMap<u64, KeyUsage>>,
+}
+
+#[derive(Debug, Default)]
+struct KeyUsage {
+    lookups: u64,
+    //Only known when the key was looked up by text
+    key: Option<String>,
+}
+
+impl LookupStats {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    //Up to MISSED_KEY_HISTORY keys, oldest first. Keys looked up by hash show their interned text, or the hash in hex
+    //when they were never interned.
+    pub fn recent_misses(&self) -> Vec<String> {
+        self.recent_misses
+            .lock()
+            .map(|misses| misses.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    //Clears the counters and missed keys, they stay enabled
+    pub fn reset(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        if let Ok(mut misses) = self.recent_misses.lock() {
+            misses.clear();
+        }
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.clear();
+        }
+    }
+
+    //How often a key was looked up since the stats were enabled
+    pub fn lookups(&self, key: &str) -> u64 {
+        self.usage
+            .lock()
+            .ok()
+            .and_then(|usage| usage.get(&KeyId::of(key).0).map(|usage| usage.lookups))
+            .unwrap_or_default()
+    }
+
+    //CSV of lookup counts, one row per key that was looked up or is in `keys`, most used first. Keys in `keys` that
+    //were never looked up are listed with 0, which are the candidates for cutting. `found` says whether `contains`
+    //has the key.
+    fn usage_csv(&self, keys: impl Iterator<Item = u64>, contains: impl Fn(u64) -> bool) -> String {
+        let mut rows: HashMap<u64, (u64, String)> = keys.map(|hash| (hash, (0, key_name(hash)))).collect();
+        if let Ok(usage) = self.usage.lock() {
+            for (&hash, usage) in usage.iter() {
+                let name = usage.key.clone().unwrap_or_else(|| key_name(hash));
+                rows.insert(hash, (usage.lookups, name));
+            }
+        }
+        let mut rows: Vec<(u64, (u64, String))> = rows.into_iter().collect();
+        rows.sort_unstable_by(|(_, (left_count, left)), (_, (right_count, right))| {
+            right_count.cmp(left_count).then_with(|| left.cmp(right))
+        });
+
+        let mut csv = String::from("key,lookups,found\n");
+        for (hash, (lookups, name)) in rows {
+            let _ = writeln!(csv, "{},{lookups},{}", csv_field(&name), contains(hash));
+        }
+        csv
+    }
+
+    pub(crate) fn record(&self, hash: u64, key: Option<&[u8]>, found: bool) {
+        if !self.is_enabled() {
+            return;
+        }
+        let key = key.map(|key| String::from_utf8_lossy(key).into_owned());
+        if let Ok(mut usage) = self.usage.lock() {
+            let usage = usage.entry(hash).or_default();
+            usage.lookups += 1;
+            if usage.key.is_none() {
+                usage.key.clone_from(&key);
+            }
+        }
+        if found {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let key = key.unwrap_or_else(|| key_name(hash));
+        if let Ok(mut misses) = self.recent_misses.lock() {
+            if misses.len() == MISSED_KEY_HISTORY {
+                misses.pop_front();
+            }
+            misses.push_back(key);
+        }
+    }
+}
+
+//Interned text of a key hash, or the hash in hex
+pub(crate) fn key_name(hash: u64) -> String {
+    key_text(KeyId(hash)).map_or_else(|| format!("{hash:016x}"), str::to_string)
+}
+
+pub(crate) fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+impl LocaleTable {
+    //Lookup counts of every key in the table and every missed key, see LookupStats. Keys only have a name once they
+    //were looked up by text or interned, the others are listed by hash.
+    pub fn export_usage_csv(&self) -> String {
+        let keys = self.entries.iter().map(|entry| entry.key);
+        self.stats.usage_csv(keys, |hash| self.contains_hash(hash))
+    }
+}
+
+impl LocaleManager {
+    //Lookup counts of every key looked up through the manager and every key of the embedded default, `found` is
+    //whether the key has a value in the active language or the default
+    pub fn export_usage_csv(&self) -> String {
+        let default_keys = self
+            .embedded_default()
+            .into_iter()
+            .flat_map(|table| table.entries.iter().map(|entry| entry.key));
+        self.lookup_stats().usage_csv(default_keys, |hash| self.contains_hash(hash))
+    }
+}
+
+#[repr(C)]
+pub struct LookupStatsResult {
+    pub hits: u64,
+    pub misses: u64,
+    pub stats_state: FindEntryError,
+}
+
+//Turns lookup counting on or off for one table, get_entry calls are counted from then on
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_set_lookup_stats(table: *const LocaleTable, enabled: bool) -> FindEntryError {
+    match ffi_util::table_ref(table) {
+        Ok(table) => {
+            table.lookup_stats().set_enabled(enabled);
+            FindEntryError::Normal
+        }
+        Err(error) => error.into(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_lookup_stats(table: *const LocaleTable) -> LookupStatsResult {
+    match ffi_util::table_ref(table) {
+        Ok(table) => LookupStatsResult {
+            hits: table.lookup_stats().hits(),
+            misses: table.lookup_stats().misses(),
+            stats_state: FindEntryError::Normal,
+        },
+        Err(error) => LookupStatsResult {
+            hits: 0,
+            misses: 0,
+            stats_state: error.into(),
+        },
+    }
+}
+
+//Copies the recently missed keys into `buffer` as UTF-8, one per line and oldest first, and returns the number of bytes
+//they need. Nothing is written when the buffer is too small, so call it with a null buffer first to get the size.
+//Returns 0 for tables that fail the handle check.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_recent_misses(table: *const LocaleTable, buffer: *mut u8, buffer_len: usize) -> usize {
+    let Ok(table) = ffi_util::table_ref(table) else {
+        return 0;
+    };
+    let text: String = table
+        .lookup_stats()
+        .recent_misses()
+        .iter()
+        .map(|key| format!("{key}\n"))
+        .collect();
+    if text.len() > buffer_len {
+        return text.len();
+    }
+    if let Some(buffer) = unsafe { ffi_util::slice_mut(buffer, text.len()) } {
+        buffer.copy_from_slice(text.as_bytes());
+    }
+    text.len()
+}
+
+//CSV from LocaleTable::export_usage_csv, copied like r3l_recent_misses
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn r3l_export_usage_csv(table: *const LocaleTable, buffer: *mut u8, buffer_len: usize) -> usize {
+    let Ok(table) = ffi_util::table_ref(table) else {
+        return 0;
+    };
+    let csv = table.export_usage_csv();
+    if csv.len() > buffer_len {
+        return csv.len();
+    }
+    if let Some(buffer) = unsafe { ffi_util::slice_mut(buffer, csv.len()) } {
+        buffer.copy_from_slice(csv.as_bytes());
+    }
+    csv.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::keys::intern_key;
+    use crate::locale_api::manager::LocaleManager;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_lookup_stats() {
+        let table = parse_r3locale_bytes(&mut b"[[play]]\nPlay\n".to_vec()).expect("Parse failed");
+        table.find_entry(b"missing");
+        assert_eq!(table.lookup_stats().misses(), 0);
+
+        table.lookup_stats().set_enabled(true);
+        table.find_entry(b"play");
+        table.find_entry(b"quit");
+        table.find_id(intern_key("stats_test_options"));
+        assert_eq!(table.lookup_stats().hits(), 1);
+        assert_eq!(table.lookup_stats().recent_misses(), ["quit", "stats_test_options"]);
+
+        for index in 0..MISSED_KEY_HISTORY {
+            table.find_entry(format!("key{index}").as_bytes());
+        }
+        assert_eq!(table.lookup_stats().misses(), 2 + MISSED_KEY_HISTORY as u64);
+        assert_eq!(table.lookup_stats().recent_misses().first().map(String::as_str), Some("key0"));
+
+        let manager = LocaleManager::new();
+        manager.lookup_stats().set_enabled(true);
+        assert_eq!(manager.find_entry(b"play"), None);
+        assert_eq!(manager.lookup_stats().recent_misses(), ["play"]);
+    }
+
+    #[test]
+    fn test_usage_csv() {
+        let table = parse_r3locale_bytes(&mut b"[[play]]\nPlay\n[[quit]]\nQuit\n[[credits]]\nCredits\n".to_vec())
+            .expect("Parse failed");
+        table.lookup_stats().set_enabled(true);
+        table.find_entry(b"play");
+        table.find_entry(b"play");
+        table.find_entry(b"quit");
+        table.find_entry(b"a,b");
+        assert_eq!(table.lookup_stats().lookups("play"), 2);
+
+        let csv = table.export_usage_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("key,lookups,found"));
+        assert_eq!(lines.next(), Some("play,2,true"));
+        assert_eq!(lines.next(), Some("\"a,b\",1,false"));
+        assert_eq!(lines.next(), Some("quit,1,true"));
+        //Never looked up and not interned, so only the hash is known
+        assert_eq!(lines.next(), Some(format!("{:016x},0,true", KeyId::of("credits").0).as_str()));
+    }
+}
@@ -0,0 +1,371 @@
+use super::editor::EditorError;
+use super::interop::{FindEntryError, FreeTableError, TableFrozenError};
+use super::migration::MigrationError;
+use super::parser::{MergeTableError, ParseR3Error};
+use super::preview::PreviewError;
+use super::runtime::InitError;
+use std::ffi::{CStr, c_char};
+use std::fmt;
+
+//Stable numeric code for every error and warning, for crash reporters and analytics that aggregate failures across
+//versions. A code never changes meaning and is never reused, new ones are only added. The same cause has the same code
+//whichever function reports it, e.g. a missing file is always 1001.
+//
+//0 is success, 1xxx are file and syntax errors, 2xxx merge errors, 3xxx lookup errors, 4xxx setup and tooling errors
+//and 5xxx warnings that don't stop an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct StatusCode(pub u32);
+
+impl StatusCode {
+    pub const OK: StatusCode = StatusCode(0);
+
+    pub const FILE_NOT_FOUND: StatusCode = StatusCode(1001);
+    pub const FAILED_TO_READ: StatusCode = StatusCode(1002);
+    pub const KEY_VALUE_MISMATCH: StatusCode = StatusCode(1003);
+    pub const BRACKET_MISMATCH: StatusCode = StatusCode(1004);
+    pub const INVALID_UTF8_VALUE: StatusCode = StatusCode(1005);
+    pub const INVALID_UTF8_PATH: StatusCode = StatusCode(1006);
+    pub const NULL_PATH_PROVIDED: StatusCode = StatusCode(1007);
+    pub const DUPLICATE_KEYS: StatusCode = StatusCode(1008);
+    pub const TEXT_OUTSIDE_ENTRY: StatusCode = StatusCode(1009);
+    pub const EMPTY_VALUE: StatusCode = StatusCode(1010);
+    pub const INVALID_ANNOTATION: StatusCode = StatusCode(1011);
+    pub const CANCELLED: StatusCode = StatusCode(1012);
+    pub const FAILED_TO_WRITE: StatusCode = StatusCode(1013);
+    pub const INVALID_SYNTAX: StatusCode = StatusCode(1014);
+    pub const TOO_MANY_ENTRIES: StatusCode = StatusCode(1015);
+    pub const INVALID_PACK: StatusCode = StatusCode(1016);
+    pub const DECRYPTION_FAILED: StatusCode = StatusCode(1017);
+    pub const MISSING_SIGNATURE: StatusCode = StatusCode(1018);
+    pub const INVALID_PUBLIC_KEY: StatusCode = StatusCode(1019);
+    pub const INVALID_SIGNATURE: StatusCode = StatusCode(1020);
+    pub const DOWNLOAD_FAILED: StatusCode = StatusCode(1021);
+    pub const PACK_TOO_LARGE: StatusCode = StatusCode(1022);
+    pub const HASH_MISMATCH: StatusCode = StatusCode(1023);
+    pub const UNTERMINATED_BLOCK: StatusCode = StatusCode(1024);
+
+    pub const NULL_TABLE: StatusCode = StatusCode(2001);
+    pub const INVALID_HANDLE: StatusCode = StatusCode(2002);
+    pub const TABLE_FROZEN: StatusCode = StatusCode(2003);
+
+    pub const NULL_KEY: StatusCode = StatusCode(3001);
+    pub const NO_ENTRY_FOUND: StatusCode = StatusCode(3002);
+    pub const INVALID_ARGUMENTS: StatusCode = StatusCode(3003);
+    pub const UNREPRESENTABLE_VALUE: StatusCode = StatusCode(3004);
+    pub const INVALID_KEY: StatusCode = StatusCode(3005);
+
+    pub const NULL_CONFIG: StatusCode = StatusCode(4001);
+    pub const ALREADY_INITIALIZED: StatusCode = StatusCode(4002);
+    pub const MAPPING_OUTSIDE_RELEASE: StatusCode = StatusCode(4003);
+    pub const INVALID_CONFIG: StatusCode = StatusCode(4004);
+    pub const GENERATION_FAILED: StatusCode = StatusCode(4005);
+
+    pub const IGNORED_TEXT_OUTSIDE_ENTRY: StatusCode = StatusCode(5001);
+    pub const EMPTY_VALUE_LEFT_OUT: StatusCode = StatusCode(5002);
+    pub const ENTRIES_OVERRIDDEN: StatusCode = StatusCode(5003);
+    pub const MISSING_KEY: StatusCode = StatusCode(5004);
+    pub const RELOADED: StatusCode = StatusCode(5005);
+    pub const RELOAD_FAILED: StatusCode = StatusCode(5006);
+    pub const QUARANTINED: StatusCode = StatusCode(5007);
+    pub const SKIPPED_QUARANTINED: StatusCode = StatusCode(5008);
+    pub const LEAKED_HANDLE: StatusCode = StatusCode(5009);
+    pub const NEAR_ENTRY_LIMIT: StatusCode = StatusCode(5010);
+    pub const INVISIBLE_CHARACTERS: StatusCode = StatusCode(5011);
+    pub const CONFUSABLE_KEYS: StatusCode = StatusCode(5012);
+    pub const STRING_FREEZE_VIOLATION: StatusCode = StatusCode(5013);
+
+    pub fn is_ok(self) -> bool {
+        self == StatusCode::OK
+    }
+
+    pub fn is_warning(self) -> bool {
+        (5000..6000).contains(&self.0)
+    }
+
+    //Nul terminated so the C API can return it without allocating
+    fn name_with_nul(self) -> &'static str {
+        match self.0 {
+            0 => "Ok\0",
+            1001 => "FileNotFound\0",
+            1002 => "FailedToRead\0",
+            1003 => "KeyValueMismatch\0",
+            1004 => "BracketMismatch\0",
+            1005 => "InvalidUTF8Value\0",
+            1006 => "InvalidUTF8Path\0",
+            1007 => "NullPathProvided\0",
+            1008 => "DuplicateKeys\0",
+            1009 => "TextOutsideEntry\0",
+            1010 => "EmptyValue\0",
+            1011 => "InvalidAnnotation\0",
+            1012 => "Cancelled\0",
+            1013 => "FailedToWrite\0",
+            1014 => "InvalidSyntax\0",
+            1015 => "TooManyEntries\0",
+            1016 => "InvalidPack\0",
+            1017 => "DecryptionFailed\0",
+            1018 => "MissingSignature\0",
+            1019 => "InvalidPublicKey\0",
+            1020 => "InvalidSignature\0",
+            1021 => "DownloadFailed\0",
+            1022 => "PackTooLarge\0",
+            1023 => "HashMismatch\0",
+            1024 => "UnterminatedBlock\0",
+            2001 => "NullTable\0",
+            2002 => "InvalidHandle\0",
+            2003 => "TableFrozen\0",
+            3001 => "NullKey\0",
+            3002 => "NoEntryFound\0",
+            3003 => "InvalidArguments\0",
+            3004 => "UnrepresentableValue\0",
+            3005 => "InvalidKey\0",
+            4001 => "NullConfig\0",
+            4002 => "AlreadyInitialized\0",
+            4003 => "MappingOutsideRelease\0",
+            4004 => "InvalidConfig\0",
+            4005 => "GenerationFailed\0",
+            5001 => "IgnoredTextOutsideEntry\0",
+            5002 => "EmptyValueLeftOut\0",
+            5003 => "EntriesOverridden\0",
+            5004 => "MissingKey\0",
+            5005 => "Reloaded\0",
+            5006 => "ReloadFailed\0",
+            5007 => "Quarantined\0",
+            5008 => "SkippedQuarantined\0",
+            5009 => "LeakedHandle\0",
+            5010 => "NearEntryLimit\0",
+            5011 => "InvisibleCharacters\0",
+            5012 => "ConfusableKeys\0",
+            5013 => "StringFreezeViolation\0",
+            _ => "Unknown\0",
+        }
+    }
+
+    //Name of the code, "Unknown" for codes newer than this library
+    pub fn name(self) -> &'static str {
+        self.name_with_nul().trim_end_matches('\0')
+    }
+}
+
+//`R3L1001 FileNotFound`, the form used at the start of log messages
+impl fmt::Display for StatusCode {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "R3L{:04} {}", self.0, self.name())
+    }
+}
+
+impl From<ParseR3Error> for StatusCode {
+    fn from(error: ParseR3Error) -> Self {
+        match error {
+            ParseR3Error::Normal => StatusCode::OK,
+            ParseR3Error::FileNotFound => StatusCode::FILE_NOT_FOUND,
+            ParseR3Error::FailedToRead => StatusCode::FAILED_TO_READ,
+            ParseR3Error::KeyValueMismatch => StatusCode::KEY_VALUE_MISMATCH,
+            ParseR3Error::BracketMismatch => StatusCode::BRACKET_MISMATCH,
+            ParseR3Error::InvalidUTF8Value => StatusCode::INVALID_UTF8_VALUE,
+            ParseR3Error::InvalidUTF8Path => StatusCode::INVALID_UTF8_PATH,
+            ParseR3Error::NullPathProvided => StatusCode::NULL_PATH_PROVIDED,
+            ParseR3Error::DuplicateKeys => StatusCode::DUPLICATE_KEYS,
+            ParseR3Error::TextOutsideEntry => StatusCode::TEXT_OUTSIDE_ENTRY,
+            ParseR3Error::EmptyValue => StatusCode::EMPTY_VALUE,
+            ParseR3Error::InvalidAnnotation => StatusCode::INVALID_ANNOTATION,
+            ParseR3Error::Cancelled => StatusCode::CANCELLED,
+            ParseR3Error::TooManyEntries => StatusCode::TOO_MANY_ENTRIES,
+            ParseR3Error::InvalidPack => StatusCode::INVALID_PACK,
+            ParseR3Error::DecryptionFailed => StatusCode::DECRYPTION_FAILED,
+            ParseR3Error::UnterminatedBlock => StatusCode::UNTERMINATED_BLOCK,
+        }
+    }
+}
+
+impl From<MergeTableError> for StatusCode {
+    fn from(error: MergeTableError) -> Self {
+        match error {
+            MergeTableError::Normal => StatusCode::OK,
+            MergeTableError::NullTablePointer => StatusCode::NULL_TABLE,
+            MergeTableError::FileNotFound => StatusCode::FILE_NOT_FOUND,
+            MergeTableError::FailedToRead => StatusCode::FAILED_TO_READ,
+            MergeTableError::KeyValueMismatch => StatusCode::KEY_VALUE_MISMATCH,
+            MergeTableError::BracketMismatch => StatusCode::BRACKET_MISMATCH,
+            MergeTableError::InvalidUTF8Value => StatusCode::INVALID_UTF8_VALUE,
+            MergeTableError::InvalidUTF8Path => StatusCode::INVALID_UTF8_PATH,
+            MergeTableError::NullPathProvided => StatusCode::NULL_PATH_PROVIDED,
+            MergeTableError::DuplicateKeys => StatusCode::DUPLICATE_KEYS,
+            MergeTableError::TextOutsideEntry => StatusCode::TEXT_OUTSIDE_ENTRY,
+            MergeTableError::EmptyValue => StatusCode::EMPTY_VALUE,
+            MergeTableError::InvalidAnnotation => StatusCode::INVALID_ANNOTATION,
+            MergeTableError::Cancelled => StatusCode::CANCELLED,
+            MergeTableError::InvalidHandle => StatusCode::INVALID_HANDLE,
+            MergeTableError::TooManyEntries => StatusCode::TOO_MANY_ENTRIES,
+            MergeTableError::InvalidPack => StatusCode::INVALID_PACK,
+            MergeTableError::DecryptionFailed => StatusCode::DECRYPTION_FAILED,
+            MergeTableError::UnterminatedBlock => StatusCode::UNTERMINATED_BLOCK,
+        }
+    }
+}
+
+impl From<FindEntryError> for StatusCode {
+    fn from(error: FindEntryError) -> Self {
+        match error {
+            FindEntryError::Normal => StatusCode::OK,
+            FindEntryError::NullTable => StatusCode::NULL_TABLE,
+            FindEntryError::NullKeyPtr => StatusCode::NULL_KEY,
+            FindEntryError::NoEntryFound => StatusCode::NO_ENTRY_FOUND,
+            FindEntryError::InvalidHandle => StatusCode::INVALID_HANDLE,
+        }
+    }
+}
+
+impl From<FreeTableError> for StatusCode {
+    fn from(error: FreeTableError) -> Self {
+        match error {
+            FreeTableError::Normal => StatusCode::OK,
+            FreeTableError::NullTable => StatusCode::NULL_TABLE,
+            FreeTableError::InvalidHandle => StatusCode::INVALID_HANDLE,
+        }
+    }
+}
+
+impl From<TableFrozenError> for StatusCode {
+    fn from(_: TableFrozenError) -> Self {
+        StatusCode::TABLE_FROZEN
+    }
+}
+
+impl From<PreviewError> for StatusCode {
+    fn from(error: PreviewError) -> Self {
+        match error {
+            PreviewError::Normal => StatusCode::OK,
+            PreviewError::NullTable => StatusCode::NULL_TABLE,
+            PreviewError::NullKeyPtr => StatusCode::NULL_KEY,
+            PreviewError::NoEntryFound => StatusCode::NO_ENTRY_FOUND,
+            PreviewError::InvalidArguments => StatusCode::INVALID_ARGUMENTS,
+            PreviewError::InvalidHandle => StatusCode::INVALID_HANDLE,
+        }
+    }
+}
+
+impl From<InitError> for StatusCode {
+    fn from(error: InitError) -> Self {
+        match error {
+            InitError::Normal => StatusCode::OK,
+            InitError::NullConfig => StatusCode::NULL_CONFIG,
+            InitError::AlreadyInitialized => StatusCode::ALREADY_INITIALIZED,
+        }
+    }
+}
+
+impl From<MigrationError> for StatusCode {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::Normal => StatusCode::OK,
+            MigrationError::FileNotFound => StatusCode::FILE_NOT_FOUND,
+            MigrationError::FailedToRead => StatusCode::FAILED_TO_READ,
+            MigrationError::InvalidSyntax => StatusCode::INVALID_SYNTAX,
+            MigrationError::MappingOutsideRelease => StatusCode::MAPPING_OUTSIDE_RELEASE,
+        }
+    }
+}
+
+impl From<EditorError> for StatusCode {
+    fn from(error: EditorError) -> Self {
+        match error {
+            EditorError::Normal => StatusCode::OK,
+            EditorError::NoEntryFound => StatusCode::NO_ENTRY_FOUND,
+            EditorError::UnrepresentableValue => StatusCode::UNREPRESENTABLE_VALUE,
+            EditorError::FailedToWrite => StatusCode::FAILED_TO_WRITE,
+            EditorError::InvalidKey => StatusCode::INVALID_KEY,
+            EditorError::FailedToRead => StatusCode::FAILED_TO_READ,
+            EditorError::InvalidSyntax => StatusCode::INVALID_SYNTAX,
+        }
+    }
+}
+
+#[cfg(feature = "cbindgen")]
+impl From<super::headers::HeaderError> for StatusCode {
+    fn from(error: super::headers::HeaderError) -> Self {
+        use super::headers::HeaderError;
+        match error {
+            HeaderError::Normal => StatusCode::OK,
+            HeaderError::InvalidConfig => StatusCode::INVALID_CONFIG,
+            HeaderError::GenerationFailed => StatusCode::GENERATION_FAILED,
+            HeaderError::FailedToWrite => StatusCode::FAILED_TO_WRITE,
+        }
+    }
+}
+
+#[cfg(feature = "signatures")]
+impl From<super::signing::SignatureError> for StatusCode {
+    fn from(error: super::signing::SignatureError) -> Self {
+        use super::signing::SignatureError;
+        match error {
+            SignatureError::Normal => StatusCode::OK,
+            SignatureError::NullPathProvided => StatusCode::NULL_PATH_PROVIDED,
+            SignatureError::InvalidUTF8Path => StatusCode::INVALID_UTF8_PATH,
+            SignatureError::FileNotFound => StatusCode::FILE_NOT_FOUND,
+            SignatureError::FailedToRead => StatusCode::FAILED_TO_READ,
+            SignatureError::MissingSignature => StatusCode::MISSING_SIGNATURE,
+            SignatureError::InvalidPublicKey => StatusCode::INVALID_PUBLIC_KEY,
+            SignatureError::InvalidSignature => StatusCode::INVALID_SIGNATURE,
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+impl From<super::net::InstallError> for StatusCode {
+    fn from(error: super::net::InstallError) -> Self {
+        use super::net::InstallError;
+        match error {
+            InstallError::Normal => StatusCode::OK,
+            InstallError::NullPathProvided => StatusCode::NULL_PATH_PROVIDED,
+            InstallError::InvalidUTF8Path => StatusCode::INVALID_UTF8_PATH,
+            InstallError::InvalidHash => StatusCode::INVALID_ARGUMENTS,
+            InstallError::DownloadFailed => StatusCode::DOWNLOAD_FAILED,
+            InstallError::PackTooLarge => StatusCode::PACK_TOO_LARGE,
+            InstallError::HashMismatch => StatusCode::HASH_MISMATCH,
+            InstallError::FailedToWrite => StatusCode::FAILED_TO_WRITE,
+            InstallError::MissingSignature => StatusCode::MISSING_SIGNATURE,
+            InstallError::InvalidSignature => StatusCode::INVALID_SIGNATURE,
+            InstallError::InvalidPack => StatusCode::INVALID_PACK,
+        }
+    }
+}
+
+//Stable codes for the error enums of the C API, so hosts don't have to keep their own mapping
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_parse_error_code(error: ParseR3Error) -> u32 {
+    StatusCode::from(error).0
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_merge_error_code(error: MergeTableError) -> u32 {
+    StatusCode::from(error).0
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_find_error_code(error: FindEntryError) -> u32 {
+    StatusCode::from(error).0
+}
+
+//Static string, never free it
+#[unsafe(no_mangle)]
+pub extern "C" fn r3l_status_name(code: u32) -> *const c_char {
+    CStr::from_bytes_with_nul(StatusCode(code).name_with_nul().as_bytes())
+        .map_or(std::ptr::null(), CStr::as_ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(StatusCode::from(ParseR3Error::FileNotFound), StatusCode::from(MergeTableError::FileNotFound));
+        assert_eq!(r3l_merge_error_code(MergeTableError::DuplicateKeys), 1008);
+        assert_eq!(StatusCode::MISSING_KEY.to_string(), "R3L5004 MissingKey");
+        assert!(StatusCode::QUARANTINED.is_warning() && !StatusCode::CANCELLED.is_warning());
+        assert_eq!(StatusCode(9999).name(), "Unknown");
+        let name = unsafe { CStr::from_ptr(r3l_status_name(1012)) };
+        assert_eq!(name.to_str(), Ok("Cancelled"));
+    }
+}
@@ -0,0 +1,148 @@
+use super::annotations::find_annotation;
+use super::document::LocaleDocument;
+use super::interop::LocaleTable;
+use super::parser::{ParseR3Error, parse_r3locale_bytes};
+use super::stats::key_name;
+use hashbrown::HashMap;
+use std::fs;
+use std::path::Path;
+use xxhash_rust::xxh3::xxh3_64;
+
+//String freeze for release branches: once the source strings are handed to translators, the source file is kept as it
+//was at that point, and any key added or reworded since is reported so it can wait for the next release or be sent
+//for translation on purpose. The freeze is the frozen source file itself, e.g. `en.frozen.r3l` committed next to
+//`en.r3l` when the branch is cut, and its `@version` header names the release.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreezeChange {
+    //The key is not in the frozen file
+    Added,
+    //The value differs from the frozen one
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreezeViolation {
+    pub key: String,
+    pub change: FreezeChange,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StringFreeze {
+    //`@version` of the frozen file
+    pub version: Option<String>,
+    //`@language` of the frozen file, the language LocaleManager checks
+    pub language: Option<String>,
+    //Key text and value hash of every frozen entry by key hash
+    entries: HashMap<u64, (String, u64)>,
+}
+
+impl StringFreeze {
+    //Freezes the strings of a source file's text
+    pub fn parse(frozen: &str) -> Result<Self, ParseR3Error> {
+        let document = LocaleDocument::parse(frozen)?;
+        let table = parse_r3locale_bytes(&mut frozen.as_bytes().to_vec())?;
+        let header = |name: &str| {
+            find_annotation(table.metadata(), name).and_then(|annotation| annotation.value.clone())
+        };
+        let entries = document
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let value = table.find_entry(entry.key.as_bytes())?;
+                Some((xxh3_64(entry.key.as_bytes()), (entry.key.clone(), xxh3_64(value.as_bytes()))))
+            })
+            .collect();
+        Ok(Self {
+            version: header("version"),
+            language: header("language"),
+            entries,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ParseR3Error> {
+        if !path.exists() {
+            return Err(ParseR3Error::FileNotFound);
+        }
+        let text = fs::read_to_string(path).map_err(|_| ParseR3Error::FailedToRead)?;
+        Self::parse(&text)
+    }
+
+    //Keys of the table added or changed since the freeze, sorted by key. Added keys are named by their interned text,
+    //or their hash in hex since the table doesn't keep key text, use check for a report with every name.
+    pub fn check_table(&self, table: &LocaleTable) -> Vec<FreezeViolation> {
+        self.violations(table, key_name)
+    }
+
+    //Validator mode: every key of a source file's text added or changed since the freeze, sorted by key
+    pub fn check(&self, current: &str) -> Result<Vec<FreezeViolation>, ParseR3Error> {
+        let document = LocaleDocument::parse(current)?;
+        let table = parse_r3locale_bytes(&mut current.as_bytes().to_vec())?;
+        let names: HashMap<u64, &str> = document
+            .entries
+            .iter()
+            .map(|entry| (xxh3_64(entry.key.as_bytes()), entry.key.as_str()))
+            .collect();
+        Ok(self.violations(&table, |hash| names.get(&hash).map_or_else(|| key_name(hash), |key| key.to_string())))
+    }
+
+    fn violations(&self, table: &LocaleTable, added_name: impl Fn(u64) -> String) -> Vec<FreezeViolation> {
+        let mut violations: Vec<FreezeViolation> = table
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let value = table.string_values.get(entry.string_id).unwrap_or_default();
+                match self.entries.get(&entry.key) {
+                    None => Some(FreezeViolation {
+                        key: added_name(entry.key),
+                        change: FreezeChange::Added,
+                    }),
+                    Some((key, frozen)) if *frozen != xxh3_64(value.as_bytes()) => Some(FreezeViolation {
+                        key: key.clone(),
+                        change: FreezeChange::Changed,
+                    }),
+                    Some(_) => None,
+                }
+            })
+            .collect();
+        violations.sort_unstable_by(|left, right| left.key.cmp(&right.key));
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_freeze() {
+        let freeze = StringFreeze::parse("@language=en\n@version=1.4\n[[play]]\nPlay\n[[quit]]\nQuit ## reviewed\n")
+            .expect("Parse failed");
+        assert_eq!(freeze.version.as_deref(), Some("1.4"));
+        assert_eq!(freeze.language.as_deref(), Some("en"));
+
+        //Comments and removed keys don't need translating, only new and reworded text does
+        let current = "@language=en\n[[play]]\nPlay ## note\n[[quit]]\nQuit game\n[[credits]]\nCredits\n";
+        let violations = freeze.check(current).expect("Parse failed");
+        assert_eq!(
+            violations,
+            vec![
+                FreezeViolation {
+                    key: "credits".to_string(),
+                    change: FreezeChange::Added,
+                },
+                FreezeViolation {
+                    key: "quit".to_string(),
+                    change: FreezeChange::Changed,
+                },
+            ]
+        );
+        assert!(freeze.check("[[play]]\nPlay\n").expect("Parse failed").is_empty());
+
+        let table = parse_r3locale_bytes(&mut current.as_bytes().to_vec()).expect("Parse failed");
+        let changed = freeze.check_table(&table);
+        assert_eq!(changed.len(), 2);
+        let changed_keys = changed.iter().filter(|violation| violation.change == FreezeChange::Changed);
+        assert_eq!(changed_keys.map(|violation| violation.key.as_str()).collect::<Vec<_>>(), vec!["quit"]);
+    }
+}
@@ -0,0 +1,321 @@
+use super::blocks::{BlockLine, BlockTracker};
+use super::formats::json::quote;
+use super::parser::{
+    ANNOTATION_START, BLOCK_DELIMITER, COMMENT_ESCAPE, COMMENT_START, KEY_CLOSE, KEY_OPEN, PLACEHOLDER_CLOSE,
+    PLACEHOLDER_OPEN,
+};
+use super::sanitizer::find_comment;
+use memchr::{memchr, memmem};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum TokenKind {
+    //The whole `[[key]]` header, brackets included
+    Key,
+    //Annotations after a key, and metadata lines like `@language=fr` before the first key
+    Annotation,
+    Comment,
+    Value,
+    //A `{name}` inside a value, braces included
+    Placeholder,
+    //A line opening or closing a block value, without any comment after it
+    BlockDelimiter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    //Byte range in the input
+    pub span: Range<usize>,
+}
+
+//Splits a file into tokens for syntax highlighting, without validating it or allocating per token. Text outside of
+//entries produces no tokens, and line breaks are never part of a token.
+pub fn tokenize(bytes: &[u8]) -> impl Iterator<Item = Token> + '_ {
+    let mut line_start = 0;
+    let mut in_entries = false;
+    let mut blocks = BlockTracker::default();
+    let mut pending: VecDeque<Token> = VecDeque::new();
+    std::iter::from_fn(move || {
+        loop {
+            if let Some(token) = pending.pop_front() {
+                return Some(token);
+            }
+            if line_start >= bytes.len() {
+                return None;
+            }
+            let line_end = memchr(b'\n', &bytes[line_start..]).map_or(bytes.len(), |index| line_start + index);
+            let content_end = if bytes[line_start..line_end].ends_with(b"\r") { line_end - 1 } else { line_end };
+            let line = line_start..content_end;
+            match blocks.classify(&bytes[line.clone()]) {
+                //Everything in a block is value text, comment markers and key headers included
+                BlockLine::Content => tokenize_value(bytes, line, &mut pending),
+                block_line => tokenize_line(bytes, line, block_line, &mut in_entries, &mut pending),
+            }
+            line_start = line_end + 1;
+        }
+    })
+}
+
+fn tokenize_line(
+    bytes: &[u8],
+    line: Range<usize>,
+    block_line: BlockLine,
+    in_entries: &mut bool,
+    tokens: &mut VecDeque<Token>,
+) {
+    //The parser blanks comments wherever they start before looking at a line
+    let code = match find_comment(&bytes[line.clone()]) {
+        Some(index) => line.start..line.start + index,
+        None => line.clone(),
+    };
+    if block_line == BlockLine::Delimiter {
+        tokens.push_back(Token {
+            kind: TokenKind::BlockDelimiter,
+            span: trim_span(bytes, code.clone()),
+        });
+    } else {
+        tokenize_code(bytes, code.clone(), in_entries, tokens);
+    }
+    let comment = trim_span(bytes, code.end..line.end);
+    if !comment.is_empty() {
+        tokens.push_back(Token {
+            kind: TokenKind::Comment,
+            span: comment,
+        });
+    }
+}
+
+fn tokenize_code(bytes: &[u8], line: Range<usize>, in_entries: &mut bool, tokens: &mut VecDeque<Token>) {
+    let text = &bytes[line.clone()];
+    let content = trim_span(bytes, line.clone());
+    if content.is_empty() {
+        return;
+    }
+    let mut push = |kind, span: Range<usize>| {
+        if !span.is_empty() {
+            tokens.push_back(Token { kind, span });
+        }
+    };
+
+    let key_close = text
+        .starts_with(KEY_OPEN.as_bytes())
+        .then(|| memmem::find(text, KEY_CLOSE.as_bytes()))
+        .flatten();
+    if let Some(close) = key_close {
+        *in_entries = true;
+        let header_end = line.start + close + KEY_CLOSE.len();
+        push(TokenKind::Key, line.start..header_end);
+        push(TokenKind::Annotation, trim_span(bytes, header_end..line.end));
+        return;
+    }
+    if !*in_entries {
+        if bytes[content.start] == ANNOTATION_START {
+            push(TokenKind::Annotation, content);
+        }
+    } else {
+        tokenize_value(bytes, line, tokens);
+    }
+}
+
+//Values keep their whitespace, it is part of the text
+fn tokenize_value(bytes: &[u8], line: Range<usize>, tokens: &mut VecDeque<Token>) {
+    let mut push = |kind, span: Range<usize>| {
+        if !span.is_empty() {
+            tokens.push_back(Token { kind, span });
+        }
+    };
+    let (open, close) = (PLACEHOLDER_OPEN as u8, PLACEHOLDER_CLOSE as u8);
+    let mut start = line.start;
+    let mut position = line.start;
+    while position < line.end {
+        //An escaped brace is text, see parser::split_placeholders
+        let byte = bytes[position];
+        if (byte == open || byte == close) && position + 1 < line.end && bytes[position + 1] == byte {
+            position += 2;
+            continue;
+        }
+        let placeholder_end = (byte == open)
+            .then(|| memchr(close, &bytes[position + 1..line.end]))
+            .flatten()
+            .map(|index| position + 1 + index + 1)
+            .filter(|&end| !bytes[position + 1..end - 1].contains(&open) && end - position > 2);
+        match placeholder_end {
+            Some(end) => {
+                push(TokenKind::Value, start..position);
+                push(TokenKind::Placeholder, position..end);
+                start = end;
+                position = end;
+            }
+            None => position += 1,
+        }
+    }
+    push(TokenKind::Value, start..line.end);
+}
+
+fn trim_span(bytes: &[u8], span: Range<usize>) -> Range<usize> {
+    let text = &bytes[span.clone()];
+    match (
+        text.iter().position(|byte| !byte.is_ascii_whitespace()),
+        text.iter().rposition(|byte| !byte.is_ascii_whitespace()),
+    ) {
+        (Some(first), Some(last)) => span.start + first..span.start + last + 1,
+        _ => span.start..span.start,
+    }
+}
+
+//TextMate grammar (`.tmLanguage.json`) for VS Code, Sublime Text and other editors, built from the parser's token
+//definitions and scoped the same way tokenize splits a file
+pub fn textmate_grammar() -> String {
+    let key_open = escape_regex(KEY_OPEN);
+    let key_close = escape_regex(KEY_CLOSE);
+    let comment = escape_regex(COMMENT_START);
+    let escape = escape_regex(&char::from(COMMENT_ESCAPE).to_string());
+    let annotation = escape_regex(&char::from(ANNOTATION_START).to_string());
+    let open = escape_regex(&PLACEHOLDER_OPEN.to_string());
+    let close = escape_regex(&PLACEHOLDER_CLOSE.to_string());
+    //Any character that doesn't start a comment
+    let code = format!("(?:(?!{comment}).)");
+
+    format!(
+        r##"{{
+  "name": "Reloaded3 Localisation",
+  "scopeName": "source.r3l",
+  "fileTypes": ["r3l"],
+  "patterns": [
+    {{ "include": "#metadata" }},
+    {{ "include": "#block" }},
+    {{ "include": "#key" }},
+    {{ "include": "#comment" }},
+    {{ "include": "#escape" }},
+    {{ "include": "#placeholder" }}
+  ],
+  "repository": {{
+    "comment": {{
+      "name": "comment.line.r3l",
+      "match": {comment_match}
+    }},
+    "metadata": {{
+      "begin": {metadata_begin},
+      "end": {metadata_end},
+      "patterns": [
+        {{ "include": "#comment" }},
+        {{ "name": "entity.other.attribute-name.r3l", "match": {metadata_match} }}
+      ]
+    }},
+    "key": {{
+      "begin": {key_begin},
+      "end": "$",
+      "beginCaptures": {{
+        "1": {{ "name": "punctuation.definition.tag.begin.r3l" }},
+        "2": {{ "name": "entity.name.tag.r3l" }},
+        "3": {{ "name": "punctuation.definition.tag.end.r3l" }}
+      }},
+      "contentName": "entity.other.attribute-name.r3l",
+      "patterns": [{{ "include": "#comment" }}]
+    }},
+    "block": {{
+      "begin": {block_delimiter},
+      "end": {block_delimiter},
+      "name": "string.quoted.triple.r3l",
+      "beginCaptures": {{ "0": {{ "name": "punctuation.definition.string.begin.r3l" }} }},
+      "endCaptures": {{ "0": {{ "name": "punctuation.definition.string.end.r3l" }} }},
+      "patterns": [{{ "include": "#escape" }}, {{ "include": "#placeholder" }}]
+    }},
+    "escape": {{
+      "name": "constant.character.escape.r3l",
+      "match": {escape_match}
+    }},
+    "placeholder": {{
+      "name": "variable.other.placeholder.r3l",
+      "match": {placeholder_match}
+    }}
+  }}
+}}
+"##,
+        comment_match = quote(&format!("(?<!{escape}){comment}.*$")),
+        //Metadata lines can only come before the first key
+        metadata_begin = quote("\\A"),
+        metadata_end = quote(&format!("(?=^{key_open})")),
+        metadata_match = quote(&format!("^\\s*{annotation}{code}*")),
+        key_begin = quote(&format!("^({key_open})({code}*?)({key_close})")),
+        //The grammar can't see whether the line before was a key, the tokenizer does
+        block_delimiter = quote(&format!("^\\s*{}\\s*(?={comment}|$)", escape_regex(BLOCK_DELIMITER))),
+        escape_match = quote(&format!("{open}{open}|{close}{close}|{escape}{comment}")),
+        placeholder_match = quote(&format!("{open}[^{open}{close}\\n]+{close}")),
+    )
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() * 2);
+    for character in text.chars() {
+        if "\\^$.|?*+()[]{}#".contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::formats::json::{JsonValue, parse_json};
+
+    #[test]
+    fn test_tokenize() {
+        let text = b"@language=en\n## Menu\n[[greet]] @note=x\nHi {name} {{x}}!\r\nBye ## {not} a placeholder\n";
+        let tokens: Vec<(TokenKind, &[u8])> = tokenize(text)
+            .map(|token| (token.kind, &text[token.span]))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Annotation, &b"@language=en"[..]),
+                (TokenKind::Comment, &b"## Menu"[..]),
+                (TokenKind::Key, &b"[[greet]]"[..]),
+                (TokenKind::Annotation, &b"@note=x"[..]),
+                (TokenKind::Value, &b"Hi "[..]),
+                (TokenKind::Placeholder, &b"{name}"[..]),
+                (TokenKind::Value, &b" {{x}}!"[..]),
+                (TokenKind::Value, &b"Bye "[..]),
+                (TokenKind::Comment, &b"## {not} a placeholder"[..]),
+            ]
+        );
+
+        let text = b"[[credits]]\n\"\"\" ## opens\n[[Lead]] {name}\n## kept\n\"\"\"\n";
+        let tokens: Vec<(TokenKind, &[u8])> = tokenize(text)
+            .map(|token| (token.kind, &text[token.span]))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Key, &b"[[credits]]"[..]),
+                (TokenKind::BlockDelimiter, &b"\"\"\""[..]),
+                (TokenKind::Comment, &b"## opens"[..]),
+                (TokenKind::Value, &b"[[Lead]] "[..]),
+                (TokenKind::Placeholder, &b"{name}"[..]),
+                (TokenKind::Value, &b"## kept"[..]),
+                (TokenKind::BlockDelimiter, &b"\"\"\""[..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_textmate_grammar() {
+        let grammar = parse_json(&textmate_grammar()).expect("Parse failed");
+        let repository = grammar.get("repository").expect("Missing repository");
+        let rule = |name: &str, field: &str| {
+            repository.get(name).and_then(|rule| rule.get(field)).and_then(JsonValue::as_str).map(str::to_string)
+        };
+
+        assert_eq!(grammar.get("scopeName").and_then(JsonValue::as_str), Some("source.r3l"));
+        assert_eq!(rule("comment", "match").as_deref(), Some("(?<!\\\\)\\#\\#.*$"));
+        assert_eq!(rule("key", "begin").as_deref(), Some("^(\\[\\[)((?:(?!\\#\\#).)*?)(\\]\\])"));
+        assert_eq!(rule("placeholder", "match").as_deref(), Some("\\{[^\\{\\}\\n]+\\}"));
+        assert_eq!(rule("escape", "match").as_deref(), Some("\\{\\{|\\}\\}|\\\\\\#\\#"));
+        assert_eq!(rule("block", "begin").as_deref(), Some("^\\s*\"\"\"\\s*(?=\\#\\#|$)"));
+    }
+}
@@ -0,0 +1,346 @@
+use super::analysis::placeholders;
+use super::annotations::{Annotation, Deprecation, example_args};
+use super::document::LocaleDocument;
+use super::formatting::primary_language;
+use super::interop::LocaleTable;
+use std::ops::{Range, RangeInclusive};
+
+//Characters a target platform can display, e.g. the coverage of a console's system font
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharacterSet {
+    ranges: Vec<RangeInclusive<char>>,
+}
+
+impl CharacterSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //Printable ASCII, from space to tilde
+    pub fn ascii() -> Self {
+        Self::new().with_range(' '..='~')
+    }
+
+    //Printable ASCII and the Latin-1 supplement
+    pub fn latin1() -> Self {
+        Self::ascii().with_range('\u{A0}'..='\u{FF}')
+    }
+
+    pub fn with_range(mut self, range: RangeInclusive<char>) -> Self {
+        self.ranges.push(range);
+        self
+    }
+
+    pub fn with_chars(mut self, characters: &str) -> Self {
+        self.ranges
+            .extend(characters.chars().map(|character| character..=character));
+        self
+    }
+
+    //Control characters such as newlines are always allowed since they are never drawn
+    pub fn contains(&self, character: char) -> bool {
+        character.is_control() || self.ranges.iter().any(|range| range.contains(&character))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterViolation {
+    pub key: String,
+    pub codepoint: char,
+}
+
+impl LocaleDocument {
+    //Reports each disallowed character once per key, in file order
+    pub fn validate_characters(&self, allowed: &CharacterSet) -> Vec<CharacterViolation> {
+        let mut violations: Vec<CharacterViolation> = Vec::new();
+        for entry in &self.entries {
+            let first_violation = violations.len();
+            for character in entry.parsed_value().chars().filter(|&character| !allowed.contains(character)) {
+                if !violations[first_violation..]
+                    .iter()
+                    .any(|violation| violation.codepoint == character)
+                {
+                    violations.push(CharacterViolation {
+                        key: entry.key.clone(),
+                        codepoint: character,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+//Words a platform's content policy doesn't allow, e.g. for gating community translation uploads
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockedTerms {
+    //Primary language subtag, or `*` for terms blocked in every language
+    languages: Vec<(String, Vec<String>)>,
+}
+
+impl BlockedTerms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //`language` is a language tag such as `de`, or `*` to block the terms everywhere
+    pub fn with_terms(mut self, language: &str, terms: &[&str]) -> Self {
+        let language = primary_language(language).to_ascii_lowercase();
+        let terms = terms.iter().map(|term| term.to_string());
+        match self.languages.iter_mut().find(|(listed, _)| *listed == language) {
+            Some((_, listed_terms)) => listed_terms.extend(terms),
+            None => self.languages.push((language, terms.collect())),
+        }
+        self
+    }
+
+    pub fn terms_for(&self, language: &str) -> impl Iterator<Item = &str> {
+        let language = primary_language(language).to_ascii_lowercase();
+        self.languages
+            .iter()
+            .filter(move |(listed, _)| *listed == language || listed == "*")
+            .flat_map(|(_, terms)| terms.iter().map(String::as_str))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedTermMatch {
+    pub key: String,
+    pub term: String,
+    //Byte range of the match in DocumentEntry::parsed_value
+    pub span: Range<usize>,
+}
+
+//Length of `term` at the start of `text` ignoring case, None when it doesn't start there
+fn match_ignoring_case(text: &str, term: &str) -> Option<usize> {
+    let mut term_characters = term.chars().flat_map(char::to_lowercase).peekable();
+    for (offset, character) in text.char_indices() {
+        if term_characters.peek().is_none() {
+            return Some(offset);
+        }
+        for lowercase in character.to_lowercase() {
+            if term_characters.next() != Some(lowercase) {
+                return None;
+            }
+        }
+    }
+    term_characters.peek().is_none().then_some(text.len())
+}
+
+impl LocaleDocument {
+    //Matches whole words ignoring case, so a blocked "ass" doesn't flag "class". Matches are in file order.
+    pub fn scan_blocked_terms(&self, language: &str, blocked: &BlockedTerms) -> Vec<BlockedTermMatch> {
+        let terms: Vec<&str> = blocked.terms_for(language).filter(|term| !term.is_empty()).collect();
+        let mut matches = Vec::new();
+        for entry in &self.entries {
+            let value = entry.parsed_value();
+            let mut previous: Option<char> = None;
+            for (start, character) in value.char_indices() {
+                let at_word_start = previous.is_none_or(|previous| !previous.is_alphanumeric());
+                previous = Some(character);
+                if !at_word_start {
+                    continue;
+                }
+                for term in &terms {
+                    let Some(length) = match_ignoring_case(&value[start..], term) else {
+                        continue;
+                    };
+                    let end = start + length;
+                    if value[end..].starts_with(char::is_alphanumeric) {
+                        continue;
+                    }
+                    matches.push(BlockedTermMatch {
+                        key: entry.key.clone(),
+                        term: term.to_string(),
+                        span: start..end,
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedTranslation {
+    pub key: String,
+    pub deprecation: Deprecation,
+}
+
+impl LocaleDocument {
+    //Entries of this translation whose key `source` marks `@deprecated`, translating them is wasted effort
+    pub fn deprecated_translations(&self, source: &LocaleDocument) -> Vec<DeprecatedTranslation> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let deprecation = Deprecation::from_annotations(&source.find(&entry.key)?.parsed_annotations())?;
+                Some(DeprecatedTranslation {
+                    key: entry.key.clone(),
+                    deprecation,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormattingIssue {
+    //The value uses a placeholder that has no example argument
+    MissingExample { key: String, placeholder: String },
+    //An example or `@placeholder` annotation names a placeholder the value doesn't use
+    UnknownPlaceholder { key: String, placeholder: String },
+    //The example doesn't parse as the `type` declared by the placeholder's annotation
+    TypeMismatch { key: String, placeholder: String, expected: String },
+}
+
+//Whether an example fits a `@placeholder` type, types other than numbers accept any text
+fn example_fits(expected: &str, example: &str) -> bool {
+    match expected.to_ascii_lowercase().as_str() {
+        "int" | "integer" => example.parse::<i64>().is_ok(),
+        "double" | "float" | "num" | "number" => example.parse::<f64>().is_ok(),
+        "bool" | "boolean" => matches!(example, "true" | "false"),
+        _ => true,
+    }
+}
+
+impl LocaleDocument {
+    //Self-test for every entry's examples, see annotations::example_args. An entry without examples reports each of
+    //its placeholders, so this also finds keys nobody has written examples for.
+    pub fn verify_formatting(&self) -> Vec<FormattingIssue> {
+        let mut issues = Vec::new();
+        for entry in &self.entries {
+            let annotations = entry.parsed_annotations();
+            let examples = example_args(&annotations);
+            let value = entry.parsed_value();
+            let used = placeholders(&value);
+            let issue_key = || entry.key.clone();
+
+            let mut reported: Vec<&str> = Vec::new();
+            for &placeholder in &used {
+                if !reported.contains(&placeholder) && !examples.iter().any(|(name, _)| name == placeholder) {
+                    reported.push(placeholder);
+                    issues.push(FormattingIssue::MissingExample {
+                        key: issue_key(),
+                        placeholder: placeholder.to_string(),
+                    });
+                }
+            }
+
+            let declared = annotations
+                .iter()
+                .filter(|annotation| annotation.name == "placeholder")
+                .filter_map(|annotation| annotation.value.as_deref());
+            let mut unknown: Vec<&str> = Vec::new();
+            for name in declared.chain(examples.iter().map(|(name, _)| name.as_str())) {
+                if !used.contains(&name) && !unknown.contains(&name) {
+                    unknown.push(name);
+                }
+            }
+            issues.extend(unknown.into_iter().map(|placeholder| FormattingIssue::UnknownPlaceholder {
+                key: issue_key(),
+                placeholder: placeholder.to_string(),
+            }));
+
+            for (placeholder, example) in &examples {
+                let Some(expected) = placeholder_type(&annotations, placeholder) else {
+                    continue;
+                };
+                if !example_fits(expected, example) {
+                    issues.push(FormattingIssue::TypeMismatch {
+                        key: issue_key(),
+                        placeholder: placeholder.clone(),
+                        expected: expected.to_string(),
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+fn placeholder_type<'a>(annotations: &'a [Annotation], placeholder: &str) -> Option<&'a str> {
+    annotations
+        .iter()
+        .find(|annotation| annotation.name == "placeholder" && annotation.value.as_deref() == Some(placeholder))
+        .and_then(|annotation| annotation.arg("type"))
+}
+
+impl LocaleTable {
+    //Tables only store key hashes, use LocaleDocument::validate_characters to find the keys
+    pub fn unsupported_codepoints(&self, allowed: &CharacterSet) -> Vec<char> {
+        self.required_codepoints()
+            .filter(|&character| !allowed.contains(character))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_characters() {
+        let document = LocaleDocument::parse("[[title]]\nCafé — “Open”\n[[plain]]\nPlain text\n[[ok]]\nÉcran\n")
+            .expect("Parse failed");
+        let violations = document.validate_characters(&CharacterSet::latin1());
+
+        let found: Vec<(&str, char)> = violations
+            .iter()
+            .map(|violation| (violation.key.as_str(), violation.codepoint))
+            .collect();
+        assert_eq!(found, vec![("title", '—'), ("title", '“'), ("title", '”')]);
+        assert!(document.validate_characters(&CharacterSet::latin1().with_chars("—“”")).is_empty());
+    }
+
+    #[test]
+    fn test_scan_blocked_terms() {
+        let document = LocaleDocument::parse("[[a]]\nA classic badword.\n[[b]]\nNo BADWORD here, just Mist\n")
+            .expect("Parse failed");
+        let blocked = BlockedTerms::new().with_terms("*", &["badword"]).with_terms("de", &["mist"]);
+
+        let found: Vec<(String, Range<usize>)> = document
+            .scan_blocked_terms("en", &blocked)
+            .into_iter()
+            .map(|found| (found.key, found.span))
+            .collect();
+        assert_eq!(found, vec![("a".to_string(), 10..17), ("b".to_string(), 3..10)]);
+        assert_eq!(document.scan_blocked_terms("de-AT", &blocked).len(), 3);
+    }
+
+    #[test]
+    fn test_deprecated_translations() {
+        let source = LocaleDocument::parse("[[old]] @deprecated replacement=new\nOld\n[[new]]\nNew\n").expect("Parse failed");
+        let french = LocaleDocument::parse("[[old]]\nAncien\n[[new]]\nNouveau\n").expect("Parse failed");
+
+        let found = french.deprecated_translations(&source);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key, "old");
+        assert_eq!(found[0].deprecation.replacement.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn test_verify_formatting() {
+        let document = LocaleDocument::parse(
+            "[[coins]] @placeholder=count type=int @example count=3.5 name=Mario\n{count} coins\n[[hello]]\nHello {name}\n[[plain]]\nPlain\n",
+        )
+        .expect("Parse failed");
+        assert_eq!(
+            document.verify_formatting(),
+            vec![
+                FormattingIssue::UnknownPlaceholder {
+                    key: "coins".to_string(),
+                    placeholder: "name".to_string(),
+                },
+                FormattingIssue::TypeMismatch {
+                    key: "coins".to_string(),
+                    placeholder: "count".to_string(),
+                    expected: "int".to_string(),
+                },
+                FormattingIssue::MissingExample {
+                    key: "hello".to_string(),
+                    placeholder: "name".to_string(),
+                },
+            ]
+        );
+    }
+}
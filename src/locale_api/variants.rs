@@ -0,0 +1,92 @@
+use super::interop::LocaleTable;
+
+//Variants of a key are written as separate entries, e.g. `[[sword|accusative]]` next to `[[sword]]`
+pub const VARIANT_SEPARATOR: u8 = b'|';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum Case {
+    Nominative,
+    Accusative,
+    Genitive,
+    Dative,
+    Instrumental,
+    Locative,
+    Vocative,
+}
+
+impl Case {
+    pub fn name(self) -> &'static str {
+        match self {
+            Case::Nominative => "nominative",
+            Case::Accusative => "accusative",
+            Case::Genitive => "genitive",
+            Case::Dative => "dative",
+            Case::Instrumental => "instrumental",
+            Case::Locative => "locative",
+            Case::Vocative => "vocative",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum Gender {
+    Masculine,
+    Feminine,
+    Neuter,
+}
+
+impl Gender {
+    pub fn name(self) -> &'static str {
+        match self {
+            Gender::Masculine => "masculine",
+            Gender::Feminine => "feminine",
+            Gender::Neuter => "neuter",
+        }
+    }
+}
+
+pub fn variant_key(key: &[u8], variant: &str) -> Vec<u8> {
+    let mut variant_key = Vec::with_capacity(key.len() + 1 + variant.len());
+    variant_key.extend_from_slice(key);
+    variant_key.push(VARIANT_SEPARATOR);
+    variant_key.extend_from_slice(variant.as_bytes());
+    variant_key
+}
+
+impl LocaleTable {
+    //Falls back to the plain key when the table has no entry for the variant
+    pub fn find_entry_variant(&self, key: &[u8], variant: &str) -> Option<&str> {
+        self.find_entry(&variant_key(key, variant))
+            .or_else(|| self.find_entry(key))
+    }
+
+    //The plain key is treated as the nominative form
+    pub fn find_entry_case(&self, key: &[u8], case: Case) -> Option<&str> {
+        self.find_entry_variant(key, case.name())
+    }
+
+    pub fn find_entry_gender(&self, key: &[u8], gender: Gender) -> Option<&str> {
+        self.find_entry_variant(key, gender.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale_api::parser::parse_r3locale_bytes;
+
+    #[test]
+    fn test_case_variants_fall_back_to_plain_key() {
+        let mut sample = "[[sword]]\nмеч\n[[axe]]\nтопор\n[[axe|instrumental]]\nтопором\n"
+            .as_bytes()
+            .to_vec();
+        let table = parse_r3locale_bytes(&mut sample).expect("Parse failed");
+
+        assert_eq!(table.find_entry_case(b"axe", Case::Instrumental), Some("топором"));
+        assert_eq!(table.find_entry_case(b"axe", Case::Nominative), Some("топор"));
+        assert_eq!(table.find_entry_case(b"sword", Case::Accusative), Some("меч"));
+        assert_eq!(table.find_entry_case(b"missing", Case::Accusative), None);
+    }
+}